@@ -12,35 +12,52 @@ use maskerad_memory_allocators::allocation_error::AllocationError as MaskeradMem
 #[derive(Debug)]
 pub enum AllocationError {
     StackError(String, MaskeradMemAllocError),
+    PoolExhausted(String),
+    StaleHandle(String),
+    StackExhausted(String),
+    FreeListExhausted(String),
 }
 
-unsafe impl Send for AllocationError {}
-unsafe impl Sync for AllocationError {}
-
 impl fmt::Display for AllocationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &AllocationError::StackError(ref desc, _) => {
                 write!(f, "Memory stack error: {}", desc)
             },
+            &AllocationError::PoolExhausted(ref desc) => {
+                write!(f, "Memory pool exhausted: {}", desc)
+            },
+            &AllocationError::StaleHandle(ref desc) => {
+                write!(f, "Stale pool handle: {}", desc)
+            },
+            &AllocationError::StackExhausted(ref desc) => {
+                write!(f, "Memory stack exhausted: {}", desc)
+            },
+            &AllocationError::FreeListExhausted(ref desc) => {
+                write!(f, "Memory free list exhausted: {}", desc)
+            },
         }
     }
 }
 
 impl Error for AllocationError {
-    fn description(&self) -> &str {
-        match self {
-            &AllocationError::StackError(_, _) => {
-                "StackError"
-            },
-        }
-    }
-
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             &AllocationError::StackError(_, ref stack_error) => {
                 Some(stack_error)
             },
+            &AllocationError::PoolExhausted(_) => {
+                None
+            },
+            &AllocationError::StaleHandle(_) => {
+                None
+            },
+            &AllocationError::StackExhausted(_) => {
+                None
+            },
+            &AllocationError::FreeListExhausted(_) => {
+                None
+            },
         }
     }
 }
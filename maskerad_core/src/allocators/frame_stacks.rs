@@ -0,0 +1,371 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::cell::Cell;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use allocators::errors::{AllocationError, AllocationResult};
+use allocators::stats::{MemoryStats, MemoryReport};
+
+/*
+FRAME STACKS.
+
+Linear allocators usable from several threads, for the data living only during one frame.
+
+An AtomicMemStack is a lock-free stack: allocating only bumps an atomic offset, and can be done
+from any thread through a shared reference. Only Copy data can be allocated, since nothing is
+dropped when the stack is reset.
+
+FrameStacks holds one AtomicMemStack per worker thread, to avoid any contention between
+workers, and one shared AtomicMemStack for the scratch data exchanged between jobs.
+All of them are reset together at the frame boundary. Resetting needs a mutable reference:
+the borrow checker guarantees that no reference to the data of the previous frame survives.
+
+A worker thread registers the id of its job system and its index with set_current_worker(), then
+FrameStacks::current() returns its own stack, if the FrameStacks has been created for this job system:
+the workers of two job systems have the same indices.
+*/
+
+thread_local! {
+    //The id of the job system and the index of the worker running on this thread.
+    static CURRENT_WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+//Register the job system id and the index of the worker running on this thread. None unregisters the thread.
+pub fn set_current_worker(worker: Option<(usize, usize)>) {
+    CURRENT_WORKER.with(|current| current.set(worker));
+}
+
+pub fn current_worker() -> Option<(usize, usize)> {
+    CURRENT_WORKER.with(|worker| worker.get())
+}
+
+//The top of the stack gets its own cache line, workers using neighbouring stacks must not
+//invalidate each other's cache.
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct CachePadded<T>(T);
+
+pub struct AtomicMemStack {
+    storage: *mut u8,
+    capacity: usize,
+    top: CachePadded<AtomicUsize>,
+    high_water_mark: AtomicUsize,
+    allocation_count: AtomicUsize,
+    failed_allocations: AtomicUsize,
+}
+
+//The storage is only written through the disjoint regions reserved with the atomic top.
+unsafe impl Send for AtomicMemStack {}
+unsafe impl Sync for AtomicMemStack {}
+
+impl fmt::Debug for AtomicMemStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AtomicMemStack [{}]", self.stats())
+    }
+}
+
+impl Drop for AtomicMemStack {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(self.storage, self.capacity)));
+        }
+    }
+}
+
+impl AtomicMemStack {
+    pub fn with_capacity(capacity: usize) -> Self {
+        debug!("Creating an AtomicMemStack of {} bytes.", capacity);
+        let storage = Box::into_raw(vec![0u8; capacity].into_boxed_slice()) as *mut u8;
+
+        AtomicMemStack {
+            storage,
+            capacity,
+            top: CachePadded(AtomicUsize::new(0)),
+            high_water_mark: AtomicUsize::new(0),
+            allocation_count: AtomicUsize::new(0),
+            failed_allocations: AtomicUsize::new(0),
+        }
+    }
+
+    //Reserve size bytes aligned to align, returning the offset of the region.
+    fn reserve(&self, size: usize, align: usize) -> AllocationResult<usize> {
+        let base = self.storage as usize;
+        let mut top = self.top.0.load(Ordering::Relaxed);
+
+        loop {
+            let region = (base + top).checked_add(align - 1)
+                .map(|address| (address & !(align - 1)) - base)
+                .and_then(|start| start.checked_add(size).map(|end| (start, end)));
+
+            let (start, end) = match region {
+                Some((start, end)) if end <= self.capacity => (start, end),
+                _ => {
+                    self.failed_allocations.fetch_add(1, Ordering::Relaxed);
+                    return Err(AllocationError::StackExhausted(format!(
+                        "Could not allocate {} bytes in an AtomicMemStack of {} bytes ({} bytes used).",
+                        size, self.capacity, top
+                    )));
+                },
+            };
+
+            match self.top.0.compare_exchange_weak(top, end, Ordering::AcqRel, Ordering::Relaxed) {
+                Ok(_) => {
+                    self.allocation_count.fetch_add(1, Ordering::Relaxed);
+                    self.high_water_mark.fetch_max(end, Ordering::Relaxed);
+                    return Ok(start);
+                },
+                Err(current_top) => top = current_top,
+            }
+        }
+    }
+
+    //Every call reserves a distinct region, the mutable references never alias.
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate<T, F>(&self, op: F) -> AllocationResult<&mut T> where
+        T: Copy,
+        F: FnOnce() -> T,
+    {
+        let offset = self.reserve(mem::size_of::<T>(), mem::align_of::<T>())?;
+        unsafe {
+            let location = self.storage.add(offset) as *mut T;
+            ptr::write(location, op());
+            Ok(&mut *location)
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_slice<T: Copy>(&self, len: usize, value: T) -> AllocationResult<&mut [T]> {
+        let size = match mem::size_of::<T>().checked_mul(len) {
+            Some(size) => size,
+            None => {
+                self.failed_allocations.fetch_add(1, Ordering::Relaxed);
+                return Err(AllocationError::StackExhausted(format!("A slice of {} elements is too big.", len)));
+            },
+        };
+
+        let offset = self.reserve(size, mem::align_of::<T>())?;
+        unsafe {
+            let location = self.storage.add(offset) as *mut T;
+            for index in 0..len {
+                ptr::write(location.add(index), value);
+            }
+            Ok(slice::from_raw_parts_mut(location, len))
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    pub fn allocate_slice_copy<T: Copy>(&self, source: &[T]) -> AllocationResult<&mut [T]> {
+        let offset = self.reserve(mem::size_of_val(source), mem::align_of::<T>())?;
+        unsafe {
+            let location = self.storage.add(offset) as *mut T;
+            ptr::copy_nonoverlapping(source.as_ptr(), location, source.len());
+            Ok(slice::from_raw_parts_mut(location, source.len()))
+        }
+    }
+
+    pub fn marker(&self) -> usize {
+        self.top.0.load(Ordering::Acquire)
+    }
+
+    pub fn used(&self) -> usize {
+        self.marker()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    //Give all the memory back. The mutable borrow guarantees that nothing allocated is still referenced.
+    pub fn reset(&mut self) {
+        self.top.0.store(0, Ordering::Release);
+    }
+
+    pub fn reset_to_marker(&mut self, marker: usize) {
+        if marker <= self.marker() {
+            self.top.0.store(marker, Ordering::Release);
+        }
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        MemoryStats::new(
+            self.used(),
+            self.capacity,
+            self.high_water_mark.load(Ordering::Relaxed),
+            self.allocation_count.load(Ordering::Relaxed),
+            self.failed_allocations.load(Ordering::Relaxed),
+            None,
+        )
+    }
+
+    //Set the allocation counters to 0, and the high-water mark to the bytes currently used.
+    pub fn reset_stats(&self) {
+        self.high_water_mark.store(self.used(), Ordering::Relaxed);
+        self.allocation_count.store(0, Ordering::Relaxed);
+        self.failed_allocations.store(0, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+pub struct FrameStacks {
+    //The id of the job system whose workers use the worker stacks.
+    system_id: usize,
+    workers: Vec<AtomicMemStack>,
+    shared: AtomicMemStack,
+    frame: u64,
+}
+
+impl FrameStacks {
+    //The FrameStacks of the workers of the job system with the given id (see JobSystem::id()).
+    pub fn new(system_id: usize, worker_count: usize, worker_capacity: usize, shared_capacity: usize) -> Self {
+        debug!("Creating FrameStacks for {} workers: {} bytes per worker, {} bytes shared.", worker_count, worker_capacity, shared_capacity);
+        FrameStacks {
+            system_id,
+            workers: (0..worker_count).map(|_| AtomicMemStack::with_capacity(worker_capacity)).collect(),
+            shared: AtomicMemStack::with_capacity(shared_capacity),
+            frame: 0,
+        }
+    }
+
+    pub fn system_id(&self) -> usize {
+        self.system_id
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn worker(&self, index: usize) -> Option<&AtomicMemStack> {
+        self.workers.get(index)
+    }
+
+    //The stack used by every thread for the data shared between jobs.
+    pub fn shared(&self) -> &AtomicMemStack {
+        &self.shared
+    }
+
+    //The stack of the worker running on this thread, or the shared stack if this thread isn't a registered worker
+    //of the job system.
+    pub fn current(&self) -> &AtomicMemStack {
+        match current_worker() {
+            Some((system_id, index)) if system_id == self.system_id => self.workers.get(index).unwrap_or(&self.shared),
+            _ => &self.shared,
+        }
+    }
+
+    //Number of frames since the creation.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    //Reset every stack, at the frame boundary.
+    pub fn next_frame(&mut self) {
+        trace!("Resetting the FrameStacks of the frame {}.", self.frame);
+        for stack in self.workers.iter_mut() {
+            stack.reset();
+        }
+        self.shared.reset();
+        self.frame += 1;
+    }
+
+    pub fn report(&self) -> MemoryReport {
+        let mut report = MemoryReport::new();
+        for (index, stack) in self.workers.iter().enumerate() {
+            report.add(format!("frame_worker_{}", index), stack.stats());
+        }
+        report.add("frame_shared", self.shared.stats());
+        report
+    }
+}
+
+#[cfg(test)]
+mod frame_stacks_test {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn atomic_mem_stack_allocate_reset() {
+        let mut stack = AtomicMemStack::with_capacity(64);
+        {
+            let byte = stack.allocate(|| 1u8).unwrap();
+            let word = stack.allocate(|| 2u64).unwrap();
+            *byte += 1;
+            assert_eq!(*byte, 2);
+            assert_eq!(*word, 2);
+            assert_eq!(word as *const u64 as usize % mem::align_of::<u64>(), 0);
+
+            let slice = stack.allocate_slice_copy(&[1u32, 2, 3]).unwrap();
+            assert_eq!(slice, &[1, 2, 3]);
+            assert!(stack.allocate_slice(100, 0u8).is_err());
+        }
+
+        let stats = stack.stats();
+        assert_eq!(stats.allocation_count(), 3);
+        assert_eq!(stats.failed_allocations(), 1);
+
+        stack.reset();
+        assert_eq!(stack.used(), 0);
+        assert_eq!(stack.stats().high_water_mark(), stats.used());
+    }
+
+    #[test]
+    fn atomic_mem_stack_concurrent() {
+        //The storage of bytes may not be aligned for u64: 7 more bytes for the padding before the first u64.
+        let stack = Arc::new(AtomicMemStack::with_capacity(8 * 4 * 1000 + 7));
+        let padding = (mem::align_of::<u64>() - stack.storage as usize % mem::align_of::<u64>()) % mem::align_of::<u64>();
+        let threads: Vec<_> = (0..4u64).map(|thread_index| {
+            let stack = stack.clone();
+            thread::spawn(move || {
+                let values: Vec<&mut u64> = (0..1000u64)
+                    .map(|i| stack.allocate(|| thread_index * 1000 + i).unwrap())
+                    .collect();
+                values.iter().enumerate().all(|(i, value)| **value == thread_index * 1000 + i as u64)
+            })
+        }).collect();
+
+        for thread in threads {
+            assert!(thread.join().unwrap());
+        }
+        assert_eq!(stack.used(), padding + 8 * 4 * 1000);
+        assert!(stack.allocate(|| 0u64).is_err());
+    }
+
+    #[test]
+    fn frame_stacks_per_worker() {
+        let mut frame_stacks = Arc::new(FrameStacks::new(7, 2, 128, 128));
+
+        let threads: Vec<_> = (0..2).map(|worker_index| {
+            let frame_stacks = frame_stacks.clone();
+            thread::spawn(move || {
+                set_current_worker(Some((7, worker_index)));
+                frame_stacks.current().allocate(|| [worker_index as u8; 16]).unwrap();
+                frame_stacks.shared().allocate(|| 1u8).unwrap();
+                //The workers of another job system use the shared stack.
+                set_current_worker(Some((8, worker_index)));
+                frame_stacks.current().allocate(|| 1u8).unwrap();
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(frame_stacks.worker(0).unwrap().used(), 16);
+        assert_eq!(frame_stacks.worker(1).unwrap().used(), 16);
+        assert_eq!(frame_stacks.shared().used(), 4);
+        assert_eq!(frame_stacks.current().used(), 4);
+        assert_eq!(frame_stacks.report().entries().len(), 3);
+
+        let frame_stacks = Arc::get_mut(&mut frame_stacks).unwrap();
+        frame_stacks.next_frame();
+        assert_eq!(frame_stacks.frame(), 1);
+        assert_eq!(frame_stacks.report().total_used(), 0);
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+use allocators::errors::{AllocationError, AllocationResult};
+use allocators::stats::MemoryStats;
+
+/*
+FREE LIST ALLOCATOR.
+
+A buffer of bytes, allocated once at creation, from which blocks of any size and alignment are allocated
+and freed in any order. Suited for data of variable size with an unpredictable lifetime: streamed
+meshes, sound buffers, strings...
+
+The free ranges of the buffer are kept sorted by offset. Allocating takes the first free range big
+enough for the block (first fit), freeing a block merges it with the free ranges just before and after it,
+to limit the fragmentation.
+
+Allocating returns a FreeListBlock, the offset and the size of the block in the buffer, instead of a reference.
+Like the handles of the pools, a block has a generation: a block freed and allocated again at the same offset
+doesn't give access to the new allocation.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FreeListBlock {
+    offset: usize,
+    size: usize,
+    generation: u32,
+}
+
+impl FreeListBlock {
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+#[derive(Debug)]
+pub struct MemFreeList {
+    buffer: Vec<u8>,
+    //Offset -> size of the free ranges.
+    free_ranges: BTreeMap<usize, usize>,
+    //Offset -> the allocated block, and the start of the range taken by the block, alignment padding included.
+    allocated: BTreeMap<usize, (FreeListBlock, usize)>,
+    //The generation of the next allocated block.
+    generation: u32,
+    used: usize,
+    high_water_mark: usize,
+    allocation_count: usize,
+    failed_allocations: usize,
+}
+
+impl MemFreeList {
+    pub fn with_capacity(capacity: usize) -> Self {
+        debug!("Creating a MemFreeList of {} bytes.", capacity);
+        let mut free_ranges = BTreeMap::new();
+        if capacity > 0 {
+            free_ranges.insert(0, capacity);
+        }
+
+        MemFreeList {
+            buffer: vec![0; capacity],
+            free_ranges,
+            allocated: BTreeMap::new(),
+            generation: 0,
+            used: 0,
+            high_water_mark: 0,
+            allocation_count: 0,
+            failed_allocations: 0,
+        }
+    }
+
+    //Allocate a block of size bytes, whose offset is a multiple of align (a power of two).
+    pub fn allocate(&mut self, size: usize, align: usize) -> AllocationResult<FreeListBlock> {
+        assert!(align.is_power_of_two(), "The alignment of a MemFreeList block must be a power of two.");
+
+        //Empty blocks still take one byte, so each block has its own offset.
+        let size = size.max(1);
+        let fit = self.free_ranges.iter()
+            .filter_map(|(&start, &range_size)| {
+                let offset = start.checked_add(align - 1)? & !(align - 1);
+                let end = offset.checked_add(size)?;
+                if end <= start + range_size {
+                    Some((start, range_size, offset))
+                } else {
+                    None
+                }
+            })
+            .next();
+
+        let (start, range_size, offset) = match fit {
+            Some(fit) => fit,
+            None => {
+                self.failed_allocations += 1;
+                error!("The MemFreeList has no free range of {} bytes aligned on {} ({} of {} bytes used).",
+                    size, align, self.used, self.buffer.len());
+                return Err(AllocationError::FreeListExhausted(format!(
+                    "No free range of {} bytes aligned on {} in the free list ({} of {} bytes used).",
+                    size, align, self.used, self.buffer.len()
+                )));
+            },
+        };
+
+        //The padding before the block stays with the block, the end of the range goes back to the free list.
+        self.free_ranges.remove(&start);
+        let end = offset + size;
+        if end < start + range_size {
+            self.free_ranges.insert(end, start + range_size - end);
+        }
+        let block = FreeListBlock {
+            offset,
+            size,
+            generation: self.generation,
+        };
+        self.generation = self.generation.wrapping_add(1);
+        self.allocated.insert(offset, (block, start));
+
+        trace!("Allocating {} bytes at the offset {} of the MemFreeList.", size, offset);
+        self.used += end - start;
+        self.allocation_count += 1;
+        if self.used > self.high_water_mark {
+            self.high_water_mark = self.used;
+        }
+
+        Ok(block)
+    }
+
+    //Give the block back to the free list, merging it with its free neighbours.
+    pub fn free(&mut self, block: FreeListBlock) -> AllocationResult<()> {
+        self.check(block)?;
+        let (_, mut start) = self.allocated.remove(&block.offset).expect("A checked block is allocated.");
+        let size = block.size;
+        trace!("Freeing {} bytes at the offset {} of the MemFreeList.", size, block.offset);
+
+        let mut end = block.offset + size;
+        self.used -= end - start;
+
+        if let Some(next_size) = self.free_ranges.remove(&end) {
+            end += next_size;
+        }
+        let previous = self.free_ranges.range(..start).next_back().map(|(&offset, &size)| (offset, size));
+        if let Some((previous_start, previous_size)) = previous {
+            if previous_start + previous_size == start {
+                self.free_ranges.remove(&previous_start);
+                start = previous_start;
+            }
+        }
+        self.free_ranges.insert(start, end - start);
+        Ok(())
+    }
+
+    pub fn is_valid(&self, block: FreeListBlock) -> bool {
+        match self.allocated.get(&block.offset) {
+            Some(&(allocated, _)) => allocated == block,
+            None => false,
+        }
+    }
+
+    pub fn get(&self, block: FreeListBlock) -> AllocationResult<&[u8]> {
+        self.check(block)?;
+        Ok(&self.buffer[block.offset..block.offset + block.size])
+    }
+
+    pub fn get_mut(&mut self, block: FreeListBlock) -> AllocationResult<&mut [u8]> {
+        self.check(block)?;
+        Ok(&mut self.buffer[block.offset..block.offset + block.size])
+    }
+
+    fn check(&self, block: FreeListBlock) -> AllocationResult<()> {
+        if self.is_valid(block) {
+            Ok(())
+        } else {
+            warn!("Stale block used with a MemFreeList: {:?}.", block);
+            Err(AllocationError::StaleHandle(format!(
+                "No block of {} bytes and generation {} is allocated at the offset {} of the free list.",
+                block.size, block.generation, block.offset
+            )))
+        }
+    }
+
+    //Free every block. All the blocks become stale.
+    pub fn clear(&mut self) {
+        debug!("Clearing the MemFreeList.");
+        self.allocated.clear();
+        self.free_ranges.clear();
+        if !self.buffer.is_empty() {
+            self.free_ranges.insert(0, self.buffer.len());
+        }
+        self.used = 0;
+    }
+
+    //Number of allocated blocks.
+    pub fn len(&self) -> usize {
+        self.allocated.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allocated.is_empty()
+    }
+
+    //Bytes used by the blocks, alignment padding included.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    //The biggest block which can be allocated without alignment padding.
+    pub fn largest_free_range(&self) -> usize {
+        self.free_ranges.values().cloned().max().unwrap_or(0)
+    }
+
+    pub fn free_range_count(&self) -> usize {
+        self.free_ranges.len()
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        MemoryStats::new(
+            self.used,
+            self.buffer.len(),
+            self.high_water_mark,
+            self.allocation_count,
+            self.failed_allocations,
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod free_lists_test {
+    use super::*;
+
+    #[test]
+    fn free_list_allocate_get_free() {
+        let mut free_list = MemFreeList::with_capacity(64);
+        let first = free_list.allocate(10, 1).unwrap();
+        let second = free_list.allocate(20, 8).unwrap();
+        assert_eq!(first.offset(), 0);
+        assert_eq!(second.offset(), 16);
+        assert_eq!(free_list.used(), 36);
+        assert_eq!(free_list.len(), 2);
+
+        free_list.get_mut(second).unwrap()[0] = 42;
+        assert_eq!(free_list.get(second).unwrap()[0], 42);
+        assert_eq!(free_list.get(first).unwrap().len(), 10);
+
+        free_list.free(first).unwrap();
+        assert!(!free_list.is_valid(first));
+        assert!(free_list.get(first).is_err());
+        assert!(free_list.free(first).is_err());
+        //The padding before the second block is still counted.
+        assert_eq!(free_list.used(), 26);
+        assert_eq!(free_list.stats().high_water_mark(), 36);
+    }
+
+    #[test]
+    fn free_list_coalescing() {
+        let mut free_list = MemFreeList::with_capacity(30);
+        let blocks: Vec<_> = (0..3).map(|_| free_list.allocate(10, 1).unwrap()).collect();
+        assert_eq!(free_list.largest_free_range(), 0);
+        assert!(free_list.allocate(1, 1).is_err());
+        assert_eq!(free_list.stats().failed_allocations(), 1);
+
+        //Freeing the first and the last blocks leaves two free ranges, too small for 20 bytes.
+        free_list.free(blocks[0]).unwrap();
+        free_list.free(blocks[2]).unwrap();
+        assert_eq!(free_list.free_range_count(), 2);
+        match free_list.allocate(20, 1) {
+            Err(AllocationError::FreeListExhausted(_)) => {},
+            other => panic!("Expected a FreeListExhausted error, got {:?}", other),
+        }
+
+        //Freeing the middle block merges the three ranges.
+        free_list.free(blocks[1]).unwrap();
+        assert_eq!(free_list.free_range_count(), 1);
+        assert_eq!(free_list.largest_free_range(), 30);
+        assert!(free_list.is_empty());
+        assert_eq!(free_list.allocate(30, 1).unwrap().offset(), 0);
+
+        free_list.clear();
+        assert_eq!(free_list.used(), 0);
+        assert_eq!(MemFreeList::with_capacity(0).allocate(1, 1).map(|block| block.size()).ok(), None);
+    }
+
+    #[test]
+    fn free_list_stale_blocks() {
+        let mut free_list = MemFreeList::with_capacity(16);
+        let old = free_list.allocate(8, 1).unwrap();
+        free_list.free(old).unwrap();
+
+        //The same offset and size are allocated again, the old block must not give access to the new one.
+        let new = free_list.allocate(8, 1).unwrap();
+        assert_eq!((old.offset(), old.size()), (new.offset(), new.size()));
+        assert_ne!(old, new);
+        assert!(!free_list.is_valid(old));
+        match free_list.get(old) {
+            Err(AllocationError::StaleHandle(_)) => {},
+            other => panic!("Expected a StaleHandle error, got {:?}", other),
+        }
+        assert!(free_list.get_mut(old).is_err());
+        assert!(free_list.free(old).is_err());
+        assert!(free_list.is_valid(new));
+        free_list.free(new).unwrap();
+    }
+}
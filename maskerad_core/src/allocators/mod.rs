@@ -7,4 +7,8 @@
 
 pub mod stack_map;
 pub mod stacks;
+pub mod pools;
+pub mod free_lists;
+pub mod stats;
+pub mod frame_stacks;
 pub mod errors;
\ No newline at end of file
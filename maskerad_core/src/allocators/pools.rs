@@ -0,0 +1,346 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use allocators::errors::{AllocationError, AllocationResult};
+use allocators::stats::MemoryStats;
+
+/*
+MEMORY POOL.
+
+A pool of fixed-size blocks, all able to hold one T, allocated once at creation.
+Suited for objects created and destroyed often: components, particles, sound voices...
+
+Free blocks form a linked list (free list), allocating and freeing a block is O(1).
+
+Allocating returns a PoolHandle instead of a reference. Each block has a generation counter,
+incremented when the block is freed: a handle to a freed block, even if the block has been
+reused since, is detected as stale.
+
+The stats of a pool count its blocks in bytes, size_of::<T>() bytes per block.
+*/
+
+pub struct PoolHandle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> PoolHandle<T> {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Clone for PoolHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PoolHandle<T> {}
+
+impl<T> PartialEq for PoolHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for PoolHandle<T> {}
+
+impl<T> Hash for PoolHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for PoolHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PoolHandle [index: {}, generation: {}]", self.index, self.generation)
+    }
+}
+
+#[derive(Debug)]
+enum Block<T> {
+    Occupied(T),
+    //Index of the next free block.
+    Free(Option<u32>),
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    generation: u32,
+    block: Block<T>,
+}
+
+#[derive(Debug)]
+pub struct MemPool<T> {
+    slots: Vec<Slot<T>>,
+    free_head: Option<u32>,
+    len: usize,
+    //Highest number of blocks used since the creation.
+    high_water_mark: usize,
+    allocation_count: usize,
+    failed_allocations: usize,
+}
+
+impl<T> MemPool<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        debug!("Creating a MemPool of {} blocks of {} bytes.", capacity, ::std::mem::size_of::<T>());
+        assert!(capacity <= u32::MAX as usize, "A MemPool cannot hold more than u32::MAX blocks.");
+
+        let slots = (0..capacity)
+            .map(|index| {
+                let next = if index + 1 < capacity { Some(index as u32 + 1) } else { None };
+                Slot {
+                    generation: 0,
+                    block: Block::Free(next),
+                }
+            })
+            .collect();
+
+        MemPool {
+            slots,
+            free_head: if capacity > 0 { Some(0) } else { None },
+            len: 0,
+            high_water_mark: 0,
+            allocation_count: 0,
+            failed_allocations: 0,
+        }
+    }
+
+    pub fn allocate<F>(&mut self, op: F) -> AllocationResult<PoolHandle<T>> where
+        F: FnOnce() -> T
+    {
+        let index = match self.free_head {
+            Some(index) => index,
+            None => {
+                error!("The MemPool is exhausted ({} blocks used).", self.slots.len());
+                self.failed_allocations += 1;
+                return Err(AllocationError::PoolExhausted(format!(
+                    "All the {} blocks of the memory pool are used.",
+                    self.slots.len()
+                )));
+            },
+        };
+
+        trace!("Allocating the block {} of the MemPool.", index);
+        let next = match self.slots[index as usize].block {
+            Block::Free(next) => next,
+            Block::Occupied(_) => unreachable!("The free list of a MemPool points to an occupied block."),
+        };
+        //The block leaves the free list once op() returned: a panicking op() leaves the pool unchanged.
+        let value = op();
+        let slot = &mut self.slots[index as usize];
+        slot.block = Block::Occupied(value);
+        self.free_head = next;
+        self.len += 1;
+        self.allocation_count += 1;
+        if self.len > self.high_water_mark {
+            self.high_water_mark = self.len;
+        }
+
+        Ok(PoolHandle {
+            index,
+            generation: slot.generation,
+            _marker: PhantomData,
+        })
+    }
+
+    //Free the block pointed by the handle, returning its content.
+    pub fn free(&mut self, handle: PoolHandle<T>) -> AllocationResult<T> {
+        self.check(handle)?;
+        trace!("Freeing the block {} of the MemPool.", handle.index);
+
+        let slot = &mut self.slots[handle.index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        let block = ::std::mem::replace(&mut slot.block, Block::Free(self.free_head));
+        self.free_head = Some(handle.index);
+        self.len -= 1;
+
+        match block {
+            Block::Occupied(value) => Ok(value),
+            Block::Free(_) => unreachable!("A checked handle points to a free block."),
+        }
+    }
+
+    fn check(&self, handle: PoolHandle<T>) -> AllocationResult<()> {
+        if self.is_valid(handle) {
+            Ok(())
+        } else {
+            warn!("Stale handle used with a MemPool: {:?}.", handle);
+            Err(AllocationError::StaleHandle(format!(
+                "The block {} of generation {} has been freed.",
+                handle.index, handle.generation
+            )))
+        }
+    }
+
+    pub fn is_valid(&self, handle: PoolHandle<T>) -> bool {
+        match self.slots.get(handle.index as usize) {
+            Some(&Slot { generation, block: Block::Occupied(_) }) => generation == handle.generation,
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, handle: PoolHandle<T>) -> AllocationResult<&T> {
+        self.check(handle)?;
+        match self.slots[handle.index as usize].block {
+            Block::Occupied(ref value) => Ok(value),
+            Block::Free(_) => unreachable!("A checked handle points to a free block."),
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: PoolHandle<T>) -> AllocationResult<&mut T> {
+        self.check(handle)?;
+        match self.slots[handle.index as usize].block {
+            Block::Occupied(ref mut value) => Ok(value),
+            Block::Free(_) => unreachable!("A checked handle points to a free block."),
+        }
+    }
+
+    //Free every block. All the handles become stale.
+    pub fn clear(&mut self) {
+        debug!("Clearing the MemPool.");
+        let capacity = self.slots.len();
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if let Block::Occupied(_) = slot.block {
+                slot.generation = slot.generation.wrapping_add(1);
+            }
+            let next = if index + 1 < capacity { Some(index as u32 + 1) } else { None };
+            slot.block = Block::Free(next);
+        }
+        self.free_head = if capacity > 0 { Some(0) } else { None };
+        self.len = 0;
+    }
+
+    pub fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = (PoolHandle<T>, &'a T)> + 'a> {
+        Box::new(self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            match slot.block {
+                Block::Occupied(ref value) => Some((PoolHandle {
+                    index: index as u32,
+                    generation: slot.generation,
+                    _marker: PhantomData,
+                }, value)),
+                Block::Free(_) => None,
+            }
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.free_head.is_none()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        let block_size = ::std::mem::size_of::<T>();
+        MemoryStats::new(
+            self.len * block_size,
+            self.slots.len() * block_size,
+            self.high_water_mark * block_size,
+            self.allocation_count,
+            self.failed_allocations,
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod pools_test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Particle {
+        lifetime: u32,
+    }
+
+    #[test]
+    fn pool_allocate_get_free() {
+        let mut pool = MemPool::with_capacity(2);
+        assert!(pool.is_empty());
+        assert_eq!(pool.capacity(), 2);
+
+        let first = pool.allocate(|| Particle { lifetime: 10 }).unwrap();
+        let second = pool.allocate(|| Particle { lifetime: 20 }).unwrap();
+        assert!(pool.is_full());
+        assert_eq!(pool.get(first).unwrap().lifetime, 10);
+
+        pool.get_mut(second).unwrap().lifetime = 25;
+        assert_eq!(pool.free(second).unwrap(), Particle { lifetime: 25 });
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.iter().count(), 1);
+
+        let stats = pool.stats();
+        assert_eq!(stats.used(), 4);
+        assert_eq!(stats.capacity(), 8);
+        assert_eq!(stats.high_water_mark(), 8);
+        assert_eq!(stats.allocation_count(), 2);
+        assert_eq!(stats.failed_allocations(), 0);
+    }
+
+    #[test]
+    fn pool_exhausted() {
+        let mut pool = MemPool::with_capacity(1);
+        pool.allocate(|| 1u32).unwrap();
+        match pool.allocate(|| 2u32) {
+            Err(AllocationError::PoolExhausted(_)) => {},
+            other => panic!("Expected a PoolExhausted error, got {:?}", other),
+        }
+        assert_eq!(pool.stats().failed_allocations(), 1);
+
+        let mut empty_pool = MemPool::with_capacity(0);
+        assert!(empty_pool.allocate(|| 1u32).is_err());
+
+        //A panicking constructor doesn't take the block.
+        let mut pool = MemPool::with_capacity(1);
+        assert!(::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| pool.allocate(|| -> u32 { panic!("no value") }))).is_err());
+        assert!(pool.is_empty());
+        assert!(pool.allocate(|| 1u32).is_ok());
+    }
+
+    #[test]
+    fn pool_stale_handles() {
+        let mut pool = MemPool::with_capacity(1);
+        let old = pool.allocate(|| 1u32).unwrap();
+        pool.free(old).unwrap();
+
+        //The block is reused, but the old handle must not give access to the new value.
+        let new = pool.allocate(|| 2u32).unwrap();
+        assert_eq!(old.index(), new.index());
+        assert_ne!(old, new);
+        assert!(!pool.is_valid(old));
+        match pool.get(old) {
+            Err(AllocationError::StaleHandle(_)) => {},
+            other => panic!("Expected a StaleHandle error, got {:?}", other),
+        }
+        assert!(pool.free(old).is_err());
+        assert_eq!(*pool.get(new).unwrap(), 2);
+
+        pool.clear();
+        assert!(pool.get(new).is_err());
+        assert!(pool.is_empty());
+        assert!(pool.allocate(|| 3u32).is_ok());
+    }
+}
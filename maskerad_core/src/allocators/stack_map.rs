@@ -6,6 +6,7 @@
 // copied, modified, or distributed except according to those terms.
 
 use allocators::stacks::{MemStack, DoubleMemStack};
+use allocators::stats::MemoryReport;
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -22,11 +23,15 @@ impl MemStackMap {
         Default::default()
     }
 
+    //The stack is tagged with the key, which is used in its warnings and in the reports.
     pub fn insert_mem_stack<S, M>(&mut self, key: S, stack: M) -> Option<MemStack> where
         S: Into<String>,
         M: Into<MemStack>,
     {
-        self.0.insert(key.into(), stack.into())
+        let key = key.into();
+        let mut stack = stack.into();
+        stack.set_name(key.as_str());
+        self.0.insert(key, stack)
     }
 
     pub fn get_stack<S>(&self, key: S) -> Option<&MemStack> where
@@ -40,6 +45,28 @@ impl MemStackMap {
     {
         self.0.get_mut(key.as_ref())
     }
+
+    //Set the budget of the stack associated with the key. Returns false if there is no such stack.
+    pub fn set_budget<S, B>(&mut self, key: S, budget: B) -> bool where
+        S: AsRef<str>,
+        B: Into<Option<usize>>,
+    {
+        match self.0.get_mut(key.as_ref()) {
+            Some(stack) => {
+                stack.set_budget(budget);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn report(&self) -> MemoryReport {
+        let mut report = MemoryReport::new();
+        for (key, stack) in self.0.iter() {
+            report.add(key.as_str(), stack.stats());
+        }
+        report
+    }
 }
 
 #[derive(Debug)]
@@ -56,11 +83,15 @@ impl DoubleMemStackMap {
         Default::default()
     }
 
+    //The double stack is tagged with the key, which is used in its warnings and in the reports.
     pub fn insert_double_mem_stack<S, M>(&mut self, key: S, double_stack: M) -> Option<DoubleMemStack> where
         S: Into<String>,
         M: Into<DoubleMemStack>,
     {
-        self.0.insert(key.into(), double_stack.into())
+        let key = key.into();
+        let mut double_stack = double_stack.into();
+        double_stack.set_name(key.as_str());
+        self.0.insert(key, double_stack)
     }
 
     pub fn get_double_stack<S>(&self, key: S) -> Option<&DoubleMemStack> where
@@ -74,4 +105,54 @@ impl DoubleMemStackMap {
     {
         self.0.get_mut(key.as_ref())
     }
+
+    //Set the budget of the double stack associated with the key. Returns false if there is no such double stack.
+    pub fn set_budget<S, B>(&mut self, key: S, budget: B) -> bool where
+        S: AsRef<str>,
+        B: Into<Option<usize>>,
+    {
+        match self.0.get_mut(key.as_ref()) {
+            Some(double_stack) => {
+                double_stack.set_budget(budget);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn report(&self) -> MemoryReport {
+        let mut report = MemoryReport::new();
+        for (key, double_stack) in self.0.iter() {
+            report.add(key.as_str(), double_stack.stats());
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod stack_map_test {
+    use super::*;
+
+    #[test]
+    fn stack_map_report() {
+        let mut map = MemStackMap::new();
+        map.insert_mem_stack("level", MemStack::with_capacity(100, 100));
+        map.insert_mem_stack("frame", MemStack::with_capacity(100, 100));
+        assert_eq!(map.get_stack("frame").unwrap().name(), Some("frame"));
+
+        assert!(map.set_budget("frame", 4));
+        assert!(!map.set_budget("audio", 4));
+
+        map.get_stack("frame").unwrap().allocate(|| 1u64).unwrap();
+        let report = map.report();
+        assert_eq!(report.entries().len(), 2);
+        assert_eq!(report.entries()[0].0, "frame");
+        assert!(report.get("frame").unwrap().is_over_budget());
+        assert_eq!(report.get("level").unwrap().used(), 0);
+
+        let mut double_map = DoubleMemStackMap::new();
+        double_map.insert_double_mem_stack("particles", DoubleMemStack::with_capacity(100, 100));
+        assert_eq!(double_map.get_double_stack("particles").unwrap().name(), Some("particles"));
+        assert_eq!(double_map.report().entries().len(), 1);
+    }
 }
\ No newline at end of file
@@ -8,41 +8,136 @@
 use maskerad_memory_allocators::StackAllocator;
 use maskerad_memory_allocators::DoubleBufferedAllocator;
 use allocators::errors::{AllocationError, AllocationResult};
+use allocators::stats::MemoryStats;
+use std::cell::Cell;
 
+//Keep track of the statistics of a stack: the bytes used are read from the markers of the stack,
+//everything else is recorded after each allocation.
+#[derive(Debug, Default)]
+struct StatsTracker {
+    name: Option<String>,
+    high_water_mark: Cell<usize>,
+    allocation_count: Cell<usize>,
+    failed_allocations: Cell<usize>,
+    budget: Option<usize>,
+    over_budget: Cell<bool>,
+}
+
+impl StatsTracker {
+    fn record<T>(&self, result: AllocationResult<T>, used: usize) -> AllocationResult<T> {
+        match result {
+            Ok(allocated) => {
+                self.record_success(used);
+                Ok(allocated)
+            },
+            Err(error) => {
+                self.failed_allocations.set(self.failed_allocations.get() + 1);
+                Err(error)
+            },
+        }
+    }
+
+    fn record_success(&self, used: usize) {
+        self.allocation_count.set(self.allocation_count.get() + 1);
+        if used > self.high_water_mark.get() {
+            self.high_water_mark.set(used);
+        }
+
+        if let Some(budget) = self.budget {
+            //Only warn when the budget is crossed, not after each allocation.
+            if used > budget && !self.over_budget.get() {
+                warn!("The memory stack {} exceeded its budget: {} bytes used, {} bytes allowed.",
+                    self.name.as_deref().unwrap_or("(unnamed)"), used, budget);
+            }
+            self.over_budget.set(used > budget);
+        }
+    }
+
+    fn reset_counters(&self, used: usize) {
+        self.high_water_mark.set(used);
+        self.allocation_count.set(0);
+        self.failed_allocations.set(0);
+    }
+
+    fn stats(&self, used: usize, capacity: usize) -> MemoryStats {
+        MemoryStats::new(
+            used,
+            capacity,
+            self.high_water_mark.get(),
+            self.allocation_count.get(),
+            self.failed_allocations.get(),
+            self.budget,
+        )
+    }
+}
+
+//The third field is the number of MemStackScopes currently open on the stack.
 #[derive(Debug)]
-pub struct MemStack(StackAllocator);
+pub struct MemStack(StackAllocator, StatsTracker, Cell<usize>);
 
 impl MemStack {
     pub fn with_capacity(capacity: usize, capacity_copy: usize) -> Self {
-        MemStack(StackAllocator::with_capacity(capacity, capacity_copy))
+        MemStack(StackAllocator::with_capacity(capacity, capacity_copy), StatsTracker::default(), Cell::new(0))
+    }
+
+    //Open a scope: everything allocated through it is dropped, and the memory given back to the stack,
+    //when the scope is dropped. Scopes can be nested, and must be dropped in the reverse order of their creation.
+    //While a scope is open, allocate through the scope and not through the stack.
+    ///
+    /// The references given by a scope cannot outlive it:
+    ///
+    /// ```rust,compile_fail,E0597
+    ///
+    /// # use maskerad_core::allocators::stacks::MemStack;
+    ///
+    /// let stack = MemStack::with_capacity(100, 100);
+    /// let escaped = {
+    ///     let frame = stack.scope();
+    ///     frame.allocate(|| 42u64).unwrap()
+    /// };
+    ///
+    /// ```
+    pub fn scope<'b>(&'b self) -> MemStackScope<'b> {
+        MemStackScope::new(self)
+    }
+
+    //Number of scopes currently open on this stack.
+    pub fn scope_depth(&self) -> usize {
+        self.2.get()
     }
 
     pub fn allocate_mutable<T, F>(&self, op: F) -> AllocationResult<&mut T> where
         F: FnOnce() -> T
     {
-        self.0.alloc_mut(op).map_err(|maskerad_mem_error| {
+        let result = self.0.alloc_mut(op).map_err(|maskerad_mem_error| {
             AllocationError::from(maskerad_mem_error)
-        })
+        });
+        self.1.record(result, self.used())
     }
 
     pub fn allocate_mutable_unchecked<T, F>(&self, op: F) -> &mut T where
         F: FnOnce() -> T
     {
-        self.0.alloc_mut_unchecked(op)
+        let allocated = self.0.alloc_mut_unchecked(op);
+        self.1.record_success(self.used());
+        allocated
     }
 
     pub fn allocate<T, F>(&self, op: F) -> AllocationResult<&T> where
         F: FnOnce() -> T
     {
-        self.0.alloc(op).map_err(|maskerad_mem_error| {
+        let result = self.0.alloc(op).map_err(|maskerad_mem_error| {
             AllocationError::from(maskerad_mem_error)
-        })
+        });
+        self.1.record(result, self.used())
     }
 
     pub fn allocate_unchecked<T, F>(&self, op: F) -> &T where
         F: FnOnce() -> T
     {
-        self.0.alloc_unchecked(op)
+        let allocated = self.0.alloc_unchecked(op);
+        self.1.record_success(self.used());
+        allocated
     }
 
     pub fn marker(&self) -> usize {
@@ -76,42 +171,159 @@ impl MemStack {
     pub fn capacity_copy(&self) -> usize {
         self.0.capacity_copy()
     }
+
+    //Bytes used in both storages.
+    pub fn used(&self) -> usize {
+        self.0.marker() + self.0.marker_copy()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.1.name.as_deref()
+    }
+
+    pub fn set_name<S>(&mut self, name: S) where
+        S: Into<String>
+    {
+        self.1.name = Some(name.into());
+    }
+
+    //Warn when more than budget bytes are used in both storages. None removes the budget.
+    pub fn set_budget<B>(&mut self, budget: B) where
+        B: Into<Option<usize>>
+    {
+        self.1.budget = budget.into();
+        self.1.over_budget.set(false);
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        self.1.stats(self.used(), self.capacity() + self.capacity_copy())
+    }
+
+    //Set the allocation counters to 0, and the high-water mark to the bytes currently used.
+    pub fn reset_stats(&self) {
+        self.1.reset_counters(self.used())
+    }
 }
 
+//Captures the markers of a MemStack on creation, and resets the stack to them when dropped.
 #[derive(Debug)]
-pub struct DoubleMemStack(DoubleBufferedAllocator);
+pub struct MemStackScope<'a> {
+    stack: &'a MemStack,
+    marker: usize,
+    marker_copy: usize,
+    depth: usize,
+}
+
+impl<'a> MemStackScope<'a> {
+    fn new(stack: &'a MemStack) -> Self {
+        let depth = stack.2.get() + 1;
+        stack.2.set(depth);
+        trace!("Opening a MemStackScope of depth {}.", depth);
+
+        MemStackScope {
+            stack,
+            marker: stack.marker(),
+            marker_copy: stack.marker_copy(),
+            depth,
+        }
+    }
+
+    //Open a nested scope. It borrows this scope, so it must be dropped first.
+    pub fn scope<'b>(&'b self) -> MemStackScope<'b> {
+        MemStackScope::new(self.stack)
+    }
+
+    pub fn allocate_mutable<T, F>(&self, op: F) -> AllocationResult<&mut T> where
+        F: FnOnce() -> T
+    {
+        self.stack.allocate_mutable(op)
+    }
+
+    pub fn allocate_mutable_unchecked<T, F>(&self, op: F) -> &mut T where
+        F: FnOnce() -> T
+    {
+        self.stack.allocate_mutable_unchecked(op)
+    }
+
+    pub fn allocate<T, F>(&self, op: F) -> AllocationResult<&T> where
+        F: FnOnce() -> T
+    {
+        self.stack.allocate(op)
+    }
+
+    pub fn allocate_unchecked<T, F>(&self, op: F) -> &T where
+        F: FnOnce() -> T
+    {
+        self.stack.allocate_unchecked(op)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    //Bytes allocated since the scope has been opened, in both storages. 0 if the stack has been reset below the scope.
+    pub fn used(&self) -> usize {
+        self.stack.marker().saturating_sub(self.marker) + self.stack.marker_copy().saturating_sub(self.marker_copy)
+    }
+}
+
+impl<'a> Drop for MemStackScope<'a> {
+    fn drop(&mut self) {
+        trace!("Closing a MemStackScope of depth {}.", self.depth);
+        let open_scopes = self.stack.2.get();
+        debug_assert_eq!(open_scopes, self.depth, "MemStackScopes must be dropped in the reverse order of their creation.");
+
+        //Never move the top of the stack up: that would happen if an outer scope was dropped first.
+        if self.marker <= self.stack.marker() && self.marker_copy <= self.stack.marker_copy() {
+            self.stack.reset_to_marker(self.marker);
+            self.stack.reset_to_marker_copy(self.marker_copy);
+        } else {
+            error!("A MemStackScope of depth {} has been dropped after one of its parent scopes.", self.depth);
+        }
+        self.stack.2.set(self.depth - 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct DoubleMemStack(DoubleBufferedAllocator, StatsTracker);
 
 impl DoubleMemStack {
     pub fn with_capacity(capacity: usize, capacity_copy: usize) -> Self {
-        DoubleMemStack(DoubleBufferedAllocator::with_capacity(capacity, capacity_copy))
+        DoubleMemStack(DoubleBufferedAllocator::with_capacity(capacity, capacity_copy), StatsTracker::default())
     }
 
     pub fn allocate_mutable<T, F>(&self, op: F) -> AllocationResult<&mut T> where
         F: FnOnce() -> T
     {
-        self.0.alloc_mut(op).map_err(|maskerad_mem_error| {
+        let result = self.0.alloc_mut(op).map_err(|maskerad_mem_error| {
             AllocationError::from(maskerad_mem_error)
-        })
+        });
+        self.1.record(result, self.used())
     }
 
     pub fn allocate_mutable_unchecked<T, F>(&self, op: F) -> &mut T where
         F: FnOnce() -> T
     {
-        self.0.alloc_mut_unchecked(op)
+        let allocated = self.0.alloc_mut_unchecked(op);
+        self.1.record_success(self.used());
+        allocated
     }
 
     pub fn allocate<T, F>(&self, op: F) -> AllocationResult<&T> where
         F: FnOnce() -> T
     {
-        self.0.alloc(op).map_err(|maskerad_mem_error| {
+        let result = self.0.alloc(op).map_err(|maskerad_mem_error| {
             AllocationError::from(maskerad_mem_error)
-        })
+        });
+        self.1.record(result, self.used())
     }
 
     pub fn allocate_unchecked<T, F>(&self, op: F) -> &T where
         F: FnOnce() -> T
     {
-        self.0.alloc_unchecked(op)
+        let allocated = self.0.alloc_unchecked(op);
+        self.1.record_success(self.used());
+        allocated
     }
 
     pub fn reset(&self) {
@@ -149,4 +361,143 @@ impl DoubleMemStack {
     pub fn capacity_copy(&self) -> usize {
         self.0.capacity_copy()
     }
-}
\ No newline at end of file
+
+    //Bytes used in both storages of the current buffer.
+    pub fn used(&self) -> usize {
+        self.0.marker() + self.0.marker_copy()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.1.name.as_deref()
+    }
+
+    pub fn set_name<S>(&mut self, name: S) where
+        S: Into<String>
+    {
+        self.1.name = Some(name.into());
+    }
+
+    //Warn when more than budget bytes are used in both storages of the current buffer. None removes the budget.
+    pub fn set_budget<B>(&mut self, budget: B) where
+        B: Into<Option<usize>>
+    {
+        self.1.budget = budget.into();
+        self.1.over_budget.set(false);
+    }
+
+    //The bytes used and the capacity are the ones of the current buffer.
+    pub fn stats(&self) -> MemoryStats {
+        self.1.stats(self.used(), self.capacity() + self.capacity_copy())
+    }
+
+    //Set the allocation counters to 0, and the high-water mark to the bytes currently used.
+    pub fn reset_stats(&self) {
+        self.1.reset_counters(self.used())
+    }
+}
+
+#[cfg(test)]
+mod stacks_test {
+    use super::*;
+
+    struct Monster {
+        _hp: u32,
+        _name: String,
+    }
+
+    #[test]
+    fn mem_stack_stats() {
+        let stack = MemStack::with_capacity(1000, 1000);
+        assert_eq!(stack.stats().used(), 0);
+        assert_eq!(stack.stats().capacity(), 2000);
+
+        let marker = stack.marker();
+        stack.allocate(|| Monster { _hp: 10, _name: String::from("orc") }).unwrap();
+        stack.allocate(|| 42u64).unwrap();
+        let peak = stack.used();
+        assert!(peak > 0);
+
+        stack.reset_to_marker(marker);
+        stack.reset_copy();
+        let stats = stack.stats();
+        assert_eq!(stats.used(), 0);
+        assert_eq!(stats.high_water_mark(), peak);
+        assert_eq!(stats.allocation_count(), 2);
+        assert_eq!(stats.failed_allocations(), 0);
+
+        assert!(stack.allocate(|| [0u8; 2000]).is_err());
+        assert_eq!(stack.stats().failed_allocations(), 1);
+
+        stack.reset_stats();
+        assert_eq!(stack.stats().allocation_count(), 0);
+        assert_eq!(stack.stats().high_water_mark(), 0);
+    }
+
+    #[test]
+    fn mem_stack_budget() {
+        let mut stack = MemStack::with_capacity(1000, 1000);
+        stack.set_name("frame");
+        stack.set_budget(16);
+        assert_eq!(stack.name(), Some("frame"));
+
+        stack.allocate(|| 1u64).unwrap();
+        assert!(!stack.stats().is_over_budget());
+        stack.allocate(|| [1u64; 4]).unwrap();
+        assert!(stack.stats().is_over_budget());
+
+        stack.set_budget(None);
+        assert!(!stack.stats().is_over_budget());
+    }
+
+    #[test]
+    fn mem_stack_scopes() {
+        let stack = MemStack::with_capacity(1000, 1000);
+        stack.allocate(|| 1u32).unwrap();
+        let base = stack.used();
+        {
+            let outer = stack.scope();
+            outer.allocate(|| Monster { _hp: 10, _name: String::from("orc") }).unwrap();
+            let after_outer = stack.used();
+            {
+                let inner = outer.scope();
+                assert_eq!(stack.scope_depth(), 2);
+                inner.allocate(|| [0u64; 8]).unwrap();
+                assert!(inner.used() > 0);
+            }
+            assert_eq!(stack.used(), after_outer);
+            assert_eq!(stack.scope_depth(), 1);
+        }
+        assert_eq!(stack.used(), base);
+        assert_eq!(stack.scope_depth(), 0);
+
+        //A reset of the stack under an open scope doesn't underflow its size.
+        let scope = stack.scope();
+        scope.allocate(|| 1u64).unwrap();
+        stack.reset();
+        stack.reset_copy();
+        assert_eq!(scope.used(), 0);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn mem_stack_scopes_out_of_order() {
+        let stack = MemStack::with_capacity(1000, 1000);
+        let first = stack.scope();
+        let second = stack.scope();
+        second.allocate(|| 1u32).unwrap();
+        drop(first);
+        drop(second);
+    }
+
+    #[test]
+    fn double_mem_stack_stats() {
+        let mut stack = DoubleMemStack::with_capacity(100, 100);
+        stack.allocate(|| 1u32).unwrap();
+        let used = stack.used();
+        stack.swap_buffers();
+        assert_eq!(stack.stats().used(), 0);
+        assert_eq!(stack.stats().high_water_mark(), used);
+        assert_eq!(stack.stats().allocation_count(), 1);
+    }
+}
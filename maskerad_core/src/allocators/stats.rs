@@ -0,0 +1,189 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+
+/*
+ALLOCATOR STATISTICS.
+
+A MemoryStats is a snapshot of the state of an allocator: the bytes used (both storages of a
+memory stack), the highest number of bytes ever used, the number of allocations, the number of
+failed allocations and the budget.
+
+A MemoryReport gathers the stats of several named allocators. It can be printed to the logs or
+displayed by the debugging overlay every frame.
+*/
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct MemoryStats {
+    used: usize,
+    capacity: usize,
+    high_water_mark: usize,
+    allocation_count: usize,
+    failed_allocations: usize,
+    budget: Option<usize>,
+}
+
+impl MemoryStats {
+    pub fn new(used: usize, capacity: usize, high_water_mark: usize, allocation_count: usize, failed_allocations: usize, budget: Option<usize>) -> Self {
+        MemoryStats {
+            used,
+            capacity,
+            high_water_mark,
+            allocation_count,
+            failed_allocations,
+            budget,
+        }
+    }
+
+    //Bytes currently used.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    //Bytes available at creation.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    //Highest number of bytes used since the creation, or the last counters reset.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    pub fn allocation_count(&self) -> usize {
+        self.allocation_count
+    }
+
+    pub fn failed_allocations(&self) -> usize {
+        self.failed_allocations
+    }
+
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    pub fn is_over_budget(&self) -> bool {
+        match self.budget {
+            Some(budget) => self.used > budget,
+            None => false,
+        }
+    }
+
+    //Used bytes / capacity, between 0.0 and 1.0.
+    pub fn usage(&self) -> f64 {
+        if self.capacity == 0 {
+            0.0
+        } else {
+            self.used as f64 / self.capacity as f64
+        }
+    }
+}
+
+impl fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} bytes ({:.1}%), peak: {} bytes, allocations: {}, failed: {}",
+            self.used, self.capacity, self.usage() * 100.0, self.high_water_mark, self.allocation_count, self.failed_allocations)?;
+        if let Some(budget) = self.budget {
+            write!(f, ", budget: {} bytes", budget)?;
+            if self.is_over_budget() {
+                write!(f, " (EXCEEDED)")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryReport(Vec<(String, MemoryStats)>);
+
+impl MemoryReport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add<S>(&mut self, name: S, stats: MemoryStats) where
+        S: Into<String>
+    {
+        self.0.push((name.into(), stats));
+        self.0.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    pub fn get<S>(&self, name: S) -> Option<&MemoryStats> where
+        S: AsRef<str>
+    {
+        self.0.iter()
+            .find(|entry| entry.0 == name.as_ref())
+            .map(|entry| &entry.1)
+    }
+
+    //The entries, sorted by name.
+    pub fn entries(&self) -> &[(String, MemoryStats)] {
+        self.0.as_slice()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn total_used(&self) -> usize {
+        self.0.iter().map(|entry| entry.1.used()).sum()
+    }
+
+    pub fn total_capacity(&self) -> usize {
+        self.0.iter().map(|entry| entry.1.capacity()).sum()
+    }
+
+    //Print the report with the debug log level, and the allocators over budget with the warn level.
+    pub fn log(&self) {
+        for (name, stats) in self.0.iter() {
+            if stats.is_over_budget() {
+                warn!("{}: {}", name, stats);
+            } else {
+                debug!("{}: {}", name, stats);
+            }
+        }
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, stats) in self.0.iter() {
+            writeln!(f, "{}: {}", name, stats)?;
+        }
+        write!(f, "total: {}/{} bytes", self.total_used(), self.total_capacity())
+    }
+}
+
+#[cfg(test)]
+mod stats_test {
+    use super::*;
+
+    #[test]
+    fn memory_stats_budget() {
+        let stats = MemoryStats::new(60, 100, 80, 3, 1, Some(50));
+        assert!(stats.is_over_budget());
+        assert_eq!(stats.usage(), 0.6);
+
+        let stats = MemoryStats::new(60, 100, 80, 3, 1, None);
+        assert!(!stats.is_over_budget());
+        assert_eq!(MemoryStats::default().usage(), 0.0);
+    }
+
+    #[test]
+    fn memory_report_sorted_by_name() {
+        let mut report = MemoryReport::new();
+        report.add("level", MemoryStats::new(10, 100, 10, 1, 0, None));
+        report.add("frame", MemoryStats::new(20, 100, 30, 2, 0, None));
+
+        assert_eq!(report.entries()[0].0, "frame");
+        assert_eq!(report.get("level").unwrap().used(), 10);
+        assert_eq!(report.total_used(), 30);
+        assert_eq!(report.total_capacity(), 200);
+        assert!(format!("{}", report).ends_with("total: 30/200 bytes"));
+    }
+}
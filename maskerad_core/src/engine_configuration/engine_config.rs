@@ -9,11 +9,14 @@ use std::io::{Read, Write};
 use std::path::PathBuf;
 use toml;
 use engine_configuration::engine_config_error::{EngineConfigError, EngineConfigResult};
+use engine_configuration::log_config::LogConfig;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EngineConfig {
     locale: String,
     script: Option<String>,
+    #[serde(default)]
+    logging: LogConfig,
 }
 
 impl Default for EngineConfig {
@@ -21,6 +24,7 @@ impl Default for EngineConfig {
         EngineConfig {
             locale: String::from("EN"),
             script: None,
+            logging: LogConfig::default(),
         }
     }
 }
@@ -33,6 +37,7 @@ impl EngineConfig {
         EngineConfig {
             locale: locale.into(),
             script: script_path.into(),
+            logging: LogConfig::default(),
         }
     }
 
@@ -69,6 +74,14 @@ impl EngineConfig {
         }
     }
 
+    pub fn logging(&self) -> &LogConfig {
+        &self.logging
+    }
+
+    pub fn logging_mut(&mut self) -> &mut LogConfig {
+        &mut self.logging
+    }
+
     pub fn set_locale<S>(&mut self, locale: S) where
         S: Into<String>
     {
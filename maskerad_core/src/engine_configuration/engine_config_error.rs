@@ -18,9 +18,6 @@ pub enum EngineConfigError {
     TomlDeserError(String, TomlDeserError),
 }
 
-unsafe impl Send for EngineConfigError {}
-unsafe impl Sync for EngineConfigError {}
-
 impl fmt::Display for EngineConfigError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -38,21 +35,7 @@ impl fmt::Display for EngineConfigError {
 }
 
 impl Error for EngineConfigError {
-    fn description(&self) -> &str {
-        match self {
-            &EngineConfigError::IOError(_, _) => {
-                "I/O error"
-            },
-            &EngineConfigError::TomlSerError(_, _) => {
-                "TomlSerError"
-            },
-            &EngineConfigError::TomlDeserError(_, _) => {
-                "TomlDeserError"
-            },
-        }
-    }
-
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             &EngineConfigError::IOError(_, ref io_error) => {
                 Some(io_error)
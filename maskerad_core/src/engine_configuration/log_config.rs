@@ -0,0 +1,122 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+
+//The [logging] section of the engine configuration.
+//
+//[logging]
+//level = "info"
+//console = true
+//file = true
+//max_file_size = 1048576
+//max_files = 5
+//ring_buffer_capacity = 512
+//
+//[logging.modules]
+//"maskerad_core::filesystem" = "trace"
+//"maskerad_core::jobs" = "off"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    level: String,
+    console: bool,
+    file: bool,
+    max_file_size: u64,
+    max_files: usize,
+    ring_buffer_capacity: usize,
+    modules: BTreeMap<String, String>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: String::from("info"),
+            console: true,
+            file: true,
+            max_file_size: 1024 * 1024,
+            max_files: 5,
+            ring_buffer_capacity: 512,
+            modules: BTreeMap::new(),
+        }
+    }
+}
+
+impl LogConfig {
+    //The level of the modules without a level of their own.
+    pub fn level(&self) -> &str {
+        self.level.as_str()
+    }
+
+    pub fn console(&self) -> bool {
+        self.console
+    }
+
+    pub fn file(&self) -> bool {
+        self.file
+    }
+
+    //The size, in bytes, from which the log file is rotated.
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size
+    }
+
+    //The number of rotated log files kept.
+    pub fn max_files(&self) -> usize {
+        self.max_files
+    }
+
+    //The number of records kept in memory, for the debug console.
+    pub fn ring_buffer_capacity(&self) -> usize {
+        self.ring_buffer_capacity
+    }
+
+    //The level of each module, by module path.
+    pub fn modules(&self) -> &BTreeMap<String, String> {
+        &self.modules
+    }
+
+    pub fn set_level<S>(&mut self, level: S) -> &mut Self where
+        S: Into<String>
+    {
+        self.level = level.into();
+        self
+    }
+
+    pub fn set_console(&mut self, console: bool) -> &mut Self {
+        self.console = console;
+        self
+    }
+
+    pub fn set_file(&mut self, file: bool) -> &mut Self {
+        self.file = file;
+        self
+    }
+
+    pub fn set_max_file_size(&mut self, max_file_size: u64) -> &mut Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    pub fn set_max_files(&mut self, max_files: usize) -> &mut Self {
+        self.max_files = max_files;
+        self
+    }
+
+    pub fn set_ring_buffer_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.ring_buffer_capacity = capacity;
+        self
+    }
+
+    pub fn set_module_level<M, L>(&mut self, module: M, level: L) -> &mut Self where
+        M: Into<String>,
+        L: Into<String>,
+    {
+        self.modules.insert(module.into(), level.into());
+        self
+    }
+}
@@ -6,4 +6,5 @@
 // copied, modified, or distributed except according to those terms.
 
 pub mod engine_config;
-pub mod engine_config_error;
\ No newline at end of file
+pub mod engine_config_error;
+pub mod log_config;
\ No newline at end of file
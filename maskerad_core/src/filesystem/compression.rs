@@ -0,0 +1,571 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+use zstd::stream::read::Decoder as ZstdDecoder;
+use zstd::stream::write::Encoder as ZstdEncoder;
+use flate2::Compression;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+
+/*
+COMPRESSION.
+
+Stream adapters compressing/decompressing data on the fly, on top of any reader/writer.
+
+The codec is chosen from the last extension of a path ("untitled.tga.lz4" is an LZ4 stream
+containing a TGA file), or explicitly.
+
+Decompression is streamed: no intermediate buffer holding the whole file is needed.
+A CompressedReader can seek if the underlying reader can seek, which is needed by decoders like
+imagefmt. Seeking forward decompresses and discards data, seeking backward restarts the
+decompression from the start of the compressed stream: the position of the underlying reader when the
+CompressedReader was created (a compressed file in a pack starts after the header of the pack).
+
+A CompressedWriter writes the end of the compressed stream when dropped. Use finish() to get
+the errors, if any, and the underlying writer back.
+*/
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Codec {
+    Plain,
+    Lz4,
+    Zstd,
+    Deflate,
+    Gzip,
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Codec::Plain => {
+                write!(f, "no compression")
+            },
+            Codec::Lz4 => {
+                write!(f, "LZ4")
+            },
+            Codec::Zstd => {
+                write!(f, "Zstandard")
+            },
+            Codec::Deflate => {
+                write!(f, "Deflate")
+            },
+            Codec::Gzip => {
+                write!(f, "Gzip")
+            },
+        }
+    }
+}
+
+impl Codec {
+    pub fn from_extension<S>(extension: S) -> Codec where
+        S: AsRef<str>
+    {
+        match extension.as_ref().to_lowercase().as_str() {
+            "lz4" => Codec::Lz4,
+            "zst" | "zstd" => Codec::Zstd,
+            "deflate" => Codec::Deflate,
+            "gz" => Codec::Gzip,
+            _ => Codec::Plain,
+        }
+    }
+
+    //Find the codec from the last extension of the path.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Codec {
+        match path.as_ref().extension() {
+            Some(extension) => Codec::from_extension(extension.to_string_lossy()),
+            None => Codec::Plain,
+        }
+    }
+
+    pub fn extension(&self) -> Option<&'static str> {
+        match *self {
+            Codec::Plain => None,
+            Codec::Lz4 => Some("lz4"),
+            Codec::Zstd => Some("zst"),
+            Codec::Deflate => Some("deflate"),
+            Codec::Gzip => Some("gz"),
+        }
+    }
+
+    //Remove the compression extension of the path, if any: "untitled.tga.lz4" -> "untitled.tga".
+    pub fn strip_extension<P: AsRef<Path>>(path: P) -> PathBuf {
+        match Codec::from_path(path.as_ref()) {
+            Codec::Plain => path.as_ref().to_path_buf(),
+            _ => path.as_ref().with_extension(""),
+        }
+    }
+}
+
+fn lz4_error(error: ::lz4_flex::frame::Error) -> io::Error {
+    io::Error::other(error)
+}
+
+enum Decoder<R: Read> {
+    Plain(R),
+    Lz4(FrameDecoder<R>),
+    Zstd(ZstdDecoder<'static, BufReader<R>>),
+    Deflate(DeflateDecoder<R>),
+    Gzip(GzDecoder<R>),
+}
+
+impl<R: Read> Decoder<R> {
+    fn new(reader: R, codec: Codec) -> io::Result<Self> {
+        let decoder = match codec {
+            Codec::Plain => Decoder::Plain(reader),
+            Codec::Lz4 => Decoder::Lz4(FrameDecoder::new(reader)),
+            Codec::Zstd => Decoder::Zstd(ZstdDecoder::new(reader)?),
+            Codec::Deflate => Decoder::Deflate(DeflateDecoder::new(reader)),
+            Codec::Gzip => Decoder::Gzip(GzDecoder::new(reader)),
+        };
+        Ok(decoder)
+    }
+
+    fn into_inner(self) -> R {
+        match self {
+            Decoder::Plain(reader) => reader,
+            Decoder::Lz4(decoder) => decoder.into_inner(),
+            Decoder::Zstd(decoder) => decoder.finish().into_inner(),
+            Decoder::Deflate(decoder) => decoder.into_inner(),
+            Decoder::Gzip(decoder) => decoder.into_inner(),
+        }
+    }
+}
+
+impl<R: Read + Seek> Decoder<R> {
+    //Seek the underlying reader. The data buffered by the decoder isn't discarded: the decoder must be recreated.
+    fn seek_reader(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match *self {
+            Decoder::Plain(ref mut reader) => reader.seek(pos),
+            Decoder::Lz4(ref mut decoder) => decoder.get_mut().seek(pos),
+            Decoder::Zstd(ref mut decoder) => decoder.get_mut().seek(pos),
+            Decoder::Deflate(ref mut decoder) => decoder.get_mut().seek(pos),
+            Decoder::Gzip(ref mut decoder) => decoder.get_mut().seek(pos),
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Decoder::Plain(ref mut reader) => reader.read(buf),
+            Decoder::Lz4(ref mut decoder) => decoder.read(buf),
+            Decoder::Zstd(ref mut decoder) => decoder.read(buf),
+            Decoder::Deflate(ref mut decoder) => decoder.read(buf),
+            Decoder::Gzip(ref mut decoder) => decoder.read(buf),
+        }
+    }
+}
+
+pub struct CompressedReader<R: Read> {
+    codec: Codec,
+    //None if the decoder couldn't be recreated after a seek.
+    decoder: Option<Decoder<R>>,
+    //Position of the compressed stream in the underlying reader.
+    start: u64,
+    //Position in the decompressed stream.
+    position: u64,
+}
+
+impl<R: Read> fmt::Debug for CompressedReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CompressedReader [codec: {}, position: {}]", self.codec, self.position)
+    }
+}
+
+fn lost_decoder_error() -> io::Error {
+    io::Error::other("The decoder of the CompressedReader couldn't be recreated after a seek")
+}
+
+impl<R: Read + Seek> CompressedReader<R> {
+    //The compressed stream starts at the current position of the reader.
+    pub fn new(mut reader: R, codec: Codec) -> io::Result<Self> {
+        let start = reader.stream_position()?;
+        debug!("Creating a CompressedReader decompressing {}, from the position {}.", codec, start);
+        Ok(CompressedReader {
+            codec,
+            decoder: Some(Decoder::new(reader, codec)?),
+            start,
+            position: 0,
+        })
+    }
+}
+
+impl<R: Read> CompressedReader<R> {
+    //Decompress a reader which can't seek: the CompressedReader can't seek either.
+    pub fn from_stream(reader: R, codec: Codec) -> io::Result<Self> {
+        debug!("Creating a CompressedReader decompressing {} from a stream.", codec);
+        Ok(CompressedReader {
+            codec,
+            decoder: Some(Decoder::new(reader, codec)?),
+            start: 0,
+            position: 0,
+        })
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    //An error if the underlying reader has been lost by a seek which couldn't recreate the decoder.
+    pub fn into_inner(mut self) -> io::Result<R> {
+        self.decoder.take().map(Decoder::into_inner).ok_or_else(lost_decoder_error)
+    }
+
+    fn decoder(&mut self) -> io::Result<&mut Decoder<R>> {
+        self.decoder.as_mut().ok_or_else(lost_decoder_error)
+    }
+
+    //Decompress and discard bytes until the given position is reached.
+    fn skip_to(&mut self, target: u64) -> io::Result<u64> {
+        let to_skip = target - self.position;
+        let skipped = io::copy(&mut self.by_ref().take(to_skip), &mut io::sink())?;
+        if skipped < to_skip {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Tried to seek past the end of a compressed stream"));
+        }
+        Ok(self.position)
+    }
+}
+
+impl<R: Read + Seek> CompressedReader<R> {
+    //Start decompressing from the start of the compressed stream again. If the underlying reader can't seek,
+    //the decoder is kept at its position.
+    fn rewind_decoder(&mut self) -> io::Result<()> {
+        trace!("Rewinding the {} decoder.", self.codec);
+        let start = self.start;
+        self.decoder()?.seek_reader(SeekFrom::Start(start))?;
+        let reader = self.decoder.take().ok_or_else(lost_decoder_error)?.into_inner();
+        self.position = 0;
+        self.decoder = Some(Decoder::new(reader, self.codec)?);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for CompressedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.decoder()?.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for CompressedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if let Some(Decoder::Plain(ref mut reader)) = self.decoder {
+            let pos = match pos {
+                SeekFrom::Start(offset) => SeekFrom::Start(self.start.checked_add(offset).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Tried to seek past the end of a stream")
+                })?),
+                pos => pos,
+            };
+            let position = reader.seek(pos)?;
+            if position < self.start {
+                reader.seek(SeekFrom::Start(self.start + self.position))?;
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Tried to seek before the start of a stream"));
+            }
+            self.position = position - self.start;
+            return Ok(self.position);
+        }
+
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => {
+                //The size of the decompressed data is only known once everything has been read.
+                io::copy(self, &mut io::sink())?;
+                self.position.checked_add_signed(offset)
+            },
+        };
+
+        let target = match target {
+            Some(target) => target,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Tried to seek before the start of a compressed stream")),
+        };
+
+        if target < self.position {
+            self.rewind_decoder()?;
+        }
+        self.skip_to(target)
+    }
+}
+
+enum Encoder<W: Write> {
+    Plain(W),
+    Lz4(FrameEncoder<W>),
+    Zstd(ZstdEncoder<'static, W>),
+    Deflate(DeflateEncoder<W>),
+    Gzip(GzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(writer: W, codec: Codec) -> io::Result<Self> {
+        let encoder = match codec {
+            Codec::Plain => Encoder::Plain(writer),
+            Codec::Lz4 => Encoder::Lz4(FrameEncoder::new(writer)),
+            Codec::Zstd => Encoder::Zstd(ZstdEncoder::new(writer, 0)?),
+            Codec::Deflate => Encoder::Deflate(DeflateEncoder::new(writer, Compression::default())),
+            Codec::Gzip => Encoder::Gzip(GzEncoder::new(writer, Compression::default())),
+        };
+        Ok(encoder)
+    }
+
+    fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Plain(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            },
+            Encoder::Lz4(encoder) => encoder.finish().map_err(lz4_error),
+            Encoder::Zstd(encoder) => encoder.finish(),
+            Encoder::Deflate(encoder) => encoder.finish(),
+            Encoder::Gzip(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Encoder::Plain(ref mut writer) => writer.write(buf),
+            Encoder::Lz4(ref mut encoder) => encoder.write(buf),
+            Encoder::Zstd(ref mut encoder) => encoder.write(buf),
+            Encoder::Deflate(ref mut encoder) => encoder.write(buf),
+            Encoder::Gzip(ref mut encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Encoder::Plain(ref mut writer) => writer.flush(),
+            Encoder::Lz4(ref mut encoder) => encoder.flush(),
+            Encoder::Zstd(ref mut encoder) => encoder.flush(),
+            Encoder::Deflate(ref mut encoder) => encoder.flush(),
+            Encoder::Gzip(ref mut encoder) => encoder.flush(),
+        }
+    }
+}
+
+pub struct CompressedWriter<W: Write> {
+    codec: Codec,
+    encoder: Option<Encoder<W>>,
+}
+
+impl<W: Write> fmt::Debug for CompressedWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CompressedWriter [codec: {}]", self.codec)
+    }
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn new(writer: W, codec: Codec) -> io::Result<Self> {
+        debug!("Creating a CompressedWriter compressing with {}.", codec);
+        Ok(CompressedWriter {
+            codec,
+            encoder: Some(Encoder::new(writer, codec)?),
+        })
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    //Write the end of the compressed stream and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        debug!("Finishing the {} stream of a CompressedWriter.", self.codec);
+        self.encoder.take().expect("The encoder of a CompressedWriter is always set").finish()
+    }
+
+    fn encoder(&mut self) -> &mut Encoder<W> {
+        self.encoder.as_mut().expect("The encoder of a CompressedWriter is always set")
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder().flush()
+    }
+}
+
+impl<W: Write> Drop for CompressedWriter<W> {
+    fn drop(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            if let Err(error) = encoder.finish() {
+                error!("Could not finish the {} stream of a dropped CompressedWriter: {}", self.codec, error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod compression_test {
+    use super::*;
+    use std::io::Cursor;
+
+    const CODECS: [Codec; 5] = [Codec::Plain, Codec::Lz4, Codec::Zstd, Codec::Deflate, Codec::Gzip];
+
+    fn sample_data() -> Vec<u8> {
+        (0..10000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn compress(codec: Codec, data: &[u8]) -> Vec<u8> {
+        let mut writer = CompressedWriter::new(Vec::new(), codec).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn compression_codec_from_path() {
+        assert_eq!(Codec::from_path("textures/untitled.tga.lz4"), Codec::Lz4);
+        assert_eq!(Codec::from_path("saves/slot_1.zst"), Codec::Zstd);
+        assert_eq!(Codec::from_path("buffers/untitled.bin.deflate"), Codec::Deflate);
+        assert_eq!(Codec::from_path("logs/maskerad.log.GZ"), Codec::Gzip);
+        assert_eq!(Codec::from_path("textures/untitled.tga"), Codec::Plain);
+        assert_eq!(Codec::strip_extension("textures/untitled.tga.lz4"), PathBuf::from("textures/untitled.tga"));
+        assert_eq!(Codec::strip_extension("textures/untitled.tga"), PathBuf::from("textures/untitled.tga"));
+    }
+
+    #[test]
+    fn compression_round_trip() {
+        let data = sample_data();
+        for codec in CODECS.iter() {
+            let compressed = compress(*codec, data.as_slice());
+            if *codec != Codec::Plain {
+                assert!(compressed.len() < data.len());
+            }
+
+            let mut reader = CompressedReader::new(Cursor::new(compressed), *codec).unwrap();
+            let mut decompressed = Vec::new();
+            reader.read_to_end(&mut decompressed).unwrap();
+            assert_eq!(decompressed, data);
+        }
+    }
+
+    #[test]
+    fn compression_writer_finishes_on_drop() {
+        let data = sample_data();
+        let mut compressed = Vec::new();
+        {
+            let mut writer = CompressedWriter::new(&mut compressed, Codec::Lz4).unwrap();
+            writer.write_all(data.as_slice()).unwrap();
+        }
+
+        let mut reader = CompressedReader::new(Cursor::new(compressed), Codec::Lz4).unwrap();
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn compression_seek() {
+        let data = sample_data();
+        for codec in CODECS.iter() {
+            let compressed = compress(*codec, data.as_slice());
+            let mut reader = CompressedReader::new(Cursor::new(compressed), *codec).unwrap();
+            let mut byte = [0u8; 1];
+
+            assert_eq!(reader.seek(SeekFrom::Start(5000)).unwrap(), 5000);
+            reader.read_exact(&mut byte).unwrap();
+            assert_eq!(byte[0], data[5000]);
+
+            assert_eq!(reader.seek(SeekFrom::Current(-1001)).unwrap(), 4000);
+            reader.read_exact(&mut byte).unwrap();
+            assert_eq!(byte[0], data[4000]);
+
+            assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 9999);
+            reader.read_exact(&mut byte).unwrap();
+            assert_eq!(byte[0], data[9999]);
+
+            assert!(reader.seek(SeekFrom::Current(-20000)).is_err());
+        }
+    }
+
+    //A reader whose seeks fail when asked.
+    struct FailingSeek {
+        reader: Cursor<Vec<u8>>,
+        fail: bool,
+    }
+
+    impl Read for FailingSeek {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reader.read(buf)
+        }
+    }
+
+    impl Seek for FailingSeek {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            if self.fail {
+                return Err(io::Error::other("seek failure"));
+            }
+            self.reader.seek(pos)
+        }
+    }
+
+    #[test]
+    fn compression_seek_after_header() {
+        let data = sample_data();
+        for codec in CODECS.iter() {
+            //The compressed stream starts after a header, like a file in a pack.
+            let mut packed = b"PACK".to_vec();
+            packed.extend(compress(*codec, data.as_slice()));
+            let mut packed = Cursor::new(packed);
+            packed.set_position(4);
+            let mut reader = CompressedReader::new(packed, *codec).unwrap();
+            let mut bytes = [0u8; 4];
+
+            reader.seek(SeekFrom::Start(100)).unwrap();
+            assert_eq!(reader.seek(SeekFrom::Start(0)).unwrap(), 0);
+            reader.read_exact(&mut bytes).unwrap();
+            assert_eq!(bytes, data[..4]);
+            assert!(reader.seek(SeekFrom::Current(-5)).is_err());
+            assert_eq!(reader.into_inner().unwrap().get_ref()[..4], b"PACK"[..]);
+        }
+    }
+
+    #[test]
+    fn compression_failed_rewind() {
+        let data = sample_data();
+        for codec in CODECS.iter().filter(|codec| **codec != Codec::Plain) {
+            let failing = FailingSeek {
+                reader: Cursor::new(compress(*codec, data.as_slice())),
+                fail: false,
+            };
+            let mut reader = CompressedReader::new(failing, *codec).unwrap();
+            let mut byte = [0u8; 1];
+            reader.seek(SeekFrom::Start(200)).unwrap();
+
+            //The decoder is kept at its position when the underlying reader can't seek back.
+            match reader.decoder {
+                Some(Decoder::Lz4(ref mut decoder)) => decoder.get_mut().fail = true,
+                Some(Decoder::Zstd(ref mut decoder)) => decoder.get_mut().get_mut().fail = true,
+                Some(Decoder::Deflate(ref mut decoder)) => decoder.get_mut().fail = true,
+                Some(Decoder::Gzip(ref mut decoder)) => decoder.get_mut().fail = true,
+                _ => unreachable!(),
+            }
+            assert!(reader.seek(SeekFrom::Start(100)).is_err());
+            reader.read_exact(&mut byte).unwrap();
+            assert_eq!(byte[0], data[200]);
+            assert_eq!(reader.stream_position().unwrap(), 201);
+            assert!(reader.into_inner().is_ok());
+        }
+
+        //A decoder which couldn't be recreated gives errors, not panics.
+        let mut reader = CompressedReader::new(Cursor::new(compress(Codec::Lz4, data.as_slice())), Codec::Lz4).unwrap();
+        reader.decoder = None;
+        assert!(reader.read(&mut [0u8; 4]).is_err());
+        assert!(reader.seek(SeekFrom::Start(10)).is_err());
+        assert!(reader.into_inner().is_err());
+    }
+}
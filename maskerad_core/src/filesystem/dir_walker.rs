@@ -0,0 +1,471 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fs;
+use std::io;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use filesystem::filesystem_error::{FileSystemError, FileSystemResult};
+use filesystem::glob_pattern::GlobPattern;
+
+/*
+DIRECTORY WALKER.
+
+Walks a directory recursively and returns every entry matching the WalkOptions, sorted by
+relative path so the output is the same on every platform and every run.
+
+The entries directly inside the walked directory have a depth of 1. A maximum depth of 1 is
+equivalent to a non-recursive read_dir.
+
+Exclude patterns are checked before descending into a directory: an excluded directory is
+never walked.
+*/
+
+//What to do when a symbolic link is found while walking a directory.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SymlinkPolicy {
+    //Ignore symbolic links.
+    Skip,
+    //Return the link itself, without following it.
+    Report,
+    //Follow the link, as if the target was in the walked directory. Links pointing to one of
+    //their parent directories are skipped.
+    Follow,
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SymlinkPolicy::Skip => {
+                write!(f, "skip symlinks")
+            },
+            SymlinkPolicy::Report => {
+                write!(f, "report symlinks")
+            },
+            SymlinkPolicy::Follow => {
+                write!(f, "follow symlinks")
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkOptions {
+    max_depth: Option<usize>,
+    includes: Vec<String>,
+    excludes: Vec<String>,
+    symlink_policy: SymlinkPolicy,
+    include_directories: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            max_depth: None,
+            includes: Vec::new(),
+            excludes: Vec::new(),
+            symlink_policy: SymlinkPolicy::Report,
+            include_directories: false,
+        }
+    }
+}
+
+impl AsRef<WalkOptions> for WalkOptions {
+    fn as_ref(&self) -> &WalkOptions {
+        self
+    }
+}
+
+impl fmt::Display for WalkOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.max_depth {
+            Some(depth) => write!(f, "[max depth: {}, ", depth)?,
+            None => write!(f, "[max depth: unlimited, ")?,
+        }
+        write!(f, "includes: {:?}, excludes: {:?}, {}", self.includes, self.excludes, self.symlink_policy)?;
+        if self.include_directories {
+            write!(f, ", directories")?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl WalkOptions {
+    // Create a new instance, walking files at any depth and reporting symlinks.
+    pub fn new() -> WalkOptions {
+        debug!("Creating a WalkOptions.");
+        Default::default()
+    }
+
+    // Do not walk deeper than the given depth. None means unlimited.
+    pub fn set_max_depth<D>(&mut self, max_depth: D) -> &mut WalkOptions where
+        D: Into<Option<usize>>,
+    {
+        self.max_depth = max_depth.into();
+        debug!("Setting the max depth of the WalkOptions to {:?}", self.max_depth);
+        self
+    }
+
+    // Only return entries matching one of the include patterns. Everything is included if
+    // there are no include patterns.
+    pub fn add_include<S>(&mut self, pattern: S) -> &mut WalkOptions where
+        S: Into<String>,
+    {
+        let pattern = pattern.into();
+        debug!("Adding the include pattern {} to the WalkOptions", pattern);
+        self.includes.push(pattern);
+        self
+    }
+
+    // Never return, nor walk into, entries matching one of the exclude patterns.
+    pub fn add_exclude<S>(&mut self, pattern: S) -> &mut WalkOptions where
+        S: Into<String>,
+    {
+        let pattern = pattern.into();
+        debug!("Adding the exclude pattern {} to the WalkOptions", pattern);
+        self.excludes.push(pattern);
+        self
+    }
+
+    // Choose how symbolic links are handled.
+    pub fn set_symlink_policy(&mut self, symlink_policy: SymlinkPolicy) -> &mut WalkOptions {
+        debug!("Setting the symlink policy of the WalkOptions to {}", symlink_policy);
+        self.symlink_policy = symlink_policy;
+        self
+    }
+
+    // Return directories too, not only files.
+    pub fn set_include_directories(&mut self, include_directories: bool) -> &mut WalkOptions {
+        debug!("Setting the include directories option of the WalkOptions to {}", include_directories);
+        self.include_directories = include_directories;
+        self
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    pub fn symlink_policy(&self) -> SymlinkPolicy {
+        self.symlink_policy
+    }
+
+    pub fn include_directories(&self) -> bool {
+        self.include_directories
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EntryType {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkEntry {
+    path: PathBuf,
+    relative_path: PathBuf,
+    depth: usize,
+    entry_type: EntryType,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl WalkEntry {
+    fn new(path: PathBuf, relative_path: PathBuf, depth: usize, entry_type: EntryType, metadata: &fs::Metadata) -> Self {
+        WalkEntry {
+            path,
+            relative_path,
+            depth,
+            entry_type,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    //The path of the entry, relative to the walked directory.
+    pub fn relative_path(&self) -> &Path {
+        self.relative_path.as_path()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.entry_type == EntryType::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.entry_type == EntryType::Directory
+    }
+
+    //Size in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    //Last modification time, if the platform supports it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+}
+
+struct Walker<'a> {
+    options: &'a WalkOptions,
+    includes: Vec<GlobPattern>,
+    excludes: Vec<GlobPattern>,
+    //Canonical paths of the directories being walked, used to detect symlink loops.
+    ancestors: Vec<PathBuf>,
+    entries: Vec<WalkEntry>,
+}
+
+impl<'a> Walker<'a> {
+    fn is_included(&self, relative_path: &Path) -> bool {
+        self.includes.is_empty() || self.includes.iter().any(|pattern| pattern.matches(relative_path))
+    }
+
+    fn is_excluded(&self, relative_path: &Path) -> bool {
+        self.excludes.iter().any(|pattern| pattern.matches(relative_path))
+    }
+
+    fn walk(&mut self, directory: &Path, relative_directory: &Path, depth: usize) -> FileSystemResult<()> {
+        trace!("Walking the directory {} at depth {}", directory.display(), depth);
+        let mut children = fs::read_dir(directory)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<PathBuf>, _>>()?;
+        children.sort();
+
+        for path in children {
+            let relative_path = match path.file_name() {
+                Some(name) => relative_directory.join(name),
+                None => continue,
+            };
+
+            if self.is_excluded(relative_path.as_path()) {
+                trace!("{} is excluded.", relative_path.display());
+                continue;
+            }
+
+            let mut metadata = fs::symlink_metadata(path.as_path())?;
+            if metadata.file_type().is_symlink() {
+                match self.options.symlink_policy {
+                    SymlinkPolicy::Skip => {
+                        trace!("Skipping the symlink {}.", path.display());
+                        continue;
+                    },
+                    SymlinkPolicy::Report => {
+                        if self.is_included(relative_path.as_path()) {
+                            self.entries.push(WalkEntry::new(path, relative_path, depth, EntryType::Symlink, &metadata));
+                        }
+                        continue;
+                    },
+                    SymlinkPolicy::Follow => {
+                        metadata = match fs::metadata(path.as_path()) {
+                            Ok(metadata) => metadata,
+                            Err(_) => {
+                                warn!("The symlink {} is broken, skipping it.", path.display());
+                                continue;
+                            },
+                        };
+                    },
+                }
+            }
+
+            if metadata.is_dir() {
+                if self.options.include_directories && self.is_included(relative_path.as_path()) {
+                    self.entries.push(WalkEntry::new(path.clone(), relative_path.clone(), depth, EntryType::Directory, &metadata));
+                }
+
+                let deeper_allowed = match self.options.max_depth {
+                    Some(max_depth) => depth < max_depth,
+                    None => true,
+                };
+                if deeper_allowed {
+                    let canonical = fs::canonicalize(path.as_path())?;
+                    if self.ancestors.contains(&canonical) {
+                        warn!("{} loops back to one of its parent directories, skipping it.", path.display());
+                        continue;
+                    }
+                    self.ancestors.push(canonical);
+                    self.walk(path.as_path(), relative_path.as_path(), depth + 1)?;
+                    self.ancestors.pop();
+                }
+            } else if self.is_included(relative_path.as_path()) {
+                self.entries.push(WalkEntry::new(path, relative_path, depth, EntryType::File, &metadata));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+//Walk the directory at path, returning the matching entries sorted by relative path.
+pub fn walk<P, O>(path: P, options: O) -> FileSystemResult<Vec<WalkEntry>> where
+    P: AsRef<Path>,
+    O: AsRef<WalkOptions>,
+{
+    debug!("Walking the directory at path {} with options {}", path.as_ref().display(), options.as_ref());
+    if !path.as_ref().is_dir() {
+        error!("{} is not a directory !", path.as_ref().display());
+        return Err(FileSystemError::IOError(
+            format!("{} is not a directory", path.as_ref().display()),
+            io::Error::new(io::ErrorKind::InvalidInput, "not a directory"),
+        ));
+    }
+
+    let includes = options.as_ref().includes.iter()
+        .map(GlobPattern::new)
+        .collect::<FileSystemResult<Vec<GlobPattern>>>()?;
+    let excludes = options.as_ref().excludes.iter()
+        .map(GlobPattern::new)
+        .collect::<FileSystemResult<Vec<GlobPattern>>>()?;
+
+    let mut walker = Walker {
+        options: options.as_ref(),
+        includes,
+        excludes,
+        ancestors: vec![fs::canonicalize(path.as_ref())?],
+        entries: Vec::new(),
+    };
+
+    if walker.options.max_depth != Some(0) {
+        walker.walk(path.as_ref(), Path::new(""), 1)?;
+    }
+
+    let mut entries = walker.entries;
+    entries.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    trace!("Found {} entries.", entries.len());
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod dir_walker_test {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+
+    fn create_tree(name: &str) -> PathBuf {
+        let mut root = env::temp_dir();
+        root.push(name);
+        if root.exists() {
+            fs::remove_dir_all(root.as_path()).unwrap();
+        }
+
+        for dir in &["textures/characters/hero", "textures/ui", "models", "build"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in &[
+            "textures/sky.tga",
+            "textures/characters/hero/diffuse.tga",
+            "textures/characters/hero/normal.png",
+            "textures/ui/button.tga",
+            "models/hero.gltf",
+            "build/cache.tga",
+        ] {
+            let mut writer = File::create(root.join(file)).unwrap();
+            writer.write_all(file.as_bytes()).unwrap();
+        }
+        root
+    }
+
+    fn relative_paths(entries: &[WalkEntry]) -> Vec<String> {
+        entries.iter()
+            .map(|entry| entry.relative_path().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn dir_walker_sorted_files() {
+        let root = create_tree("maskerad_dir_walker_sorted");
+        let entries = walk(root.as_path(), WalkOptions::new()).unwrap();
+        assert_eq!(relative_paths(&entries), vec![
+            "build/cache.tga",
+            "models/hero.gltf",
+            "textures/characters/hero/diffuse.tga",
+            "textures/characters/hero/normal.png",
+            "textures/sky.tga",
+            "textures/ui/button.tga",
+        ]);
+
+        let sky = entries.iter().find(|entry| entry.relative_path().ends_with("sky.tga")).unwrap();
+        assert!(sky.is_file());
+        assert_eq!(sky.depth(), 2);
+        assert_eq!(sky.size(), "textures/sky.tga".len() as u64);
+        assert!(sky.modified().is_some());
+
+        match walk(root.join("textures/sky.tga"), WalkOptions::new()) {
+            Err(FileSystemError::IOError(_, ref io_error)) => assert_eq!(io_error.kind(), io::ErrorKind::InvalidInput),
+            other => panic!("A file can't be walked: {:?}", other),
+        }
+
+        fs::remove_dir_all(root.as_path()).unwrap();
+    }
+
+    #[test]
+    fn dir_walker_globs_and_depth() {
+        let root = create_tree("maskerad_dir_walker_globs");
+        let entries = walk(root.as_path(), WalkOptions::new()
+            .add_include("textures/**/*.tga")
+            .add_exclude("textures/ui"))
+            .unwrap();
+        assert_eq!(relative_paths(&entries), vec![
+            "textures/characters/hero/diffuse.tga",
+            "textures/sky.tga",
+        ]);
+
+        let entries = walk(root.as_path(), WalkOptions::new()
+            .set_max_depth(1)
+            .set_include_directories(true))
+            .unwrap();
+        assert_eq!(relative_paths(&entries), vec!["build", "models", "textures"]);
+        assert!(entries.iter().all(|entry| entry.is_dir()));
+
+        let entries = walk(root.as_path(), WalkOptions::new().set_max_depth(0)).unwrap();
+        assert!(entries.is_empty());
+
+        assert!(walk(root.as_path(), WalkOptions::new().add_include("textures/[a-z")).is_err());
+
+        fs::remove_dir_all(root.as_path()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_walker_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let root = create_tree("maskerad_dir_walker_symlinks");
+        symlink(root.join("models"), root.join("textures/models_link")).unwrap();
+        symlink(root.join("textures"), root.join("textures/ui/loop")).unwrap();
+
+        let options = WalkOptions::new().add_include("textures/**").clone();
+
+        let entries = walk(root.as_path(), options.clone().set_symlink_policy(SymlinkPolicy::Skip)).unwrap();
+        assert!(!relative_paths(&entries).iter().any(|path| path.contains("link") || path.contains("loop")));
+
+        let entries = walk(root.as_path(), options.clone().set_symlink_policy(SymlinkPolicy::Report)).unwrap();
+        let links: Vec<&WalkEntry> = entries.iter().filter(|entry| entry.entry_type() == EntryType::Symlink).collect();
+        assert_eq!(links.len(), 2);
+
+        let entries = walk(root.as_path(), options.clone().set_symlink_policy(SymlinkPolicy::Follow)).unwrap();
+        let paths = relative_paths(&entries);
+        assert!(paths.contains(&String::from("textures/models_link/hero.gltf")));
+        assert!(!paths.iter().any(|path| path.starts_with("textures/ui/loop")));
+
+        fs::remove_dir_all(root.as_path()).unwrap();
+    }
+}
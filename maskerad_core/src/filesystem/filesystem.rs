@@ -12,6 +12,8 @@ use std::io::{BufReader, BufWriter};
 use filesystem::game_directories::{GameDirectories, RootDir};
 use filesystem::filesystem_error::{FileSystemError, FileSystemResult};
 use filesystem::open_options::OpenOptions;
+use filesystem::dir_walker::{self, WalkOptions, WalkEntry};
+use filesystem::compression::{Codec, CompressedReader, CompressedWriter};
 use remove_dir_all;
 
 //Open to read file
@@ -100,6 +102,32 @@ impl Filesystem {
         Ok(BufWriter::new(buf))
     }
 
+    //Open file at path to read, decompressing it according to its extension ("untitled.tga.lz4").
+    pub fn open_compressed<P: AsRef<Path>>(path: P) -> FileSystemResult<CompressedReader<BufReader<File>>> {
+        let codec = Codec::from_path(path.as_ref());
+        Filesystem::open_with_codec(path, codec)
+    }
+
+    //Open file at path to read, decompressing it with the given codec.
+    pub fn open_with_codec<P: AsRef<Path>>(path: P, codec: Codec) -> FileSystemResult<CompressedReader<BufReader<File>>> {
+        debug!("Opening file at path {}, decompressing with {}", path.as_ref().display(), codec);
+        let reader = Filesystem::open(path.as_ref())?;
+        CompressedReader::new(reader, codec).map_err(|io_error| FileSystemError::from(io_error))
+    }
+
+    //Open file at path for writing, compressing it according to its extension. Truncates if file already exist.
+    pub fn create_compressed<P: AsRef<Path>>(path: P) -> FileSystemResult<CompressedWriter<BufWriter<File>>> {
+        let codec = Codec::from_path(path.as_ref());
+        Filesystem::create_with_codec(path, codec)
+    }
+
+    //Open file at path for writing, compressing it with the given codec. Truncates if file already exist.
+    pub fn create_with_codec<P: AsRef<Path>>(path: P, codec: Codec) -> FileSystemResult<CompressedWriter<BufWriter<File>>> {
+        debug!("Creating/truncating file at path {}, compressing with {}", path.as_ref().display(), codec);
+        let writer = Filesystem::create(path.as_ref())?;
+        CompressedWriter::new(writer, codec).map_err(|io_error| FileSystemError::from(io_error))
+    }
+
     //create directory at path
     pub fn mkdir<P: AsRef<Path>>(path: P) -> FileSystemResult<()> {
         debug!("Creating directory at path {}", path.as_ref().display());
@@ -126,12 +154,22 @@ impl Filesystem {
         remove_dir_all::remove_dir_all(path.as_ref()).map_err(|io_error| FileSystemError::from(io_error))
     }
 
-    //Retrieve all file entries in the given directory (recursive).
+    //Retrieve all file entries in the given directory (non-recursive).
     pub fn read_dir<P: AsRef<Path>>(path: P) -> FileSystemResult<fs::ReadDir> {
         debug!("Getting all entries in the directory at path {}", path.as_ref().display());
         fs::read_dir(path.as_ref()).map_err(|io_error| FileSystemError::from(io_error))
     }
 
+    //Retrieve the entries in the given directory and its subdirectories matching the options,
+    //sorted by relative path.
+    pub fn walk_dir<P, O>(path: P, options: O) -> FileSystemResult<Vec<WalkEntry>> where
+        P: AsRef<Path>,
+        O: AsRef<WalkOptions>,
+    {
+        debug!("Walking the directory at path {}", path.as_ref().display());
+        dir_walker::walk(path.as_ref(), options.as_ref())
+    }
+
     fn path(&self, root_dir: RootDir) -> FileSystemResult<PathBuf> {
         debug!("Getting the full path of the {}.", root_dir);
         match self.directories.get(&root_dir) {
@@ -164,7 +202,7 @@ impl Filesystem {
 #[cfg(test)]
 mod filesystem_test {
     use super::*;
-    use std::io::Write;
+    use std::io::{Read, Write};
     use filesystem::game_directories::{GameDirectories, RootDir};
 
     #[test]
@@ -198,6 +236,31 @@ mod filesystem_test {
         log_dir_bufwriter.write_all(b"text_test\n").unwrap();
     }
 
+    #[test]
+    fn filesystem_compressed_io() {
+        let fs =
+            Filesystem::new("test_filesystem_compression", "Malkaviel")
+                .expect("Couldn't create FS");
+
+        let compressed_file = fs
+            .construct_path_from_root(RootDir::EngineLogRoot, "compression_test/file_test.txt.zst")
+            .expect("Could not create compression_test/file_test.txt.zst");
+        Filesystem::mkdir(compressed_file.parent().unwrap())
+            .expect("Could not create the compression_test directory");
+
+        let mut writer = Filesystem::create_compressed(compressed_file.as_path())
+            .expect("Could not create compression_test/file_test.txt.zst");
+        writer.write_all(b"text_test\n").unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = Filesystem::open_compressed(compressed_file.as_path())
+            .expect("Could not open compression_test/file_test.txt.zst");
+        assert_eq!(reader.codec(), Codec::Zstd);
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "text_test\n");
+    }
+
     #[test]
     fn filesystem_read_dir() {
         let fs =
@@ -209,4 +272,17 @@ mod filesystem_test {
         let mut entries = Filesystem::read_dir(src_dir).unwrap();
         assert!(entries.next().is_some());
     }
+
+    #[test]
+    fn filesystem_walk_dir() {
+        let fs =
+            Filesystem::new("test_filesystem_walk_dir", "Malkaviel")
+                .expect("Couldn't create GameDirs");
+        let src_dir = fs
+            .construct_path_from_root(RootDir::WorkingDirectory, "src")
+            .unwrap();
+        let entries = Filesystem::walk_dir(src_dir, WalkOptions::new().add_include("filesystem/*.rs")).unwrap();
+        assert!(entries.iter().any(|entry| entry.relative_path().ends_with("dir_walker.rs")));
+        assert!(entries.iter().all(|entry| entry.is_file() && entry.depth() == 2));
+    }
 }
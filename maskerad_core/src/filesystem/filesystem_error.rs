@@ -17,11 +17,9 @@ pub enum FileSystemError {
     IOError(String, IOError),
     EnvironmentError(String, VarError),
     ExtensionError(String),
+    GlobPatternError(String),
 }
 
-unsafe impl Send for FileSystemError {}
-unsafe impl Sync for FileSystemError {}
-
 impl fmt::Display for FileSystemError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -40,28 +38,22 @@ impl fmt::Display for FileSystemError {
             &FileSystemError::ExtensionError(ref description) => {
                 write!(f, "file extension error: {}", description)
             }
+            &FileSystemError::GlobPatternError(ref description) => {
+                write!(f, "glob pattern error: {}", description)
+            }
         }
     }
 }
 
 impl Error for FileSystemError {
-    fn description(&self) -> &str {
-        match self {
-            &FileSystemError::GameDirectoryError(_) => "GameDirectoryError",
-            &FileSystemError::CreationError(_) => "CreationError",
-            &FileSystemError::EnvironmentError(_, _) => "EnvironmentError",
-            &FileSystemError::IOError(_, _) => "IOError",
-            &FileSystemError::ExtensionError(_) => "ExtensionError",
-        }
-    }
-
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             &FileSystemError::GameDirectoryError(_) => None,
             &FileSystemError::CreationError(_) => None,
             &FileSystemError::IOError(_, ref cause) => Some(cause),
             &FileSystemError::EnvironmentError(_, ref cause) => Some(cause),
             &FileSystemError::ExtensionError(_) => None,
+            &FileSystemError::GlobPatternError(_) => None,
         }
     }
 }
@@ -0,0 +1,294 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+use std::path::{Path, Component};
+use filesystem::filesystem_error::{FileSystemError, FileSystemResult};
+
+/*
+GLOB PATTERNS.
+
+Patterns are matched against paths relative to the walked directory, '/' being the separator
+on every platform.
+
+- '?' matches exactly one character, except '/'.
+- '*' matches any sequence of characters inside a path segment.
+- '**' matches zero or more whole path segments. It must be alone in its segment.
+- '[abc]', '[a-z]' and '[!a-z]' match a single character from (or not from) a set.
+
+"textures/**/*.tga" matches "textures/hero.tga" and "textures/characters/hero/diffuse.tga".
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnySequence,
+    Class(bool, Vec<(char, char)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    AnySegments,
+    Tokens(Vec<Token>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobPattern {
+    original: String,
+    segments: Vec<Segment>,
+}
+
+impl fmt::Display for GlobPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.original)
+    }
+}
+
+impl GlobPattern {
+    pub fn new<S>(pattern: S) -> FileSystemResult<Self> where
+        S: AsRef<str>
+    {
+        debug!("Compiling the glob pattern {}.", pattern.as_ref());
+        let mut segments = Vec::new();
+
+        for segment in pattern.as_ref().split('/').filter(|segment| !segment.is_empty()) {
+            if segment == "**" {
+                //Consecutive '**' are equivalent to a single one.
+                if segments.last() != Some(&Segment::AnySegments) {
+                    segments.push(Segment::AnySegments);
+                }
+            } else if segment.contains("**") {
+                error!("'**' must be alone in its path segment in the glob pattern {}.", pattern.as_ref());
+                return Err(FileSystemError::GlobPatternError(format!(
+                    "'**' must be alone in its path segment in {}",
+                    pattern.as_ref()
+                )));
+            } else {
+                segments.push(Segment::Tokens(GlobPattern::tokenize(pattern.as_ref(), segment)?));
+            }
+        }
+
+        if segments.is_empty() {
+            error!("The glob pattern is empty.");
+            return Err(FileSystemError::GlobPatternError(String::from("The glob pattern is empty")));
+        }
+
+        Ok(GlobPattern {
+            original: String::from(pattern.as_ref()),
+            segments,
+        })
+    }
+
+    fn tokenize(pattern: &str, segment: &str) -> FileSystemResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut chars = segment.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '?' => tokens.push(Token::AnyChar),
+                '*' => {
+                    if tokens.last() != Some(&Token::AnySequence) {
+                        tokens.push(Token::AnySequence);
+                    }
+                },
+                '[' => {
+                    let mut negated = false;
+                    let mut ranges = Vec::new();
+                    let mut closed = false;
+                    let mut first = true;
+
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '!' if first => negated = true,
+                            ']' if !first => {
+                                closed = true;
+                                break;
+                            },
+                            _ => {
+                                //Look ahead for a range such as a-z.
+                                let mut lookahead = chars.clone();
+                                match (lookahead.next(), lookahead.next()) {
+                                    (Some('-'), Some(end)) if end != ']' => {
+                                        chars = lookahead;
+                                        ranges.push((c, end));
+                                    },
+                                    _ => ranges.push((c, c)),
+                                }
+                            },
+                        }
+                        first = false;
+                    }
+
+                    if !closed {
+                        error!("Unclosed character class in the glob pattern {}.", pattern);
+                        return Err(FileSystemError::GlobPatternError(format!(
+                            "Unclosed character class in {}",
+                            pattern
+                        )));
+                    }
+                    tokens.push(Token::Class(negated, ranges));
+                },
+                _ => tokens.push(Token::Char(c)),
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.original.as_str()
+    }
+
+    /// Returns true if the relative path matches this pattern.
+    pub fn matches<P: AsRef<Path>>(&self, path: P) -> bool {
+        let components: Vec<Vec<char>> = path.as_ref()
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().chars().collect()),
+                _ => None,
+            })
+            .collect();
+
+        GlobPattern::match_segments(self.segments.as_slice(), components.as_slice())
+    }
+
+    //'**' and '*' are matched with the two-pointer algorithm: on a mismatch, only the last wildcard
+    //absorbs one more element, the earlier ones can't give a match the last one can't give. At worst
+    //proportional to the length of the pattern times the length of the path, without recursion.
+    fn match_segments(segments: &[Segment], components: &[Vec<char>]) -> bool {
+        let (mut segment, mut component) = (0, 0);
+        //The index of the last '**', and of the first component it doesn't absorb yet.
+        let mut wildcard: Option<(usize, usize)> = None;
+
+        while component < components.len() {
+            match segments.get(segment) {
+                Some(&Segment::AnySegments) => {
+                    wildcard = Some((segment, component));
+                    segment += 1;
+                    continue;
+                },
+                Some(&Segment::Tokens(ref tokens)) if GlobPattern::match_tokens(tokens.as_slice(), components[component].as_slice()) => {
+                    segment += 1;
+                    component += 1;
+                    continue;
+                },
+                _ => {},
+            }
+
+            match wildcard {
+                Some((wildcard_segment, absorbed)) => {
+                    wildcard = Some((wildcard_segment, absorbed + 1));
+                    segment = wildcard_segment + 1;
+                    component = absorbed + 1;
+                },
+                None => return false,
+            }
+        }
+
+        segments[segment..].iter().all(|segment| *segment == Segment::AnySegments)
+    }
+
+    fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+        let (mut token, mut c) = (0, 0);
+        //The index of the last '*', and of the first character it doesn't absorb yet.
+        let mut wildcard: Option<(usize, usize)> = None;
+
+        while c < chars.len() {
+            match tokens.get(token) {
+                Some(&Token::AnySequence) => {
+                    wildcard = Some((token, c));
+                    token += 1;
+                    continue;
+                },
+                Some(other) if GlobPattern::match_char(other, chars[c]) => {
+                    token += 1;
+                    c += 1;
+                    continue;
+                },
+                _ => {},
+            }
+
+            match wildcard {
+                Some((wildcard_token, absorbed)) => {
+                    wildcard = Some((wildcard_token, absorbed + 1));
+                    token = wildcard_token + 1;
+                    c = absorbed + 1;
+                },
+                None => return false,
+            }
+        }
+
+        tokens[token..].iter().all(|token| *token == Token::AnySequence)
+    }
+
+    fn match_char(token: &Token, c: char) -> bool {
+        match *token {
+            Token::Char(expected) => expected == c,
+            Token::AnyChar => true,
+            Token::Class(negated, ref ranges) => {
+                let in_class = ranges.iter().any(|&(start, end)| start <= c && c <= end);
+                in_class != negated
+            },
+            Token::AnySequence => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod glob_pattern_test {
+    use super::*;
+
+    #[test]
+    fn glob_pattern_wildcards() {
+        let pattern = GlobPattern::new("textures/*.tga").unwrap();
+        assert!(pattern.matches("textures/hero.tga"));
+        assert!(!pattern.matches("textures/hero.png"));
+        assert!(!pattern.matches("textures/characters/hero.tga"));
+
+        let pattern = GlobPattern::new("level_?.json").unwrap();
+        assert!(pattern.matches("level_3.json"));
+        assert!(!pattern.matches("level_10.json"));
+
+        let pattern = GlobPattern::new("sound_[0-9a].ogg").unwrap();
+        assert!(pattern.matches("sound_7.ogg"));
+        assert!(pattern.matches("sound_a.ogg"));
+        assert!(!pattern.matches("sound_b.ogg"));
+
+        let pattern = GlobPattern::new("[!_]*").unwrap();
+        assert!(pattern.matches("hero.tga"));
+        assert!(!pattern.matches("_hero.tga"));
+    }
+
+    #[test]
+    fn glob_pattern_recursive() {
+        let pattern = GlobPattern::new("textures/**/*.tga").unwrap();
+        assert!(pattern.matches("textures/hero.tga"));
+        assert!(pattern.matches("textures/characters/hero/diffuse.tga"));
+        assert!(!pattern.matches("models/hero.tga"));
+
+        let pattern = GlobPattern::new("**/*.gltf").unwrap();
+        assert!(pattern.matches("untitled.gltf"));
+        assert!(pattern.matches("gltf/untitled.gltf"));
+        assert!(!pattern.matches("gltf/untitled.bin"));
+
+        //Many wildcards don't make the matching exponential.
+        let pattern = GlobPattern::new("**/a/**/a/**/a/**/b/*a*a*a*a*b").unwrap();
+        let path = format!("{}{}", "a/".repeat(200), "a".repeat(200));
+        assert!(!pattern.matches(path.as_str()));
+        assert!(pattern.matches("x/a/a/y/a/b/aaaab"));
+        assert!(GlobPattern::new("*a*b").unwrap().matches("xaab"));
+        assert!(!GlobPattern::new("*a*b").unwrap().matches("xaaba"));
+    }
+
+    #[test]
+    fn glob_pattern_invalid() {
+        assert!(GlobPattern::new("").is_err());
+        assert!(GlobPattern::new("textures/a**/*.tga").is_err());
+        assert!(GlobPattern::new("textures/[a-z.tga").is_err());
+    }
+}
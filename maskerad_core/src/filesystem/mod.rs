@@ -8,4 +8,7 @@
 pub mod filesystem;
 pub mod filesystem_error;
 pub mod game_directories;
-pub mod open_options;
\ No newline at end of file
+pub mod open_options;
+pub mod glob_pattern;
+pub mod dir_walker;
+pub mod compression;
\ No newline at end of file
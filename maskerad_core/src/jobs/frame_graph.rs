@@ -0,0 +1,214 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+use std::sync::Arc;
+use jobs::job_error::{JobError, JobResult};
+use jobs::job_system::{JobHandle, JobSystem};
+
+/*
+FRAME GRAPH.
+
+The work of a frame, described as a graph of passes: "animation -> physics -> culling -> render submit".
+
+A pass is a named job, executed every frame once the passes it depends on are complete. The passes
+without any dependency between them run in parallel on the job system.
+
+A pass can only depend on passes already added to the graph, the graph cannot contain any cycle.
+*/
+
+type Pass = Arc<dyn Fn() + Send + Sync + 'static>;
+
+struct PassNode {
+    name: String,
+    dependencies: Vec<usize>,
+    pass: Pass,
+}
+
+#[derive(Default)]
+pub struct FrameGraph {
+    nodes: Vec<PassNode>,
+}
+
+impl fmt::Debug for FrameGraph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FrameGraph {:?}", self.levels())
+    }
+}
+
+impl FrameGraph {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    //Add a pass, executed after all the passes named in dependencies.
+    pub fn add_pass<S, F>(&mut self, name: S, dependencies: &[&str], pass: F) -> JobResult<()> where
+        S: Into<String>,
+        F: Fn() + Send + Sync + 'static,
+    {
+        let name = name.into();
+        debug!("Adding the pass {} to the frame graph.", name);
+        if self.index_of(name.as_str()).is_some() {
+            return Err(JobError::DuplicatePass(format!("The frame graph already contains a pass named {}.", name)));
+        }
+
+        let mut dependency_indices = Vec::with_capacity(dependencies.len());
+        for dependency in dependencies {
+            match self.index_of(dependency) {
+                Some(index) => dependency_indices.push(index),
+                None => return Err(JobError::UnknownPass(format!(
+                    "The pass {} depends on {}, which hasn't been added to the frame graph.",
+                    name, dependency
+                ))),
+            }
+        }
+
+        self.nodes.push(PassNode {
+            name,
+            dependencies: dependency_indices,
+            pass: Arc::new(pass),
+        });
+        Ok(())
+    }
+
+    pub fn pass_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn contains_pass<S>(&self, name: S) -> bool where
+        S: AsRef<str>
+    {
+        self.index_of(name.as_ref()).is_some()
+    }
+
+    //The passes grouped by level: the passes of a level only depend on passes of the previous levels, and can run in parallel.
+    pub fn levels(&self) -> Vec<Vec<&str>> {
+        let mut node_levels: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        let mut levels: Vec<Vec<&str>> = Vec::new();
+
+        //A pass only depends on the passes added before it.
+        for node in self.nodes.iter() {
+            let level = node.dependencies.iter()
+                .map(|dependency| node_levels[*dependency] + 1)
+                .max()
+                .unwrap_or(0);
+            node_levels.push(level);
+
+            if levels.len() <= level {
+                levels.push(Vec::new());
+            }
+            levels[level].push(node.name.as_str());
+        }
+
+        levels
+    }
+
+    //Execute every pass on the job system, and wait until the frame is complete.
+    pub fn execute(&self, job_system: &JobSystem) -> JobResult<()> {
+        trace!("Executing a frame graph of {} passes.", self.nodes.len());
+        let mut handles: Vec<JobHandle> = Vec::with_capacity(self.nodes.len());
+
+        for node in self.nodes.iter() {
+            let dependencies: Vec<JobHandle> = node.dependencies.iter()
+                .map(|dependency| handles[*dependency].clone())
+                .collect();
+            let pass = node.pass.clone();
+            handles.push(job_system.spawn_after(&dependencies, move || pass()));
+        }
+
+        let mut result = Ok(());
+        for (node, handle) in self.nodes.iter().zip(handles.iter()) {
+            let pass_result = job_system.wait(handle).map_err(|job_error| match job_error {
+                JobError::JobPanicked(message) => JobError::JobPanicked(format!("The pass {} panicked: {}", node.name, message)),
+                JobError::DependencyFailed(_) => JobError::DependencyFailed(format!("The pass {} has been cancelled, one of its dependencies failed.", node.name)),
+                other => other,
+            });
+            if result.is_ok() {
+                result = pass_result;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod frame_graph_test {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn recording_pass(log: &Arc<Mutex<Vec<&'static str>>>, name: &'static str) -> impl Fn() + Send + Sync + 'static {
+        let log = log.clone();
+        move || log.lock().unwrap().push(name)
+    }
+
+    #[test]
+    fn frame_graph_levels_and_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut frame_graph = FrameGraph::new();
+        frame_graph.add_pass("animation", &[], recording_pass(&log, "animation")).unwrap();
+        frame_graph.add_pass("audio", &[], recording_pass(&log, "audio")).unwrap();
+        frame_graph.add_pass("physics", &["animation"], recording_pass(&log, "physics")).unwrap();
+        frame_graph.add_pass("culling", &["physics"], recording_pass(&log, "culling")).unwrap();
+        frame_graph.add_pass("render_submit", &["culling", "audio"], recording_pass(&log, "render_submit")).unwrap();
+
+        assert_eq!(frame_graph.levels(), vec![
+            vec!["animation", "audio"],
+            vec!["physics"],
+            vec!["culling"],
+            vec!["render_submit"],
+        ]);
+
+        let job_system = JobSystem::new(2).unwrap();
+        for _ in 0..10 {
+            log.lock().unwrap().clear();
+            frame_graph.execute(&job_system).unwrap();
+
+            let log = log.lock().unwrap();
+            let position = |name| log.iter().position(|pass| *pass == name).unwrap();
+            assert_eq!(log.len(), 5);
+            assert!(position("animation") < position("physics"));
+            assert!(position("physics") < position("culling"));
+            assert!(position("culling") < position("render_submit"));
+            assert!(position("audio") < position("render_submit"));
+        }
+    }
+
+    #[test]
+    fn frame_graph_invalid_passes() {
+        let mut frame_graph = FrameGraph::new();
+        frame_graph.add_pass("physics", &[], || {}).unwrap();
+
+        match frame_graph.add_pass("physics", &[], || {}) {
+            Err(JobError::DuplicatePass(_)) => {},
+            other => panic!("Expected a DuplicatePass error, got {:?}", other),
+        }
+        match frame_graph.add_pass("culling", &["rendering"], || {}) {
+            Err(JobError::UnknownPass(_)) => {},
+            other => panic!("Expected an UnknownPass error, got {:?}", other),
+        }
+        assert_eq!(frame_graph.pass_count(), 1);
+        assert!(!frame_graph.contains_pass("culling"));
+    }
+
+    #[test]
+    fn frame_graph_failed_pass() {
+        let mut frame_graph = FrameGraph::new();
+        frame_graph.add_pass("physics", &[], || panic!("physics exploded")).unwrap();
+        frame_graph.add_pass("culling", &["physics"], || {}).unwrap();
+
+        //Headless execution, on the calling thread.
+        let job_system = JobSystem::new(0).unwrap();
+        match frame_graph.execute(&job_system) {
+            Err(JobError::JobPanicked(message)) => assert!(message.contains("physics")),
+            other => panic!("Expected a JobPanicked error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Error as IOError;
+
+#[derive(Debug)]
+pub enum JobError {
+    ThreadError(String, IOError),
+    JobPanicked(String),
+    DependencyFailed(String),
+    DuplicatePass(String),
+    UnknownPass(String),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &JobError::ThreadError(ref desc, _) => {
+                write!(f, "Thread error: {}", desc)
+            },
+            &JobError::JobPanicked(ref desc) => {
+                write!(f, "Job panicked: {}", desc)
+            },
+            &JobError::DependencyFailed(ref desc) => {
+                write!(f, "Dependency failed: {}", desc)
+            },
+            &JobError::DuplicatePass(ref desc) => {
+                write!(f, "Duplicate pass: {}", desc)
+            },
+            &JobError::UnknownPass(ref desc) => {
+                write!(f, "Unknown pass: {}", desc)
+            },
+        }
+    }
+}
+
+impl Error for JobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            &JobError::ThreadError(_, ref io_error) => {
+                Some(io_error)
+            },
+            &JobError::JobPanicked(_) => {
+                None
+            },
+            &JobError::DependencyFailed(_) => {
+                None
+            },
+            &JobError::DuplicatePass(_) => {
+                None
+            },
+            &JobError::UnknownPass(_) => {
+                None
+            },
+        }
+    }
+}
+
+pub type JobResult<T> = Result<T, JobError>;
+
+impl From<IOError> for JobError {
+    fn from(error: IOError) -> Self {
+        JobError::ThreadError(String::from("Error while spawning a worker thread."), error)
+    }
+}
@@ -0,0 +1,683 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::mem;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use allocators::frame_stacks;
+use jobs::job_error::{JobError, JobResult};
+use logging::crash_report;
+
+/*
+JOB SYSTEM.
+
+The engine is based on a job model: the work of a frame is cut into small jobs, executed by a fixed
+pool of worker threads.
+
+Each worker owns a deque of jobs. A job spawned by a worker is pushed at the back of its own deque,
+and the worker pops the jobs from the back (the most recent job, whose data is still in the cache).
+When its deque is empty, a worker takes the jobs spawned by the other threads (the injector queue),
+then steals the oldest jobs at the front of the deques of the other workers.
+
+Spawning a job returns a JobHandle, a counter telling if the job is complete. A job can be spawned
+after other jobs: it is queued only when all its dependencies are complete. If a dependency panicked,
+the job is cancelled.
+
+Waiting for a job doesn't block the thread: the waiting thread executes the pending jobs until the job
+is complete. A job system without any worker executes everything on the thread waiting for the jobs,
+which is handy for headless tests.
+
+A JobHandle is also a Future, which can be awaited by the tasks (see the tasks module).
+*/
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+//Ids of the job systems, to know if the current thread is a worker of a given job system.
+static NEXT_SYSTEM_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    //(id of the job system, index of the worker) of the worker running on this thread.
+    static CURRENT_WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+//The jobs never run while a lock is held, and their panics are caught: a poisoned lock can be used safely.
+pub(crate) fn lock<'a, T>(mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic payload")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum JobState {
+    Pending,
+    Completed,
+    Panicked(String),
+    Cancelled,
+}
+
+impl JobState {
+    fn result(&self) -> Option<JobResult<()>> {
+        match *self {
+            JobState::Pending => None,
+            JobState::Completed => Some(Ok(())),
+            JobState::Panicked(ref message) => Some(Err(JobError::JobPanicked(message.clone()))),
+            JobState::Cancelled => Some(Err(JobError::DependencyFailed(String::from("A dependency of the job failed, the job has been cancelled.")))),
+        }
+    }
+}
+
+struct CounterState {
+    state: JobState,
+    //The jobs waiting for this one.
+    continuations: Vec<Arc<PendingTask>>,
+    //The tasks awaiting this one.
+    wakers: Vec<Waker>,
+}
+
+struct Counter {
+    state: Mutex<CounterState>,
+    condvar: Condvar,
+}
+
+impl Counter {
+    fn new() -> Self {
+        Counter {
+            state: Mutex::new(CounterState {
+                state: JobState::Pending,
+                continuations: Vec::new(),
+                wakers: Vec::new(),
+            }),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn complete(&self, shared: &Shared, state: JobState) {
+        let failed = state != JobState::Completed;
+        let (continuations, wakers) = {
+            let mut counter_state = lock(&self.state);
+            counter_state.state = state;
+            (mem::take(&mut counter_state.continuations), mem::take(&mut counter_state.wakers))
+        };
+        self.condvar.notify_all();
+
+        for waker in wakers {
+            waker.wake();
+        }
+
+        for pending_task in continuations {
+            pending_task.dependency_done(shared, failed);
+        }
+    }
+}
+
+struct Task {
+    job: Job,
+    counter: Arc<Counter>,
+}
+
+//A task waiting for its dependencies.
+struct PendingTask {
+    remaining: AtomicUsize,
+    failed: AtomicBool,
+    task: Mutex<Option<Task>>,
+}
+
+impl PendingTask {
+    fn dependency_done(&self, shared: &Shared, failed: bool) {
+        if failed {
+            self.failed.store(true, Ordering::Release);
+        }
+
+        if self.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if let Some(task) = lock(&self.task).take() {
+                if self.failed.load(Ordering::Acquire) {
+                    trace!("A dependency of a job failed, cancelling the job.");
+                    task.counter.complete(shared, JobState::Cancelled);
+                } else {
+                    shared.push(task);
+                }
+            }
+        }
+    }
+}
+
+struct Shared {
+    id: usize,
+    //The jobs spawned by the threads which are not workers of this job system.
+    injector: Mutex<VecDeque<Task>>,
+    deques: Vec<Mutex<VecDeque<Task>>>,
+    queued: AtomicUsize,
+    //True when the job system is shutting down.
+    sleep_lock: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn current_worker(&self) -> Option<usize> {
+        match CURRENT_WORKER.with(|worker| worker.get()) {
+            Some((id, index)) if id == self.id => Some(index),
+            _ => None,
+        }
+    }
+
+    fn push(&self, task: Task) {
+        //Count the job before queuing it: a worker could take it, and decrement the counter, before the increment.
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        match self.current_worker() {
+            Some(index) => lock(&self.deques[index]).push_back(task),
+            None => lock(&self.injector).push_back(task),
+        }
+
+        //Notify while holding the lock, a worker going to sleep cannot miss the job.
+        let _sleep_guard = lock(&self.sleep_lock);
+        self.wake.notify_one();
+    }
+
+    fn find_task(&self, worker: Option<usize>) -> Option<Task> {
+        let task = worker
+            .and_then(|index| lock(&self.deques[index]).pop_back())
+            .or_else(|| lock(&self.injector).pop_front())
+            .or_else(|| self.steal(worker));
+
+        if task.is_some() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+        }
+        task
+    }
+
+    fn steal(&self, thief: Option<usize>) -> Option<Task> {
+        let worker_count = self.deques.len();
+        let start = thief.map(|index| index + 1).unwrap_or(0);
+
+        (0..worker_count)
+            .map(|offset| (start + offset) % worker_count)
+            .filter(|victim| Some(*victim) != thief)
+            .filter_map(|victim| lock(&self.deques[victim]).pop_front())
+            .next()
+    }
+
+    fn run(&self, task: Task) {
+        let Task { job, counter } = task;
+        let state = match crash_report::catch_unwind(AssertUnwindSafe(job)) {
+            Ok(()) => JobState::Completed,
+            Err(payload) => {
+                let message = panic_message(&*payload);
+                error!("A job panicked: {}", message);
+                JobState::Panicked(message)
+            },
+        };
+        counter.complete(self, state);
+    }
+
+    fn schedule(&self, dependencies: &[JobHandle], job: Job) -> JobHandle {
+        let counter = Arc::new(Counter::new());
+        let task = Task {
+            job,
+            counter: counter.clone(),
+        };
+
+        if dependencies.is_empty() {
+            self.push(task);
+            return JobHandle(counter);
+        }
+
+        //One more dependency than needed, removed once every dependency has been registered.
+        let pending_task = Arc::new(PendingTask {
+            remaining: AtomicUsize::new(dependencies.len() + 1),
+            failed: AtomicBool::new(false),
+            task: Mutex::new(Some(task)),
+        });
+
+        for dependency in dependencies {
+            let finished_state = {
+                let mut dependency_state = lock(&(dependency.0).state);
+                if dependency_state.state == JobState::Pending {
+                    dependency_state.continuations.push(pending_task.clone());
+                    None
+                } else {
+                    Some(dependency_state.state.clone())
+                }
+            };
+
+            if let Some(state) = finished_state {
+                pending_task.dependency_done(self, state != JobState::Completed);
+            }
+        }
+        pending_task.dependency_done(self, false);
+
+        JobHandle(counter)
+    }
+}
+
+#[derive(Clone)]
+pub struct JobHandle(Arc<Counter>);
+
+impl JobHandle {
+    //True when the job has been executed, has panicked or has been cancelled.
+    pub fn is_complete(&self) -> bool {
+        lock(&(self.0).state).state != JobState::Pending
+    }
+
+    fn result(&self) -> Option<JobResult<()>> {
+        lock(&(self.0).state).state.result()
+    }
+
+    //Sleep until the job is complete, or the timeout expires.
+    fn sleep(&self, timeout: Duration) {
+        let state = lock(&(self.0).state);
+        if state.state == JobState::Pending {
+            drop((self.0).condvar.wait_timeout(state, timeout));
+        }
+    }
+}
+
+impl Future for JobHandle {
+    type Output = JobResult<()>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let mut counter_state = lock(&(self.0).state);
+        match counter_state.state.result() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                if !counter_state.wakers.iter().any(|waker| waker.will_wake(context.waker())) {
+                    counter_state.wakers.push(context.waker().clone());
+                }
+                Poll::Pending
+            },
+        }
+    }
+}
+
+impl fmt::Debug for JobHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JobHandle [{:?}]", lock(&(self.0).state).state)
+    }
+}
+
+//A cloneable handle to a job system, to spawn jobs from other jobs.
+//The jobs spawned after the destruction of the job system are never executed.
+#[derive(Clone)]
+pub struct Spawner(Arc<Shared>);
+
+impl fmt::Debug for Spawner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Spawner [queued jobs: {}]", self.0.queued.load(Ordering::SeqCst))
+    }
+}
+
+impl Spawner {
+    pub fn spawn<F>(&self, job: F) -> JobHandle where
+        F: FnOnce() + Send + 'static
+    {
+        self.0.schedule(&[], Box::new(job))
+    }
+
+    pub fn spawn_after<F>(&self, dependencies: &[JobHandle], job: F) -> JobHandle where
+        F: FnOnce() + Send + 'static
+    {
+        self.0.schedule(dependencies, Box::new(job))
+    }
+
+    //A handle which isn't attached to any job, completed with complete().
+    pub(crate) fn pending_handle(&self) -> JobHandle {
+        JobHandle(Arc::new(Counter::new()))
+    }
+
+    pub(crate) fn complete(&self, handle: &JobHandle, result: Result<(), String>) {
+        let state = match result {
+            Ok(()) => JobState::Completed,
+            Err(message) => JobState::Panicked(message),
+        };
+        (handle.0).complete(&self.0, state);
+    }
+}
+
+pub struct JobSystem {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl fmt::Debug for JobSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JobSystem [workers: {}, queued jobs: {}]", self.worker_count(), self.shared.queued.load(Ordering::SeqCst))
+    }
+}
+
+impl Drop for JobSystem {
+    //The workers finish the queued jobs before stopping.
+    fn drop(&mut self) {
+        debug!("Shutting down the job system.");
+        {
+            let mut shutdown = lock(&self.shared.sleep_lock);
+            *shutdown = true;
+            self.shared.wake.notify_all();
+        }
+
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                error!("A worker thread of the job system panicked.");
+            }
+        }
+    }
+}
+
+impl JobSystem {
+    //Create a job system with worker_count worker threads. With 0 workers, the jobs are executed by the threads waiting for them.
+    pub fn new(worker_count: usize) -> JobResult<Self> {
+        debug!("Creating a job system with {} workers.", worker_count);
+        let shared = Arc::new(Shared {
+            id: NEXT_SYSTEM_ID.fetch_add(1, Ordering::Relaxed),
+            injector: Mutex::new(VecDeque::new()),
+            deques: (0..worker_count).map(|_| Mutex::new(VecDeque::new())).collect(),
+            queued: AtomicUsize::new(0),
+            sleep_lock: Mutex::new(false),
+            wake: Condvar::new(),
+        });
+
+        let mut job_system = JobSystem {
+            shared,
+            workers: Vec::with_capacity(worker_count),
+        };
+
+        for index in 0..worker_count {
+            let shared = job_system.shared.clone();
+            let worker = thread::Builder::new()
+                .name(format!("maskerad_worker_{}", index))
+                .spawn(move || worker_loop(&shared, index))?;
+            job_system.workers.push(worker);
+        }
+
+        Ok(job_system)
+    }
+
+    //Create a job system with one worker per logical core, minus the core used by the main thread.
+    pub fn with_available_parallelism() -> JobResult<Self> {
+        let cores = thread::available_parallelism().map(|cores| cores.get()).unwrap_or(1);
+        JobSystem::new(cmp::max(cores, 2) - 1)
+    }
+
+    //The id of the job system, unique in the process. The FrameStacks of its workers are created with it.
+    pub fn id(&self) -> usize {
+        self.shared.id
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    //The index of the worker running on this thread, if this thread is a worker of this job system.
+    pub fn current_worker(&self) -> Option<usize> {
+        self.shared.current_worker()
+    }
+
+    pub fn spawner(&self) -> Spawner {
+        Spawner(self.shared.clone())
+    }
+
+    pub fn spawn<F>(&self, job: F) -> JobHandle where
+        F: FnOnce() + Send + 'static
+    {
+        self.shared.schedule(&[], Box::new(job))
+    }
+
+    //Spawn a job executed once all the dependencies are complete.
+    pub fn spawn_after<F>(&self, dependencies: &[JobHandle], job: F) -> JobHandle where
+        F: FnOnce() + Send + 'static
+    {
+        self.shared.schedule(dependencies, Box::new(job))
+    }
+
+    //A handle complete when all the dependencies are complete.
+    pub fn fence(&self, dependencies: &[JobHandle]) -> JobHandle {
+        self.spawn_after(dependencies, || {})
+    }
+
+    //Spawn a job borrowing non-'static data.
+    //Safety: the job must be complete before the data it borrows goes out of scope, even when the caller unwinds.
+    //Push its handle in a ScopedJobs, which waits for the jobs when dropped.
+    unsafe fn spawn_scoped<'a, F>(&self, job: F) -> JobHandle where
+        F: FnOnce() + Send + 'a
+    {
+        let job: Box<dyn FnOnce() + Send + 'a> = Box::new(job);
+        self.shared.schedule(&[], mem::transmute::<Box<dyn FnOnce() + Send + 'a>, Job>(job))
+    }
+
+    //Execute the pending jobs until the job is complete.
+    pub fn wait(&self, handle: &JobHandle) -> JobResult<()> {
+        let worker = self.current_worker();
+        loop {
+            if let Some(result) = handle.result() {
+                return result;
+            }
+
+            match self.shared.find_task(worker) {
+                Some(task) => self.shared.run(task),
+                None => handle.sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    //Wait for every job, and return the first error.
+    pub fn wait_all(&self, handles: &[JobHandle]) -> JobResult<()> {
+        let mut result = Ok(());
+        for handle in handles {
+            let job_result = self.wait(handle);
+            if result.is_ok() {
+                result = job_result;
+            }
+        }
+        result
+    }
+
+    //Call op on every element of data, chunk_size elements per job. op receives the index of the element.
+    pub fn parallel_for<T, F>(&self, data: &mut [T], chunk_size: usize, op: F) -> JobResult<()> where
+        T: Send,
+        F: Fn(usize, &mut T) + Sync,
+    {
+        let chunk_size = cmp::max(chunk_size, 1);
+        let op = &op;
+
+        //The jobs are waited for before data and op go out of scope, even if a job panicked or this thread unwinds.
+        let mut scoped = ScopedJobs { job_system: self, handles: Vec::new() };
+        for (chunk_index, chunk) in data.chunks_mut(chunk_size).enumerate() {
+            let first = chunk_index * chunk_size;
+            let job = move || {
+                for (offset, element) in chunk.iter_mut().enumerate() {
+                    op(first + offset, element);
+                }
+            };
+            let handle = unsafe { self.spawn_scoped(job) };
+            scoped.handles.push(handle);
+        }
+
+        self.wait_all(&scoped.handles)
+    }
+}
+
+//The handles of the jobs borrowing non-'static data, waited for when dropped.
+struct ScopedJobs<'s> {
+    job_system: &'s JobSystem,
+    handles: Vec<JobHandle>,
+}
+
+impl<'s> Drop for ScopedJobs<'s> {
+    fn drop(&mut self) {
+        //The errors are returned by wait_all() when the jobs complete normally.
+        let _ = self.job_system.wait_all(&self.handles);
+    }
+}
+
+fn worker_loop(shared: &Shared, index: usize) {
+    trace!("Starting the worker {}.", index);
+    CURRENT_WORKER.with(|worker| worker.set(Some((shared.id, index))));
+    frame_stacks::set_current_worker(Some((shared.id, index)));
+
+    loop {
+        if let Some(task) = shared.find_task(Some(index)) {
+            shared.run(task);
+            continue;
+        }
+
+        let shutdown = lock(&shared.sleep_lock);
+        if *shutdown {
+            break;
+        }
+        if shared.queued.load(Ordering::SeqCst) == 0 {
+            drop(shared.wake.wait(shutdown));
+        }
+    }
+
+    trace!("Stopping the worker {}.", index);
+    frame_stacks::set_current_worker(None);
+    CURRENT_WORKER.with(|worker| worker.set(None));
+}
+
+#[cfg(test)]
+mod job_system_test {
+    use super::*;
+    use std::slice;
+    use allocators::frame_stacks::FrameStacks;
+
+    #[test]
+    fn job_system_spawn_wait() {
+        let job_system = JobSystem::new(4).unwrap();
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<JobHandle> = (0..100).map(|_| {
+            let counter = counter.clone();
+            job_system.spawn(move || { counter.fetch_add(1, Ordering::SeqCst); })
+        }).collect();
+
+        job_system.wait_all(&handles).unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 100);
+        assert!(handles.iter().all(|handle| handle.is_complete()));
+    }
+
+    #[test]
+    fn job_system_dependencies() {
+        let job_system = JobSystem::new(2).unwrap();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let push = |value: u32| {
+            let order = order.clone();
+            move || lock(&order).push(value)
+        };
+        let first = job_system.spawn(push(1));
+        let second = job_system.spawn_after(slice::from_ref(&first), push(2));
+        let third = job_system.spawn_after(&[first, second.clone()], push(3));
+        let fence = job_system.fence(&[second, third]);
+
+        job_system.wait(&fence).unwrap();
+        assert_eq!(*lock(&order), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn job_system_without_workers() {
+        let job_system = JobSystem::new(0).unwrap();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let counter_clone = counter.clone();
+        let handle = job_system.spawn(move || { counter_clone.fetch_add(1, Ordering::SeqCst); });
+
+        //Nothing runs until a thread waits for the job.
+        assert!(!handle.is_complete());
+        job_system.wait(&handle).unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn job_system_panics_cancel_dependents() {
+        let job_system = JobSystem::new(2).unwrap();
+        let failing = job_system.spawn(|| panic!("job failure"));
+        let dependent = job_system.spawn_after(slice::from_ref(&failing), || {});
+
+        match job_system.wait(&failing) {
+            Err(JobError::JobPanicked(message)) => assert_eq!(message, "job failure"),
+            other => panic!("Expected a JobPanicked error, got {:?}", other),
+        }
+        match job_system.wait(&dependent) {
+            Err(JobError::DependencyFailed(_)) => {},
+            other => panic!("Expected a DependencyFailed error, got {:?}", other),
+        }
+
+        //The workers survived the panic.
+        let handle = job_system.spawn(|| {});
+        assert!(job_system.wait(&handle).is_ok());
+    }
+
+    #[test]
+    fn job_system_parallel_for() {
+        let job_system = JobSystem::new(3).unwrap();
+        let mut data: Vec<usize> = vec![0; 1000];
+        let offset = 10;
+
+        job_system.parallel_for(&mut data, 64, |index, value| *value = index + offset).unwrap();
+        assert!(data.iter().enumerate().all(|(index, value)| *value == index + offset));
+
+        let mut empty: Vec<usize> = Vec::new();
+        assert!(job_system.parallel_for(&mut empty, 0, |_, _| {}).is_ok());
+    }
+
+    #[test]
+    fn job_system_frame_stacks() {
+        let job_system = JobSystem::new(1).unwrap();
+        let other_system = JobSystem::new(1).unwrap();
+        assert_ne!(job_system.id(), other_system.id());
+
+        //The workers of another job system, with the same indices, use the shared stack.
+        let frame_stacks = Arc::new(FrameStacks::new(job_system.id(), 1, 64, 64));
+        let handles: Vec<JobHandle> = [&job_system, &other_system].iter().map(|system| {
+            let frame_stacks = frame_stacks.clone();
+            system.spawn(move || { frame_stacks.current().allocate(|| 1u8).unwrap(); })
+        }).collect();
+        //Not wait(): the calling thread would run the jobs.
+        while !handles.iter().all(JobHandle::is_complete) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(frame_stacks.worker(0).unwrap().used(), 1);
+        assert_eq!(frame_stacks.shared().used(), 1);
+    }
+
+    #[test]
+    fn job_system_nested_jobs() {
+        let job_system = Arc::new(JobSystem::new(2).unwrap());
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let inner_system = job_system.clone();
+        let inner_counter = counter.clone();
+        let outer = job_system.spawn(move || {
+            let handles: Vec<JobHandle> = (0..10).map(|_| {
+                let counter = inner_counter.clone();
+                inner_system.spawn(move || { counter.fetch_add(1, Ordering::SeqCst); })
+            }).collect();
+            //Waiting from a worker executes the jobs instead of blocking it.
+            inner_system.wait_all(&handles).unwrap();
+        });
+
+        job_system.wait(&outer).unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 10);
+        assert!(job_system.current_worker().is_none());
+    }
+}
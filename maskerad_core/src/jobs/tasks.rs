@@ -0,0 +1,470 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll, Wake, Waker};
+use jobs::job_error::{JobError, JobResult};
+use jobs::job_system::{self, JobHandle, JobSystem, Spawner};
+use logging::crash_report;
+
+/*
+TASKS.
+
+A task is a Future executed by the job system. When a task awaits something not ready yet (a job
+loading a file, another task...), it is suspended and the worker executes other jobs. The task is
+queued again as a job when it is woken up.
+
+Long-running gameplay sequences, like "load the level, then spawn the actors, then fade in",
+are written as sequential steps without blocking any worker. The crate uses the 2015 edition,
+without async blocks: the steps are chained with the combinators of TaskExt. The closure of a step
+is called when the previous step is complete, and the task awaits the future it returns:
+
+    let spawner = job_system.spawner();
+    let actors = spawner.clone();
+    let level = job_system.spawn_task(spawner.spawn(|| load_level())
+        .and_then(move |()| actors.spawn(|| spawn_actors()))
+        .and_then(|()| future::ready(Ok(fade_in()))));
+
+and_then() stops the sequence at the first error, a job or a task which panicked. then() gives the
+output of the previous step as it is. The futures which aren't made of steps can still be written
+by hand, with std::future::poll_fn.
+
+A task is polled by one worker at a time. A task woken up while it is polled is queued again when
+the poll returns, instead of blocking another worker until the poll is over.
+
+Awaiting a JobHandle or a TaskHandle returns a JobResult, an error if the job or the task panicked.
+*/
+
+type BoxedFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+//The states of a task.
+const IDLE: usize = 0;
+//A job polling the task is queued.
+const SCHEDULED: usize = 1;
+const RUNNING: usize = 2;
+//Woken up while running: the task must be polled again.
+const NOTIFIED: usize = 3;
+const COMPLETE: usize = 4;
+
+struct TaskCell<T> {
+    future: Mutex<Option<BoxedFuture<T>>>,
+    output: Mutex<Option<T>>,
+    state: AtomicUsize,
+    spawner: Spawner,
+    completion: JobHandle,
+}
+
+impl<T: Send + 'static> TaskCell<T> {
+    fn schedule(self: &Arc<Self>) {
+        loop {
+            let state = self.state.load(Ordering::Acquire);
+            let next = match state {
+                IDLE => SCHEDULED,
+                RUNNING => NOTIFIED,
+                _ => return,
+            };
+            if self.state.compare_exchange(state, next, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                if next == SCHEDULED {
+                    self.spawn_poll();
+                }
+                return;
+            }
+        }
+    }
+
+    fn spawn_poll(self: &Arc<Self>) {
+        let task = self.clone();
+        self.spawner.spawn(move || task.poll());
+    }
+
+    fn poll(self: &Arc<Self>) {
+        if self.state.compare_exchange(SCHEDULED, RUNNING, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return;
+        }
+
+        let result = {
+            let mut future_slot = job_system::lock(&self.future);
+            let result = match future_slot.as_mut() {
+                Some(future) => {
+                    let waker = Waker::from(self.clone());
+                    let mut context = Context::from_waker(&waker);
+                    match crash_report::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut context))) {
+                        Ok(Poll::Pending) => None,
+                        Ok(Poll::Ready(output)) => {
+                            *job_system::lock(&self.output) = Some(output);
+                            Some(Ok(()))
+                        },
+                        Err(payload) => {
+                            let message = job_system::panic_message(&*payload);
+                            error!("A task panicked: {}", message);
+                            Some(Err(message))
+                        },
+                    }
+                },
+                //The task is already complete.
+                None => return,
+            };
+            if result.is_some() {
+                *future_slot = None;
+            }
+            result
+        };
+
+        match result {
+            Some(result) => {
+                self.state.store(COMPLETE, Ordering::Release);
+                self.spawner.complete(&self.completion, result);
+            },
+            None => {
+                //Woken up during the poll: queue the task again, the future lock has been released.
+                if self.state.compare_exchange(RUNNING, IDLE, Ordering::AcqRel, Ordering::Acquire).is_err() {
+                    self.state.store(SCHEDULED, Ordering::Release);
+                    self.spawn_poll();
+                }
+            },
+        }
+    }
+}
+
+impl<T: Send + 'static> Wake for TaskCell<T> {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.schedule();
+    }
+}
+
+pub struct TaskHandle<T> {
+    cell: Arc<TaskCell<T>>,
+}
+
+impl<T> fmt::Debug for TaskHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TaskHandle [{:?}]", self.cell.completion)
+    }
+}
+
+impl<T> TaskHandle<T> {
+    //True when the task has returned or has panicked.
+    pub fn is_complete(&self) -> bool {
+        self.cell.completion.is_complete()
+    }
+
+    //A handle complete when the task is complete, to spawn jobs after the task.
+    pub fn job_handle(&self) -> &JobHandle {
+        &self.cell.completion
+    }
+
+    fn take_output(&self) -> JobResult<T> {
+        match job_system::lock(&self.cell.output).take() {
+            Some(output) => Ok(output),
+            None => Err(JobError::JobPanicked(String::from("The output of the task has already been taken."))),
+        }
+    }
+
+    //Execute the pending jobs until the task is complete, and return its output.
+    pub fn join(self, job_system: &JobSystem) -> JobResult<T> {
+        job_system.wait(&self.cell.completion)?;
+        self.take_output()
+    }
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = JobResult<T>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let mut completion = self.cell.completion.clone();
+        match Pin::new(&mut completion).poll(context) {
+            Poll::Ready(Ok(())) => Poll::Ready(self.take_output()),
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Spawner {
+    //Spawn a future, polled by the workers of the job system.
+    pub fn spawn_task<F>(&self, future: F) -> TaskHandle<F::Output> where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let cell = Arc::new(TaskCell {
+            future: Mutex::new(Some(Box::pin(future) as BoxedFuture<F::Output>)),
+            output: Mutex::new(None),
+            state: AtomicUsize::new(IDLE),
+            spawner: self.clone(),
+            completion: self.pending_handle(),
+        });
+        cell.schedule();
+
+        TaskHandle {
+            cell,
+        }
+    }
+}
+
+impl JobSystem {
+    pub fn spawn_task<F>(&self, future: F) -> TaskHandle<F::Output> where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.spawner().spawn_task(future)
+    }
+}
+
+//The sequential steps of a task, built by TaskExt::then().
+pub struct Then<A, B, F> {
+    first: Option<A>,
+    next: Option<F>,
+    second: Option<B>,
+}
+
+impl<A, B, F> Future for Then<A, B, F> where
+    A: Future + Unpin,
+    B: Future + Unpin,
+    F: FnOnce(A::Output) -> B + Unpin,
+{
+    type Output = B::Output;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<B::Output> {
+        let this = self.get_mut();
+        if let Some(first) = this.first.as_mut() {
+            match Pin::new(first).poll(context) {
+                Poll::Ready(output) => {
+                    this.first = None;
+                    let next = this.next.take().expect("The next step of a task is called once.");
+                    this.second = Some(next(output));
+                },
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        match this.second.as_mut() {
+            Some(second) => Pin::new(second).poll(context),
+            None => panic!("A sequence of steps has been polled after its completion."),
+        }
+    }
+}
+
+//The sequential steps of a task stopping at the first error, built by TaskExt::and_then().
+pub struct AndThen<A, B, F> {
+    first: Option<A>,
+    next: Option<F>,
+    second: Option<B>,
+}
+
+impl<A, B, F, T, U> Future for AndThen<A, B, F> where
+    A: Future<Output = JobResult<T>> + Unpin,
+    B: Future<Output = JobResult<U>> + Unpin,
+    F: FnOnce(T) -> B + Unpin,
+{
+    type Output = JobResult<U>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<JobResult<U>> {
+        let this = self.get_mut();
+        if let Some(first) = this.first.as_mut() {
+            match Pin::new(first).poll(context) {
+                Poll::Ready(Ok(output)) => {
+                    this.first = None;
+                    let next = this.next.take().expect("The next step of a task is called once.");
+                    this.second = Some(next(output));
+                },
+                Poll::Ready(Err(error)) => {
+                    this.first = None;
+                    this.next = None;
+                    return Poll::Ready(Err(error));
+                },
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        match this.second.as_mut() {
+            Some(second) => Pin::new(second).poll(context),
+            None => panic!("A sequence of steps has been polled after its completion."),
+        }
+    }
+}
+
+//The combinators chaining the steps of a task. Implemented by every future which can be moved while
+//polled (Unpin): the job handles, the task handles, std::future::ready()...
+pub trait TaskExt: Future + Unpin + Sized {
+    //Await this future, then the future made from its output.
+    fn then<B, F>(self, next: F) -> Then<Self, B, F> where
+        B: Future + Unpin,
+        F: FnOnce(Self::Output) -> B + Unpin,
+    {
+        Then {
+            first: Some(self),
+            next: Some(next),
+            second: None,
+        }
+    }
+
+    //Await this future, then the future made from its output if it succeeded. An error skips the next step.
+    fn and_then<T, U, B, F>(self, next: F) -> AndThen<Self, B, F> where
+        Self: Future<Output = JobResult<T>>,
+        B: Future<Output = JobResult<U>> + Unpin,
+        F: FnOnce(T) -> B + Unpin,
+    {
+        AndThen {
+            first: Some(self),
+            next: Some(next),
+            second: None,
+        }
+    }
+}
+
+impl<F: Future + Unpin> TaskExt for F {}
+
+//A future suspending the task once, to let the worker execute the other jobs.
+#[derive(Debug, Default)]
+pub struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+pub fn yield_now() -> YieldNow {
+    YieldNow(false)
+}
+
+#[cfg(test)]
+mod tasks_test {
+    use super::*;
+    use std::future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn task_sequential_steps() {
+        let job_system = JobSystem::new(2).unwrap();
+        let spawner = job_system.spawner();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let task_log = log.clone();
+        let mut step = 0;
+        let mut loading: Option<JobHandle> = None;
+        let task = job_system.spawn_task(future::poll_fn(move |context| {
+            loop {
+                match step {
+                    0 => {
+                        let log = task_log.clone();
+                        loading = Some(spawner.spawn(move || log.lock().unwrap().push("load level")));
+                        step = 1;
+                    },
+                    1 => {
+                        match Pin::new(loading.as_mut().unwrap()).poll(context) {
+                            Poll::Ready(result) => result.unwrap(),
+                            Poll::Pending => return Poll::Pending,
+                        }
+                        task_log.lock().unwrap().push("spawn actors");
+                        step = 2;
+                    },
+                    _ => {
+                        task_log.lock().unwrap().push("fade in");
+                        return Poll::Ready(42);
+                    },
+                }
+            }
+        }));
+
+        assert_eq!(task.join(&job_system).unwrap(), 42);
+        assert_eq!(*log.lock().unwrap(), vec!["load level", "spawn actors", "fade in"]);
+    }
+
+    #[test]
+    fn task_combinators() {
+        let job_system = JobSystem::new(2).unwrap();
+        let spawner = job_system.spawner();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        //The second job is spawned after the first one, slower, is complete.
+        let (load_log, actors_log, fade_log) = (log.clone(), log.clone(), log.clone());
+        let actors = spawner.clone();
+        let task = job_system.spawn_task(spawner.spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            load_log.lock().unwrap().push("load level");
+        })
+            .and_then(move |()| actors.spawn(move || actors_log.lock().unwrap().push("spawn actors")))
+            .and_then(move |()| {
+                fade_log.lock().unwrap().push("fade in");
+                future::ready(Ok(42))
+            })
+            .then(|result: JobResult<u32>| future::ready(result.map(|value| value + 1))));
+
+        assert_eq!(task.join(&job_system).unwrap().unwrap(), 43);
+        assert_eq!(*log.lock().unwrap(), vec!["load level", "spawn actors", "fade in"]);
+
+        //A failed step stops the sequence.
+        let skipped = Arc::new(AtomicUsize::new(0));
+        let next = skipped.clone();
+        let task = job_system.spawn_task(spawner.spawn(|| panic!("no level"))
+            .and_then(move |()| {
+                next.fetch_add(1, Ordering::SeqCst);
+                future::ready(Ok(()))
+            }));
+        match task.join(&job_system).unwrap() {
+            Err(JobError::JobPanicked(message)) => assert_eq!(message, "no level"),
+            other => panic!("Expected a JobPanicked error, got {:?}", other),
+        }
+        assert_eq!(skipped.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn task_awaits_other_task() {
+        //Without workers, the tasks are executed by the thread joining them.
+        let job_system = JobSystem::new(0).unwrap();
+        let polls = Arc::new(AtomicUsize::new(0));
+
+        let inner_polls = polls.clone();
+        let mut yielding = yield_now();
+        let mut inner = job_system.spawn_task(future::poll_fn(move |context| {
+            inner_polls.fetch_add(1, Ordering::SeqCst);
+            match Pin::new(&mut yielding).poll(context) {
+                Poll::Ready(()) => Poll::Ready(String::from("actors")),
+                Poll::Pending => Poll::Pending,
+            }
+        }));
+        let inner_job = inner.job_handle().clone();
+        let outer = job_system.spawn_task(future::poll_fn(move |context| {
+            Pin::new(&mut inner).poll(context).map(|result| result.unwrap().len())
+        }));
+
+        assert_eq!(outer.join(&job_system).unwrap(), 6);
+        assert!(inner_job.is_complete());
+        assert_eq!(polls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn task_panic() {
+        let job_system = JobSystem::new(1).unwrap();
+        let task = job_system.spawn_task(future::poll_fn(|_| -> Poll<u32> { panic!("task failure") }));
+        let after = job_system.spawn_after(&[task.job_handle().clone()], || {});
+
+        match task.join(&job_system) {
+            Err(JobError::JobPanicked(message)) => assert_eq!(message, "task failure"),
+            other => panic!("Expected a JobPanicked error, got {:?}", other),
+        }
+        assert!(job_system.wait(&after).is_err());
+    }
+}
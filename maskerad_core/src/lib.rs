@@ -11,6 +11,11 @@ pub mod engine_configuration;
 pub mod filesystem;
 pub mod localization;
 pub mod allocators;
+pub mod jobs;
+pub mod math;
+pub mod tweening;
+pub mod logging;
+pub mod maskerad_error;
 
 extern crate maskerad_memory_allocators;
 
@@ -18,6 +23,10 @@ extern crate time;
 
 extern crate remove_dir_all;
 
+extern crate lz4_flex;
+extern crate zstd;
+extern crate flate2;
+
 #[macro_use]
 extern crate log;
 
@@ -29,4 +38,7 @@ extern crate rand;
 
 extern crate serde;
 #[macro_use]
-extern crate serde_derive;
\ No newline at end of file
+extern crate serde_derive;
+
+#[cfg(test)]
+extern crate proptest;
\ No newline at end of file
@@ -17,9 +17,6 @@ pub enum LocalizationError {
     JSONError(String, JSONError),
 }
 
-unsafe impl Send for LocalizationError {}
-unsafe impl Sync for LocalizationError {}
-
 impl fmt::Display for LocalizationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -34,18 +31,7 @@ impl fmt::Display for LocalizationError {
 }
 
 impl Error for LocalizationError {
-    fn description(&self) -> &str {
-        match self {
-            &LocalizationError::IOError(_, _) => {
-                "I/O error"
-            },
-            &LocalizationError::JSONError(_, _) => {
-                "JSON error"
-            },
-        }
-    }
-
-    fn cause(&self) -> Option<&Error> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             &LocalizationError::IOError(_, ref io_error) => {
                 Some(io_error)
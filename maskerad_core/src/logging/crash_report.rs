@@ -0,0 +1,299 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::Write;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use time;
+use allocators::stats::MemoryReport;
+use engine_configuration::engine_config::EngineConfig;
+use filesystem::game_directories::{GameDirectories, RootDir};
+use logging::log_buffer::LogBuffer;
+use logging::logger::current_frame;
+use logging::logging_error::{LoggingError, LoggingResult};
+
+/*
+CRASH REPORTS.
+
+When the game panics, the crash handler writes a crash report directory, which players can attach to bug reports:
+
+<engine log root>/crash_reports/crash_<date>_<time>/
+    report.txt          the panic message, its location, the thread, the frame number and the time.
+    backtrace.txt       the backtrace of the panicking thread.
+    log.txt             the last lines of the log ring buffer.
+    engine_config.toml  the engine configuration used by the game.
+    memory.txt          the memory report of the allocators, if a memory report provider has been given.
+
+The previous panic hook is still called after the report has been written.
+
+The panics caught by the engine, like the panics of the jobs which are reported by their JobHandle, aren't crashes:
+no report is written for a panic inside crash_report::catch_unwind().
+
+The allocators aren't Sync, the memory report can't be read from the panicking thread: the memory report
+provider is a function, which can return a report updated by the game loop (in a Mutex for example).
+*/
+
+//The name of the crash reports directory, in the engine log root.
+pub const CRASH_REPORTS_DIRECTORY: &str = "crash_reports";
+
+thread_local! {
+    //True while the thread runs a closure whose panics are caught.
+    static CATCHING_PANICS: Cell<bool> = const { Cell::new(false) };
+}
+
+//panic::catch_unwind, without crash report when the closure panics.
+pub fn catch_unwind<F, R>(f: F) -> thread::Result<R> where
+    F: FnOnce() -> R + panic::UnwindSafe
+{
+    let previous = CATCHING_PANICS.with(|catching| catching.replace(true));
+    let result = panic::catch_unwind(f);
+    CATCHING_PANICS.with(|catching| catching.set(previous));
+    result
+}
+
+fn is_catching_panics() -> bool {
+    CATCHING_PANICS.with(|catching| catching.get())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashReport {
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: String,
+    pub frame: u64,
+    pub timestamp: String,
+    pub backtrace: String,
+    pub log_lines: Vec<String>,
+    pub engine_config: Option<String>,
+    pub memory_report: Option<String>,
+}
+
+impl CrashReport {
+    //Write the report in a new directory, in the given directory. Return the path of the new directory.
+    pub fn write_to<P>(&self, directory: P) -> LoggingResult<PathBuf> where
+        P: AsRef<Path>
+    {
+        let name = format!("crash_{}", time::strftime("%Y%m%d_%H%M%S", &time::now()).unwrap_or_else(|_| self.frame.to_string()));
+        let mut report_directory = directory.as_ref().join(name.as_str());
+        let mut index = 1;
+        while report_directory.exists() {
+            report_directory = directory.as_ref().join(format!("{}_{}", name, index));
+            index += 1;
+        }
+        fs::create_dir_all(report_directory.as_path())?;
+
+        let mut summary = File::create(report_directory.join("report.txt"))?;
+        writeln!(summary, "message: {}", self.message)?;
+        writeln!(summary, "location: {}", self.location.as_deref().unwrap_or("unknown"))?;
+        writeln!(summary, "thread: {}", self.thread)?;
+        writeln!(summary, "frame: {}", self.frame)?;
+        writeln!(summary, "time: {}", self.timestamp)?;
+
+        File::create(report_directory.join("backtrace.txt"))?.write_all(self.backtrace.as_bytes())?;
+
+        let mut log = File::create(report_directory.join("log.txt"))?;
+        for line in self.log_lines.iter() {
+            writeln!(log, "{}", line)?;
+        }
+
+        if let Some(ref engine_config) = self.engine_config {
+            File::create(report_directory.join("engine_config.toml"))?.write_all(engine_config.as_bytes())?;
+        }
+
+        if let Some(ref memory_report) = self.memory_report {
+            File::create(report_directory.join("memory.txt"))?.write_all(memory_report.as_bytes())?;
+        }
+
+        Ok(report_directory)
+    }
+}
+
+pub struct CrashHandler {
+    report_directory: PathBuf,
+    buffer: Option<LogBuffer>,
+    log_lines: usize,
+    engine_config: Option<String>,
+    memory_report: Option<Box<dyn Fn() -> MemoryReport + Send + Sync>>,
+}
+
+impl CrashHandler {
+    //A crash handler writing its reports in the given directory.
+    pub fn new<P>(report_directory: P) -> Self where
+        P: Into<PathBuf>
+    {
+        CrashHandler {
+            report_directory: report_directory.into(),
+            buffer: None,
+            log_lines: 100,
+            engine_config: None,
+            memory_report: None,
+        }
+    }
+
+    //A crash handler writing its reports in the crash reports directory of the engine log root.
+    pub fn from_directories(directories: &GameDirectories) -> LoggingResult<Self> {
+        let log_directory = directories.get(&RootDir::EngineLogRoot).ok_or_else(|| {
+            LoggingError::DirectoryError(format!("The game directories don't have a {}.", RootDir::EngineLogRoot))
+        })?;
+        Ok(CrashHandler::new(log_directory.join(CRASH_REPORTS_DIRECTORY)))
+    }
+
+    pub fn report_directory(&self) -> &Path {
+        self.report_directory.as_path()
+    }
+
+    //The ring buffer of the engine logger, from which the last log lines are taken.
+    pub fn set_log_buffer(&mut self, buffer: LogBuffer) -> &mut Self {
+        self.buffer = Some(buffer);
+        self
+    }
+
+    //The number of log lines in the report.
+    pub fn set_log_lines(&mut self, log_lines: usize) -> &mut Self {
+        self.log_lines = log_lines;
+        self
+    }
+
+    //The configuration is serialized now: the report contains the configuration as it was when it was given.
+    pub fn set_engine_config(&mut self, config: &EngineConfig) -> LoggingResult<&mut Self> {
+        let mut toml = Vec::new();
+        config.save_to_toml(&mut toml).map_err(|config_error| {
+            LoggingError::ConfigError(String::from("Could not serialize the engine configuration for the crash reports."), config_error)
+        })?;
+        self.engine_config = Some(String::from_utf8_lossy(toml.as_slice()).into_owned());
+        Ok(self)
+    }
+
+    pub fn set_memory_report<F>(&mut self, memory_report: F) -> &mut Self where
+        F: Fn() -> MemoryReport + Send + Sync + 'static
+    {
+        self.memory_report = Some(Box::new(memory_report));
+        self
+    }
+
+    //Create the report of a panic.
+    pub fn report<S>(&self, message: S, location: Option<String>) -> CrashReport where
+        S: Into<String>
+    {
+        let log_lines = match self.buffer {
+            Some(ref buffer) => {
+                let records = buffer.records();
+                let skipped = records.len().saturating_sub(self.log_lines);
+                records.iter().skip(skipped).map(|record| record.to_string()).collect()
+            },
+            None => Vec::new(),
+        };
+
+        CrashReport {
+            message: message.into(),
+            location,
+            thread: thread::current().name().unwrap_or("<unnamed>").to_string(),
+            frame: current_frame(),
+            timestamp: time::now().rfc3339().to_string(),
+            backtrace: Backtrace::force_capture().to_string(),
+            log_lines,
+            engine_config: self.engine_config.clone(),
+            memory_report: self.memory_report.as_ref().map(|memory_report| memory_report().to_string()),
+        }
+    }
+
+    //Install the crash handler as the panic hook. The previous hook is called after the report has been written.
+    pub fn install(self) {
+        debug!("Installing the crash handler, writing the crash reports in {}.", self.report_directory.display());
+        let handler = Arc::new(self);
+        let previous_hook = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if is_catching_panics() {
+                previous_hook(info);
+                return;
+            }
+
+            let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = info.payload().downcast_ref::<String>() {
+                message.clone()
+            } else {
+                String::from("unknown panic payload")
+            };
+            let location = info.location().map(|location| format!("{}:{}:{}", location.file(), location.line(), location.column()));
+
+            let report = handler.report(message, location);
+            match report.write_to(handler.report_directory.as_path()) {
+                Ok(path) => error!("The game crashed, a crash report has been written in {}.", path.display()),
+                Err(report_error) => error!("The game crashed, but the crash report could not be written: {}", report_error),
+            }
+            log::logger().flush();
+
+            previous_hook(info);
+        }));
+    }
+}
+
+#[cfg(test)]
+mod crash_report_test {
+    use super::*;
+    use std::env;
+    use log::Level;
+    use remove_dir_all::remove_dir_all;
+    use allocators::stats::MemoryStats;
+    use logging::log_buffer::LogRecord;
+
+    #[test]
+    fn crash_report_bundle() {
+        let directory = env::temp_dir().join("maskerad_crash_report_test");
+        if directory.exists() {
+            remove_dir_all(directory.as_path()).unwrap();
+        }
+
+        let buffer = LogBuffer::new(16);
+        for index in 0..5 {
+            buffer.push(LogRecord {
+                frame: index,
+                timestamp: String::new(),
+                level: Level::Info,
+                target: String::from("test"),
+                message: format!("line {}", index),
+                fields: Vec::new(),
+            });
+        }
+
+        let mut handler = CrashHandler::new(directory.as_path());
+        handler
+            .set_log_buffer(buffer)
+            .set_log_lines(2)
+            .set_memory_report(|| {
+                let mut report = MemoryReport::new();
+                report.add("frame", MemoryStats::new(10, 100, 20, 1, 0, None));
+                report
+            })
+            .set_engine_config(&EngineConfig::new("FR", None)).unwrap();
+
+        let report = handler.report("something went wrong", Some(String::from("src/main.rs:1:1")));
+        assert_eq!(report.log_lines.len(), 2);
+        assert!(report.log_lines[1].ends_with("line 4"));
+
+        let path = report.write_to(directory.as_path()).unwrap();
+        let other_path = report.write_to(directory.as_path()).unwrap();
+        assert_ne!(path, other_path);
+
+        let summary = fs::read_to_string(path.join("report.txt")).unwrap();
+        assert!(summary.contains("message: something went wrong"));
+        assert!(summary.contains("location: src/main.rs:1:1"));
+        assert!(fs::read_to_string(path.join("engine_config.toml")).unwrap().contains("locale = \"FR\""));
+        assert!(fs::read_to_string(path.join("memory.txt")).unwrap().starts_with("frame: "));
+        assert_eq!(fs::read_to_string(path.join("log.txt")).unwrap().lines().count(), 2);
+        assert!(path.join("backtrace.txt").exists());
+
+        remove_dir_all(directory.as_path()).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use log::Level;
+
+/*
+LOG BUFFER.
+
+The last records written by the engine logger, kept in memory for the debug console.
+
+The buffer is a ring buffer: when it's full, the oldest record is dropped.
+The buffer can be cloned, the clones share the same records.
+*/
+
+//A record, as written by the engine logger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub frame: u64,
+    pub timestamp: String,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    //The structured key/value fields, in the order they were given.
+    pub fields: Vec<(String, String)>,
+}
+
+impl LogRecord {
+    //The value of the field with the given key.
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(field_key, _)| field_key == key).map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}][frame {}][{}][{}] {}", self.timestamp, self.frame, self.level, self.target, self.message)?;
+        for (key, value) in self.fields.iter() {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    records: Arc<Mutex<VecDeque<LogRecord>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        LogBuffer {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn push(&self, record: LogRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    //The records, from the oldest to the newest.
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter().cloned().collect()
+    }
+
+    //The records with the given level or a more severe one.
+    pub fn records_with_level(&self, level: Level) -> Vec<LogRecord> {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .filter(|record| record.level <= level)
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+    }
+}
+
+#[cfg(test)]
+mod log_buffer_test {
+    use super::*;
+
+    fn record(message: &str, level: Level) -> LogRecord {
+        LogRecord {
+            frame: 0,
+            timestamp: String::new(),
+            level,
+            target: String::from("test"),
+            message: String::from(message),
+            fields: vec![(String::from("key"), String::from("value"))],
+        }
+    }
+
+    #[test]
+    fn ring_buffer_drops_oldest_records() {
+        let buffer = LogBuffer::new(2);
+        let shared = buffer.clone();
+        buffer.push(record("first", Level::Info));
+        buffer.push(record("second", Level::Error));
+        shared.push(record("third", Level::Debug));
+
+        let messages: Vec<String> = buffer.records().into_iter().map(|record| record.message).collect();
+        assert_eq!(messages, vec![String::from("second"), String::from("third")]);
+        assert_eq!(buffer.records_with_level(Level::Warn).len(), 1);
+        assert_eq!(buffer.records()[0].field("key"), Some("value"));
+
+        shared.clear();
+        assert!(buffer.is_empty());
+    }
+}
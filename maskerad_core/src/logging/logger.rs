@@ -0,0 +1,283 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use log::{self, Level, LevelFilter, Log, Metadata, Record};
+use log::kv::{self, Key, Value, VisitSource};
+use time;
+use engine_configuration::engine_config::EngineConfig;
+use engine_configuration::log_config::LogConfig;
+use filesystem::game_directories::{GameDirectories, RootDir};
+use logging::log_buffer::{LogBuffer, LogRecord};
+use logging::logging_error::{LoggingError, LoggingResult};
+use logging::rotating_file::RotatingFile;
+
+/*
+ENGINE LOGGER.
+
+The backend of the log macros used by every module of the engine.
+
+Each record is:
+- stamped with the time and the current frame number,
+- filtered with the level of its module (the longest module path of the configuration matching its target),
+- written to the console (errors and warnings to stderr), to a rotating file in the engine log root,
+  and to a ring buffer which can be displayed by a debug console.
+
+Records can have structured key/value fields:
+
+info!(path = resource_path.display().to_string(), size = 1024; "Resource loaded.");
+
+which are written after the message: "... Resource loaded. path=... size=1024".
+
+The game loop calls set_frame() (or next_frame()) once per frame.
+*/
+
+//The name of the log file, in the engine log root.
+pub const LOG_FILE_NAME: &str = "maskerad.log";
+
+static CURRENT_FRAME: AtomicU64 = AtomicU64::new(0);
+
+//The frame number stamped on the records.
+pub fn current_frame() -> u64 {
+    CURRENT_FRAME.load(Ordering::Relaxed)
+}
+
+pub fn set_frame(frame: u64) {
+    CURRENT_FRAME.store(frame, Ordering::Relaxed);
+}
+
+//Increment the frame number, and return the new one.
+pub fn next_frame() -> u64 {
+    CURRENT_FRAME.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+fn parse_level(level: &str) -> LoggingResult<LevelFilter> {
+    LevelFilter::from_str(level).map_err(|_| {
+        LoggingError::LevelError(format!("{} is not a log level (off, error, warn, info, debug or trace).", level))
+    })
+}
+
+//Collect the key/value fields of a record.
+struct FieldCollector(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct EngineLogger {
+    default_level: LevelFilter,
+    //Sorted from the longest module path to the shortest one.
+    module_levels: Vec<(String, LevelFilter)>,
+    console: bool,
+    file: Option<Mutex<RotatingFile>>,
+    buffer: LogBuffer,
+}
+
+impl EngineLogger {
+    //Create a logger from the configuration. The log file is created in log_directory, if the configuration
+    //enables it and a directory is given.
+    pub fn new(config: &LogConfig, log_directory: Option<&Path>) -> LoggingResult<Self> {
+        let default_level = parse_level(config.level())?;
+
+        let mut module_levels = Vec::with_capacity(config.modules().len());
+        for (module, level) in config.modules().iter() {
+            module_levels.push((module.clone(), parse_level(level.as_str())?));
+        }
+        module_levels.sort_by_key(|(module, _)| ::std::cmp::Reverse(module.len()));
+
+        let file = match log_directory {
+            Some(directory) if config.file() => {
+                Some(Mutex::new(RotatingFile::open(directory, LOG_FILE_NAME, config.max_file_size(), config.max_files())?))
+            },
+            _ => None,
+        };
+
+        Ok(EngineLogger {
+            default_level,
+            module_levels,
+            console: config.console(),
+            file,
+            buffer: LogBuffer::new(config.ring_buffer_capacity()),
+        })
+    }
+
+    //The ring buffer of the logger. Clones share the same records.
+    pub fn buffer(&self) -> LogBuffer {
+        self.buffer.clone()
+    }
+
+    //The level of the module, or of the closest parent module with a level.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.module_levels.iter()
+            .find(|(module, _)| {
+                target == module.as_str() || (target.starts_with(module.as_str()) && target[module.len()..].starts_with("::"))
+            })
+            .map(|&(_, level)| level)
+            .unwrap_or(self.default_level)
+    }
+
+    //The most verbose level of the logger, for log::set_max_level.
+    pub fn max_level(&self) -> LevelFilter {
+        self.module_levels.iter().map(|&(_, level)| level).fold(self.default_level, |max, level| max.max(level))
+    }
+
+    //Install the logger as the backend of the log macros. Can only be done once per process.
+    pub fn install(self) -> LoggingResult<LogBuffer> {
+        let buffer = self.buffer();
+        let max_level = self.max_level();
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        debug!("The engine logger has been installed, with a max level of {}.", max_level);
+        Ok(buffer)
+    }
+}
+
+impl Log for EngineLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut fields = FieldCollector(Vec::new());
+        //The collector never fails.
+        let _ = record.key_values().visit(&mut fields);
+
+        let log_record = LogRecord {
+            frame: current_frame(),
+            timestamp: time::now().rfc3339().to_string(),
+            level: record.level(),
+            target: String::from(record.target()),
+            message: record.args().to_string(),
+            fields: fields.0,
+        };
+        let line = log_record.to_string();
+
+        if self.console {
+            if log_record.level <= Level::Warn {
+                let _ = writeln!(io::stderr(), "{}", line);
+            } else {
+                let _ = writeln!(io::stdout(), "{}", line);
+            }
+        }
+
+        if let Some(ref file) = self.file {
+            let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            //Logging must not fail: a record which can't be written is only lost.
+            if let Err(error) = file.write_line(line.as_str()) {
+                let _ = writeln!(io::stderr(), "Could not write to the log file {}: {}", file.path().display(), error);
+            }
+        }
+
+        self.buffer.push(log_record);
+    }
+
+    fn flush(&self) {
+        if let Some(ref file) = self.file {
+            let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let _ = file.flush();
+        }
+    }
+}
+
+//Install the engine logger, writing its files in the engine log root.
+pub fn init(config: &EngineConfig, directories: &GameDirectories) -> LoggingResult<LogBuffer> {
+    let log_directory = directories.get(&RootDir::EngineLogRoot).ok_or_else(|| {
+        LoggingError::DirectoryError(format!("The game directories don't have a {}.", RootDir::EngineLogRoot))
+    })?;
+    EngineLogger::new(config.logging(), Some(log_directory))?.install()
+}
+
+#[cfg(test)]
+mod logger_test {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use remove_dir_all::remove_dir_all;
+
+    fn log(logger: &EngineLogger, level: Level, target: &str, message: &str) {
+        logger.log(&Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{}", message))
+            .build());
+    }
+
+    #[test]
+    fn module_levels() {
+        let mut config = LogConfig::default();
+        config
+            .set_level("warn")
+            .set_console(false)
+            .set_module_level("maskerad_core::filesystem", "trace")
+            .set_module_level("maskerad_core::filesystem::dir_walker", "off");
+        let logger = EngineLogger::new(&config, None).unwrap();
+
+        assert_eq!(logger.level_for("maskerad_core::clock"), LevelFilter::Warn);
+        assert_eq!(logger.level_for("maskerad_core::filesystem"), LevelFilter::Trace);
+        assert_eq!(logger.level_for("maskerad_core::filesystem::filesystem"), LevelFilter::Trace);
+        assert_eq!(logger.level_for("maskerad_core::filesystem::dir_walker"), LevelFilter::Off);
+        //Only whole module names match.
+        assert_eq!(logger.level_for("maskerad_core::filesystem_extra"), LevelFilter::Warn);
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+
+        log(&logger, Level::Info, "maskerad_core::clock", "filtered");
+        log(&logger, Level::Error, "maskerad_core::clock", "kept");
+        log(&logger, Level::Trace, "maskerad_core::filesystem::glob_pattern", "kept");
+        log(&logger, Level::Error, "maskerad_core::filesystem::dir_walker", "filtered");
+        let messages: Vec<String> = logger.buffer().records().into_iter().map(|record| record.message).collect();
+        assert_eq!(messages, vec![String::from("kept"), String::from("kept")]);
+
+        assert!(EngineLogger::new(LogConfig::default().set_level("loud"), None).is_err());
+    }
+
+    #[test]
+    fn records_are_stamped_and_written() {
+        let directory = env::temp_dir().join("maskerad_engine_logger_test");
+        if directory.exists() {
+            remove_dir_all(directory.as_path()).unwrap();
+        }
+
+        let mut config = LogConfig::default();
+        config.set_console(false).set_level("debug");
+        let logger = EngineLogger::new(&config, Some(directory.as_path())).unwrap();
+
+        set_frame(41);
+        assert_eq!(next_frame(), 42);
+        let fields: &[(&str, i32)] = &[("size", 1024), ("count", 3)];
+        logger.log(&Record::builder()
+            .level(Level::Info)
+            .target("maskerad_core::logging")
+            .args(format_args!("Resource loaded."))
+            .key_values(&fields)
+            .build());
+        logger.flush();
+
+        let records = logger.buffer().records();
+        assert_eq!(records.len(), 1);
+        //Other tests may change the frame concurrently.
+        assert!(records[0].frame >= 42);
+        assert_eq!(records[0].field("size"), Some("1024"));
+        assert_eq!(records[0].field("count"), Some("3"));
+
+        let content = fs::read_to_string(directory.join(LOG_FILE_NAME)).unwrap();
+        assert!(content.contains("[INFO][maskerad_core::logging] Resource loaded. size=1024 count=3"));
+
+        remove_dir_all(directory.as_path()).unwrap();
+    }
+}
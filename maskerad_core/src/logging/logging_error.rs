@@ -0,0 +1,79 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Error as IOError;
+use log::SetLoggerError;
+use engine_configuration::engine_config_error::EngineConfigError;
+
+#[derive(Debug)]
+pub enum LoggingError {
+    IOError(String, IOError),
+    LevelError(String),
+    DirectoryError(String),
+    InstallationError(String, SetLoggerError),
+    ConfigError(String, EngineConfigError),
+}
+
+impl fmt::Display for LoggingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &LoggingError::IOError(ref desc, _) => {
+                write!(f, "I/O error: {}", desc)
+            },
+            &LoggingError::LevelError(ref desc) => {
+                write!(f, "Level error: {}", desc)
+            },
+            &LoggingError::DirectoryError(ref desc) => {
+                write!(f, "Directory error: {}", desc)
+            },
+            &LoggingError::InstallationError(ref desc, _) => {
+                write!(f, "Installation error: {}", desc)
+            },
+            &LoggingError::ConfigError(ref desc, _) => {
+                write!(f, "Engine configuration error: {}", desc)
+            },
+        }
+    }
+}
+
+impl Error for LoggingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            &LoggingError::IOError(_, ref io_error) => {
+                Some(io_error)
+            },
+            &LoggingError::LevelError(_) => {
+                None
+            },
+            &LoggingError::DirectoryError(_) => {
+                None
+            },
+            &LoggingError::InstallationError(_, ref set_logger_error) => {
+                Some(set_logger_error)
+            },
+            &LoggingError::ConfigError(_, ref config_error) => {
+                Some(config_error)
+            },
+        }
+    }
+}
+
+pub type LoggingResult<T> = Result<T, LoggingError>;
+
+impl From<IOError> for LoggingError {
+    fn from(error: IOError) -> Self {
+        LoggingError::IOError(String::from("Error while writing the log files."), error)
+    }
+}
+
+impl From<SetLoggerError> for LoggingError {
+    fn from(error: SetLoggerError) -> Self {
+        LoggingError::InstallationError(String::from("A logger has already been installed."), error)
+    }
+}
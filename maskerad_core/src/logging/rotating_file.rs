@@ -0,0 +1,148 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use logging::logging_error::LoggingResult;
+
+/*
+ROTATING FILE.
+
+A log file which is rotated when it becomes too big:
+
+maskerad.log   -> maskerad.log.1
+maskerad.log.1 -> maskerad.log.2
+...
+
+The oldest file, maskerad.log.<max_files>, is deleted. With max_files == 0, the log file is simply truncated.
+*/
+
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    //Open the log file file_name in the directory, creating the directory if needed.
+    //The records are appended to an existing log file.
+    pub fn open<P, S>(directory: P, file_name: S, max_size: u64, max_files: usize) -> LoggingResult<Self> where
+        P: AsRef<Path>,
+        S: AsRef<str>,
+    {
+        fs::create_dir_all(directory.as_ref())?;
+        let path = directory.as_ref().join(file_name.as_ref());
+        let file = OpenOptions::new().create(true).append(true).open(path.as_path())?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            path,
+            file,
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    //The path of the n-th rotated file.
+    pub fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut file_name = self.path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        file_name.push(format!(".{}", index));
+        self.path.with_file_name(file_name)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    //Write a line, rotating the file first if the line doesn't fit.
+    pub fn write_line(&mut self, line: &str) -> LoggingResult<()> {
+        let line_size = line.len() as u64 + 1;
+        if self.size > 0 && self.size + line_size > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.size += line_size;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> LoggingResult<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> LoggingResult<()> {
+        self.file.flush()?;
+
+        if self.max_files > 0 {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(oldest.as_path())?;
+            }
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from.as_path(), self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(self.path.as_path(), self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(self.path.as_path())?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod rotating_file_test {
+    use super::*;
+    use std::env;
+    use std::io::Read;
+    use remove_dir_all::remove_dir_all;
+
+    fn read(path: &Path) -> String {
+        let mut content = String::new();
+        File::open(path).unwrap().read_to_string(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn rotation() {
+        let directory = env::temp_dir().join("maskerad_rotating_file_test");
+        if directory.exists() {
+            remove_dir_all(directory.as_path()).unwrap();
+        }
+
+        let mut file = RotatingFile::open(directory.as_path(), "test.log", 10, 2).unwrap();
+        //Each line is 6 bytes: a single line fits in the file.
+        for line in ["line1", "line2", "line3", "line4"].iter() {
+            file.write_line(line).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(read(file.path()), "line4\n");
+        assert_eq!(read(file.rotated_path(1).as_path()), "line3\n");
+        assert_eq!(read(file.rotated_path(2).as_path()), "line2\n");
+        assert!(!file.rotated_path(3).exists());
+
+        //Reopening appends to the current file.
+        let file = RotatingFile::open(directory.as_path(), "test.log", 10, 2).unwrap();
+        assert_eq!(file.size(), 6);
+
+        remove_dir_all(directory.as_path()).unwrap();
+    }
+}
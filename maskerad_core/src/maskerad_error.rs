@@ -0,0 +1,307 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Error as IOError;
+use allocators::errors::AllocationError;
+use engine_configuration::engine_config_error::EngineConfigError;
+use filesystem::filesystem_error::FileSystemError;
+use jobs::job_error::JobError;
+use localization::localization_error::LocalizationError;
+use logging::logging_error::LoggingError;
+
+/*
+MASKERAD ERROR.
+
+The error type of the game code. The error of every module of the engine converts into a MaskeradError,
+so a single result type can be used with '?':
+
+fn load_level(fs: &Filesystem, path: &Path) -> MaskeradResult<Level> {
+    let file = Filesystem::open(path).with_context(|| format!("Opening the level {}", path.display()))?;
+    ...
+}
+
+The context methods wrap the error in a new layer, describing what was being done. The layers and the errors
+they wrap form a causal chain, walked with Error::source():
+
+Loading the level "forest"          <- context
+Opening the level levels/forest.lvl <- context
+I/O error: ...                      <- the error of the filesystem module
+No such file or directory           <- the cause of the filesystem error
+*/
+
+//The module from which the error comes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    FileSystem,
+    EngineConfig,
+    Localization,
+    Allocation,
+    Job,
+    Logging,
+    Resource,
+    IO,
+    Other,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ErrorKind::FileSystem => write!(f, "filesystem"),
+            &ErrorKind::EngineConfig => write!(f, "engine configuration"),
+            &ErrorKind::Localization => write!(f, "localization"),
+            &ErrorKind::Allocation => write!(f, "allocation"),
+            &ErrorKind::Job => write!(f, "job system"),
+            &ErrorKind::Logging => write!(f, "logging"),
+            &ErrorKind::Resource => write!(f, "resource"),
+            &ErrorKind::IO => write!(f, "I/O"),
+            &ErrorKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Layer {
+    Error(Box<dyn Error + Send + Sync>),
+    Context(String, Box<MaskeradError>),
+}
+
+#[derive(Debug)]
+pub struct MaskeradError {
+    kind: ErrorKind,
+    layer: Layer,
+}
+
+impl MaskeradError {
+    pub fn new<E>(kind: ErrorKind, error: E) -> Self where
+        E: Into<Box<dyn Error + Send + Sync>>
+    {
+        MaskeradError {
+            kind,
+            layer: Layer::Error(error.into()),
+        }
+    }
+
+    //An error without a cause, for the errors of the game code.
+    pub fn msg<S>(message: S) -> Self where
+        S: Into<String>
+    {
+        MaskeradError::new(ErrorKind::Other, message.into())
+    }
+
+    //The kind of the original error, kept by the context layers.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    //Wrap the error in a context layer.
+    pub fn context<S>(self, context: S) -> Self where
+        S: Into<String>
+    {
+        MaskeradError {
+            kind: self.kind,
+            layer: Layer::Context(context.into(), Box::new(self)),
+        }
+    }
+
+    //The contexts, from the outermost to the innermost.
+    pub fn contexts(&self) -> Vec<&str> {
+        let mut contexts = Vec::new();
+        let mut error = self;
+        while let Layer::Context(ref context, ref inner) = error.layer {
+            contexts.push(context.as_str());
+            error = inner;
+        }
+        contexts
+    }
+
+    //The original error, without the contexts.
+    pub fn root(&self) -> &(dyn Error + 'static) {
+        match self.layer {
+            Layer::Error(ref error) => error.as_ref(),
+            Layer::Context(_, ref inner) => inner.root(),
+        }
+    }
+
+    //This error, followed by its sources.
+    pub fn chain(&self) -> Chain<'_> {
+        Chain {
+            next: Some(self),
+        }
+    }
+
+    //The first error of the given type in the chain.
+    pub fn find<E>(&self) -> Option<&E> where
+        E: Error + 'static
+    {
+        self.chain().filter_map(|error| error.downcast_ref::<E>()).next()
+    }
+
+    //The whole causal chain, one error per line.
+    pub fn report(&self) -> String {
+        let mut report = format!("{} error: {}", self.kind, self);
+        for error in self.chain().skip(1) {
+            report.push_str(format!("\ncaused by: {}", error).as_str());
+        }
+        report
+    }
+}
+
+impl fmt::Display for MaskeradError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.layer {
+            Layer::Error(ref error) => write!(f, "{}", error),
+            Layer::Context(ref context, _) => write!(f, "{}", context),
+        }
+    }
+}
+
+impl Error for MaskeradError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.layer {
+            //The wrapped error is displayed by this layer, the chain continues with its source.
+            Layer::Error(ref error) => error.source(),
+            Layer::Context(_, ref inner) => Some(inner.as_ref()),
+        }
+    }
+}
+
+pub type MaskeradResult<T> = Result<T, MaskeradError>;
+
+//Iterator over an error and its sources.
+pub struct Chain<'a> {
+    next: Option<&'a (dyn Error + 'static)>,
+}
+
+impl<'a> Iterator for Chain<'a> {
+    type Item = &'a (dyn Error + 'static);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let error = self.next?;
+        self.next = error.source();
+        Some(error)
+    }
+}
+
+//Add a context to the errors of a result.
+pub trait Context<T> {
+    fn context<S>(self, context: S) -> MaskeradResult<T> where
+        S: Into<String>;
+
+    //The context is only created if there's an error.
+    fn with_context<S, F>(self, context: F) -> MaskeradResult<T> where
+        S: Into<String>,
+        F: FnOnce() -> S;
+}
+
+impl<T, E> Context<T> for Result<T, E> where
+    E: Into<MaskeradError>
+{
+    fn context<S>(self, context: S) -> MaskeradResult<T> where
+        S: Into<String>
+    {
+        self.map_err(|error| error.into().context(context))
+    }
+
+    fn with_context<S, F>(self, context: F) -> MaskeradResult<T> where
+        S: Into<String>,
+        F: FnOnce() -> S
+    {
+        self.map_err(|error| error.into().context(context()))
+    }
+}
+
+impl From<FileSystemError> for MaskeradError {
+    fn from(error: FileSystemError) -> Self {
+        MaskeradError::new(ErrorKind::FileSystem, error)
+    }
+}
+
+impl From<EngineConfigError> for MaskeradError {
+    fn from(error: EngineConfigError) -> Self {
+        MaskeradError::new(ErrorKind::EngineConfig, error)
+    }
+}
+
+impl From<LocalizationError> for MaskeradError {
+    fn from(error: LocalizationError) -> Self {
+        MaskeradError::new(ErrorKind::Localization, error)
+    }
+}
+
+impl From<AllocationError> for MaskeradError {
+    fn from(error: AllocationError) -> Self {
+        MaskeradError::new(ErrorKind::Allocation, error)
+    }
+}
+
+impl From<JobError> for MaskeradError {
+    fn from(error: JobError) -> Self {
+        MaskeradError::new(ErrorKind::Job, error)
+    }
+}
+
+impl From<LoggingError> for MaskeradError {
+    fn from(error: LoggingError) -> Self {
+        MaskeradError::new(ErrorKind::Logging, error)
+    }
+}
+
+impl From<IOError> for MaskeradError {
+    fn from(error: IOError) -> Self {
+        MaskeradError::new(ErrorKind::IO, error)
+    }
+}
+
+#[cfg(test)]
+mod maskerad_error_test {
+    use super::*;
+    use std::io::ErrorKind as IOErrorKind;
+
+    fn open_level() -> MaskeradResult<()> {
+        let io_error = IOError::new(IOErrorKind::NotFound, "No such file or directory");
+        Err(FileSystemError::from(io_error)).context("Opening the level levels/forest.lvl")
+    }
+
+    fn load_level() -> MaskeradResult<()> {
+        open_level().with_context(|| format!("Loading the level {}", "forest"))?;
+        Ok(())
+    }
+
+    #[test]
+    fn context_chain() {
+        let error = load_level().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::FileSystem);
+        assert_eq!(error.to_string(), "Loading the level forest");
+        assert_eq!(error.contexts(), vec!["Loading the level forest", "Opening the level levels/forest.lvl"]);
+
+        let chain: Vec<String> = error.chain().map(|error| error.to_string()).collect();
+        assert_eq!(chain, vec![
+            String::from("Loading the level forest"),
+            String::from("Opening the level levels/forest.lvl"),
+            String::from("I/O error: Error while doing I/O operations"),
+            String::from("No such file or directory"),
+        ]);
+
+        assert!(error.root().is::<FileSystemError>());
+        assert_eq!(error.find::<IOError>().unwrap().kind(), IOErrorKind::NotFound);
+        assert!(error.report().starts_with("filesystem error: Loading the level forest\ncaused by: Opening the level"));
+    }
+
+    #[test]
+    fn errors_without_cause() {
+        let error = MaskeradError::msg("The save file is corrupted.");
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert!(error.source().is_none());
+        assert_eq!(error.chain().count(), 1);
+        assert!(error.contexts().is_empty());
+
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<MaskeradError>();
+    }
+}
@@ -0,0 +1,397 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use cgmath::{Point3, Quaternion, Vector3, InnerSpace, EuclideanSpace, MetricSpace, One, Rotation};
+use math::transform::Transform;
+
+/*
+BOUNDING VOLUMES.
+
+Simple volumes enclosing more complex geometry, used by the culling, the physics and the picking.
+
+- Aabb: an axis-aligned bounding box. The cheapest to test, but loose around rotated objects.
+- Sphere: invariant under rotation.
+- Obb: an oriented bounding box, a box with its own rotation.
+- Capsule: a segment with a radius, the usual shape of a character.
+
+The intersection tests between them are in the intersection module.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    min: Point3<f32>,
+    max: Point3<f32>,
+}
+
+impl Aabb {
+    //The corners can be given in any order.
+    pub fn new(a: Point3<f32>, b: Point3<f32>) -> Self {
+        Aabb {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    pub fn from_center(center: Point3<f32>, half_extents: Vector3<f32>) -> Self {
+        Aabb::new(center + -half_extents, center + half_extents)
+    }
+
+    //The smallest box enclosing all the points, None if there is no point.
+    pub fn from_points(points: &[Point3<f32>]) -> Option<Self> {
+        let (first, others) = points.split_first()?;
+        Some(others.iter().fold(Aabb::new(*first, *first), |aabb, point| aabb.expanded(*point)))
+    }
+
+    pub fn min(&self) -> Point3<f32> {
+        self.min
+    }
+
+    pub fn max(&self) -> Point3<f32> {
+        self.max
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    //The smallest box enclosing this box and the point.
+    pub fn expanded(&self, point: Point3<f32>) -> Aabb {
+        Aabb {
+            min: Point3::new(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z)),
+            max: Point3::new(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z)),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        self.expanded(other.min).expanded(other.max)
+    }
+
+    //The point of the box closest to the given point.
+    pub fn closest_point(&self, point: Point3<f32>) -> Point3<f32> {
+        Point3::new(
+            point.x.max(self.min.x).min(self.max.x),
+            point.y.max(self.min.y).min(self.max.y),
+            point.z.max(self.min.z).min(self.max.z),
+        )
+    }
+
+    //The axis-aligned box enclosing the transformed box.
+    pub fn transformed(&self, transform: &Transform) -> Aabb {
+        let corners = self.corners();
+        let transformed: Vec<Point3<f32>> = corners.iter().map(|corner| transform.transform_point(*corner)).collect();
+        Aabb::from_points(transformed.as_slice()).unwrap_or(*self)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sphere {
+    center: Point3<f32>,
+    radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Point3<f32>, radius: f32) -> Self {
+        Sphere {
+            center,
+            radius: radius.abs(),
+        }
+    }
+
+    //The sphere enclosing the box.
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        Sphere::new(aabb.center(), aabb.half_extents().magnitude())
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.center
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn closest_point(&self, point: Point3<f32>) -> Point3<f32> {
+        let offset = point - self.center;
+        let distance = offset.magnitude();
+        if distance <= self.radius {
+            point
+        } else {
+            self.center + offset * (self.radius / distance)
+        }
+    }
+
+    pub fn transformed(&self, transform: &Transform) -> Sphere {
+        Sphere::new(transform.transform_point(self.center), self.radius * transform.scale())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Obb {
+    center: Point3<f32>,
+    half_extents: Vector3<f32>,
+    rotation: Quaternion<f32>,
+}
+
+impl Obb {
+    pub fn new(center: Point3<f32>, half_extents: Vector3<f32>, rotation: Quaternion<f32>) -> Self {
+        Obb {
+            center,
+            half_extents: Vector3::new(half_extents.x.abs(), half_extents.y.abs(), half_extents.z.abs()),
+            rotation: rotation.normalize(),
+        }
+    }
+
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        Obb::new(aabb.center(), aabb.half_extents(), Quaternion::one())
+    }
+
+    //The box, with its transform applied.
+    pub fn from_transformed_aabb(aabb: &Aabb, transform: &Transform) -> Self {
+        Obb::new(
+            transform.transform_point(aabb.center()),
+            aabb.half_extents() * transform.scale(),
+            transform.rotation(),
+        )
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.center
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        self.half_extents
+    }
+
+    pub fn rotation(&self) -> Quaternion<f32> {
+        self.rotation
+    }
+
+    //The local x, y and z axes of the box, in world space.
+    pub fn axes(&self) -> [Vector3<f32>; 3] {
+        [
+            self.rotation.rotate_vector(Vector3::unit_x()),
+            self.rotation.rotate_vector(Vector3::unit_y()),
+            self.rotation.rotate_vector(Vector3::unit_z()),
+        ]
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let mut corners = Aabb::from_center(Point3::origin(), self.half_extents).corners();
+        for corner in corners.iter_mut() {
+            *corner = self.center + self.rotation.rotate_vector(corner.to_vec());
+        }
+        corners
+    }
+
+    pub fn closest_point(&self, point: Point3<f32>) -> Point3<f32> {
+        let offset = point - self.center;
+        let half_extents = [self.half_extents.x, self.half_extents.y, self.half_extents.z];
+        self.axes().iter().zip(half_extents.iter()).fold(self.center, |closest, (axis, half_extent)| {
+            let distance = offset.dot(*axis).max(-half_extent).min(*half_extent);
+            closest + axis * distance
+        })
+    }
+
+    //The axis-aligned box enclosing this box.
+    pub fn to_aabb(&self) -> Aabb {
+        let corners = self.corners();
+        Aabb::from_points(&corners).unwrap_or_else(|| Aabb::new(self.center, self.center))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Capsule {
+    start: Point3<f32>,
+    end: Point3<f32>,
+    radius: f32,
+}
+
+impl Capsule {
+    pub fn new(start: Point3<f32>, end: Point3<f32>, radius: f32) -> Self {
+        Capsule {
+            start,
+            end,
+            radius: radius.abs(),
+        }
+    }
+
+    pub fn start(&self) -> Point3<f32> {
+        self.start
+    }
+
+    pub fn end(&self) -> Point3<f32> {
+        self.end
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    //The point of the inner segment closest to the given point.
+    pub fn closest_point_on_segment(&self, point: Point3<f32>) -> Point3<f32> {
+        closest_point_on_segment(self.start, self.end, point)
+    }
+
+    pub fn closest_point(&self, point: Point3<f32>) -> Point3<f32> {
+        Sphere::new(self.closest_point_on_segment(point), self.radius).closest_point(point)
+    }
+
+    pub fn to_aabb(&self) -> Aabb {
+        let radius = Vector3::new(self.radius, self.radius, self.radius);
+        Aabb::from_center(self.start, radius).union(&Aabb::from_center(self.end, radius))
+    }
+
+    pub fn transformed(&self, transform: &Transform) -> Capsule {
+        Capsule::new(transform.transform_point(self.start), transform.transform_point(self.end), self.radius * transform.scale())
+    }
+}
+
+pub fn closest_point_on_segment(start: Point3<f32>, end: Point3<f32>, point: Point3<f32>) -> Point3<f32> {
+    let segment = end - start;
+    let length2 = segment.magnitude2();
+    if length2 <= f32::EPSILON {
+        return start;
+    }
+    let t = ((point - start).dot(segment) / length2).clamp(0.0, 1.0);
+    start + segment * t
+}
+
+//The closest points of two segments.
+pub fn closest_points_between_segments(start_a: Point3<f32>, end_a: Point3<f32>, start_b: Point3<f32>, end_b: Point3<f32>) -> (Point3<f32>, Point3<f32>) {
+    let direction_a = end_a - start_a;
+    let direction_b = end_b - start_b;
+    let offset = start_a - start_b;
+    let length2_a = direction_a.magnitude2();
+    let length2_b = direction_b.magnitude2();
+    let f = direction_b.dot(offset);
+
+    if length2_a <= f32::EPSILON && length2_b <= f32::EPSILON {
+        return (start_a, start_b);
+    }
+    if length2_a <= f32::EPSILON {
+        return (start_a, closest_point_on_segment(start_b, end_b, start_a));
+    }
+
+    let c = direction_a.dot(offset);
+    let (s, t) = if length2_b <= f32::EPSILON {
+        ((-c / length2_a).clamp(0.0, 1.0), 0.0)
+    } else {
+        let b = direction_a.dot(direction_b);
+        let denominator = length2_a * length2_b - b * b;
+        let mut s = if denominator > f32::EPSILON {
+            ((b * f - c * length2_b) / denominator).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let mut t = (b * s + f) / length2_b;
+        if t < 0.0 {
+            t = 0.0;
+            s = (-c / length2_a).clamp(0.0, 1.0);
+        } else if t > 1.0 {
+            t = 1.0;
+            s = ((b - c) / length2_a).clamp(0.0, 1.0);
+        }
+        (s, t)
+    };
+
+    (start_a + direction_a * s, start_b + direction_b * t)
+}
+
+//The distance between a point and a segment.
+pub fn distance_to_segment(start: Point3<f32>, end: Point3<f32>, point: Point3<f32>) -> f32 {
+    closest_point_on_segment(start, end, point).distance(point)
+}
+
+#[cfg(test)]
+mod bounding_volumes_test {
+    use super::*;
+    use cgmath::{Rad, Rotation3};
+    use std::f32::consts;
+    use proptest::prelude::*;
+
+    prop_compose! {
+        fn arb_point()(x in -50.0f32..50.0, y in -50.0f32..50.0, z in -50.0f32..50.0) -> Point3<f32> {
+            Point3::new(x, y, z)
+        }
+    }
+
+    #[test]
+    fn aabb_construction() {
+        let aabb = Aabb::new(Point3::new(1.0, -1.0, 2.0), Point3::new(-1.0, 1.0, 0.0));
+        assert_eq!(aabb.min(), Point3::new(-1.0, -1.0, 0.0));
+        assert_eq!(aabb.center(), Point3::new(0.0, 0.0, 1.0));
+        assert_eq!(aabb.half_extents(), Vector3::new(1.0, 1.0, 1.0));
+        assert!(Aabb::from_points(&[]).is_none());
+
+        //A box rotated by 45 degrees around z is wider.
+        let rotated = aabb.transformed(&Transform::from_rotation(Quaternion::from_angle_z(Rad(consts::FRAC_PI_4))));
+        assert!((rotated.half_extents().x - consts::SQRT_2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn segment_closest_points() {
+        let (a, b) = closest_points_between_segments(
+            Point3::new(-1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, -1.0, 1.0), Point3::new(0.0, 1.0, 1.0),
+        );
+        assert!(a.distance(Point3::new(0.0, 0.0, 0.0)) < 1e-5);
+        assert!(b.distance(Point3::new(0.0, 0.0, 1.0)) < 1e-5);
+        assert_eq!(distance_to_segment(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0), Point3::new(3.0, 4.0, 0.0)), 5.0);
+    }
+
+    proptest! {
+        #[test]
+        fn aabb_from_points_encloses(points in prop::collection::vec(arb_point(), 1..20)) {
+            let aabb = Aabb::from_points(&points).unwrap();
+            for point in points.iter() {
+                prop_assert_eq!(aabb.closest_point(*point), *point);
+            }
+        }
+
+        #[test]
+        fn obb_closest_point_is_inside(center in arb_point(), point in arb_point(), angle in -consts::PI..consts::PI) {
+            let obb = Obb::new(center, Vector3::new(1.0, 2.0, 3.0), Quaternion::from_angle_y(Rad(angle)));
+            let closest = obb.closest_point(point);
+            //The closest point of the box is as close as the closest corner.
+            let closest_distance = closest.distance(point);
+            for corner in obb.corners().iter() {
+                prop_assert!(closest_distance <= corner.distance(point) + 1e-3);
+            }
+            prop_assert_eq!(obb.to_aabb().closest_point(closest).distance(closest) < 1e-3, true);
+        }
+
+        #[test]
+        fn segments_closest_points_are_closest(a in arb_point(), b in arb_point(), c in arb_point(), d in arb_point(), s in 0.0f32..1.0, t in 0.0f32..1.0) {
+            let (closest_a, closest_b) = closest_points_between_segments(a, b, c, d);
+            let other_a = a + (b - a) * s;
+            let other_b = c + (d - c) * t;
+            prop_assert!(closest_a.distance(closest_b) <= other_a.distance(other_b) + 1e-2);
+        }
+    }
+}
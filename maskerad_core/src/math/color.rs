@@ -0,0 +1,81 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+/*
+COLOR.
+
+A linear RGBA color, each channel between 0.0 and 1.0.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color {
+            r,
+            g,
+            b,
+            a,
+        }
+    }
+
+    //An opaque color.
+    pub fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Color::new(r, g, b, 1.0)
+    }
+
+    pub fn from_rgba8(rgba: [u8; 4]) -> Self {
+        Color::new(rgba[0] as f32 / 255.0, rgba[1] as f32 / 255.0, rgba[2] as f32 / 255.0, rgba[3] as f32 / 255.0)
+    }
+
+    //The channels are clamped between 0.0 and 1.0, then rounded.
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        let to_u8 = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [to_u8(self.r), to_u8(self.g), to_u8(self.b), to_u8(self.a)]
+    }
+
+    pub fn lerp(&self, other: &Color, amount: f32) -> Color {
+        Color::new(
+            self.r + (other.r - self.r) * amount,
+            self.g + (other.g - self.g) * amount,
+            self.b + (other.b - self.b) * amount,
+            self.a + (other.a - self.a) * amount,
+        )
+    }
+
+    pub fn white() -> Self {
+        Color::rgb(1.0, 1.0, 1.0)
+    }
+
+    pub fn black() -> Self {
+        Color::rgb(0.0, 0.0, 0.0)
+    }
+
+    pub fn transparent() -> Self {
+        Color::new(0.0, 0.0, 0.0, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod color_test {
+    use super::*;
+
+    #[test]
+    fn color_conversions() {
+        let color = Color::from_rgba8([255, 0, 51, 255]);
+        assert_eq!(color, Color::rgb(1.0, 0.0, 0.2));
+        assert_eq!(color.to_rgba8(), [255, 0, 51, 255]);
+        assert_eq!(Color::new(2.0, -1.0, 0.5, 1.0).to_rgba8(), [255, 0, 128, 255]);
+        assert_eq!(Color::black().lerp(&Color::white(), 0.5), Color::rgb(0.5, 0.5, 0.5));
+    }
+}
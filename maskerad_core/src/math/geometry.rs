@@ -0,0 +1,197 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use cgmath::{Matrix, Matrix4, Point3, Vector3, Vector4, InnerSpace, EuclideanSpace};
+use math::transform::Transform;
+
+/*
+RAYS, PLANES AND FRUSTUMS.
+
+A Ray is a half-line, used for the picking and the visibility queries.
+A Plane is given by its unit normal and its signed distance to the origin: the points p with
+normal.dot(p) == distance. The positive half-space is in front of the plane.
+A Frustum is the volume seen by a camera, six planes pointing inward, extracted from a
+view-projection matrix.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+}
+
+impl Ray {
+    //The direction is normalized.
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn from_points(origin: Point3<f32>, target: Point3<f32>) -> Self {
+        Ray::new(origin, target - origin)
+    }
+
+    pub fn origin(&self) -> Point3<f32> {
+        self.origin
+    }
+
+    pub fn direction(&self) -> Vector3<f32> {
+        self.direction
+    }
+
+    //The point at the given distance along the ray.
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    pub fn transformed(&self, transform: &Transform) -> Ray {
+        Ray::new(transform.transform_point(self.origin), transform.transform_vector(self.direction))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane {
+    normal: Vector3<f32>,
+    distance: f32,
+}
+
+impl Plane {
+    //The normal is normalized.
+    pub fn new(normal: Vector3<f32>, distance: f32) -> Self {
+        let magnitude = normal.magnitude();
+        Plane {
+            normal: normal / magnitude,
+            distance: distance / magnitude,
+        }
+    }
+
+    pub fn from_point_normal(point: Point3<f32>, normal: Vector3<f32>) -> Self {
+        let normal = normal.normalize();
+        Plane {
+            normal,
+            distance: normal.dot(point.to_vec()),
+        }
+    }
+
+    //The plane going through the 3 points, facing the side from which they are in counter-clockwise order.
+    //None if the points are aligned.
+    pub fn from_points(a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Option<Self> {
+        let normal = (b - a).cross(c - a);
+        if normal.magnitude2() <= f32::EPSILON {
+            None
+        } else {
+            Some(Plane::from_point_normal(a, normal))
+        }
+    }
+
+    //Plane from the (a, b, c, d) coefficients of the equation ax + by + cz + d = 0.
+    pub fn from_coefficients(coefficients: Vector4<f32>) -> Self {
+        Plane::new(coefficients.truncate(), -coefficients.w)
+    }
+
+    pub fn normal(&self) -> Vector3<f32> {
+        self.normal
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    //Positive in front of the plane, negative behind it.
+    pub fn signed_distance(&self, point: Point3<f32>) -> f32 {
+        self.normal.dot(point.to_vec()) - self.distance
+    }
+
+    pub fn project_point(&self, point: Point3<f32>) -> Point3<f32> {
+        point + self.normal * -self.signed_distance(point)
+    }
+}
+
+//Indices of the planes of a frustum.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrustumPlane {
+    Left = 0,
+    Right = 1,
+    Bottom = 2,
+    Top = 3,
+    Near = 4,
+    Far = 5,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    planes: [Plane; 6],
+}
+
+impl Frustum {
+    pub fn new(planes: [Plane; 6]) -> Self {
+        Frustum {
+            planes,
+        }
+    }
+
+    //Extract the planes of a view-projection matrix, with a clip space depth between -1 and 1 (OpenGL convention).
+    pub fn from_matrix(view_projection: Matrix4<f32>) -> Self {
+        let row = |index| view_projection.row(index);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        Frustum::new([
+            Plane::from_coefficients(w + x),
+            Plane::from_coefficients(w - x),
+            Plane::from_coefficients(w + y),
+            Plane::from_coefficients(w - y),
+            Plane::from_coefficients(w + z),
+            Plane::from_coefficients(w - z),
+        ])
+    }
+
+    pub fn planes(&self) -> &[Plane; 6] {
+        &self.planes
+    }
+
+    pub fn plane(&self, plane: FrustumPlane) -> &Plane {
+        &self.planes[plane as usize]
+    }
+}
+
+#[cfg(test)]
+mod geometry_test {
+    use super::*;
+    use cgmath::{perspective, Deg};
+
+    #[test]
+    fn plane_distances() {
+        let plane = Plane::from_point_normal(Point3::new(0.0, 2.0, 0.0), Vector3::new(0.0, 3.0, 0.0));
+        assert_eq!(plane.distance(), 2.0);
+        assert_eq!(plane.signed_distance(Point3::new(5.0, 5.0, 0.0)), 3.0);
+        assert_eq!(plane.project_point(Point3::new(1.0, -1.0, 1.0)), Point3::new(1.0, 2.0, 1.0));
+
+        let plane = Plane::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)).unwrap();
+        assert_eq!(plane.normal(), Vector3::unit_z());
+        assert!(Plane::from_points(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(2.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn frustum_from_matrix() {
+        //The camera is at the origin and looks toward -z.
+        let frustum = Frustum::from_matrix(perspective(Deg(90.0), 1.0, 1.0, 100.0));
+        let near = frustum.plane(FrustumPlane::Near);
+        let far = frustum.plane(FrustumPlane::Far);
+        assert!((near.signed_distance(Point3::new(0.0, 0.0, -1.0))).abs() < 1e-4);
+        assert!((far.signed_distance(Point3::new(0.0, 0.0, -100.0))).abs() < 1e-2);
+        assert!(frustum.planes().iter().all(|plane| plane.signed_distance(Point3::new(0.0, 0.0, -10.0)) > 0.0));
+        assert!(frustum.plane(FrustumPlane::Left).signed_distance(Point3::new(-20.0, 0.0, -10.0)) < 0.0);
+    }
+
+    #[test]
+    fn ray_at() {
+        let ray = Ray::from_points(Point3::new(1.0, 0.0, 0.0), Point3::new(1.0, 0.0, 10.0));
+        assert_eq!(ray.direction(), Vector3::unit_z());
+        assert_eq!(ray.at(2.0), Point3::new(1.0, 0.0, 2.0));
+    }
+}
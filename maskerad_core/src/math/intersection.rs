@@ -0,0 +1,703 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use cgmath::{Point3, Vector3, InnerSpace, EuclideanSpace, MetricSpace, Rotation};
+use math::bounding_volumes::{self, Aabb, Sphere, Obb, Capsule};
+use math::geometry::{Ray, Plane, Frustum};
+
+/*
+INTERSECTION TESTS.
+
+- Intersects: the two volumes overlap. Touching volumes intersect.
+- Contains: the second volume (or point) is entirely inside the first one.
+- RayCast: the distance along the ray to the first point of the volume, 0 if the ray starts inside.
+
+Frustum::classify_*() tell if a volume is outside, partially inside or inside the frustum, for the
+culling. The tests against a frustum are conservative: a volume near a corner of the frustum may be
+reported as intersecting while it is outside.
+*/
+
+const EPSILON: f32 = 1e-6;
+
+pub trait Intersects<T> {
+    fn intersects(&self, other: &T) -> bool;
+}
+
+pub trait Contains<T> {
+    fn contains(&self, other: &T) -> bool;
+}
+
+pub trait RayCast {
+    fn ray_cast(&self, ray: &Ray) -> Option<f32>;
+}
+
+impl<T: RayCast> Intersects<Ray> for T {
+    fn intersects(&self, ray: &Ray) -> bool {
+        self.ray_cast(ray).is_some()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Containment {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
+//Implement Intersects<$b> for $a with the implementation of Intersects<$a> for $b.
+macro_rules! symmetric_intersection {
+    ($a:ty, $b:ty) => {
+        impl Intersects<$b> for $a {
+            fn intersects(&self, other: &$b) -> bool {
+                other.intersects(self)
+            }
+        }
+    };
+}
+
+// ---------------------------------------------- Points
+
+impl Contains<Point3<f32>> for Aabb {
+    fn contains(&self, point: &Point3<f32>) -> bool {
+        let (min, max) = (self.min(), self.max());
+        point.x >= min.x && point.x <= max.x &&
+            point.y >= min.y && point.y <= max.y &&
+            point.z >= min.z && point.z <= max.z
+    }
+}
+
+impl Contains<Point3<f32>> for Sphere {
+    fn contains(&self, point: &Point3<f32>) -> bool {
+        self.center().distance2(*point) <= self.radius() * self.radius()
+    }
+}
+
+impl Contains<Point3<f32>> for Obb {
+    fn contains(&self, point: &Point3<f32>) -> bool {
+        let local = self.rotation().invert().rotate_vector(point - self.center());
+        let half_extents = self.half_extents();
+        local.x.abs() <= half_extents.x && local.y.abs() <= half_extents.y && local.z.abs() <= half_extents.z
+    }
+}
+
+impl Contains<Point3<f32>> for Capsule {
+    fn contains(&self, point: &Point3<f32>) -> bool {
+        self.closest_point_on_segment(*point).distance2(*point) <= self.radius() * self.radius()
+    }
+}
+
+impl Contains<Point3<f32>> for Frustum {
+    fn contains(&self, point: &Point3<f32>) -> bool {
+        self.planes().iter().all(|plane| plane.signed_distance(*point) >= 0.0)
+    }
+}
+
+// ---------------------------------------------- Volumes
+
+impl Intersects<Aabb> for Aabb {
+    fn intersects(&self, other: &Aabb) -> bool {
+        self.min().x <= other.max().x && self.max().x >= other.min().x &&
+            self.min().y <= other.max().y && self.max().y >= other.min().y &&
+            self.min().z <= other.max().z && self.max().z >= other.min().z
+    }
+}
+
+impl Contains<Aabb> for Aabb {
+    fn contains(&self, other: &Aabb) -> bool {
+        self.contains(&other.min()) && self.contains(&other.max())
+    }
+}
+
+impl Contains<Sphere> for Aabb {
+    fn contains(&self, sphere: &Sphere) -> bool {
+        let radius = Vector3::new(sphere.radius(), sphere.radius(), sphere.radius());
+        self.contains(&Aabb::from_center(sphere.center(), radius))
+    }
+}
+
+impl Intersects<Sphere> for Sphere {
+    fn intersects(&self, other: &Sphere) -> bool {
+        let radii = self.radius() + other.radius();
+        self.center().distance2(other.center()) <= radii * radii
+    }
+}
+
+impl Contains<Sphere> for Sphere {
+    fn contains(&self, other: &Sphere) -> bool {
+        self.center().distance(other.center()) + other.radius() <= self.radius()
+    }
+}
+
+impl Contains<Aabb> for Sphere {
+    fn contains(&self, aabb: &Aabb) -> bool {
+        aabb.corners().iter().all(|corner| self.contains(corner))
+    }
+}
+
+impl Intersects<Sphere> for Aabb {
+    fn intersects(&self, sphere: &Sphere) -> bool {
+        sphere.contains(&self.closest_point(sphere.center()))
+    }
+}
+symmetric_intersection!(Sphere, Aabb);
+
+impl Intersects<Sphere> for Obb {
+    fn intersects(&self, sphere: &Sphere) -> bool {
+        sphere.contains(&self.closest_point(sphere.center()))
+    }
+}
+symmetric_intersection!(Sphere, Obb);
+
+//Separating axis test, from Real-Time Collision Detection (C. Ericson).
+impl Intersects<Obb> for Obb {
+    fn intersects(&self, other: &Obb) -> bool {
+        let axes_a = self.axes();
+        let axes_b = other.axes();
+        let extents_a = [self.half_extents().x, self.half_extents().y, self.half_extents().z];
+        let extents_b = [other.half_extents().x, other.half_extents().y, other.half_extents().z];
+
+        //The rotation of b in the frame of a, and the translation in the frame of a.
+        let mut rotation = [[0.0f32; 3]; 3];
+        let mut abs_rotation = [[0.0f32; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                rotation[i][j] = axes_a[i].dot(axes_b[j]);
+                //The epsilon avoids false negatives when two edges are parallel (null cross product).
+                abs_rotation[i][j] = rotation[i][j].abs() + EPSILON;
+            }
+        }
+        let offset = other.center() - self.center();
+        let translation = [offset.dot(axes_a[0]), offset.dot(axes_a[1]), offset.dot(axes_a[2])];
+
+        //The axes of a.
+        for i in 0..3 {
+            let radius_b = extents_b[0] * abs_rotation[i][0] + extents_b[1] * abs_rotation[i][1] + extents_b[2] * abs_rotation[i][2];
+            if translation[i].abs() > extents_a[i] + radius_b {
+                return false;
+            }
+        }
+
+        //The axes of b.
+        for j in 0..3 {
+            let radius_a = extents_a[0] * abs_rotation[0][j] + extents_a[1] * abs_rotation[1][j] + extents_a[2] * abs_rotation[2][j];
+            let distance = translation[0] * rotation[0][j] + translation[1] * rotation[1][j] + translation[2] * rotation[2][j];
+            if distance.abs() > radius_a + extents_b[j] {
+                return false;
+            }
+        }
+
+        //The cross products of the axes of a and b.
+        for i in 0..3 {
+            let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+            for j in 0..3 {
+                let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+                let radius_a = extents_a[i1] * abs_rotation[i2][j] + extents_a[i2] * abs_rotation[i1][j];
+                let radius_b = extents_b[j1] * abs_rotation[i][j2] + extents_b[j2] * abs_rotation[i][j1];
+                let distance = translation[i2] * rotation[i1][j] - translation[i1] * rotation[i2][j];
+                if distance.abs() > radius_a + radius_b {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl Intersects<Aabb> for Obb {
+    fn intersects(&self, aabb: &Aabb) -> bool {
+        self.intersects(&Obb::from_aabb(aabb))
+    }
+}
+symmetric_intersection!(Aabb, Obb);
+
+impl Contains<Obb> for Obb {
+    fn contains(&self, other: &Obb) -> bool {
+        other.corners().iter().all(|corner| self.contains(corner))
+    }
+}
+
+impl Intersects<Sphere> for Capsule {
+    fn intersects(&self, sphere: &Sphere) -> bool {
+        let radii = self.radius() + sphere.radius();
+        self.closest_point_on_segment(sphere.center()).distance2(sphere.center()) <= radii * radii
+    }
+}
+symmetric_intersection!(Sphere, Capsule);
+
+impl Intersects<Capsule> for Capsule {
+    fn intersects(&self, other: &Capsule) -> bool {
+        let (a, b) = bounding_volumes::closest_points_between_segments(self.start(), self.end(), other.start(), other.end());
+        let radii = self.radius() + other.radius();
+        a.distance2(b) <= radii * radii
+    }
+}
+
+impl Intersects<Obb> for Capsule {
+    //The distance between the segment and the box is a convex function of the position along the segment:
+    //its minimum is found with a ternary search.
+    fn intersects(&self, obb: &Obb) -> bool {
+        let distance = |t: f32| {
+            let point = self.start() + (self.end() - self.start()) * t;
+            obb.closest_point(point).distance(point)
+        };
+
+        let (mut low, mut high) = (0.0f32, 1.0f32);
+        for _ in 0..64 {
+            let third = (high - low) / 3.0;
+            if distance(low + third) <= distance(high - third) {
+                high -= third;
+            } else {
+                low += third;
+            }
+        }
+        distance((low + high) * 0.5) <= self.radius() + EPSILON
+    }
+}
+symmetric_intersection!(Obb, Capsule);
+
+impl Intersects<Aabb> for Capsule {
+    fn intersects(&self, aabb: &Aabb) -> bool {
+        self.intersects(&Obb::from_aabb(aabb))
+    }
+}
+symmetric_intersection!(Aabb, Capsule);
+
+// ---------------------------------------------- Planes
+
+//The volume has points on both sides of the plane.
+impl Intersects<Sphere> for Plane {
+    fn intersects(&self, sphere: &Sphere) -> bool {
+        self.signed_distance(sphere.center()).abs() <= sphere.radius()
+    }
+}
+symmetric_intersection!(Sphere, Plane);
+
+impl Intersects<Aabb> for Plane {
+    fn intersects(&self, aabb: &Aabb) -> bool {
+        let half_extents = aabb.half_extents();
+        let normal = self.normal();
+        let radius = half_extents.x * normal.x.abs() + half_extents.y * normal.y.abs() + half_extents.z * normal.z.abs();
+        self.signed_distance(aabb.center()).abs() <= radius
+    }
+}
+symmetric_intersection!(Aabb, Plane);
+
+impl Intersects<Obb> for Plane {
+    fn intersects(&self, obb: &Obb) -> bool {
+        let axes = obb.axes();
+        let half_extents = obb.half_extents();
+        let normal = self.normal();
+        let radius = half_extents.x * normal.dot(axes[0]).abs() + half_extents.y * normal.dot(axes[1]).abs() + half_extents.z * normal.dot(axes[2]).abs();
+        self.signed_distance(obb.center()).abs() <= radius
+    }
+}
+symmetric_intersection!(Obb, Plane);
+
+impl Intersects<Capsule> for Plane {
+    fn intersects(&self, capsule: &Capsule) -> bool {
+        let start = self.signed_distance(capsule.start());
+        let end = self.signed_distance(capsule.end());
+        (start * end <= 0.0) || start.abs().min(end.abs()) <= capsule.radius()
+    }
+}
+symmetric_intersection!(Capsule, Plane);
+
+// ---------------------------------------------- Frustums
+
+impl Frustum {
+    pub fn classify_sphere(&self, sphere: &Sphere) -> Containment {
+        let mut containment = Containment::Inside;
+        for plane in self.planes().iter() {
+            let distance = plane.signed_distance(sphere.center());
+            if distance < -sphere.radius() {
+                return Containment::Outside;
+            } else if distance < sphere.radius() {
+                containment = Containment::Intersecting;
+            }
+        }
+        containment
+    }
+
+    pub fn classify_aabb(&self, aabb: &Aabb) -> Containment {
+        self.classify_obb(&Obb::from_aabb(aabb))
+    }
+
+    pub fn classify_obb(&self, obb: &Obb) -> Containment {
+        let axes = obb.axes();
+        let half_extents = obb.half_extents();
+        let mut containment = Containment::Inside;
+
+        for plane in self.planes().iter() {
+            let normal = plane.normal();
+            let radius = half_extents.x * normal.dot(axes[0]).abs() + half_extents.y * normal.dot(axes[1]).abs() + half_extents.z * normal.dot(axes[2]).abs();
+            let distance = plane.signed_distance(obb.center());
+            if distance < -radius {
+                return Containment::Outside;
+            } else if distance < radius {
+                containment = Containment::Intersecting;
+            }
+        }
+        containment
+    }
+
+    pub fn classify_capsule(&self, capsule: &Capsule) -> Containment {
+        let mut containment = Containment::Inside;
+        for plane in self.planes().iter() {
+            let start = plane.signed_distance(capsule.start());
+            let end = plane.signed_distance(capsule.end());
+            if start.max(end) < -capsule.radius() {
+                return Containment::Outside;
+            } else if start.min(end) < capsule.radius() {
+                containment = Containment::Intersecting;
+            }
+        }
+        containment
+    }
+}
+
+impl Intersects<Sphere> for Frustum {
+    fn intersects(&self, sphere: &Sphere) -> bool {
+        self.classify_sphere(sphere) != Containment::Outside
+    }
+}
+
+impl Intersects<Aabb> for Frustum {
+    fn intersects(&self, aabb: &Aabb) -> bool {
+        self.classify_aabb(aabb) != Containment::Outside
+    }
+}
+
+impl Intersects<Obb> for Frustum {
+    fn intersects(&self, obb: &Obb) -> bool {
+        self.classify_obb(obb) != Containment::Outside
+    }
+}
+
+impl Intersects<Capsule> for Frustum {
+    fn intersects(&self, capsule: &Capsule) -> bool {
+        self.classify_capsule(capsule) != Containment::Outside
+    }
+}
+
+impl Contains<Sphere> for Frustum {
+    fn contains(&self, sphere: &Sphere) -> bool {
+        self.classify_sphere(sphere) == Containment::Inside
+    }
+}
+
+impl Contains<Aabb> for Frustum {
+    fn contains(&self, aabb: &Aabb) -> bool {
+        self.classify_aabb(aabb) == Containment::Inside
+    }
+}
+
+// ---------------------------------------------- Rays
+
+//The distances along the ray where it enters and leaves an axis-aligned box, with the slab method.
+fn ray_box_interval(origin: Point3<f32>, direction: Vector3<f32>, min: Point3<f32>, max: Point3<f32>) -> Option<(f32, f32)> {
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+
+    for axis in 0..3 {
+        if direction[axis].abs() < EPSILON {
+            //The ray is parallel to the slab.
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+        } else {
+            let inverse = 1.0 / direction[axis];
+            let near = (min[axis] - origin[axis]) * inverse;
+            let far = (max[axis] - origin[axis]) * inverse;
+            entry = entry.max(near.min(far));
+            exit = exit.min(near.max(far));
+            if entry > exit {
+                return None;
+            }
+        }
+    }
+
+    if exit < 0.0 {
+        None
+    } else {
+        Some((entry.max(0.0), exit))
+    }
+}
+
+fn ray_sphere(ray: &Ray, center: Point3<f32>, radius: f32) -> Option<f32> {
+    let offset = ray.origin() - center;
+    let b = offset.dot(ray.direction());
+    let c = offset.magnitude2() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    //The ray starts outside and points away from the sphere.
+    if b > 0.0 {
+        return None;
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        None
+    } else {
+        Some((-b - discriminant.sqrt()).max(0.0))
+    }
+}
+
+impl RayCast for Aabb {
+    fn ray_cast(&self, ray: &Ray) -> Option<f32> {
+        ray_box_interval(ray.origin(), ray.direction(), self.min(), self.max()).map(|(entry, _)| entry)
+    }
+}
+
+impl RayCast for Sphere {
+    fn ray_cast(&self, ray: &Ray) -> Option<f32> {
+        ray_sphere(ray, self.center(), self.radius())
+    }
+}
+
+impl RayCast for Obb {
+    //Cast the ray in the local space of the box.
+    fn ray_cast(&self, ray: &Ray) -> Option<f32> {
+        let inverse_rotation = self.rotation().invert();
+        let origin = Point3::from_vec(inverse_rotation.rotate_vector(ray.origin() - self.center()));
+        let direction = inverse_rotation.rotate_vector(ray.direction());
+        let extents = self.half_extents();
+        ray_box_interval(origin, direction, Point3::from_vec(-extents), Point3::from_vec(extents)).map(|(entry, _)| entry)
+    }
+}
+
+impl RayCast for Plane {
+    fn ray_cast(&self, ray: &Ray) -> Option<f32> {
+        let denominator = self.normal().dot(ray.direction());
+        let distance = -self.signed_distance(ray.origin());
+        if denominator.abs() < EPSILON {
+            return if distance.abs() < EPSILON { Some(0.0) } else { None };
+        }
+
+        let t = distance / denominator;
+        if t >= 0.0 { Some(t) } else { None }
+    }
+}
+
+impl RayCast for Capsule {
+    //The first hit among the cylinder around the segment and the spheres at both ends.
+    fn ray_cast(&self, ray: &Ray) -> Option<f32> {
+        if self.contains(&ray.origin()) {
+            return Some(0.0);
+        }
+
+        let axis = self.end() - self.start();
+        let axis_length2 = axis.magnitude2();
+        let origin = ray.origin() - self.start();
+        let direction = ray.direction();
+        let radius = self.radius();
+
+        let mut hit = ray_sphere(ray, self.start(), radius);
+        let mut keep_closest = |distance: Option<f32>| {
+            if let Some(distance) = distance {
+                hit = Some(match hit {
+                    Some(current) => current.min(distance),
+                    None => distance,
+                });
+            }
+        };
+        keep_closest(ray_sphere(ray, self.end(), radius));
+
+        if axis_length2 > EPSILON {
+            let axis_direction = axis.dot(direction);
+            let axis_origin = axis.dot(origin);
+            let a = axis_length2 - axis_direction * axis_direction;
+            let b = axis_length2 * origin.dot(direction) - axis_origin * axis_direction;
+            let c = axis_length2 * origin.magnitude2() - axis_origin * axis_origin - radius * radius * axis_length2;
+            let discriminant = b * b - a * c;
+
+            if a > EPSILON && discriminant >= 0.0 {
+                let t = (-b - discriminant.sqrt()) / a;
+                let along_axis = axis_origin + t * axis_direction;
+                if t >= 0.0 && along_axis >= 0.0 && along_axis <= axis_length2 {
+                    keep_closest(Some(t));
+                }
+            }
+        }
+
+        hit
+    }
+}
+
+#[cfg(test)]
+mod intersection_test {
+    use super::*;
+    use cgmath::{perspective, Deg, Quaternion, Rad, Rotation3};
+    use std::f32::consts;
+    use proptest::prelude::*;
+
+    prop_compose! {
+        fn arb_point()(x in -10.0f32..10.0, y in -10.0f32..10.0, z in -10.0f32..10.0) -> Point3<f32> {
+            Point3::new(x, y, z)
+        }
+    }
+
+    prop_compose! {
+        fn arb_extents()(x in 0.1f32..5.0, y in 0.1f32..5.0, z in 0.1f32..5.0) -> Vector3<f32> {
+            Vector3::new(x, y, z)
+        }
+    }
+
+    prop_compose! {
+        fn arb_rotation()(x in -1.0f32..1.0, y in -1.0f32..1.0, z in -1.0f32..1.0, angle in -consts::PI..consts::PI) -> Quaternion<f32> {
+            let axis = Vector3::new(x, y, z);
+            let axis = if axis.magnitude() < 1e-3 { Vector3::unit_x() } else { axis.normalize() };
+            Quaternion::from_axis_angle(axis, Rad(angle))
+        }
+    }
+
+    prop_compose! {
+        fn arb_aabb()(center in arb_point(), extents in arb_extents()) -> Aabb {
+            Aabb::from_center(center, extents)
+        }
+    }
+
+    prop_compose! {
+        fn arb_sphere()(center in arb_point(), radius in 0.1f32..5.0) -> Sphere {
+            Sphere::new(center, radius)
+        }
+    }
+
+    prop_compose! {
+        fn arb_obb()(center in arb_point(), extents in arb_extents(), rotation in arb_rotation()) -> Obb {
+            Obb::new(center, extents, rotation)
+        }
+    }
+
+    prop_compose! {
+        fn arb_capsule()(start in arb_point(), end in arb_point(), radius in 0.1f32..3.0) -> Capsule {
+            Capsule::new(start, end, radius)
+        }
+    }
+
+    #[test]
+    fn simple_intersections() {
+        let unit = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0));
+        let touching = Aabb::new(Point3::new(1.0, 0.0, 0.0), Point3::new(2.0, 1.0, 1.0));
+        let far = Aabb::new(Point3::new(3.0, 0.0, 0.0), Point3::new(4.0, 1.0, 1.0));
+        assert!(unit.intersects(&touching));
+        assert!(!unit.intersects(&far));
+
+        //Rotated by 45 degrees, the box reaches x = 1 + sqrt(2) / 2.
+        let rotated = Obb::new(Point3::new(2.5, 0.5, 0.5), Vector3::new(0.5, 0.5, 0.5), Quaternion::from_angle_z(Rad(consts::FRAC_PI_4)));
+        assert!(!rotated.intersects(&unit));
+        assert!(rotated.intersects(&Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.9, 1.0, 1.0))));
+
+        let capsule = Capsule::new(Point3::new(0.5, 5.0, 0.5), Point3::new(0.5, 2.0, 0.5), 0.5);
+        assert!(!capsule.intersects(&unit));
+        assert!(capsule.intersects(&Sphere::new(Point3::new(0.5, 1.0, 0.5), 0.6)));
+        assert!(Plane::from_point_normal(Point3::new(0.0, 3.0, 0.0), Vector3::unit_y()).intersects(&capsule));
+    }
+
+    #[test]
+    fn simple_ray_casts() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::unit_z());
+        assert_eq!(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0).ray_cast(&ray), Some(9.0));
+        assert_eq!(Aabb::from_center(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 2.0)).ray_cast(&ray), Some(8.0));
+        assert_eq!(Plane::from_point_normal(Point3::new(0.0, 0.0, 5.0), -Vector3::unit_z()).ray_cast(&ray), Some(15.0));
+        assert_eq!(Sphere::new(Point3::new(0.0, 5.0, 0.0), 1.0).ray_cast(&ray), None);
+        assert!(!Sphere::new(Point3::new(0.0, 0.0, -20.0), 1.0).intersects(&ray));
+
+        let capsule = Capsule::new(Point3::new(-5.0, 0.0, 0.0), Point3::new(5.0, 0.0, 0.0), 1.0);
+        assert!((capsule.ray_cast(&ray).unwrap() - 9.0).abs() < 1e-4);
+        let end_ray = Ray::new(Point3::new(10.0, 0.0, 0.0), -Vector3::unit_x());
+        assert!((capsule.ray_cast(&end_ray).unwrap() - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn frustum_culling() {
+        //The camera is at the origin and looks toward -z.
+        let frustum = Frustum::from_matrix(perspective(Deg(90.0), 1.0, 1.0, 100.0));
+        assert_eq!(frustum.classify_sphere(&Sphere::new(Point3::new(0.0, 0.0, -10.0), 1.0)), Containment::Inside);
+        assert_eq!(frustum.classify_sphere(&Sphere::new(Point3::new(0.0, 0.0, 10.0), 1.0)), Containment::Outside);
+        assert_eq!(frustum.classify_aabb(&Aabb::from_center(Point3::new(0.0, 0.0, -100.0), Vector3::new(1.0, 1.0, 1.0))), Containment::Intersecting);
+        assert!(frustum.contains(&Point3::new(0.0, 0.0, -50.0)));
+        assert!(!frustum.intersects(&Capsule::new(Point3::new(-50.0, 0.0, -10.0), Point3::new(-50.0, 0.0, -20.0), 1.0)));
+    }
+
+    //A point inside two volumes proves that they intersect.
+    fn common_point<A, B>(a: &A, b: &B, point: &Point3<f32>) -> bool where
+        A: Contains<Point3<f32>> + Intersects<B>,
+        B: Contains<Point3<f32>> + Intersects<A>,
+    {
+        !(a.contains(point) && b.contains(point)) || (a.intersects(b) && b.intersects(a))
+    }
+
+    proptest! {
+        #[test]
+        fn intersections_are_consistent(aabb in arb_aabb(), other_aabb in arb_aabb(), sphere in arb_sphere(), other_sphere in arb_sphere(),
+            obb in arb_obb(), other_obb in arb_obb(), capsule in arb_capsule(), point in arb_point())
+        {
+            prop_assert!(common_point(&aabb, &other_aabb, &point));
+            prop_assert!(common_point(&aabb, &sphere, &point));
+            prop_assert!(common_point(&aabb, &obb, &point));
+            prop_assert!(common_point(&aabb, &capsule, &point));
+            prop_assert!(common_point(&sphere, &other_sphere, &point));
+            prop_assert!(common_point(&sphere, &obb, &point));
+            prop_assert!(common_point(&sphere, &capsule, &point));
+            prop_assert!(common_point(&obb, &other_obb, &point));
+            prop_assert!(common_point(&obb, &capsule, &point));
+        }
+
+        #[test]
+        fn aabb_obb_agree(aabb in arb_aabb(), other_aabb in arb_aabb()) {
+            prop_assert_eq!(aabb.intersects(&other_aabb), Obb::from_aabb(&aabb).intersects(&Obb::from_aabb(&other_aabb)));
+        }
+
+        #[test]
+        fn separated_volumes_dont_intersect(obb in arb_obb(), sphere in arb_sphere(), capsule in arb_capsule()) {
+            //Bounding spheres far enough from each other.
+            let obb_sphere = Sphere::new(obb.center(), obb.half_extents().magnitude());
+            let capsule_sphere = Sphere::new(capsule.start().midpoint(capsule.end()), capsule.start().distance(capsule.end()) * 0.5 + capsule.radius());
+            if !obb_sphere.intersects(&sphere) {
+                prop_assert!(!obb.intersects(&sphere));
+            }
+            if !obb_sphere.intersects(&capsule_sphere) {
+                prop_assert!(!obb.intersects(&capsule));
+            }
+        }
+
+        #[test]
+        fn containment_implies_intersection(aabb in arb_aabb(), sphere in arb_sphere()) {
+            if aabb.contains(&sphere) || sphere.contains(&aabb) {
+                prop_assert!(aabb.intersects(&sphere));
+            }
+            prop_assert!(aabb.contains(&aabb));
+            //With a margin for the rounding errors at the corners.
+            prop_assert!(Sphere::new(aabb.center(), aabb.half_extents().magnitude() * 1.0001).contains(&aabb));
+        }
+
+        #[test]
+        fn ray_hits_lie_on_volumes(origin in arb_point(), target in arb_point(), aabb in arb_aabb(), sphere in arb_sphere(), obb in arb_obb(), capsule in arb_capsule()) {
+            prop_assume!(origin.distance(target) > 1e-2);
+            let ray = Ray::from_points(origin, target);
+
+            let close_to = |point: Point3<f32>, closest: Point3<f32>| point.distance(closest) < 1e-2;
+            if let Some(distance) = aabb.ray_cast(&ray) {
+                prop_assert!(close_to(ray.at(distance), aabb.closest_point(ray.at(distance))));
+            }
+            if let Some(distance) = sphere.ray_cast(&ray) {
+                prop_assert!(close_to(ray.at(distance), sphere.closest_point(ray.at(distance))));
+            }
+            if let Some(distance) = obb.ray_cast(&ray) {
+                prop_assert!(close_to(ray.at(distance), obb.closest_point(ray.at(distance))));
+            }
+            if let Some(distance) = capsule.ray_cast(&ray) {
+                prop_assert!(close_to(ray.at(distance), capsule.closest_point(ray.at(distance))));
+            }
+
+            //A ray aimed at the center of a volume hits it.
+            prop_assert!(sphere.intersects(&Ray::from_points(origin + Vector3::new(0.0, 0.0, 1e-3), sphere.center())));
+            prop_assert!(aabb.intersects(&Ray::from_points(origin + Vector3::new(0.0, 0.0, 1e-3), aabb.center())));
+        }
+    }
+}
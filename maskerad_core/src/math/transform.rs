@@ -0,0 +1,221 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::ops::Mul;
+use cgmath::{Matrix4, Point3, Quaternion, Vector3, InnerSpace, EuclideanSpace, One, Zero, Rotation};
+
+/*
+TRANSFORM.
+
+The position, orientation and size of an object: a translation, a rotation and a scale.
+
+The scale is uniform: the composition of two transforms, and the inverse of a transform, are still
+transforms (a non-uniform scale followed by a rotation would introduce a shear).
+
+A point is first scaled, then rotated, then translated.
+a * b is the transform applying b, then a: a child transform b, relative to its parent a.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    translation: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform {
+            translation: Vector3::zero(),
+            rotation: Quaternion::one(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl Transform {
+    //The rotation is normalized.
+    pub fn new(translation: Vector3<f32>, rotation: Quaternion<f32>, scale: f32) -> Self {
+        Transform {
+            translation,
+            rotation: rotation.normalize(),
+            scale,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Default::default()
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Transform {
+            translation,
+            .. Default::default()
+        }
+    }
+
+    pub fn from_rotation(rotation: Quaternion<f32>) -> Self {
+        Transform::new(Vector3::zero(), rotation, 1.0)
+    }
+
+    pub fn from_scale(scale: f32) -> Self {
+        Transform {
+            scale,
+            .. Default::default()
+        }
+    }
+
+    pub fn translation(&self) -> Vector3<f32> {
+        self.translation
+    }
+
+    pub fn rotation(&self) -> Quaternion<f32> {
+        self.rotation
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn set_translation(&mut self, translation: Vector3<f32>) -> &mut Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quaternion<f32>) -> &mut Self {
+        self.rotation = rotation.normalize();
+        self
+    }
+
+    pub fn set_scale(&mut self, scale: f32) -> &mut Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn transform_point(&self, point: Point3<f32>) -> Point3<f32> {
+        Point3::from_vec(self.rotation.rotate_vector(point.to_vec() * self.scale) + self.translation)
+    }
+
+    //Directions are scaled and rotated, but not translated.
+    pub fn transform_vector(&self, vector: Vector3<f32>) -> Vector3<f32> {
+        self.rotation.rotate_vector(vector * self.scale)
+    }
+
+    //The transform applying other, then self.
+    pub fn concat(&self, other: &Transform) -> Transform {
+        Transform {
+            translation: self.transform_vector(other.translation) + self.translation,
+            rotation: (self.rotation * other.rotation).normalize(),
+            scale: self.scale * other.scale,
+        }
+    }
+
+    //None if the scale is 0.
+    pub fn inverse(&self) -> Option<Transform> {
+        if self.scale.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let scale = 1.0 / self.scale;
+        let rotation = self.rotation.invert();
+        Some(Transform {
+            translation: rotation.rotate_vector(-self.translation) * scale,
+            rotation,
+            scale,
+        })
+    }
+
+    //Interpolate the translation and the scale linearly, and the rotation along the shortest arc.
+    pub fn lerp(&self, other: &Transform, amount: f32) -> Transform {
+        let other_rotation = if self.rotation.dot(other.rotation) < 0.0 { -other.rotation } else { other.rotation };
+        Transform {
+            translation: self.translation.lerp(other.translation, amount),
+            rotation: self.rotation.slerp(other_rotation, amount).normalize(),
+            scale: self.scale + (other.scale - self.scale) * amount,
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation) * Matrix4::from(self.rotation) * Matrix4::from_scale(self.scale)
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        self.concat(&other)
+    }
+}
+
+impl From<Transform> for Matrix4<f32> {
+    fn from(transform: Transform) -> Self {
+        transform.to_matrix()
+    }
+}
+
+#[cfg(test)]
+mod transform_test {
+    use super::*;
+    use cgmath::{Rad, Rotation3, Vector4};
+    use std::f32::consts;
+    use proptest::prelude::*;
+
+    fn approx_point(a: Point3<f32>, b: Point3<f32>) -> bool {
+        (a - b).magnitude() <= 1e-3 * (1.0 + a.to_vec().magnitude().max(b.to_vec().magnitude()))
+    }
+
+    prop_compose! {
+        fn arb_vector()(x in -100.0f32..100.0, y in -100.0f32..100.0, z in -100.0f32..100.0) -> Vector3<f32> {
+            Vector3::new(x, y, z)
+        }
+    }
+
+    prop_compose! {
+        fn arb_transform()(translation in arb_vector(), axis in arb_vector(), angle in -consts::PI..consts::PI, scale in 0.1f32..10.0) -> Transform {
+            let axis = if axis.magnitude() < 1e-3 { Vector3::unit_y() } else { axis.normalize() };
+            Transform::new(translation, Quaternion::from_axis_angle(axis, Rad(angle)), scale)
+        }
+    }
+
+    #[test]
+    fn transform_point_order() {
+        let transform = Transform::new(Vector3::new(1.0, 0.0, 0.0), Quaternion::from_angle_z(Rad(consts::FRAC_PI_2)), 2.0);
+        //Scaled to (2, 0, 0), rotated to (0, 2, 0), translated to (1, 2, 0).
+        assert!(approx_point(transform.transform_point(Point3::new(1.0, 0.0, 0.0)), Point3::new(1.0, 2.0, 0.0)));
+        assert!(Transform::from_scale(0.0).inverse().is_none());
+        assert_eq!(Transform::identity().transform_point(Point3::new(1.0, 2.0, 3.0)), Point3::new(1.0, 2.0, 3.0));
+    }
+
+    proptest! {
+        #[test]
+        fn transform_inverse_roundtrip(transform in arb_transform(), point in arb_vector()) {
+            let point = Point3::from_vec(point);
+            let inverse = transform.inverse().unwrap();
+            prop_assert!(approx_point(inverse.transform_point(transform.transform_point(point)), point));
+        }
+
+        #[test]
+        fn transform_composition(a in arb_transform(), b in arb_transform(), point in arb_vector()) {
+            let point = Point3::from_vec(point);
+            prop_assert!(approx_point((a * b).transform_point(point), a.transform_point(b.transform_point(point))));
+        }
+
+        #[test]
+        fn transform_matrix_matches(transform in arb_transform(), point in arb_vector()) {
+            let transformed = transform.to_matrix() * Vector4::new(point.x, point.y, point.z, 1.0);
+            prop_assert!(approx_point(Point3::new(transformed.x, transformed.y, transformed.z), transform.transform_point(Point3::from_vec(point))));
+        }
+
+        #[test]
+        fn transform_lerp_bounds(a in arb_transform(), b in arb_transform()) {
+            prop_assert!(approx_point(Point3::from_vec(a.lerp(&b, 0.0).translation()), Point3::from_vec(a.translation())));
+            prop_assert!(approx_point(Point3::from_vec(a.lerp(&b, 1.0).translation()), Point3::from_vec(b.translation())));
+            prop_assert!((a.lerp(&b, 0.5).rotation().magnitude() - 1.0).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::f32::consts::PI;
+
+/*
+EASING CURVES.
+
+The easing functions of Robert Penner. An easing function maps the progression of an animation,
+between 0.0 and 1.0, to an eased progression: f(0) == 0 and f(1) == 1, but the progression can
+accelerate, decelerate, overshoot (back, elastic) or bounce in between.
+
+- In: the curve starts slowly.
+- Out: the curve ends slowly.
+- InOut: the curve starts and ends slowly.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    QuintIn,
+    QuintOut,
+    QuintInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+//The overshoot of the back curves (10%).
+const BACK_OVERSHOOT: f32 = 1.70158;
+
+//Build the Out and InOut curves from an In curve.
+fn ease_out(ease_in: fn(f32) -> f32, t: f32) -> f32 {
+    1.0 - ease_in(1.0 - t)
+}
+
+fn ease_in_out(ease_in: fn(f32) -> f32, t: f32) -> f32 {
+    if t < 0.5 {
+        ease_in(t * 2.0) * 0.5
+    } else {
+        1.0 - ease_in((1.0 - t) * 2.0) * 0.5
+    }
+}
+
+fn quad_in(t: f32) -> f32 {
+    t * t
+}
+
+fn cubic_in(t: f32) -> f32 {
+    t * t * t
+}
+
+fn quart_in(t: f32) -> f32 {
+    t * t * t * t
+}
+
+fn quint_in(t: f32) -> f32 {
+    t * t * t * t * t
+}
+
+fn sine_in(t: f32) -> f32 {
+    1.0 - (t * PI * 0.5).cos()
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t <= 0.0 { 0.0 } else { 2.0f32.powf(10.0 * (t - 1.0)) }
+}
+
+fn circ_in(t: f32) -> f32 {
+    1.0 - (1.0 - t * t).max(0.0).sqrt()
+}
+
+fn back_in(t: f32) -> f32 {
+    t * t * ((BACK_OVERSHOOT + 1.0) * t - BACK_OVERSHOOT)
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    let period = 0.3;
+    -(2.0f32.powf(10.0 * (t - 1.0))) * ((t - 1.0 - period / 4.0) * (2.0 * PI) / period).sin()
+}
+
+fn bounce_out(t: f32) -> f32 {
+    if t < 1.0 / 2.75 {
+        7.5625 * t * t
+    } else if t < 2.0 / 2.75 {
+        let t = t - 1.5 / 2.75;
+        7.5625 * t * t + 0.75
+    } else if t < 2.5 / 2.75 {
+        let t = t - 2.25 / 2.75;
+        7.5625 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / 2.75;
+        7.5625 * t * t + 0.984375
+    }
+}
+
+fn bounce_in(t: f32) -> f32 {
+    1.0 - bounce_out(1.0 - t)
+}
+
+impl Easing {
+    //The eased progression. t is clamped between 0.0 and 1.0.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => quad_in(t),
+            Easing::QuadOut => ease_out(quad_in, t),
+            Easing::QuadInOut => ease_in_out(quad_in, t),
+            Easing::CubicIn => cubic_in(t),
+            Easing::CubicOut => ease_out(cubic_in, t),
+            Easing::CubicInOut => ease_in_out(cubic_in, t),
+            Easing::QuartIn => quart_in(t),
+            Easing::QuartOut => ease_out(quart_in, t),
+            Easing::QuartInOut => ease_in_out(quart_in, t),
+            Easing::QuintIn => quint_in(t),
+            Easing::QuintOut => ease_out(quint_in, t),
+            Easing::QuintInOut => ease_in_out(quint_in, t),
+            Easing::SineIn => sine_in(t),
+            Easing::SineOut => ease_out(sine_in, t),
+            Easing::SineInOut => ease_in_out(sine_in, t),
+            Easing::ExpoIn => expo_in(t),
+            Easing::ExpoOut => ease_out(expo_in, t),
+            Easing::ExpoInOut => ease_in_out(expo_in, t),
+            Easing::CircIn => circ_in(t),
+            Easing::CircOut => ease_out(circ_in, t),
+            Easing::CircInOut => ease_in_out(circ_in, t),
+            Easing::BackIn => back_in(t),
+            Easing::BackOut => ease_out(back_in, t),
+            Easing::BackInOut => ease_in_out(back_in, t),
+            Easing::ElasticIn => elastic_in(t),
+            Easing::ElasticOut => ease_out(elastic_in, t),
+            Easing::ElasticInOut => ease_in_out(elastic_in, t),
+            Easing::BounceIn => bounce_in(t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => ease_in_out(bounce_in, t),
+        }
+    }
+
+    pub fn all() -> &'static [Easing] {
+        &[
+            Easing::Linear,
+            Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+            Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+            Easing::QuartIn, Easing::QuartOut, Easing::QuartInOut,
+            Easing::QuintIn, Easing::QuintOut, Easing::QuintInOut,
+            Easing::SineIn, Easing::SineOut, Easing::SineInOut,
+            Easing::ExpoIn, Easing::ExpoOut, Easing::ExpoInOut,
+            Easing::CircIn, Easing::CircOut, Easing::CircInOut,
+            Easing::BackIn, Easing::BackOut, Easing::BackInOut,
+            Easing::ElasticIn, Easing::ElasticOut, Easing::ElasticInOut,
+            Easing::BounceIn, Easing::BounceOut, Easing::BounceInOut,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod easing_test {
+    use super::*;
+
+    #[test]
+    fn easing_end_points() {
+        for easing in Easing::all() {
+            assert!(easing.apply(0.0).abs() < 1e-3, "{:?}(0) == {}", easing, easing.apply(0.0));
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-3, "{:?}(1) == {}", easing, easing.apply(1.0));
+            //Clamped outside of [0, 1].
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0));
+            assert_eq!(easing.apply(2.0), easing.apply(1.0));
+        }
+    }
+
+    #[test]
+    fn easing_shapes() {
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+        assert_eq!(Easing::QuadOut.apply(0.5), 0.75);
+        assert_eq!(Easing::CubicInOut.apply(0.5), 0.5);
+        assert!((Easing::SineInOut.apply(0.5) - 0.5).abs() < 1e-5);
+        //The back curve goes below 0 before reaching 1.
+        assert!(Easing::BackIn.apply(0.2) < 0.0);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+        //The bounce curve never overshoots.
+        assert!((0..=100).map(|i| Easing::BounceOut.apply(i as f32 / 100.0)).all(|value| (0.0..=1.0 + 1e-5).contains(&value)));
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::marker::PhantomData;
+use cgmath::{InnerSpace, VectorSpace};
+
+/*
+SPLINES.
+
+Smooth curves through control points, for the camera paths and the projectile arcs.
+The splines work with any cgmath vector of f32 (Vector2, Vector3, Vector4).
+
+- CatmullRom: goes through every point. The first and last points are duplicated, the curve goes
+  from the first point to the last one.
+- Bezier: a chain of cubic Bezier segments. The curve goes through the points 0, 3, 6..., the
+  other points pull the curve toward them.
+- Hermite: goes through every point, with the given tangent at each point.
+
+A curve is sampled with a parameter t between 0.0 and 1.0, spread evenly between the segments.
+The speed along the curve isn't constant: ArcLengthCurve reparameterizes a curve by its length,
+to move at a constant speed.
+*/
+
+pub trait Curve<V> {
+    //The point at t, between 0.0 and 1.0.
+    fn sample(&self, t: f32) -> V;
+}
+
+//The index of the segment and the parameter inside the segment, for t between 0.0 and 1.0.
+fn locate_segment(t: f32, segment_count: usize) -> (usize, f32) {
+    let scaled = t.clamp(0.0, 1.0) * segment_count as f32;
+    let segment = (scaled.floor() as usize).min(segment_count - 1);
+    (segment, scaled - segment as f32)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatmullRom<V> {
+    points: Vec<V>,
+}
+
+impl<V> CatmullRom<V> where
+    V: VectorSpace<Scalar = f32>
+{
+    //None with less than 2 points.
+    pub fn new(points: Vec<V>) -> Option<Self> {
+        if points.len() < 2 {
+            None
+        } else {
+            Some(CatmullRom {
+                points,
+            })
+        }
+    }
+
+    pub fn points(&self) -> &[V] {
+        self.points.as_slice()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    fn point(&self, index: isize) -> V {
+        let last = self.points.len() as isize - 1;
+        self.points[index.clamp(0, last) as usize]
+    }
+}
+
+impl<V> Curve<V> for CatmullRom<V> where
+    V: VectorSpace<Scalar = f32>
+{
+    fn sample(&self, t: f32) -> V {
+        let (segment, t) = locate_segment(t, self.segment_count());
+        let segment = segment as isize;
+        let (p0, p1, p2, p3) = (self.point(segment - 1), self.point(segment), self.point(segment + 1), self.point(segment + 2));
+
+        let t2 = t * t;
+        let t3 = t2 * t;
+        (p1 * 2.0 +
+            (p2 - p0) * t +
+            (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 +
+            (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bezier<V> {
+    points: Vec<V>,
+}
+
+impl<V> Bezier<V> where
+    V: VectorSpace<Scalar = f32>
+{
+    //The points are: start, control, control, end/start, control, control, end/start...
+    //None if the number of points isn't 3n + 1 (with n >= 1).
+    pub fn new(points: Vec<V>) -> Option<Self> {
+        if points.len() < 4 || points.len() % 3 != 1 {
+            None
+        } else {
+            Some(Bezier {
+                points,
+            })
+        }
+    }
+
+    pub fn points(&self) -> &[V] {
+        self.points.as_slice()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        (self.points.len() - 1) / 3
+    }
+}
+
+impl<V> Curve<V> for Bezier<V> where
+    V: VectorSpace<Scalar = f32>
+{
+    fn sample(&self, t: f32) -> V {
+        let (segment, t) = locate_segment(t, self.segment_count());
+        let points = &self.points[segment * 3 .. segment * 3 + 4];
+        let u = 1.0 - t;
+        points[0] * (u * u * u) + points[1] * (3.0 * u * u * t) + points[2] * (3.0 * u * t * t) + points[3] * (t * t * t)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hermite<V> {
+    points: Vec<V>,
+    tangents: Vec<V>,
+}
+
+impl<V> Hermite<V> where
+    V: VectorSpace<Scalar = f32>
+{
+    //One tangent per point. None with less than 2 points, or if the number of tangents differs.
+    pub fn new(points: Vec<V>, tangents: Vec<V>) -> Option<Self> {
+        if points.len() < 2 || points.len() != tangents.len() {
+            None
+        } else {
+            Some(Hermite {
+                points,
+                tangents,
+            })
+        }
+    }
+
+    pub fn points(&self) -> &[V] {
+        self.points.as_slice()
+    }
+
+    pub fn tangents(&self) -> &[V] {
+        self.tangents.as_slice()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.points.len() - 1
+    }
+}
+
+impl<V> Curve<V> for Hermite<V> where
+    V: VectorSpace<Scalar = f32>
+{
+    fn sample(&self, t: f32) -> V {
+        let (segment, t) = locate_segment(t, self.segment_count());
+        let t2 = t * t;
+        let t3 = t2 * t;
+        self.points[segment] * (2.0 * t3 - 3.0 * t2 + 1.0) +
+            self.tangents[segment] * (t3 - 2.0 * t2 + t) +
+            self.points[segment + 1] * (-2.0 * t3 + 3.0 * t2) +
+            self.tangents[segment + 1] * (t3 - t2)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcLengthCurve<V, C> {
+    curve: C,
+    //The length of the curve from its start to each sample.
+    lengths: Vec<f32>,
+    _vector: PhantomData<V>,
+}
+
+impl<V, C> ArcLengthCurve<V, C> where
+    V: InnerSpace<Scalar = f32>,
+    C: Curve<V>,
+{
+    //The length is approximated with samples straight segments. More samples give a more precise length.
+    pub fn new(curve: C, samples: usize) -> Self {
+        let samples = samples.max(1);
+        let mut lengths = Vec::with_capacity(samples + 1);
+        lengths.push(0.0);
+
+        let mut previous = curve.sample(0.0);
+        let mut length = 0.0;
+        for index in 1..=samples {
+            let point = curve.sample(index as f32 / samples as f32);
+            length += (point - previous).magnitude();
+            lengths.push(length);
+            previous = point;
+        }
+
+        ArcLengthCurve {
+            curve,
+            lengths,
+            _vector: PhantomData,
+        }
+    }
+
+    pub fn curve(&self) -> &C {
+        &self.curve
+    }
+
+    pub fn length(&self) -> f32 {
+        self.lengths[self.lengths.len() - 1]
+    }
+
+    //The parameter of the curve at the given distance from its start.
+    pub fn parameter_at_distance(&self, distance: f32) -> f32 {
+        let samples = self.lengths.len() - 1;
+        let distance = distance.clamp(0.0, self.length());
+        let index = match self.lengths.binary_search_by(|length| length.partial_cmp(&distance).unwrap_or(::std::cmp::Ordering::Less)) {
+            Ok(index) => return index as f32 / samples as f32,
+            Err(index) => index.clamp(1, samples),
+        };
+
+        let (before, after) = (self.lengths[index - 1], self.lengths[index]);
+        let fraction = if after > before { (distance - before) / (after - before) } else { 0.0 };
+        (index as f32 - 1.0 + fraction) / samples as f32
+    }
+
+    pub fn sample_at_distance(&self, distance: f32) -> V {
+        self.curve.sample(self.parameter_at_distance(distance))
+    }
+}
+
+//Sampling an ArcLengthCurve moves along the curve at a constant speed.
+impl<V, C> Curve<V> for ArcLengthCurve<V, C> where
+    V: InnerSpace<Scalar = f32>,
+    C: Curve<V>,
+{
+    fn sample(&self, t: f32) -> V {
+        self.sample_at_distance(t * self.length())
+    }
+}
+
+#[cfg(test)]
+mod splines_test {
+    use super::*;
+    use cgmath::{Vector2, Vector3};
+
+    fn close(a: Vector3<f32>, b: Vector3<f32>) -> bool {
+        (a - b).magnitude() < 1e-4
+    }
+
+    #[test]
+    fn splines_go_through_points() {
+        let points = vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 0.0), Vector3::new(3.0, 2.0, 1.0)];
+
+        let catmull_rom = CatmullRom::new(points.clone()).unwrap();
+        assert!(close(catmull_rom.sample(0.0), points[0]));
+        assert!(close(catmull_rom.sample(0.5), points[1]));
+        assert!(close(catmull_rom.sample(1.0), points[2]));
+
+        let tangents = vec![Vector3::new(1.0, 0.0, 0.0); 3];
+        let hermite = Hermite::new(points.clone(), tangents).unwrap();
+        assert!(close(hermite.sample(0.5), points[1]));
+        assert!(close(hermite.sample(1.0), points[2]));
+
+        assert!(CatmullRom::new(vec![Vector3::new(0.0, 0.0, 0.0)]).is_none());
+        assert!(Hermite::new(points, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn bezier_segments() {
+        let bezier = Bezier::new(vec![
+            Vector2::new(0.0, 0.0), Vector2::new(0.0, 1.0), Vector2::new(1.0, 1.0), Vector2::new(1.0, 0.0),
+            Vector2::new(1.0, -1.0), Vector2::new(2.0, -1.0), Vector2::new(2.0, 0.0),
+        ]).unwrap();
+        assert_eq!(bezier.segment_count(), 2);
+        assert_eq!(bezier.sample(0.5), Vector2::new(1.0, 0.0));
+        assert_eq!(bezier.sample(0.25), Vector2::new(0.5, 0.75));
+        assert!(Bezier::new(vec![Vector2::new(0.0, 0.0); 5]).is_none());
+    }
+
+    #[test]
+    fn arc_length_parameterization() {
+        //The control points are unevenly spread: the raw parameter moves faster at the end of the line.
+        let curve = Bezier::new(vec![Vector2::new(0.0, 0.0), Vector2::new(0.1, 0.0), Vector2::new(0.2, 0.0), Vector2::new(10.0, 0.0)]).unwrap();
+        assert!(curve.sample(0.5).x < 2.0);
+        let arc_length = ArcLengthCurve::new(curve, 1000);
+        assert!((arc_length.length() - 10.0).abs() < 1e-2);
+
+        assert!((arc_length.sample(0.5).x - 5.0).abs() < 1e-2);
+        assert!((arc_length.sample_at_distance(2.5).x - 2.5).abs() < 1e-2);
+        assert_eq!(arc_length.sample(0.0), Vector2::new(0.0, 0.0));
+        assert!((arc_length.sample(1.0).x - 10.0).abs() < 1e-4);
+    }
+}
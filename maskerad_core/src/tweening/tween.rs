@@ -0,0 +1,450 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+use cgmath::{Quaternion, Vector2, Vector3, Vector4, InnerSpace};
+use time::Duration;
+use clock::Clock;
+use math::color::Color;
+use tweening::easing::Easing;
+
+/*
+TWEENS.
+
+A Tween animates a value from a start value to an end value, during a duration, following an easing curve.
+
+A tween is driven by a Clock: the first update() starts the tween, the next ones compute the value
+from the time elapsed on the clock. Pausing or scaling the clock pauses or scales the tween.
+
+- delay: the tween keeps its start value during the delay.
+- repeat: the tween is played once, several times, or forever.
+- yoyo: every other repetition is played backward (start -> end -> start...).
+- on_complete: a callback called once, when the last repetition ends.
+
+A Sequence plays several tweens of the same type one after the other.
+*/
+
+//A value which can be interpolated by a tween.
+pub trait Tweenable: Copy {
+    fn interpolate(&self, to: &Self, amount: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn interpolate(&self, to: &f32, amount: f32) -> f32 {
+        self + (to - self) * amount
+    }
+}
+
+impl Tweenable for f64 {
+    fn interpolate(&self, to: &f64, amount: f32) -> f64 {
+        self + (to - self) * amount as f64
+    }
+}
+
+impl Tweenable for Vector2<f32> {
+    fn interpolate(&self, to: &Self, amount: f32) -> Self {
+        self.lerp(*to, amount)
+    }
+}
+
+impl Tweenable for Vector3<f32> {
+    fn interpolate(&self, to: &Self, amount: f32) -> Self {
+        self.lerp(*to, amount)
+    }
+}
+
+impl Tweenable for Vector4<f32> {
+    fn interpolate(&self, to: &Self, amount: f32) -> Self {
+        self.lerp(*to, amount)
+    }
+}
+
+//Spherical interpolation, along the shortest arc.
+impl Tweenable for Quaternion<f32> {
+    fn interpolate(&self, to: &Self, amount: f32) -> Self {
+        let to = if self.dot(*to) < 0.0 { -*to } else { *to };
+        self.slerp(to, amount).normalize()
+    }
+}
+
+impl Tweenable for Color {
+    fn interpolate(&self, to: &Self, amount: f32) -> Self {
+        self.lerp(to, amount)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Repeat {
+    Once,
+    Times(u32),
+    Forever,
+}
+
+impl Repeat {
+    fn count(self) -> Option<u32> {
+        match self {
+            Repeat::Once => Some(1),
+            Repeat::Times(count) => Some(count.max(1)),
+            Repeat::Forever => None,
+        }
+    }
+}
+
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    duration_ms: i64,
+    delay_ms: i64,
+    easing: Easing,
+    repeat: Repeat,
+    yoyo: bool,
+    on_complete: Option<Box<dyn FnMut()>>,
+    //The clock time of the first update.
+    start_ms: Option<i64>,
+    completed: bool,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Tween<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tween [{:?} -> {:?}, duration: {} ms, delay: {} ms, easing: {:?}, repeat: {:?}, yoyo: {}, completed: {}]",
+            self.from, self.to, self.duration_ms, self.delay_ms, self.easing, self.repeat, self.yoyo, self.completed)
+    }
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub fn new(from: T, to: T, duration: Duration) -> Self {
+        Tween {
+            from,
+            to,
+            duration_ms: duration.num_milliseconds().max(0),
+            delay_ms: 0,
+            easing: Easing::Linear,
+            repeat: Repeat::Once,
+            yoyo: false,
+            on_complete: None,
+            start_ms: None,
+            completed: false,
+        }
+    }
+
+    pub fn set_easing(&mut self, easing: Easing) -> &mut Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn set_delay(&mut self, delay: Duration) -> &mut Self {
+        self.delay_ms = delay.num_milliseconds().max(0);
+        self
+    }
+
+    pub fn set_repeat(&mut self, repeat: Repeat) -> &mut Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn set_yoyo(&mut self, yoyo: bool) -> &mut Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    pub fn set_on_complete<F>(&mut self, on_complete: F) -> &mut Self where
+        F: FnMut() + 'static
+    {
+        self.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    pub fn from(&self) -> T {
+        self.from
+    }
+
+    pub fn to(&self) -> T {
+        self.to
+    }
+
+    //The total time of the tween, delay included. None if the tween repeats forever.
+    pub fn total_duration_ms(&self) -> Option<i64> {
+        self.repeat.count().map(|count| self.delay_ms + self.duration_ms * count as i64)
+    }
+
+    //The value after elapsed_ms milliseconds.
+    pub fn value_at(&self, elapsed_ms: i64) -> T {
+        let active_ms = elapsed_ms - self.delay_ms;
+        if active_ms <= 0 {
+            return self.from;
+        }
+
+        let count = self.repeat.count();
+        //The end value of the last repetition.
+        let finished = match count {
+            Some(count) => active_ms >= self.duration_ms * count as i64,
+            None => false,
+        };
+        if finished || self.duration_ms == 0 {
+            let last_is_backward = self.yoyo && count.map(|count| count % 2 == 0).unwrap_or(false);
+            return if last_is_backward { self.from } else { self.to };
+        }
+
+        let repetition = active_ms / self.duration_ms;
+        let mut progress = (active_ms % self.duration_ms) as f32 / self.duration_ms as f32;
+        if self.yoyo && repetition % 2 == 1 {
+            progress = 1.0 - progress;
+        }
+        self.from.interpolate(&self.to, self.easing.apply(progress))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed
+    }
+
+    //Restart the tween on the next update.
+    pub fn reset(&mut self) {
+        self.start_ms = None;
+        self.completed = false;
+    }
+
+    //Advance the tween to the time of the clock, and return the current value.
+    pub fn update(&mut self, clock: &Clock) -> T {
+        let now = clock.total_time_ms();
+        let start = *self.start_ms.get_or_insert(now);
+        self.update_elapsed(now - start)
+    }
+
+    //Advance the tween to elapsed_ms milliseconds since its start, and return the current value.
+    pub fn update_elapsed(&mut self, elapsed_ms: i64) -> T {
+        if !self.completed {
+            if let Some(total) = self.total_duration_ms() {
+                if elapsed_ms >= total {
+                    self.completed = true;
+                    if let Some(ref mut on_complete) = self.on_complete {
+                        on_complete();
+                    }
+                }
+            }
+        }
+        self.value_at(elapsed_ms)
+    }
+}
+
+//Tweens played one after the other.
+pub struct Sequence<T> {
+    tweens: Vec<Tween<T>>,
+    on_complete: Option<Box<dyn FnMut()>>,
+    start_ms: Option<i64>,
+    completed: bool,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Sequence<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Sequence [{:?}, completed: {}]", self.tweens, self.completed)
+    }
+}
+
+impl<T: Tweenable> Default for Sequence<T> {
+    fn default() -> Self {
+        Sequence {
+            tweens: Vec::new(),
+            on_complete: None,
+            start_ms: None,
+            completed: false,
+        }
+    }
+}
+
+impl<T: Tweenable> Sequence<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //Add a tween at the end of the sequence. A tween repeating forever never ends: the next tweens are never played.
+    pub fn then(&mut self, tween: Tween<T>) -> &mut Self {
+        self.tweens.push(tween);
+        self
+    }
+
+    //Keep the last value during the delay.
+    pub fn then_wait(&mut self, delay: Duration) -> &mut Self {
+        let value = match self.tweens.last() {
+            Some(tween) => tween.value_at(i64::MAX),
+            None => return self,
+        };
+        let tween = Tween::new(value, value, delay);
+        self.then(tween)
+    }
+
+    pub fn set_on_complete<F>(&mut self, on_complete: F) -> &mut Self where
+        F: FnMut() + 'static
+    {
+        self.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.tweens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tweens.is_empty()
+    }
+
+    //None if a tween repeats forever.
+    pub fn total_duration_ms(&self) -> Option<i64> {
+        self.tweens.iter().map(|tween| tween.total_duration_ms()).sum()
+    }
+
+    //None if the sequence is empty.
+    pub fn value_at(&self, elapsed_ms: i64) -> Option<T> {
+        let mut remaining_ms = elapsed_ms;
+        for tween in self.tweens.iter() {
+            match tween.total_duration_ms() {
+                Some(duration) if remaining_ms >= duration => remaining_ms -= duration,
+                _ => return Some(tween.value_at(remaining_ms)),
+            }
+        }
+        self.tweens.last().map(|tween| tween.value_at(i64::MAX))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed
+    }
+
+    pub fn reset(&mut self) {
+        self.start_ms = None;
+        self.completed = false;
+        for tween in self.tweens.iter_mut() {
+            tween.reset();
+        }
+    }
+
+    //Advance the sequence to the time of the clock, and return the current value. None if the sequence is empty.
+    pub fn update(&mut self, clock: &Clock) -> Option<T> {
+        let now = clock.total_time_ms();
+        let start = *self.start_ms.get_or_insert(now);
+        self.update_elapsed(now - start)
+    }
+
+    //Advance the sequence, calling the callbacks of the tweens which ended.
+    pub fn update_elapsed(&mut self, elapsed_ms: i64) -> Option<T> {
+        let mut tween_start_ms = 0;
+        for tween in self.tweens.iter_mut() {
+            if elapsed_ms < tween_start_ms {
+                break;
+            }
+            tween.update_elapsed(elapsed_ms - tween_start_ms);
+            match tween.total_duration_ms() {
+                Some(duration) => tween_start_ms += duration,
+                None => break,
+            }
+        }
+
+        if !self.completed && !self.tweens.is_empty() && self.tweens.iter().all(|tween| tween.is_complete()) {
+            self.completed = true;
+            if let Some(ref mut on_complete) = self.on_complete {
+                on_complete();
+            }
+        }
+
+        self.value_at(elapsed_ms)
+    }
+}
+
+#[cfg(test)]
+mod tween_test {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use cgmath::{Rad, Rotation3};
+
+    #[test]
+    fn tween_values() {
+        let mut tween = Tween::new(0.0f32, 10.0, Duration::milliseconds(100));
+        tween.set_delay(Duration::milliseconds(50)).set_easing(Easing::QuadIn);
+
+        assert_eq!(tween.value_at(0), 0.0);
+        assert_eq!(tween.value_at(50), 0.0);
+        assert_eq!(tween.value_at(100), 2.5);
+        assert_eq!(tween.value_at(150), 10.0);
+        assert_eq!(tween.value_at(1000), 10.0);
+        assert_eq!(tween.total_duration_ms(), Some(150));
+    }
+
+    #[test]
+    fn tween_repeat_and_yoyo() {
+        let mut tween = Tween::new(0.0f32, 1.0, Duration::milliseconds(100));
+        tween.set_repeat(Repeat::Times(2)).set_yoyo(true);
+        assert_eq!(tween.value_at(50), 0.5);
+        assert_eq!(tween.value_at(100), 1.0);
+        assert_eq!(tween.value_at(125), 0.75);
+        assert_eq!(tween.value_at(500), 0.0);
+
+        tween.set_yoyo(false).set_repeat(Repeat::Forever);
+        assert_eq!(tween.value_at(10_025), 0.25);
+        assert_eq!(tween.total_duration_ms(), None);
+    }
+
+    #[test]
+    fn tween_with_clock() {
+        let completions = Rc::new(Cell::new(0));
+        let counter = completions.clone();
+
+        let mut clock = Clock::new();
+        let mut tween = Tween::new(Color::black(), Color::white(), Duration::milliseconds(100));
+        tween.set_on_complete(move || counter.set(counter.get() + 1));
+
+        assert_eq!(tween.update(&clock), Color::black());
+        clock.update(Duration::milliseconds(50));
+        assert_eq!(tween.update(&clock), Color::rgb(0.5, 0.5, 0.5));
+
+        //A paused clock pauses the tween.
+        clock.set_paused(true);
+        clock.update(Duration::milliseconds(500));
+        assert_eq!(tween.update(&clock), Color::rgb(0.5, 0.5, 0.5));
+        assert!(!tween.is_complete());
+
+        clock.set_paused(false);
+        clock.update(Duration::milliseconds(60));
+        assert_eq!(tween.update(&clock), Color::white());
+        clock.update(Duration::milliseconds(60));
+        tween.update(&clock);
+        assert!(tween.is_complete());
+        assert_eq!(completions.get(), 1);
+    }
+
+    #[test]
+    fn tween_quaternions() {
+        let from = Quaternion::from_angle_z(Rad(0.0f32));
+        let to = Quaternion::from_angle_z(Rad(1.0f32));
+        let tween = Tween::new(from, to, Duration::milliseconds(100));
+        let halfway = tween.value_at(50);
+        let expected = Quaternion::from_angle_z(Rad(0.5f32));
+        assert!((halfway - expected).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn sequence_plays_in_order() {
+        let completed = Rc::new(Cell::new(false));
+        let flag = completed.clone();
+
+        let mut sequence = Sequence::new();
+        sequence
+            .then(Tween::new(Vector2::new(0.0f32, 0.0), Vector2::new(10.0, 0.0), Duration::milliseconds(100)))
+            .then_wait(Duration::milliseconds(50))
+            .then(Tween::new(Vector2::new(10.0, 0.0), Vector2::new(10.0, 10.0), Duration::milliseconds(100)))
+            .set_on_complete(move || flag.set(true));
+
+        assert_eq!(sequence.len(), 3);
+        assert_eq!(sequence.total_duration_ms(), Some(250));
+        assert_eq!(sequence.update_elapsed(50), Some(Vector2::new(5.0, 0.0)));
+        assert_eq!(sequence.update_elapsed(120), Some(Vector2::new(10.0, 0.0)));
+        assert_eq!(sequence.update_elapsed(200), Some(Vector2::new(10.0, 5.0)));
+        assert!(!completed.get());
+        assert_eq!(sequence.update_elapsed(300), Some(Vector2::new(10.0, 10.0)));
+        assert!(completed.get() && sequence.is_complete());
+
+        assert_eq!(Sequence::<f32>::new().value_at(0), None);
+    }
+}
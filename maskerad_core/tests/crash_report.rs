@@ -0,0 +1,53 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//The crash handler is installed as the process-wide panic hook: it's tested in its own test binary,
+//where no other test can panic while it is installed.
+
+extern crate maskerad_core;
+extern crate remove_dir_all;
+
+use std::env;
+use std::fs;
+use std::panic;
+use std::path::Path;
+use std::thread;
+use remove_dir_all::remove_dir_all;
+use maskerad_core::jobs::job_system::JobSystem;
+use maskerad_core::logging::crash_report::CrashHandler;
+
+//The reports of the given panic message.
+fn reports_of(directory: &Path, message: &str) -> usize {
+    match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| fs::read_to_string(entry.unwrap().path().join("report.txt")).ok())
+            .filter(|summary| summary.contains(message))
+            .count(),
+        Err(_) => 0,
+    }
+}
+
+#[test]
+fn crash_report_caught_panics() {
+    let directory = env::temp_dir().join("maskerad_crash_report_caught_test");
+    if directory.exists() {
+        remove_dir_all(directory.as_path()).unwrap();
+    }
+    CrashHandler::new(directory.as_path()).install();
+
+    let job_system = JobSystem::new(1).unwrap();
+    let job = job_system.spawn(|| panic!("caught job failure"));
+    assert!(job_system.wait(&job).is_err());
+    assert_eq!(reports_of(directory.as_path(), "caught job failure"), 0);
+
+    assert!(thread::spawn(|| panic!("uncaught thread failure")).join().is_err());
+    assert_eq!(reports_of(directory.as_path(), "uncaught thread failure"), 1);
+
+    //Restore the default hook.
+    let _ = panic::take_hook();
+    remove_dir_all(directory.as_path()).unwrap();
+}
@@ -0,0 +1,180 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use handle::{Handle, HandleId};
+use registries::registry::Registry;
+use resources::resource_errors::ResourceResult;
+
+/*
+ASYNCHRONOUS LOADING.
+
+A resource loaded asynchronously is reserved in its registry, and its handle is returned immediately,
+in the Loading state. The resource is decoded and post processed by a job on the worker threads, and sent
+back to the resource manager through a channel. ResourceManager::update(), called by the main thread once per frame,
+publishes the decoded resources in the registries.
+
+The loads can be grouped in batches (a level, a menu...). The progress of a batch, in items and in bytes,
+can be displayed by a loading screen. The bytes of an item are its size hint, given by the game: the size of its file
+(from the file metadata, or from the index of a pack). The reader isn't seeked on the main thread to find its size:
+a compressed reader would be decompressed to its end. The batches progress by items if their sizes are unknown.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BatchId(u32);
+
+impl BatchId {
+    pub(crate) fn new(id: u32) -> Self {
+        BatchId(id)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub items_total: usize,
+    pub items_done: usize,
+    pub items_failed: usize,
+    pub bytes_total: u64,
+    pub bytes_done: u64,
+}
+
+impl LoadProgress {
+    //Every item of the batch has been loaded, or has failed.
+    pub fn is_complete(&self) -> bool {
+        self.items_done + self.items_failed >= self.items_total
+    }
+
+    //The progress between 0.0 and 1.0, by bytes if the size of the items is known, by items otherwise.
+    pub fn fraction(&self) -> f32 {
+        if self.is_complete() {
+            1.0
+        } else if self.bytes_total > 0 {
+            self.bytes_done as f32 / self.bytes_total as f32
+        } else {
+            (self.items_done + self.items_failed) as f32 / self.items_total as f32
+        }
+    }
+
+    pub(crate) fn add_item(&mut self, bytes: u64) {
+        self.items_total += 1;
+        self.bytes_total += bytes;
+    }
+
+    pub(crate) fn finish_item(&mut self, bytes: u64, success: bool) {
+        if success {
+            self.items_done += 1;
+        } else {
+            self.items_failed += 1;
+        }
+        self.bytes_done += bytes;
+    }
+}
+
+//A decoded and post processed resource, sent by a loading job.
+pub(crate) struct Completed<T> {
+    pub id: HandleId,
+    pub result: ResourceResult<T>,
+}
+
+//The loads in progress for a resource type.
+pub(crate) struct PendingLoads<T> {
+    sender: Sender<Completed<T>>,
+    receiver: Receiver<Completed<T>>,
+    //The batches waiting for each load, with the size of the item in each batch.
+    waiting: HashMap<HandleId, Vec<(BatchId, u64)>>,
+}
+
+impl<T> Default for PendingLoads<T> {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        PendingLoads {
+            sender,
+            receiver,
+            waiting: HashMap::new(),
+        }
+    }
+}
+
+impl<T> PendingLoads<T> {
+    pub fn sender(&self) -> Sender<Completed<T>> {
+        self.sender.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_loading(&self, id: HandleId) -> bool {
+        self.waiting.contains_key(&id)
+    }
+
+    pub fn wait(&mut self, id: HandleId, batch: Option<BatchId>, bytes: u64, batches: &mut HashMap<BatchId, LoadProgress>) {
+        let waiting = self.waiting.entry(id).or_default();
+        if let Some(batch) = batch {
+            if let Some(progress) = batches.get_mut(&batch) {
+                progress.add_item(bytes);
+                waiting.push((batch, bytes));
+            }
+        }
+    }
+
+    //Publish the decoded resources in the registry. The handles of the resources which failed to load give access
+    //to the placeholder, if there's one. Return the number of loads completed.
+    pub fn publish(&mut self, registry: &mut Registry<T>, placeholder: Option<Handle<T>>, batches: &mut HashMap<BatchId, LoadProgress>) -> usize {
+        let mut completed = 0;
+        while let Ok(Completed { id, result }) = self.receiver.try_recv() {
+            //A resource replaced by insert() or a hot reload during its loading is dropped, but its loading succeeded.
+            let success = match result {
+                Ok(resource) => {
+                    if !registry.complete(id, resource) {
+                        debug!("The resource {} has been released or replaced during its loading.", id);
+                    }
+                    true
+                },
+                Err(resource_error) => {
+                    match placeholder {
+                        Some(_) => warn!("Could not load the resource {} asynchronously, using a placeholder: {}", id, resource_error),
+                        None => error!("Could not load the resource {} asynchronously: {}", id, resource_error),
+                    }
+                    registry.fail(id, placeholder.as_ref());
+                    false
+                },
+            };
+
+            for (batch, bytes) in self.waiting.remove(&id).unwrap_or_default() {
+                if let Some(progress) = batches.get_mut(&batch) {
+                    progress.finish_item(bytes, success);
+                }
+            }
+            completed += 1;
+        }
+        completed
+    }
+}
+
+#[cfg(test)]
+mod async_loading_test {
+    use super::*;
+
+    #[test]
+    fn progress() {
+        let mut progress = LoadProgress::default();
+        assert!(progress.is_complete());
+        assert_eq!(progress.fraction(), 1.0);
+
+        progress.add_item(300);
+        progress.add_item(100);
+        progress.finish_item(100, true);
+        assert!(!progress.is_complete());
+        assert_eq!(progress.fraction(), 0.25);
+
+        progress.finish_item(300, false);
+        assert!(progress.is_complete());
+        assert_eq!(progress.items_failed, 1);
+    }
+}
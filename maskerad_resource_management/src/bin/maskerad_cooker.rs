@@ -0,0 +1,76 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+extern crate maskerad_resource_management;
+
+use std::env;
+use std::process;
+
+use maskerad_resource_management::cooking::asset_cooker::AssetCooker;
+use maskerad_resource_management::post_processing::image_processors::GenerateMipmaps;
+
+/*
+ASSET COOKER.
+
+Cook the assets of a source directory in a cooked directory. Headless, for the build farm:
+
+maskerad_cooker [--force] <source directory> <cooked directory>
+
+--force cooks every asset, even the unchanged ones.
+The images get their mip levels, unless their metadata file skips generate_mipmaps. The images with a
+texture_compression table in their metadata file are compressed in BC1 or BC3, and written in a KTX2 or a DDS file:
+
+[texture_compression]
+format = "bc3"
+container = "ktx2"
+
+The exit code is 0 if every asset has been cooked, 1 if some assets couldn't be cooked, 2 for a usage error.
+*/
+
+const USAGE: &str = "usage: maskerad_cooker [--force] <source directory> <cooked directory>";
+
+fn main() {
+    let mut force = false;
+    let mut directories = Vec::new();
+    for argument in env::args().skip(1) {
+        match argument.as_str() {
+            "--force" => force = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if argument.starts_with('-') => {
+                eprintln!("unknown option {}\n{}", argument, USAGE);
+                process::exit(2);
+            },
+            _ => directories.push(argument),
+        }
+    }
+    if directories.len() != 2 {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut cooker = AssetCooker::new(directories[0].as_str(), directories[1].as_str());
+    cooker.set_force(force);
+    cooker.image_post_processors_mut().add(GenerateMipmaps);
+
+    let report = match cooker.cook() {
+        Ok(report) => report,
+        Err(cook_error) => {
+            eprintln!("error: {}", cook_error);
+            process::exit(1);
+        },
+    };
+    for (path, cook_error) in report.failed.iter() {
+        eprintln!("failed: {}: {}", path.display(), cook_error);
+    }
+    println!("{} cooked, {} unchanged, {} removed, {} failed.", report.cooked.len(), report.skipped.len(), report.removed.len(), report.failed.len());
+    if !report.is_success() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,189 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io::{Read, Seek};
+use std::mem;
+use maskerad_core::allocators::stats::MemoryStats;
+
+use resources::image_resource::ImageResource;
+use resources::model_resource::ModelResource;
+use resources::sound_resource::SoundResource;
+use resources::buffer_resource::BufferResource;
+use resources::texture_resource::TextureResource;
+
+/*
+RESOURCE BUDGETS.
+
+The memory of the resources is budgeted per category: the textures (images, and the compressed textures of the
+TextureLoader), the meshes (models and their buffers) and the audio (sounds). The resources of the other registered
+loaders are in the category given by their loader, if any.
+
+A category with a budget keeps its resources resident when their last handle is dropped: a resource requested
+again is still loaded, which is what a streaming game wants. When the resident resources of the category exceed
+its budget, the unused resources (without handles outside the resource manager) are evicted, from the least
+recently used. The resources still used are never evicted: a category can stay over its budget.
+
+The budgets are enforced by ResourceManager::update(), every frame, and by ResourceManager::enforce_budgets().
+The residency stats of the categories are given by ResourceManager::residency_stats() and memory_report(),
+for the debugging overlay.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceCategory {
+    Textures,
+    Meshes,
+    Audio,
+}
+
+impl ResourceCategory {
+    pub fn all() -> [ResourceCategory; 3] {
+        [ResourceCategory::Textures, ResourceCategory::Meshes, ResourceCategory::Audio]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            &ResourceCategory::Textures => "textures",
+            &ResourceCategory::Meshes => "meshes",
+            &ResourceCategory::Audio => "audio",
+        }
+    }
+}
+
+//The bytes used by a resource in memory.
+pub trait MemorySize {
+    fn memory_size(&self) -> usize;
+}
+
+impl MemorySize for ImageResource {
+    fn memory_size(&self) -> usize {
+        (0..self.mip_level_count()).filter_map(|level| self.mip_level(level)).map(|level| level.buf.len()).sum()
+    }
+}
+
+//The external buffers of a model are counted separately, as buffer resources.
+impl MemorySize for ModelResource {
+    fn memory_size(&self) -> usize {
+        mem::size_of::<ModelResource>() + self.data_size()
+    }
+}
+
+impl MemorySize for TextureResource {
+    fn memory_size(&self) -> usize {
+        (0..self.mip_level_count()).filter_map(|level| self.level(level)).map(|level| level.len()).sum()
+    }
+}
+
+impl MemorySize for BufferResource {
+    fn memory_size(&self) -> usize {
+        self.len()
+    }
+}
+
+//A streamed sound is decoded packet by packet from its reader: only the decoded sounds are counted.
+impl<R: Read + Seek> MemorySize for SoundResource<R> {
+    fn memory_size(&self) -> usize {
+        self.decoded().map(|sound| {
+            sound.channels.iter().map(|channel| channel.len() * mem::size_of::<i16>()).sum()
+        }).unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ResidencyStats {
+    pub resident_count: usize,
+    pub resident_bytes: usize,
+    //The resident resources without handles outside the resource manager, which can be evicted.
+    pub unused_count: usize,
+    pub unused_bytes: usize,
+    pub budget: Option<usize>,
+    //The highest number of resident bytes.
+    pub high_water_mark: usize,
+    pub evictions: usize,
+}
+
+impl ResidencyStats {
+    //Count a resident resource.
+    pub(crate) fn add_resource(&mut self, size: usize, unused: bool) {
+        self.resident_count += 1;
+        self.resident_bytes += size;
+        if unused {
+            self.unused_count += 1;
+            self.unused_bytes += size;
+        }
+    }
+
+    pub fn is_over_budget(&self) -> bool {
+        match self.budget {
+            Some(budget) => self.resident_bytes > budget,
+            None => false,
+        }
+    }
+
+    //The stats in the format of the allocator stats, for the memory reports. The capacity is the budget.
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats::new(self.resident_bytes, self.budget.unwrap_or(0), self.high_water_mark, self.resident_count, 0, self.budget)
+    }
+}
+
+#[cfg(test)]
+mod budgets_test {
+    use super::*;
+    use imagefmt::{ColFmt, Image};
+    use resources::sound_resource::DecodedSound;
+    use gltf::Glb;
+    use resources::model_resource::glb_data;
+    use resources::texture_resource::TextureFormat;
+    use cooking::texture_compression;
+    use post_processing::asset_metadata::AssetMetadata;
+    use post_processing::image_processors::GenerateMipmaps;
+    use post_processing::post_processor::PostProcessor;
+    use std::io::Cursor;
+
+    #[test]
+    fn memory_sizes() {
+        let mut image = ImageResource::checkerboard(4, 1);
+        assert_eq!(image.memory_size(), 64);
+        image.mipmaps_mut().push(Image {
+            w: 2,
+            h: 2,
+            fmt: ColFmt::RGBA,
+            buf: vec![0; 16],
+        });
+        assert_eq!(image.memory_size(), 80);
+
+        let sound: SoundResource<Cursor<Vec<u8>>> = SoundResource::from_decoded(DecodedSound {
+            sample_rate: 44100,
+            channels: vec![vec![0; 10], vec![0; 10]],
+        });
+        assert_eq!(sound.memory_size(), 40);
+        assert_eq!(SoundResource::<Cursor<Vec<u8>>>::silence().memory_size(), 0);
+
+        //The data uri of the unit cube, then its tangents.
+        let mut model = ModelResource::unit_cube().unwrap();
+        let data_uri = model.gltf().buffers().next().unwrap().uri().len();
+        assert_eq!(model.memory_size(), mem::size_of::<ModelResource>() + data_uri);
+        model.set_tangents(0, 0, Some(vec![[0.0; 4]; 8]));
+        assert_eq!(model.memory_size(), mem::size_of::<ModelResource>() + data_uri + 8 * 16);
+        let glb = glb_data(r#"{"asset":{"version":"2.0"},"buffers":[{"byteLength":64}]}"#, &[0; 64]);
+        let model = ModelResource::from_glb(Box::new(Glb::from_slice(glb.as_slice()).unwrap())).unwrap();
+        assert_eq!(model.memory_size(), mem::size_of::<ModelResource>() + 64);
+
+        //A BC1 block of 8 bytes per 4x4 texels, on the 4 mip levels of an 8x8 image.
+        let mut image = ImageResource::checkerboard(8, 1);
+        GenerateMipmaps.process(&mut image, &AssetMetadata::default()).unwrap();
+        let texture = texture_compression::compress(&image, TextureFormat::Bc1RgbaUnorm).unwrap();
+        assert_eq!(texture.memory_size(), 4 * 8 + 8 + 8 + 8);
+
+        let stats = ResidencyStats {
+            resident_bytes: 200,
+            budget: Some(100),
+            ..Default::default()
+        };
+        assert!(stats.is_over_budget());
+        assert!(stats.memory_stats().is_over_budget());
+    }
+}
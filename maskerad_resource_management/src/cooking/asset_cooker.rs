@@ -0,0 +1,478 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Cursor, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use cooking::content_hash::{ContentHash, ContentHasher};
+use cooking::cook_errors::{CookError, CookResult};
+use cooking::cook_manifest::{AssetKind, CookManifest, ManifestEntry, MANIFEST_FILE_NAME};
+use cooking::cooked_formats::{self, COOKED_IMAGE_EXTENSION, COOKED_SOUND_EXTENSION};
+use cooking::texture_compression;
+use post_processing::asset_metadata::AssetMetadata;
+use post_processing::post_processor::PostProcessorChain;
+use resource_loader::extension_of;
+use resources::image_resource::{ImageResource, ColorFormat};
+use resources::model_resource::ModelResource;
+use resources::sound_resource::SoundResource;
+use resources::resource_errors::{ResourceError, ResourceResult};
+
+/*
+ASSET COOKING.
+
+Decoding TGA, glTF and OGG files at runtime is slow. The asset cooker converts the source assets of a directory
+into engine ready files, in a cooked directory, ahead of time (in the build farm, with the maskerad_cooker binary):
+
+- The images are decoded in RGBA, post processed (mipmaps...) and written in the cooked image format. The images
+  with a texture compression in their metadata file are compressed, and written in a KTX2 or a DDS file
+  (see the texture_compression module).
+- The sounds are decoded, post processed and written in the cooked sound format.
+- The models and their buffers are validated and copied.
+
+The assets are post processed with the metadata files next to them, as by the resource manager.
+
+The manifest of the cooked directory records the hash of every cooked asset (see the cook_manifest module).
+An asset whose source, metadata file, dependencies and importer version haven't changed since the previous cooking
+is skipped. The cooked files of the assets removed from the source directory, and of the assets which can't be cooked
+anymore, are deleted.
+
+At runtime, the resource manager loads the cooked files listed by the manifest given to set_cook_manifest(),
+with the load_cooked_* functions. The compressed images are loaded with load_cooked_texture().
+*/
+
+//The sounds are post processed with this reader type.
+pub type CookedSoundResource = SoundResource<Cursor<Vec<u8>>>;
+
+#[derive(Debug, Default)]
+pub struct CookReport {
+    pub cooked: Vec<PathBuf>,
+    //The assets unchanged since the previous cooking.
+    pub skipped: Vec<PathBuf>,
+    //The assets removed from the source directory, whose cooked files have been deleted.
+    pub removed: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, CookError)>,
+}
+
+impl CookReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+//The hash of a source asset, and the source assets it depends on.
+struct SourceAsset {
+    kind: AssetKind,
+    hash: ContentHash,
+    dependencies: Vec<PathBuf>,
+}
+
+//The path with an extension added: textures/wood.tga -> textures/wood.tga.mkimage
+fn with_extension_added(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+fn import_error(path: &Path) -> impl FnOnce(ResourceError) -> CookError + '_ {
+    move |resource_error| CookError::ImportError(String::from("The asset can't be imported."), path.to_path_buf(), resource_error)
+}
+
+pub struct AssetCooker {
+    source_directory: PathBuf,
+    output_directory: PathBuf,
+    force: bool,
+    image_post_processors: PostProcessorChain<ImageResource>,
+    sound_post_processors: PostProcessorChain<CookedSoundResource>,
+}
+
+impl AssetCooker {
+    pub fn new<S, O>(source_directory: S, output_directory: O) -> Self where
+        S: Into<PathBuf>,
+        O: Into<PathBuf>,
+    {
+        AssetCooker {
+            source_directory: source_directory.into(),
+            output_directory: output_directory.into(),
+            force: false,
+            image_post_processors: PostProcessorChain::new(),
+            sound_post_processors: PostProcessorChain::new(),
+        }
+    }
+
+    //Cook every asset, even the unchanged ones.
+    pub fn set_force(&mut self, force: bool) -> &mut Self {
+        self.force = force;
+        self
+    }
+
+    pub fn image_post_processors_mut(&mut self) -> &mut PostProcessorChain<ImageResource> {
+        &mut self.image_post_processors
+    }
+
+    pub fn sound_post_processors_mut(&mut self) -> &mut PostProcessorChain<CookedSoundResource> {
+        &mut self.sound_post_processors
+    }
+
+    pub fn manifest_path(&self) -> PathBuf {
+        self.output_directory.join(MANIFEST_FILE_NAME)
+    }
+
+    //The source assets, relative to the source directory, sorted. The cooked directory is ignored.
+    pub fn source_assets(&self) -> CookResult<Vec<(PathBuf, AssetKind)>> {
+        let mut assets = Vec::new();
+        //The cooked directory doesn't exist before the first cooking.
+        let output_directory = fs::canonicalize(self.output_directory.as_path()).ok();
+        self.collect_assets(self.source_directory.as_path(), output_directory.as_deref(), &mut assets).map_err(|io_error| {
+            CookError::IoError(String::from("The source directory can't be read."), self.source_directory.clone(), io_error)
+        })?;
+        assets.sort();
+        Ok(assets)
+    }
+
+    fn collect_assets(&self, directory: &Path, output_directory: Option<&Path>, assets: &mut Vec<(PathBuf, AssetKind)>) -> io::Result<()> {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if output_directory.is_none() || fs::canonicalize(path.as_path()).ok().as_deref() != output_directory {
+                    self.collect_assets(path.as_path(), output_directory, assets)?;
+                }
+            } else if let Some(kind) = extension_of(path.as_path()).and_then(|extension| AssetKind::from_extension(extension.as_str())) {
+                if let Ok(relative) = path.strip_prefix(self.source_directory.as_path()) {
+                    assets.push((relative.to_path_buf(), kind));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_source(&self, path: &Path) -> CookResult<Vec<u8>> {
+        fs::read(self.source_directory.join(path)).map_err(|io_error| {
+            CookError::IoError(String::from("The source asset can't be read."), path.to_path_buf(), io_error)
+        })
+    }
+
+    //Hash the asset with its metadata file, and find its dependencies.
+    fn hash_source(&self, path: &Path, kind: AssetKind) -> CookResult<SourceAsset> {
+        let data = self.read_source(path)?;
+        let mut hasher = ContentHasher::new();
+        hasher.write(data.as_slice());
+        if let Ok(metadata) = fs::read(AssetMetadata::sidecar_path(self.source_directory.join(path))) {
+            hasher.write(metadata.as_slice());
+        }
+
+        let dependencies = match kind {
+            AssetKind::Model => {
+                let model = ModelResource::from_reader(Cursor::new(data)).map_err(import_error(path))?;
+                model.dependencies(path).into_iter().map(|dependency| dependency.path).collect()
+            },
+            AssetKind::Image | AssetKind::Sound | AssetKind::Buffer | AssetKind::Texture => Vec::new(),
+        };
+        Ok(SourceAsset {
+            kind,
+            hash: hasher.finish(),
+            dependencies,
+        })
+    }
+
+    //Cook the asset in memory. Return the path of the cooked file, relative to the cooked directory, its kind and its content.
+    fn cook_asset(&self, path: &Path, kind: AssetKind) -> CookResult<(PathBuf, AssetKind, Vec<u8>)> {
+        let data = self.read_source(path)?;
+        let write_error = |io_error: io::Error| {
+            CookError::IoError(String::from("The cooked asset can't be written."), path.to_path_buf(), io_error)
+        };
+
+        match kind {
+            AssetKind::Image => {
+                let metadata = AssetMetadata::from_sidecar(self.source_directory.join(path)).map_err(import_error(path))?;
+                let image = ImageResource::from_reader(&mut Cursor::new(data), ColorFormat::RGBA)
+                    .and_then(|image| self.post_process(&self.image_post_processors, path, image, &metadata))
+                    .map_err(import_error(path))?;
+                let mut cooked = Vec::new();
+                match texture_compression::compression_settings(&metadata).map_err(import_error(path))? {
+                    Some((format, container)) => {
+                        texture_compression::compress(&image, format)
+                            .and_then(|texture| container.write(&texture, &mut cooked))
+                            .map_err(import_error(path))?;
+                        Ok((with_extension_added(path, container.extension()), AssetKind::Texture, cooked))
+                    },
+                    None => {
+                        cooked_formats::write_image(&image, &mut cooked).map_err(write_error)?;
+                        Ok((with_extension_added(path, COOKED_IMAGE_EXTENSION), kind, cooked))
+                    },
+                }
+            },
+            AssetKind::Sound => {
+                let metadata = AssetMetadata::from_sidecar(self.source_directory.join(path)).map_err(import_error(path))?;
+                let sound = SoundResource::from_reader(Cursor::new(data))
+                    .and_then(|mut sound| sound.decode().map(|_| sound))
+                    .and_then(|sound| self.post_process(&self.sound_post_processors, path, sound, &metadata))
+                    .map_err(import_error(path))?;
+                let decoded = sound.decoded().ok_or_else(|| {
+                    import_error(path)(ResourceError::PostProcessingError(String::from("The sound has no decoded samples.")))
+                })?;
+                let mut cooked = Vec::new();
+                cooked_formats::write_sound(decoded, &mut cooked).map_err(write_error)?;
+                Ok((with_extension_added(path, COOKED_SOUND_EXTENSION), kind, cooked))
+            },
+            AssetKind::Model | AssetKind::Buffer | AssetKind::Texture => Ok((path.to_path_buf(), kind, data)),
+        }
+    }
+
+    fn post_process<T>(&self, processors: &PostProcessorChain<T>, path: &Path, mut resource: T, metadata: &AssetMetadata) -> ResourceResult<T> {
+        processors.run(path, &mut resource, metadata)?;
+        Ok(resource)
+    }
+
+    fn write_output(&self, output: &Path, data: &[u8]) -> io::Result<()> {
+        let path = self.output_directory.join(output);
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, data)
+    }
+
+    fn remove_cooked_file(&self, output: &Path) {
+        match fs::remove_file(self.output_directory.join(output)) {
+            Err(ref io_error) if io_error.kind() != ErrorKind::NotFound => {
+                warn!("The cooked file {} can't be deleted: {}", output.display(), io_error);
+            },
+            _ => {},
+        }
+    }
+
+    //The previous manifest of the cooked directory. A manifest which can't be read is ignored: every asset is cooked again.
+    fn previous_manifest(&self) -> CookManifest {
+        let path = self.manifest_path();
+        if !path.exists() {
+            return CookManifest::new();
+        }
+        CookManifest::from_path(path.as_path()).unwrap_or_else(|cook_error| {
+            warn!("The manifest {} can't be read, every asset is cooked again: {}", path.display(), cook_error);
+            CookManifest::new()
+        })
+    }
+
+    //Cook the assets of the source directory which changed since the previous cooking, and write the manifest.
+    //An asset which can't be cooked is reported, and left out of the manifest with its previous cooked file.
+    //The other assets are cooked.
+    pub fn cook(&self) -> CookResult<CookReport> {
+        debug!("Cooking the assets of {} in {}.", self.source_directory.display(), self.output_directory.display());
+        let previous = self.previous_manifest();
+        let mut manifest = CookManifest::new();
+        let mut report = CookReport::default();
+
+        let mut sources = BTreeMap::new();
+        for (path, kind) in self.source_assets()? {
+            match self.hash_source(path.as_path(), kind) {
+                Ok(source) => {
+                    sources.insert(path, source);
+                },
+                Err(cook_error) => report.failed.push((path, cook_error)),
+            }
+        }
+
+        for (path, source) in sources.iter() {
+            //A model is cooked again when one of its dependencies changes.
+            let mut hasher = ContentHasher::new();
+            hasher.write_hash(source.hash);
+            let mut missing = None;
+            for dependency in source.dependencies.iter() {
+                match sources.get(dependency) {
+                    Some(dependency_source) => {
+                        hasher.write_hash(dependency_source.hash);
+                    },
+                    None => missing = Some(dependency),
+                }
+            }
+            if let Some(dependency) = missing {
+                error!("The dependency {} of the asset {} can't be found.", dependency.display(), path.display());
+                let io_error = io::Error::new(ErrorKind::NotFound, format!("{} is missing", dependency.display()));
+                report.failed.push((path.clone(), CookError::IoError(String::from("A dependency of the asset can't be found."), dependency.clone(), io_error)));
+                continue;
+            }
+            let hash = hasher.finish();
+
+            if let Some(entry) = previous.entry(path) {
+                let unchanged = entry.hash == hash && entry.kind.source_kind() == source.kind && entry.importer_version == entry.kind.importer_version();
+                if unchanged && !self.force && self.output_directory.join(entry.output.as_path()).exists() {
+                    trace!("The asset {} is unchanged.", path.display());
+                    manifest.insert(path.as_path(), entry.clone());
+                    report.skipped.push(path.clone());
+                    continue;
+                }
+            }
+
+            debug!("Cooking the asset {}.", path.display());
+            let cooked = self.cook_asset(path.as_path(), source.kind).and_then(|(output, kind, data)| {
+                self.write_output(output.as_path(), data.as_slice()).map(|_| (output, kind)).map_err(|io_error| {
+                    CookError::IoError(String::from("The cooked asset can't be written."), path.clone(), io_error)
+                })
+            });
+            match cooked {
+                Ok((output, kind)) => {
+                    manifest.insert(path.as_path(), ManifestEntry {
+                        output,
+                        kind,
+                        hash,
+                        importer_version: kind.importer_version(),
+                        dependencies: source.dependencies.clone(),
+                    });
+                    report.cooked.push(path.clone());
+                },
+                Err(cook_error) => {
+                    error!("The asset {} can't be cooked: {}", path.display(), cook_error);
+                    report.failed.push((path.clone(), cook_error));
+                },
+            }
+        }
+
+        //The cooked files of the removed assets are deleted.
+        for source in previous.sources() {
+            if self.source_directory.join(source).exists() {
+                continue;
+            }
+            if let Some(output) = previous.cooked_path(source) {
+                debug!("The asset {} has been removed, deleting its cooked file.", source.display());
+                self.remove_cooked_file(output);
+            }
+            report.removed.push(source.to_path_buf());
+        }
+
+        //The stale cooked files of the assets which can't be cooked anymore are deleted.
+        for (source, _) in report.failed.iter() {
+            if let Some(output) = previous.cooked_path(source) {
+                debug!("The asset {} can't be cooked anymore, deleting its previous cooked file.", source.display());
+                self.remove_cooked_file(output);
+            }
+        }
+
+        fs::create_dir_all(self.output_directory.as_path()).map_err(|io_error| {
+            CookError::IoError(String::from("The cooked directory can't be created."), self.output_directory.clone(), io_error)
+        })?;
+        manifest.write(self.manifest_path())?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod asset_cooker_test {
+    use super::*;
+    use std::env;
+    use post_processing::image_processors::GenerateMipmaps;
+    use resources::texture_resource::{TextureFormat, TextureResource};
+
+    #[test]
+    fn cook_assets() {
+        let directory = env::temp_dir().join("maskerad_asset_cooker_test");
+        let _ = fs::remove_dir_all(directory.as_path());
+        let source_directory = directory.join("source");
+        let output_directory = directory.join("cooked");
+        fs::create_dir_all(source_directory.join("textures")).unwrap();
+        fs::write(source_directory.join("textures/wood.tga"), &include_bytes!("../../../test_resources/images/Untitled.tga")[..]).unwrap();
+        fs::write(source_directory.join("textures/wood.tga.meta"), "[generate_mipmaps]\nmax_levels = 2").unwrap();
+        fs::write(source_directory.join("music.ogg"), &include_bytes!("../../../test_resources/ogg/untitled.ogg")[..]).unwrap();
+        fs::write(source_directory.join("notes.txt"), "not an asset").unwrap();
+
+        let mut cooker = AssetCooker::new(source_directory.as_path(), output_directory.as_path());
+        cooker.image_post_processors_mut().add(GenerateMipmaps);
+        let report = cooker.cook().unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.cooked, vec![PathBuf::from("music.ogg"), Path::new("textures").join("wood.tga")]);
+
+        let manifest = CookManifest::from_path(cooker.manifest_path()).unwrap();
+        let cooked_image = manifest.cooked_path(Path::new("textures").join("wood.tga")).unwrap();
+        let image = cooked_formats::read_image(&mut fs::File::open(output_directory.join(cooked_image)).unwrap()).unwrap();
+        assert_eq!(image.color_format(), ColorFormat::RGBA);
+        assert_eq!(image.mip_level_count(), 2);
+        let cooked_sound = manifest.cooked_path("music.ogg").unwrap();
+        assert!(cooked_formats::read_sound(&mut fs::File::open(output_directory.join(cooked_sound)).unwrap()).is_ok());
+
+        //The unchanged assets are skipped, the modified ones are cooked again.
+        assert_eq!(cooker.cook().unwrap().skipped.len(), 2);
+        fs::write(source_directory.join("textures/wood.tga.meta"), "[generate_mipmaps]\nmax_levels = 3").unwrap();
+        let report = cooker.cook().unwrap();
+        assert_eq!(report.cooked, vec![Path::new("textures").join("wood.tga")]);
+        assert_eq!(cooker.set_force(true).cook().unwrap().cooked.len(), 2);
+        cooker.set_force(false);
+
+        //The removed assets are removed from the cooked directory, the broken ones are reported.
+        fs::remove_file(source_directory.join("music.ogg")).unwrap();
+        fs::write(source_directory.join("broken.tga"), [0u8, 1, 2]).unwrap();
+        let report = cooker.cook().unwrap();
+        assert_eq!(report.removed, vec![PathBuf::from("music.ogg")]);
+        assert!(!output_directory.join(cooked_sound).exists());
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].1.path(), Some(Path::new("broken.tga")));
+        let manifest = CookManifest::from_path(cooker.manifest_path()).unwrap();
+        assert_eq!(manifest.sources(), vec![Path::new("textures").join("wood.tga")]);
+
+        //An asset which can't be cooked anymore loses its cooked file.
+        fs::write(source_directory.join("textures/wood.tga"), [0u8, 1, 2]).unwrap();
+        let report = cooker.cook().unwrap();
+        assert_eq!(report.failed.len(), 2);
+        assert!(!output_directory.join(cooked_image).exists());
+        assert!(CookManifest::from_path(cooker.manifest_path()).unwrap().sources().is_empty());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn cook_compressed_textures() {
+        let directory = env::temp_dir().join("maskerad_asset_cooker_texture_test");
+        let _ = fs::remove_dir_all(directory.as_path());
+        let source_directory = directory.join("source");
+        let output_directory = directory.join("cooked");
+        fs::create_dir_all(source_directory.as_path()).unwrap();
+        let image = &include_bytes!("../../../test_resources/images/Untitled.tga")[..];
+        for name in ["wood.tga", "sky.tga", "broken.tga"].iter() {
+            fs::write(source_directory.join(name), image).unwrap();
+        }
+        fs::write(source_directory.join("wood.tga.meta"), "[texture_compression]\nformat = \"bc3\"\nsrgb = true").unwrap();
+        fs::write(source_directory.join("sky.tga.meta"), "[texture_compression]\nformat = \"bc1a\"\ncontainer = \"dds\"").unwrap();
+        fs::write(source_directory.join("broken.tga.meta"), "[texture_compression]\nformat = \"bc7\"").unwrap();
+
+        let mut cooker = AssetCooker::new(source_directory.as_path(), output_directory.as_path());
+        cooker.image_post_processors_mut().add(GenerateMipmaps);
+        let report = cooker.cook().unwrap();
+        assert_eq!(report.cooked, vec![PathBuf::from("sky.tga"), PathBuf::from("wood.tga")]);
+        assert_eq!(report.failed[0].0, PathBuf::from("broken.tga"));
+
+        //The compressed images are cooked in the container of their metadata, with their mip levels.
+        let manifest = CookManifest::from_path(cooker.manifest_path()).unwrap();
+        let source = ImageResource::from_reader(&mut Cursor::new(image), ColorFormat::RGBA).unwrap();
+        for (name, output, format) in [("wood.tga", "wood.tga.ktx2", TextureFormat::Bc3Srgb), ("sky.tga", "sky.tga.dds", TextureFormat::Bc1RgbaUnorm)].iter() {
+            assert_eq!(manifest.cooked_path(name), Some(Path::new(output)));
+            assert_eq!(manifest.entry(name).map(|entry| entry.kind), Some(AssetKind::Texture));
+            let texture = TextureResource::from_reader(&mut fs::File::open(output_directory.join(output)).unwrap()).unwrap();
+            assert_eq!(texture.format(), *format);
+            assert_eq!((texture.width() as usize, texture.height() as usize), (source.width(), source.height()));
+            assert!(texture.mip_level_count() > 1);
+        }
+        assert_eq!(cooker.cook().unwrap().skipped, vec![PathBuf::from("sky.tga"), PathBuf::from("wood.tga")]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn cook_in_source_directory() {
+        let directory = env::temp_dir().join("maskerad_asset_cooker_inside_test");
+        let _ = fs::remove_dir_all(directory.as_path());
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        fs::write(directory.join("data.bin"), [1u8, 2, 3]).unwrap();
+
+        //The cooked directory, inside the source directory, is found even with a path which isn't normalized.
+        let cooker = AssetCooker::new(directory.as_path(), directory.join("nested").join("..").join("cooked"));
+        assert_eq!(cooker.cook().unwrap().cooked, vec![PathBuf::from("data.bin")]);
+        assert!(directory.join("cooked").join("data.bin").exists());
+        assert_eq!(cooker.source_assets().unwrap(), vec![(PathBuf::from("data.bin"), AssetKind::Buffer)]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+
+//FNV-1a, 64 bits. The hashes are stored in the manifests of the cooked assets, they must be the same on every
+//platform and with every version of the compiler, which the hasher of the standard library doesn't guarantee.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+//The hash of the content of an asset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ContentHash(u64);
+
+impl ContentHash {
+    pub fn of(bytes: &[u8]) -> Self {
+        ContentHasher::new().write(bytes).finish()
+    }
+
+    //16 hexadecimal digits, as written in the manifests.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 16 {
+            return None;
+        }
+        u64::from_str_radix(hex, 16).ok().map(ContentHash)
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+//Hash several pieces of content together: an asset, its metadata file, the hashes of its dependencies...
+#[derive(Debug, Copy, Clone)]
+pub struct ContentHasher(u64);
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        ContentHasher(FNV_OFFSET_BASIS)
+    }
+}
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> &mut Self {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    pub fn write_u32(&mut self, value: u32) -> &mut Self {
+        self.write(&value.to_le_bytes())
+    }
+
+    pub fn write_hash(&mut self, hash: ContentHash) -> &mut Self {
+        self.write(&hash.0.to_le_bytes())
+    }
+
+    pub fn finish(&self) -> ContentHash {
+        ContentHash(self.0)
+    }
+}
+
+#[cfg(test)]
+mod content_hash_test {
+    use super::*;
+
+    #[test]
+    fn content_hash() {
+        //The reference values of FNV-1a 64.
+        assert_eq!(ContentHash::of(b"").to_hex(), "cbf29ce484222325");
+        assert_eq!(ContentHash::of(b"a").to_hex(), "af63dc4c8601ec8c");
+        assert_eq!(ContentHash::of(b"foobar").to_hex(), "85944171f73967e8");
+
+        let hash = ContentHash::of(b"texture");
+        assert_eq!(ContentHash::from_hex(hash.to_hex().as_str()), Some(hash));
+        assert_eq!(ContentHash::from_hex("abc"), None);
+        assert_eq!(ContentHash::from_hex("zzzzzzzzzzzzzzzz"), None);
+
+        let combined = ContentHasher::new().write(b"model").write_hash(hash).write_u32(1).finish();
+        assert_ne!(combined, ContentHasher::new().write(b"model").write_hash(hash).write_u32(2).finish());
+        assert_eq!(ContentHasher::new().write(b"foo").write(b"bar").finish(), ContentHash::of(b"foobar"));
+    }
+}
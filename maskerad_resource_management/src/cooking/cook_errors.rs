@@ -0,0 +1,93 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
+use toml::de::Error as TomlError;
+use resources::resource_errors::ResourceError;
+use maskerad_core::maskerad_error::{MaskeradError, ErrorKind};
+
+#[derive(Debug)]
+pub enum CookError {
+    //A file which can't be read or written, with its path.
+    IoError(String, PathBuf, IOError),
+    //A source asset which can't be imported, with its path.
+    ImportError(String, PathBuf, ResourceError),
+    //A manifest which isn't valid TOML.
+    ManifestSyntaxError(String, TomlError),
+    //A manifest with a missing or invalid entry.
+    ManifestError(String),
+}
+
+impl CookError {
+    //The path of the file which couldn't be cooked.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            &CookError::IoError(_, ref path, _) |
+            &CookError::ImportError(_, ref path, _) => {
+                Some(path.as_path())
+            },
+            _ => {
+                None
+            },
+        }
+    }
+}
+
+impl fmt::Display for CookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &CookError::IoError(ref description, ref path, _) => {
+                write!(f, "I/O error: {} ({})", description, path.display())
+            },
+            &CookError::ImportError(ref description, ref path, _) => {
+                write!(f, "Import error: {} ({})", description, path.display())
+            },
+            &CookError::ManifestSyntaxError(ref description, _) => {
+                write!(f, "Manifest syntax error: {}", description)
+            },
+            &CookError::ManifestError(ref description) => {
+                write!(f, "Manifest error: {}", description)
+            },
+        }
+    }
+}
+
+impl Error for CookError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            &CookError::IoError(_, _, ref io_error) => {
+                Some(io_error)
+            },
+            &CookError::ImportError(_, _, ref resource_error) => {
+                Some(resource_error)
+            },
+            &CookError::ManifestSyntaxError(_, ref toml_error) => {
+                Some(toml_error)
+            },
+            &CookError::ManifestError(_) => {
+                None
+            },
+        }
+    }
+}
+
+pub type CookResult<T> = Result<T, CookError>;
+
+impl From<TomlError> for CookError {
+    fn from(error: TomlError) -> Self {
+        CookError::ManifestSyntaxError(String::from("Error while parsing a manifest."), error)
+    }
+}
+
+impl From<CookError> for MaskeradError {
+    fn from(error: CookError) -> Self {
+        MaskeradError::new(ErrorKind::Resource, error)
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use toml;
+use toml::value::{Table, Value};
+
+use cooking::content_hash::ContentHash;
+use cooking::cook_errors::{CookError, CookResult};
+use cooking::cooked_formats::{IMAGE_IMPORTER_VERSION, MODEL_IMPORTER_VERSION, SOUND_IMPORTER_VERSION, BUFFER_IMPORTER_VERSION, TEXTURE_IMPORTER_VERSION};
+
+/*
+COOK MANIFEST.
+
+The manifest of a cooked directory lists the cooked assets, by the path of their source asset:
+
+[assets."textures/wood.tga"]
+output = "textures/wood.tga.mkimage"
+kind = "image"
+hash = "c3a1f0e2d4b59687"
+importer_version = 1
+dependencies = []
+
+The hash covers the source asset, its metadata file and, for a model, the hashes of its dependencies.
+An asset with the same hash and importer version as in the manifest isn't cooked again.
+
+The kind is the kind of the cooked file: an image compressed in a KTX2 or DDS file has the "texture" kind,
+and is loaded as a texture instead of an image.
+
+The paths are relative to the source directory (source) and to the cooked directory (output), with '/' separators.
+*/
+
+//The name of the manifest in the cooked directory.
+pub const MANIFEST_FILE_NAME: &str = "manifest.toml";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AssetKind {
+    Image,
+    Model,
+    Sound,
+    Buffer,
+    //A compressed image, cooked in a KTX2 or DDS file.
+    Texture,
+}
+
+impl AssetKind {
+    //The kind of the source assets with the extension, in lower case. None for the files which aren't cooked.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "tga" | "png" | "bmp" | "jpg" | "jpeg" => Some(AssetKind::Image),
+            "gltf" | "glb" => Some(AssetKind::Model),
+            "ogg" => Some(AssetKind::Sound),
+            "bin" => Some(AssetKind::Buffer),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "image" => Some(AssetKind::Image),
+            "model" => Some(AssetKind::Model),
+            "sound" => Some(AssetKind::Sound),
+            "buffer" => Some(AssetKind::Buffer),
+            "texture" => Some(AssetKind::Texture),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AssetKind::Image => "image",
+            AssetKind::Model => "model",
+            AssetKind::Sound => "sound",
+            AssetKind::Buffer => "buffer",
+            AssetKind::Texture => "texture",
+        }
+    }
+
+    pub fn importer_version(&self) -> u32 {
+        match *self {
+            AssetKind::Image => IMAGE_IMPORTER_VERSION,
+            AssetKind::Model => MODEL_IMPORTER_VERSION,
+            AssetKind::Sound => SOUND_IMPORTER_VERSION,
+            AssetKind::Buffer => BUFFER_IMPORTER_VERSION,
+            AssetKind::Texture => TEXTURE_IMPORTER_VERSION,
+        }
+    }
+
+    //The kind of the source asset of a cooked file of this kind.
+    pub fn source_kind(&self) -> Self {
+        match *self {
+            AssetKind::Texture => AssetKind::Image,
+            kind => kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    //The path of the cooked file, relative to the cooked directory.
+    pub output: PathBuf,
+    pub kind: AssetKind,
+    pub hash: ContentHash,
+    pub importer_version: u32,
+    //The source assets the asset depends on.
+    pub dependencies: Vec<PathBuf>,
+}
+
+//A relative path, with '/' separators on every platform.
+fn portable_path(path: &Path) -> String {
+    let components: Vec<String> = path.components().filter_map(|component| match component {
+        Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+        Component::ParentDir => Some(String::from("..")),
+        _ => None,
+    }).collect();
+    components.join("/")
+}
+
+fn manifest_error(source: &str, description: &str) -> CookError {
+    CookError::ManifestError(format!("The entry of {} {}.", source, description))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CookManifest {
+    entries: BTreeMap<PathBuf, ManifestEntry>,
+}
+
+impl CookManifest {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entry<P: AsRef<Path>>(&self, source: P) -> Option<&ManifestEntry> {
+        self.entries.get(source.as_ref())
+    }
+
+    pub fn insert<P: Into<PathBuf>>(&mut self, source: P, entry: ManifestEntry) -> Option<ManifestEntry> {
+        self.entries.insert(source.into(), entry)
+    }
+
+    pub fn remove<P: AsRef<Path>>(&mut self, source: P) -> Option<ManifestEntry> {
+        self.entries.remove(source.as_ref())
+    }
+
+    //The paths of the source assets, sorted.
+    pub fn sources(&self) -> Vec<&Path> {
+        self.entries.keys().map(|source| source.as_path()).collect()
+    }
+
+    //The path of the cooked file of a source asset, relative to the cooked directory.
+    pub fn cooked_path<P: AsRef<Path>>(&self, source: P) -> Option<&Path> {
+        self.entry(source).map(|entry| entry.output.as_path())
+    }
+
+    pub fn from_toml(toml: &str) -> CookResult<Self> {
+        let mut table: Table = toml::from_str(toml)?;
+        let mut manifest = CookManifest::new();
+        let assets = match table.remove("assets") {
+            Some(Value::Table(assets)) => assets,
+            Some(_) => return Err(CookError::ManifestError(String::from("The assets of the manifest are not a table."))),
+            None => return Ok(manifest),
+        };
+
+        for (source, entry) in assets {
+            let entry = match entry {
+                Value::Table(entry) => entry,
+                _ => return Err(manifest_error(source.as_str(), "is not a table")),
+            };
+            let string = |key: &str| {
+                entry.get(key).and_then(|value| value.as_str()).ok_or_else(|| manifest_error(source.as_str(), &format!("has no {}", key)))
+            };
+            let output = PathBuf::from(string("output")?);
+            let kind = AssetKind::from_name(string("kind")?).ok_or_else(|| manifest_error(source.as_str(), "has an unknown kind"))?;
+            let hash = ContentHash::from_hex(string("hash")?).ok_or_else(|| manifest_error(source.as_str(), "has an invalid hash"))?;
+            let importer_version = entry.get("importer_version").and_then(|value| value.as_integer()).ok_or_else(|| {
+                manifest_error(source.as_str(), "has no importer_version")
+            })? as u32;
+            let dependencies = match entry.get("dependencies") {
+                Some(Value::Array(dependencies)) => dependencies.iter().filter_map(|dependency| dependency.as_str()).map(PathBuf::from).collect(),
+                _ => Vec::new(),
+            };
+
+            manifest.insert(source.as_str(), ManifestEntry {
+                output,
+                kind,
+                hash,
+                importer_version,
+                dependencies,
+            });
+        }
+        Ok(manifest)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut assets = Table::new();
+        for (source, entry) in self.entries.iter() {
+            let mut table = Table::new();
+            table.insert(String::from("output"), Value::String(portable_path(entry.output.as_path())));
+            table.insert(String::from("kind"), Value::String(String::from(entry.kind.name())));
+            table.insert(String::from("hash"), Value::String(entry.hash.to_hex()));
+            table.insert(String::from("importer_version"), Value::Integer(i64::from(entry.importer_version)));
+            table.insert(String::from("dependencies"), Value::Array(entry.dependencies.iter().map(|dependency| {
+                Value::String(portable_path(dependency.as_path()))
+            }).collect()));
+            assets.insert(portable_path(source.as_path()), Value::Table(table));
+        }
+        let mut manifest = Table::new();
+        manifest.insert(String::from("assets"), Value::Table(assets));
+        Value::Table(manifest).to_string()
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> CookResult<Self> {
+        let toml = fs::read_to_string(path.as_ref()).map_err(|io_error| {
+            CookError::IoError(String::from("The manifest can't be read."), path.as_ref().to_path_buf(), io_error)
+        })?;
+        CookManifest::from_toml(toml.as_str())
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> CookResult<()> {
+        fs::write(path.as_ref(), self.to_toml()).map_err(|io_error| {
+            CookError::IoError(String::from("The manifest can't be written."), path.as_ref().to_path_buf(), io_error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod cook_manifest_test {
+    use super::*;
+
+    #[test]
+    fn cook_manifest() {
+        let mut manifest = CookManifest::new();
+        manifest.insert("models/cube.gltf", ManifestEntry {
+            output: PathBuf::from("models/cube.gltf"),
+            kind: AssetKind::Model,
+            hash: ContentHash::of(b"cube"),
+            importer_version: MODEL_IMPORTER_VERSION,
+            dependencies: vec![PathBuf::from("models/cube.bin"), PathBuf::from("textures/wood.tga")],
+        });
+        manifest.insert(Path::new("textures").join("wood.tga"), ManifestEntry {
+            output: PathBuf::from("textures/wood.tga.mkimage"),
+            kind: AssetKind::Image,
+            hash: ContentHash::of(b"wood"),
+            importer_version: IMAGE_IMPORTER_VERSION,
+            dependencies: Vec::new(),
+        });
+        manifest.insert("textures/sky.tga", ManifestEntry {
+            output: PathBuf::from("textures/sky.tga.dds"),
+            kind: AssetKind::Texture,
+            hash: ContentHash::of(b"sky"),
+            importer_version: TEXTURE_IMPORTER_VERSION,
+            dependencies: Vec::new(),
+        });
+
+        let toml = manifest.to_toml();
+        assert!(toml.contains("[assets.\"textures/wood.tga\"]"));
+        let read = CookManifest::from_toml(toml.as_str()).unwrap();
+        assert_eq!(read, manifest);
+        assert_eq!(read.cooked_path("textures/wood.tga"), Some(Path::new("textures/wood.tga.mkimage")));
+        assert_eq!(read.entry("textures/sky.tga").map(|entry| entry.kind), Some(AssetKind::Texture));
+        assert_eq!(read.sources(), vec![Path::new("models/cube.gltf"), Path::new("textures/sky.tga"), Path::new("textures/wood.tga")]);
+
+        assert!(CookManifest::from_toml("").unwrap().is_empty());
+        assert!(CookManifest::from_toml("assets = [").is_err());
+        match CookManifest::from_toml("[assets.\"a.tga\"]\noutput = \"a.tga.mkimage\"\nkind = \"shader\"") {
+            Err(CookError::ManifestError(_)) => {},
+            _ => panic!("An entry with an unknown kind should be refused."),
+        }
+        assert_eq!(AssetKind::from_extension("jpeg"), Some(AssetKind::Image));
+        assert_eq!(AssetKind::from_extension("meta"), None);
+        assert_eq!(AssetKind::Texture.source_kind(), AssetKind::Image);
+        assert_eq!(AssetKind::Sound.source_kind(), AssetKind::Sound);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io::{self, Read, Write, ErrorKind};
+use imagefmt::Image;
+
+use resources::image_resource::{ImageResource, ColorFormat};
+use resources::sound_resource::DecodedSound;
+use resources::resource_errors::{ResourceError, ResourceResult};
+
+/*
+COOKED FORMATS.
+
+The cooked images and sounds are decoded: loading them is a copy, without decompression nor post processing.
+All the integers are little endian.
+
+Cooked image:
+- "MKIM", the importer version (u32), the color format (u32), premultiplied (u32, 0 or 1), the number of mip levels (u32).
+- Per mip level, from the base level: the width (u32), the height (u32), the number of bytes (u32), the pixels.
+
+Cooked sound:
+- "MKSN", the importer version (u32), the sample rate (u32), the number of channels (u32).
+- Per channel: the number of samples (u32), the samples (i16).
+
+The cooked models and buffers are copies of their source: glTF is already the format of the engine, and the
+textures of a model are cooked as images, or as compressed textures (see the texture_containers module). A cooked file of another importer version is refused.
+*/
+
+//Change the version of an importer when its output changes: the assets it imported are cooked again.
+pub const IMAGE_IMPORTER_VERSION: u32 = 1;
+pub const MODEL_IMPORTER_VERSION: u32 = 1;
+pub const SOUND_IMPORTER_VERSION: u32 = 1;
+pub const BUFFER_IMPORTER_VERSION: u32 = 1;
+pub const TEXTURE_IMPORTER_VERSION: u32 = 1;
+
+//The extensions added to the paths of the cooked images and sounds.
+pub const COOKED_IMAGE_EXTENSION: &str = "mkimage";
+pub const COOKED_SOUND_EXTENSION: &str = "mksound";
+
+const COOKED_IMAGE_MAGIC: &[u8; 4] = b"MKIM";
+const COOKED_SOUND_MAGIC: &[u8; 4] = b"MKSN";
+
+//The color formats of the cooked images, by their number in the cooked files.
+const COLOR_FORMATS: [ColorFormat; 9] = [
+    ColorFormat::Y,
+    ColorFormat::YA,
+    ColorFormat::AY,
+    ColorFormat::RGB,
+    ColorFormat::RGBA,
+    ColorFormat::BGR,
+    ColorFormat::BGRA,
+    ColorFormat::ARGB,
+    ColorFormat::ABGR,
+];
+
+fn cooked_data_error(description: &str) -> ResourceError {
+    ResourceError::CookedDataError(String::from(description), io::Error::new(ErrorKind::InvalidData, description))
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> ResourceResult<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes).map_err(|io_error| {
+        ResourceError::CookedDataError(String::from("The cooked file is truncated."), io_error)
+    })?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+//Read a number of bytes written by the cooker. The reader may be shorter than announced, nothing is allocated in advance.
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> ResourceResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes).map_err(|io_error| {
+        ResourceError::CookedDataError(String::from("The cooked file can't be read."), io_error)
+    })?;
+    if bytes.len() != len {
+        return Err(cooked_data_error("The cooked file is truncated."));
+    }
+    Ok(bytes)
+}
+
+fn read_header<R: Read>(reader: &mut R, magic: &[u8; 4], importer_version: u32) -> ResourceResult<()> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header).map_err(|io_error| {
+        ResourceError::CookedDataError(String::from("The cooked file is truncated."), io_error)
+    })?;
+    if &header != magic {
+        return Err(cooked_data_error("The file is not a cooked file of this type."));
+    }
+    if read_u32(reader)? != importer_version {
+        return Err(cooked_data_error("The file has been cooked by another importer version."));
+    }
+    Ok(())
+}
+
+pub fn write_image<W: Write>(image: &ImageResource, writer: &mut W) -> io::Result<()> {
+    let color_format = COLOR_FORMATS.iter().position(|format| *format == image.color_format()).ok_or_else(|| {
+        io::Error::new(ErrorKind::InvalidInput, "The color format of the image is unknown.")
+    })?;
+
+    writer.write_all(COOKED_IMAGE_MAGIC)?;
+    write_u32(writer, IMAGE_IMPORTER_VERSION)?;
+    write_u32(writer, color_format as u32)?;
+    write_u32(writer, image.is_premultiplied() as u32)?;
+    write_u32(writer, image.mip_level_count() as u32)?;
+    for level in (0..image.mip_level_count()).filter_map(|level| image.mip_level(level)) {
+        write_u32(writer, level.w as u32)?;
+        write_u32(writer, level.h as u32)?;
+        write_u32(writer, level.buf.len() as u32)?;
+        writer.write_all(level.buf.as_slice())?;
+    }
+    Ok(())
+}
+
+pub fn read_image<R: Read>(reader: &mut R) -> ResourceResult<ImageResource> {
+    read_header(reader, COOKED_IMAGE_MAGIC, IMAGE_IMPORTER_VERSION)?;
+    let fmt = *COLOR_FORMATS.get(read_u32(reader)? as usize).ok_or_else(|| cooked_data_error("The color format of the cooked image is unknown."))?;
+    let premultiplied = read_u32(reader)? != 0;
+    let level_count = read_u32(reader)?;
+    if level_count == 0 {
+        return Err(cooked_data_error("The cooked image has no mip level."));
+    }
+
+    let mut levels = Vec::new();
+    for _ in 0..level_count {
+        let w = read_u32(reader)? as usize;
+        let h = read_u32(reader)? as usize;
+        let len = read_u32(reader)? as usize;
+        let buf = read_bytes(reader, len)?;
+        levels.push(Image {
+            w,
+            h,
+            fmt: fmt.into(),
+            buf,
+        });
+    }
+
+    let mut levels = levels.into_iter();
+    let mut image = ImageResource::from(levels.next().ok_or_else(|| cooked_data_error("The cooked image has no mip level."))?);
+    image.mipmaps_mut().extend(levels);
+    image.set_premultiplied(premultiplied);
+    Ok(image)
+}
+
+pub fn write_sound<W: Write>(sound: &DecodedSound, writer: &mut W) -> io::Result<()> {
+    writer.write_all(COOKED_SOUND_MAGIC)?;
+    write_u32(writer, SOUND_IMPORTER_VERSION)?;
+    write_u32(writer, sound.sample_rate)?;
+    write_u32(writer, sound.channels.len() as u32)?;
+    for channel in sound.channels.iter() {
+        write_u32(writer, channel.len() as u32)?;
+        for sample in channel.iter() {
+            writer.write_all(&sample.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn read_sound<R: Read>(reader: &mut R) -> ResourceResult<DecodedSound> {
+    read_header(reader, COOKED_SOUND_MAGIC, SOUND_IMPORTER_VERSION)?;
+    let sample_rate = read_u32(reader)?;
+    let channel_count = read_u32(reader)?;
+
+    let mut channels = Vec::new();
+    for _ in 0..channel_count {
+        let len = read_u32(reader)? as usize;
+        let bytes = read_bytes(reader, len * 2)?;
+        channels.push(bytes.chunks(2).map(|sample| i16::from_le_bytes([sample[0], sample[1]])).collect());
+    }
+    Ok(DecodedSound {
+        sample_rate,
+        channels,
+    })
+}
+
+#[cfg(test)]
+mod cooked_formats_test {
+    use super::*;
+    use imagefmt::ColFmt;
+
+    #[test]
+    fn cooked_image() {
+        let mut image = ImageResource::checkerboard(4, 2);
+        image.mipmaps_mut().push(Image {
+            w: 2,
+            h: 2,
+            fmt: ColFmt::RGBA,
+            buf: vec![7; 16],
+        });
+        image.set_premultiplied(true);
+
+        let mut cooked = Vec::new();
+        write_image(&image, &mut cooked).unwrap();
+        let read = read_image(&mut cooked.as_slice()).unwrap();
+        assert_eq!(read.color_format(), ColorFormat::RGBA);
+        assert!(read.is_premultiplied());
+        assert_eq!(read.mip_level_count(), 2);
+        assert_eq!(read.image().buf, image.image().buf);
+        assert_eq!(read.mip_level(1).unwrap().buf, vec![7; 16]);
+
+        //Truncated, or cooked by another version.
+        assert!(read_image(&mut &cooked[..cooked.len() - 1]).is_err());
+        cooked[4] = 0xff;
+        match read_image(&mut cooked.as_slice()) {
+            Err(ResourceError::CookedDataError(_, _)) => {},
+            _ => panic!("A cooked image of another importer version should be refused."),
+        }
+        assert!(read_sound(&mut cooked.as_slice()).is_err());
+    }
+
+    #[test]
+    fn cooked_sound() {
+        let sound = DecodedSound {
+            sample_rate: 44100,
+            channels: vec![vec![0, -1, i16::MAX], vec![i16::MIN, 2, 3]],
+        };
+        let mut cooked = Vec::new();
+        write_sound(&sound, &mut cooked).unwrap();
+        assert_eq!(read_sound(&mut cooked.as_slice()).unwrap(), sound);
+        assert!(read_sound(&mut &cooked[..20]).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use imagefmt::{ColFmt, Image};
+
+use post_processing::asset_metadata::AssetMetadata;
+use resources::image_resource::{ImageResource, ColorFormat};
+use resources::resource_errors::{ResourceError, ResourceResult};
+use resources::texture_containers::TextureContainer;
+use resources::texture_resource::{TextureFormat, TextureLayout, TextureResource};
+
+//The table of the metadata files with the texture compression of an image.
+pub const TEXTURE_COMPRESSION_METADATA: &str = "texture_compression";
+
+/*
+TEXTURE COMPRESSION.
+
+The offline BC1 and BC3 encoder of the asset tools, and their decoder.
+
+The texels are compressed by blocks of 4x4, the blocks on the edges of the image repeat its last row and column.
+A color block stores two endpoints in RGB565, and a 2 bits index per texel in the palette interpolated between
+them. The endpoints are the extremes of the texels along their principal axis.
+BC1 with alpha uses the 3 colors palette of the blocks with transparent texels (alpha < 128), its 4th color is
+transparent black. BC3 adds an alpha block: two alpha endpoints and a 3 bits index per texel.
+
+compress() encodes an image and its mip levels, decompress() decodes a texture back to an RGBA image, to check
+the compression.
+
+The asset cooker compresses the images with a texture_compression table in their metadata file, and writes them
+in a KTX2 (by default) or a DDS file:
+
+[texture_compression]
+format = "bc3"
+srgb = true
+container = "dds"
+
+The formats are "bc1" (without alpha), "bc1a" (BC1 with transparent texels) and "bc3". The DDS files have no
+BC1 format without alpha: "bc1" with the "dds" container is refused.
+*/
+
+type Texel = [u8; 4];
+
+fn texture_error(description: &str) -> ResourceError {
+    ResourceError::TextureError(String::from(description))
+}
+
+fn pack_565(color: [f32; 3]) -> u16 {
+    let quantize = |value: f32, max: f32| (value.clamp(0.0, 255.0) * max / 255.0).round() as u16;
+    quantize(color[0], 31.0) << 11 | quantize(color[1], 63.0) << 5 | quantize(color[2], 31.0)
+}
+
+fn unpack_565(color: u16) -> Texel {
+    let red = (color >> 11) as u8 & 31;
+    let green = (color >> 5) as u8 & 63;
+    let blue = color as u8 & 31;
+    [red << 3 | red >> 2, green << 2 | green >> 4, blue << 3 | blue >> 2, 255]
+}
+
+//The palette of a color block. The 3 colors palette has a transparent black 4th color.
+fn color_palette(color0: u16, color1: u16, four_colors: bool) -> [Texel; 4] {
+    let (first, second) = (unpack_565(color0), unpack_565(color1));
+    let mix = |weight0: u16, weight1: u16| {
+        let mut texel = [255; 4];
+        for channel in 0..3 {
+            texel[channel] = ((u16::from(first[channel]) * weight0 + u16::from(second[channel]) * weight1) / (weight0 + weight1)) as u8;
+        }
+        texel
+    };
+    if four_colors {
+        [first, second, mix(2, 1), mix(1, 2)]
+    } else {
+        [first, second, mix(1, 1), [0; 4]]
+    }
+}
+
+fn alpha_palette(alpha0: u8, alpha1: u8) -> [u8; 8] {
+    let mix = |weight0: u16, weight1: u16| ((u16::from(alpha0) * weight0 + u16::from(alpha1) * weight1) / (weight0 + weight1)) as u8;
+    if alpha0 > alpha1 {
+        [alpha0, alpha1, mix(6, 1), mix(5, 2), mix(4, 3), mix(3, 4), mix(2, 5), mix(1, 6)]
+    } else {
+        [alpha0, alpha1, mix(4, 1), mix(3, 2), mix(2, 3), mix(1, 4), 0, 255]
+    }
+}
+
+fn color_distance(first: &Texel, second: &Texel) -> i32 {
+    (0..3).map(|channel| {
+        let difference = i32::from(first[channel]) - i32::from(second[channel]);
+        difference * difference
+    }).sum()
+}
+
+//The extremes of the colors along their principal axis, the lowest first.
+fn color_endpoints(colors: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let count = colors.len() as f32;
+    let mut mean = [0.0; 3];
+    for color in colors {
+        for channel in 0..3 {
+            mean[channel] += color[channel] / count;
+        }
+    }
+
+    let mut covariance = [[0.0f32; 3]; 3];
+    for color in colors {
+        for row in 0..3 {
+            for column in 0..3 {
+                covariance[row][column] += (color[row] - mean[row]) * (color[column] - mean[column]);
+            }
+        }
+    }
+
+    //The principal axis, by power iteration from the channel with the largest variance.
+    let largest = (0..3).fold(0, |largest, channel| if covariance[channel][channel] > covariance[largest][largest] { channel } else { largest });
+    let mut axis = [0.0f32; 3];
+    axis[largest] = 1.0;
+    for _ in 0..8 {
+        let mut next = [0.0f32; 3];
+        for (row, value) in next.iter_mut().enumerate() {
+            *value = (0..3).map(|column| covariance[row][column] * axis[column]).sum();
+        }
+        let norm = next.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if norm < 1e-6 {
+            break;
+        }
+        axis = [next[0] / norm, next[1] / norm, next[2] / norm];
+    }
+
+    let projection = |color: &[f32; 3]| (0..3).map(|channel| (color[channel] - mean[channel]) * axis[channel]).sum::<f32>();
+    let lowest = colors.iter().map(&projection).fold(f32::MAX, f32::min);
+    let highest = colors.iter().map(&projection).fold(f32::MIN, f32::max);
+    let along = |distance: f32| [mean[0] + axis[0] * distance, mean[1] + axis[1] * distance, mean[2] + axis[2] * distance];
+    (along(lowest), along(highest))
+}
+
+//A BC1 color block. With alpha, the texels with an alpha < 128 are transparent.
+fn encode_color_block(texels: &[Texel; 16], alpha: bool) -> [u8; 8] {
+    let transparent = |texel: &Texel| alpha && texel[3] < 128;
+    let colors: Vec<[f32; 3]> = texels.iter()
+        .filter(|texel| !transparent(texel))
+        .map(|texel| [f32::from(texel[0]), f32::from(texel[1]), f32::from(texel[2])])
+        .collect();
+    if colors.is_empty() {
+        //The 3 colors mode, every texel is transparent black.
+        return [0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+    }
+
+    let (low, high) = color_endpoints(colors.as_slice());
+    let (mut color0, mut color1) = (pack_565(high), pack_565(low));
+    //The palette has 4 colors when color0 > color1, and 3 colors and transparent black otherwise.
+    let three_colors = texels.iter().any(&transparent);
+    if (three_colors && color0 > color1) || (!three_colors && color0 < color1) {
+        ::std::mem::swap(&mut color0, &mut color1);
+    }
+    let four_colors = color0 > color1;
+    let palette = color_palette(color0, color1, four_colors);
+    let colors_in_palette = if four_colors { 4 } else { 3 };
+
+    let mut indices = 0u32;
+    for (position, texel) in texels.iter().enumerate() {
+        let index = if transparent(texel) {
+            3
+        } else {
+            (0..colors_in_palette).min_by_key(|&index| color_distance(&palette[index], texel)).unwrap_or(0)
+        };
+        indices |= (index as u32) << (position * 2);
+    }
+
+    let mut block = [0; 8];
+    block[0..2].copy_from_slice(&color0.to_le_bytes());
+    block[2..4].copy_from_slice(&color1.to_le_bytes());
+    block[4..8].copy_from_slice(&indices.to_le_bytes());
+    block
+}
+
+fn decode_color_block(block: &[u8], bc1: bool) -> [Texel; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    //The color block of BC3 always has 4 colors.
+    let palette = color_palette(color0, color1, !bc1 || color0 > color1);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let mut texels = [[0; 4]; 16];
+    for (position, texel) in texels.iter_mut().enumerate() {
+        *texel = palette[(indices >> (position * 2)) as usize & 3];
+    }
+    texels
+}
+
+fn encode_alpha_block(texels: &[Texel; 16]) -> [u8; 8] {
+    let alpha0 = texels.iter().map(|texel| texel[3]).max().unwrap_or(255);
+    let alpha1 = texels.iter().map(|texel| texel[3]).min().unwrap_or(255);
+    let palette = alpha_palette(alpha0, alpha1);
+
+    let mut indices = 0u64;
+    if alpha0 != alpha1 {
+        for (position, texel) in texels.iter().enumerate() {
+            let index = (0..8).min_by_key(|&index| (i32::from(palette[index]) - i32::from(texel[3])).abs()).unwrap_or(0);
+            indices |= (index as u64) << (position * 3);
+        }
+    }
+
+    let mut block = [0; 8];
+    block[0] = alpha0;
+    block[1] = alpha1;
+    block[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    block
+}
+
+pub fn encode_bc1_block(texels: &[Texel; 16], alpha: bool) -> [u8; 8] {
+    encode_color_block(texels, alpha)
+}
+
+pub fn encode_bc3_block(texels: &[Texel; 16]) -> [u8; 16] {
+    let mut block = [0; 16];
+    block[..8].copy_from_slice(&encode_alpha_block(texels));
+    block[8..].copy_from_slice(&encode_color_block(texels, false));
+    block
+}
+
+//The RGBA texels of a BC1 block, row by row.
+pub fn decode_bc1_block(block: &[u8]) -> [Texel; 16] {
+    decode_color_block(block, true)
+}
+
+pub fn decode_bc3_block(block: &[u8]) -> [Texel; 16] {
+    let mut texels = decode_color_block(&block[8..16], false);
+    let palette = alpha_palette(block[0], block[1]);
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+    for (position, texel) in texels.iter_mut().enumerate() {
+        texel[3] = palette[(indices >> (position * 3)) as usize & 7];
+    }
+    texels
+}
+
+//Encode an RGBA image by blocks of 4x4.
+fn encode_image<F>(image: &Image<u8>, mut encode: F) -> Vec<u8> where
+    F: FnMut(&[Texel; 16], &mut Vec<u8>),
+{
+    let mut data = Vec::new();
+    for block_y in 0..image.h.div_ceil(4) {
+        for block_x in 0..image.w.div_ceil(4) {
+            let mut texels = [[0; 4]; 16];
+            for (position, texel) in texels.iter_mut().enumerate() {
+                let x = (block_x * 4 + position % 4).min(image.w - 1);
+                let y = (block_y * 4 + position / 4).min(image.h - 1);
+                let start = (y * image.w + x) * 4;
+                texel.copy_from_slice(&image.buf[start..start + 4]);
+            }
+            encode(&texels, &mut data);
+        }
+    }
+    data
+}
+
+//Compress the image and its mip levels in a BC1 or BC3 format.
+pub fn compress(image: &ImageResource, format: TextureFormat) -> ResourceResult<TextureResource> {
+    let rgba = image.convert(ColorFormat::RGBA)?;
+    let mut levels = Vec::with_capacity(rgba.mip_level_count());
+    for level in (0..rgba.mip_level_count()).filter_map(|level| rgba.mip_level(level)) {
+        let data = match format {
+            TextureFormat::Bc1RgbUnorm | TextureFormat::Bc1RgbSrgb => {
+                encode_image(level, |texels, data| data.extend_from_slice(&encode_bc1_block(texels, false)))
+            },
+            TextureFormat::Bc1RgbaUnorm | TextureFormat::Bc1RgbaSrgb => {
+                encode_image(level, |texels, data| data.extend_from_slice(&encode_bc1_block(texels, true)))
+            },
+            TextureFormat::Bc3Unorm | TextureFormat::Bc3Srgb => {
+                encode_image(level, |texels, data| data.extend_from_slice(&encode_bc3_block(texels)))
+            },
+            _ => return Err(texture_error("The texture compression only encodes the BC1 and BC3 formats.")),
+        };
+        levels.push(data);
+    }
+    TextureResource::new(TextureLayout::new(format, rgba.width() as u32, rgba.height() as u32), levels)
+}
+
+//Decode the first surface of every mip level in an RGBA image. The BC1, BC3 and 8 bits RGBA formats are decoded.
+pub fn decompress(texture: &TextureResource) -> ResourceResult<ImageResource> {
+    let format = texture.format();
+    let mut levels = Vec::with_capacity(texture.mip_level_count());
+    for level in 0..texture.mip_level_count() {
+        let (width, height, _) = texture.layout().level_dimensions(level as u32);
+        let (width, height) = (width as usize, height as usize);
+        let surface = texture.surface(level, 0, 0).ok_or_else(|| texture_error("The texture has no surface."))?;
+        let size = width * height * 4;
+        let mut buf = vec![0; size];
+        match format {
+            TextureFormat::R8G8B8A8Unorm | TextureFormat::R8G8B8A8Srgb => {
+                buf.copy_from_slice(&surface[..size]);
+            },
+            TextureFormat::B8G8R8A8Unorm | TextureFormat::B8G8R8A8Srgb => {
+                for (texel, source) in buf.chunks_mut(4).zip(surface.chunks(4)) {
+                    texel.copy_from_slice(&[source[2], source[1], source[0], source[3]]);
+                }
+            },
+            TextureFormat::Bc1RgbUnorm | TextureFormat::Bc1RgbSrgb | TextureFormat::Bc1RgbaUnorm |
+            TextureFormat::Bc1RgbaSrgb | TextureFormat::Bc3Unorm | TextureFormat::Bc3Srgb => {
+                let block_bytes = format.block_bytes();
+                let blocks_per_row = width.div_ceil(4);
+                for (block_index, block) in surface.chunks(block_bytes).enumerate() {
+                    let texels = if block_bytes == 8 { decode_bc1_block(block) } else { decode_bc3_block(block) };
+                    let (block_x, block_y) = (block_index % blocks_per_row * 4, block_index / blocks_per_row * 4);
+                    for (position, texel) in texels.iter().enumerate() {
+                        let (x, y) = (block_x + position % 4, block_y + position / 4);
+                        if x < width && y < height {
+                            let start = (y * width + x) * 4;
+                            buf[start..start + 4].copy_from_slice(texel);
+                        }
+                    }
+                }
+            },
+            _ => return Err(texture_error("The texture format can't be decoded.")),
+        }
+        levels.push(Image {
+            w: width,
+            h: height,
+            fmt: ColFmt::RGBA,
+            buf,
+        });
+    }
+
+    let mut levels = levels.into_iter();
+    let mut image = ImageResource::from(levels.next().ok_or_else(|| texture_error("The texture has no mip level."))?);
+    image.mipmaps_mut().extend(levels);
+    Ok(image)
+}
+
+//The format and the container of the compressed texture, from the metadata of the image. None if the image isn't compressed.
+pub fn compression_settings(metadata: &AssetMetadata) -> ResourceResult<Option<(TextureFormat, TextureContainer)>> {
+    let format = match metadata.string(TEXTURE_COMPRESSION_METADATA, "format") {
+        Some(format) => format,
+        None => return Ok(None),
+    };
+    let srgb = metadata.boolean(TEXTURE_COMPRESSION_METADATA, "srgb").unwrap_or(false);
+    let format = match (format, srgb) {
+        ("bc1", false) => TextureFormat::Bc1RgbUnorm,
+        ("bc1", true) => TextureFormat::Bc1RgbSrgb,
+        ("bc1a", false) => TextureFormat::Bc1RgbaUnorm,
+        ("bc1a", true) => TextureFormat::Bc1RgbaSrgb,
+        ("bc3", false) => TextureFormat::Bc3Unorm,
+        ("bc3", true) => TextureFormat::Bc3Srgb,
+        _ => return Err(texture_error("The texture compression format must be bc1, bc1a or bc3.")),
+    };
+    let container = match metadata.string(TEXTURE_COMPRESSION_METADATA, "container") {
+        Some(container) => TextureContainer::from_name(container).ok_or_else(|| {
+            texture_error("The container of a compressed texture must be ktx2 or dds.")
+        })?,
+        None => TextureContainer::Ktx2,
+    };
+    //A BC1 texture without alpha would be read back from a DDS file as BC1 with alpha.
+    let bc1_without_alpha = format == TextureFormat::Bc1RgbUnorm || format == TextureFormat::Bc1RgbSrgb;
+    if container == TextureContainer::Dds && bc1_without_alpha {
+        return Err(texture_error("The DDS files have no BC1 format without alpha: use bc1a, or the ktx2 container."));
+    }
+    Ok(Some((format, container)))
+}
+
+#[cfg(test)]
+mod texture_compression_test {
+    use super::*;
+    use post_processing::image_processors::GenerateMipmaps;
+    use post_processing::post_processor::PostProcessor;
+
+    //The largest difference between the channels of two images.
+    fn max_error(first: &ImageResource, second: &ImageResource) -> u8 {
+        first.pixels().iter().zip(second.pixels()).map(|(first, second)| (i16::from(*first) - i16::from(*second)).unsigned_abs() as u8).max().unwrap_or(0)
+    }
+
+    #[test]
+    fn block_compression() {
+        //A gradient is close to the line of its endpoints, its 16 values have 4 colors in the palette.
+        let mut gradient = [[0; 4]; 16];
+        for (position, texel) in gradient.iter_mut().enumerate() {
+            let value = position as u8 * 16;
+            *texel = [value, 255 - value, 128, 255 - value];
+        }
+        let decoded = decode_bc1_block(&encode_bc1_block(&gradient, false));
+        for (texel, original) in decoded.iter().zip(gradient.iter()) {
+            assert!(color_distance(texel, original) <= 2 * 44 * 44, "{:?} {:?}", texel, original);
+            assert_eq!(texel[3], 255);
+        }
+        let decoded = decode_bc3_block(&encode_bc3_block(&gradient));
+        for (texel, original) in decoded.iter().zip(gradient.iter()) {
+            assert!((i16::from(texel[3]) - i16::from(original[3])).abs() <= 18);
+        }
+
+        //The transparent texels of BC1 stay transparent, in a solid block.
+        let mut cutout = [[200, 100, 50, 255]; 16];
+        cutout[5][3] = 0;
+        let decoded = decode_bc1_block(&encode_bc1_block(&cutout, true));
+        assert_eq!(decoded[5], [0, 0, 0, 0]);
+        assert!(color_distance(&decoded[0], &cutout[0]) < 3 * 8 * 8);
+        assert_eq!(decode_bc1_block(&encode_bc1_block(&[[9; 4]; 16], true)), [[0; 4]; 16]);
+    }
+
+    #[test]
+    fn texture_compression() {
+        //A 6x6 image with mip levels: the blocks on the edges are partial.
+        let mut image = ImageResource::from(Image {
+            w: 6,
+            h: 6,
+            fmt: ColFmt::RGB,
+            buf: (0..36).flat_map(|texel| vec![(texel * 7) as u8, 100, 200 - (texel * 5) as u8]).collect(),
+        });
+        GenerateMipmaps.process(&mut image, &AssetMetadata::default()).unwrap();
+
+        let texture = compress(&image, TextureFormat::Bc3Srgb).unwrap();
+        assert_eq!(texture.mip_level_count(), 3);
+        assert_eq!(texture.level(0).unwrap().len(), 4 * 16);
+        let decoded = decompress(&texture).unwrap();
+        let original = image.convert(ColorFormat::RGBA).unwrap();
+        assert_eq!((decoded.width(), decoded.height(), decoded.mip_level_count()), (6, 6, 3));
+        assert!(max_error(&decoded, &original) < 24);
+
+        assert_eq!(compress(&image, TextureFormat::Bc1RgbUnorm).unwrap().level(0).unwrap().len(), 4 * 8);
+        assert!(compress(&image, TextureFormat::Bc7Unorm).is_err());
+    }
+
+    #[test]
+    fn texture_compression_settings() {
+        let mut metadata = AssetMetadata::new();
+        assert!(compression_settings(&metadata).unwrap().is_none());
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "format", "bc1a");
+        assert_eq!(compression_settings(&metadata).unwrap(), Some((TextureFormat::Bc1RgbaUnorm, TextureContainer::Ktx2)));
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "srgb", true);
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "container", "dds");
+        assert_eq!(compression_settings(&metadata).unwrap(), Some((TextureFormat::Bc1RgbaSrgb, TextureContainer::Dds)));
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "format", "bc1");
+        match compression_settings(&metadata) {
+            Err(ResourceError::TextureError(ref description)) => assert!(description.contains("no BC1 format without alpha")),
+            _ => panic!("BC1 without alpha can't be written in a DDS file."),
+        }
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "container", "ktx2");
+        assert_eq!(compression_settings(&metadata).unwrap(), Some((TextureFormat::Bc1RgbSrgb, TextureContainer::Ktx2)));
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "format", "bc1a");
+
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "container", "png");
+        assert!(compression_settings(&metadata).is_err());
+        metadata.set_parameter(TEXTURE_COMPRESSION_METADATA, "format", "bc7");
+        assert!(compression_settings(&metadata).is_err());
+    }
+}
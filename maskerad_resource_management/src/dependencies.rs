@@ -0,0 +1,230 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/*
+RESOURCE DEPENDENCIES.
+
+A composite resource references other resource files: a glTF model has external buffers (vertices, indices,
+skins, animations) and images (the textures of its materials).
+
+The dependencies of a resource are loaded before the resource, and are shared: a texture used by several
+models is loaded once. The dependency graph keeps the edges between the resources, by path:
+
+- A resource still referenced by a loaded resource can't be unloaded.
+- A dependency on a resource depending, directly or not, on the resource itself is a cycle, and is refused.
+
+The paths of the dependencies are relative to the directory of the resource referencing them.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DependencyKind {
+    Buffer,
+    Image,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dependency {
+    pub path: PathBuf,
+    pub kind: DependencyKind,
+}
+
+impl Dependency {
+    //The dependency of the resource at resource_path, from the uri found in the resource.
+    //None for the data embedded in the resource (data uris, GLB binary chunk).
+    pub fn from_uri(resource_path: &Path, uri: &str, kind: DependencyKind) -> Option<Self> {
+        if uri.starts_with("data:") || uri == "#bin" {
+            return None;
+        }
+        let path = match resource_path.parent() {
+            Some(directory) => directory.join(uri),
+            None => PathBuf::from(uri),
+        };
+        Some(Dependency {
+            path: normalize(path.as_path()),
+            kind,
+        })
+    }
+}
+
+//Remove the '.' and the '..' of the path, without accessing the filesystem: a dependency shared by several
+//resources must have the same path for all of them.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                if let Some(Component::Normal(_)) = normalized.components().next_back() {
+                    normalized.pop();
+                } else {
+                    normalized.push(component.as_os_str());
+                }
+            },
+            _ => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized
+}
+
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    dependencies: HashMap<PathBuf, Vec<PathBuf>>,
+    dependents: HashMap<PathBuf, HashSet<PathBuf>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //The number of resources with dependencies.
+    pub fn len(&self) -> usize {
+        self.dependencies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+
+    //Record the dependencies of a resource, replacing its previous dependencies.
+    pub fn add<I>(&mut self, resource: I, dependencies: Vec<PathBuf>) where
+        I: Into<PathBuf>,
+    {
+        let resource = resource.into();
+        self.remove(resource.as_path());
+        for dependency in dependencies.iter() {
+            self.dependents.entry(dependency.clone()).or_default().insert(resource.clone());
+        }
+        self.dependencies.insert(resource, dependencies);
+    }
+
+    //Forget the dependencies of a resource. Return them.
+    pub fn remove<I: AsRef<Path>>(&mut self, resource: I) -> Vec<PathBuf> {
+        let dependencies = self.dependencies.remove(resource.as_ref()).unwrap_or_default();
+        for dependency in dependencies.iter() {
+            let unused = match self.dependents.get_mut(dependency) {
+                Some(dependents) => {
+                    dependents.remove(resource.as_ref());
+                    dependents.is_empty()
+                },
+                None => false,
+            };
+            if unused {
+                self.dependents.remove(dependency);
+            }
+        }
+        dependencies
+    }
+
+    //The direct dependencies of the resource.
+    pub fn dependencies<I: AsRef<Path>>(&self, resource: I) -> &[PathBuf] {
+        self.dependencies.get(resource.as_ref()).map(|dependencies| dependencies.as_slice()).unwrap_or(&[])
+    }
+
+    //The resources depending directly on the resource.
+    pub fn dependents<I: AsRef<Path>>(&self, resource: I) -> Vec<&Path> {
+        match self.dependents.get(resource.as_ref()) {
+            Some(dependents) => dependents.iter().map(|dependent| dependent.as_path()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    //The resource has been added to the graph, even without dependencies.
+    pub fn contains<I: AsRef<Path>>(&self, resource: I) -> bool {
+        self.dependencies.contains_key(resource.as_ref())
+    }
+
+    pub fn is_referenced<I: AsRef<Path>>(&self, resource: I) -> bool {
+        self.dependents.contains_key(resource.as_ref())
+    }
+
+    //The resources with dependencies.
+    pub fn resources(&self) -> Vec<&Path> {
+        self.dependencies.keys().map(|resource| resource.as_path()).collect()
+    }
+
+    //The chain of dependencies from the resource to the dependency, if the resource depends on it, directly or not.
+    pub fn dependency_chain<I, J>(&self, resource: I, dependency: J) -> Option<Vec<PathBuf>> where
+        I: AsRef<Path>,
+        J: AsRef<Path>,
+    {
+        let mut visited = HashSet::new();
+        let mut chain = vec![resource.as_ref().to_path_buf()];
+        if self.search(dependency.as_ref(), &mut chain, &mut visited) {
+            Some(chain)
+        } else {
+            None
+        }
+    }
+
+    //Depth-first search, the chain is the path from the first resource to the current one.
+    fn search<'a>(&'a self, target: &Path, chain: &mut Vec<PathBuf>, visited: &mut HashSet<&'a Path>) -> bool {
+        let current = chain.last().cloned().unwrap_or_default();
+        for dependency in self.dependencies(current.as_path()) {
+            if !visited.insert(dependency.as_path()) {
+                continue;
+            }
+            chain.push(dependency.clone());
+            if dependency.as_path() == target || self.search(target, chain, visited) {
+                return true;
+            }
+            chain.pop();
+        }
+        false
+    }
+
+    pub fn clear(&mut self) {
+        self.dependencies.clear();
+        self.dependents.clear();
+    }
+}
+
+#[cfg(test)]
+mod dependencies_test {
+    use super::*;
+
+    #[test]
+    fn dependency_uris() {
+        let model = Path::new("models/cube.gltf");
+        let buffer = Dependency::from_uri(model, "cube.bin", DependencyKind::Buffer).unwrap();
+        assert_eq!(buffer.path, Path::new("models/cube.bin"));
+        assert_eq!(Dependency::from_uri(Path::new("cube.gltf"), "textures/wood.tga", DependencyKind::Image).unwrap().path, Path::new("textures/wood.tga"));
+        assert!(Dependency::from_uri(model, "data:application/octet-stream;base64,AAAA", DependencyKind::Buffer).is_none());
+        assert!(Dependency::from_uri(model, "#bin", DependencyKind::Buffer).is_none());
+        assert_eq!(Dependency::from_uri(model, "./../textures/wood.tga", DependencyKind::Image).unwrap().path, Path::new("textures/wood.tga"));
+        assert_eq!(Dependency::from_uri(model, "../../wood.tga", DependencyKind::Image).unwrap().path, Path::new("../wood.tga"));
+    }
+
+    #[test]
+    fn dependency_graph() {
+        let mut graph = DependencyGraph::new();
+        graph.add("scene", vec![PathBuf::from("model")]);
+        graph.add("model", vec![PathBuf::from("buffer"), PathBuf::from("texture")]);
+        graph.add("other_model", vec![PathBuf::from("texture")]);
+        assert_eq!(graph.len(), 3);
+        assert!(graph.contains("other_model"));
+        assert!(!graph.contains("texture"));
+        assert!(graph.is_referenced("texture"));
+        assert_eq!(graph.dependents("texture").len(), 2);
+        assert_eq!(graph.dependencies("model"), &[PathBuf::from("buffer"), PathBuf::from("texture")]);
+
+        let chain = graph.dependency_chain("scene", "texture").unwrap();
+        assert_eq!(chain, vec![PathBuf::from("scene"), PathBuf::from("model"), PathBuf::from("texture")]);
+        assert!(graph.dependency_chain("texture", "scene").is_none());
+
+        assert_eq!(graph.remove("model").len(), 2);
+        assert_eq!(graph.dependents("texture"), vec![Path::new("other_model")]);
+        assert!(!graph.is_referenced("buffer"));
+
+        graph.clear();
+        assert!(graph.is_empty());
+        assert!(!graph.is_referenced("model"));
+    }
+}
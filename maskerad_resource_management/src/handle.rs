@@ -0,0 +1,228 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
+
+/*
+RESOURCE HANDLES.
+
+The resource manager returns handles to the resources it loads, instead of references.
+
+A handle is a generational index into a registry: the index of the slot of the resource, and the generation
+of the slot. When a slot is freed, its generation is incremented: a handle to a freed resource, even if
+its slot has been reused since, doesn't find any resource.
+
+- Handle<T>: a strong handle. The resource is kept loaded as long as a strong handle to it exists.
+- WeakHandle<T>: a weak handle, which doesn't keep the resource loaded. It can be upgraded to a strong handle
+  while the resource is loaded.
+
+When the last strong handle of a resource is dropped, the handle is pushed in the release queue of its registry.
+The registry frees the released resources the next time it is modified, or when it is asked to collect them.
+*/
+
+//The index and the generation of a slot in a registry.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HandleId {
+    index: u32,
+    generation: u32,
+}
+
+impl HandleId {
+    pub fn new(index: u32, generation: u32) -> Self {
+        HandleId {
+            index,
+            generation,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for HandleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+//The ids of the resources without strong handles, waiting to be freed by their registry.
+pub type ReleaseQueue = Rc<RefCell<Vec<HandleId>>>;
+
+//Shared by the strong handles of a resource. Dropped with the last strong handle.
+#[derive(Debug)]
+pub struct HandleRef {
+    id: HandleId,
+    release_queue: Weak<RefCell<Vec<HandleId>>>,
+}
+
+impl Drop for HandleRef {
+    fn drop(&mut self) {
+        //The registry may have been dropped before the handle.
+        if let Some(queue) = self.release_queue.upgrade() {
+            trace!("The last strong handle of the resource {} has been dropped.", self.id);
+            queue.borrow_mut().push(self.id);
+        }
+    }
+}
+
+pub struct Handle<T> {
+    inner: Rc<HandleRef>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    //Only the registries create strong handles.
+    pub(crate) fn new(id: HandleId, release_queue: &ReleaseQueue) -> Self {
+        Handle {
+            inner: Rc::new(HandleRef {
+                id,
+                release_queue: Rc::downgrade(release_queue),
+            }),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> HandleId {
+        self.inner.id
+    }
+
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle {
+            id: self.inner.id,
+            inner: Rc::downgrade(&self.inner),
+            _marker: PhantomData,
+        }
+    }
+
+    //The number of strong handles to the resource.
+    pub fn strong_count(&self) -> usize {
+        Rc::strong_count(&self.inner)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle {
+            inner: self.inner.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle [{}, strong handles: {}]", self.id(), self.strong_count())
+    }
+}
+
+pub struct WeakHandle<T> {
+    id: HandleId,
+    inner: Weak<HandleRef>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> WeakHandle<T> {
+    pub fn id(&self) -> HandleId {
+        self.id
+    }
+
+    //None if the resource has no strong handle anymore.
+    pub fn upgrade(&self) -> Option<Handle<T>> {
+        self.inner.upgrade().map(|inner| {
+            Handle {
+                inner,
+                _marker: PhantomData,
+            }
+        })
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.inner.strong_count() > 0
+    }
+
+    //The number of strong handles to the resource.
+    pub fn strong_count(&self) -> usize {
+        self.inner.strong_count()
+    }
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        WeakHandle {
+            id: self.id,
+            inner: self.inner.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for WeakHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for WeakHandle<T> {}
+
+impl<T> Hash for WeakHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for WeakHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WeakHandle [{}, alive: {}]", self.id, self.is_alive())
+    }
+}
+
+#[cfg(test)]
+mod handle_test {
+    use super::*;
+
+    #[test]
+    fn last_strong_handle_releases_the_resource() {
+        let queue: ReleaseQueue = Rc::new(RefCell::new(Vec::new()));
+        let handle: Handle<u32> = Handle::new(HandleId::new(3, 1), &queue);
+        let weak = handle.downgrade();
+        let clone = handle.clone();
+        assert_eq!(handle.strong_count(), 2);
+        assert_eq!(clone, handle);
+
+        drop(handle);
+        assert!(queue.borrow().is_empty());
+        assert_eq!(weak.upgrade().map(|handle| handle.id()), Some(HandleId::new(3, 1)));
+
+        drop(clone);
+        assert_eq!(queue.borrow().as_slice(), &[HandleId::new(3, 1)]);
+        assert!(!weak.is_alive());
+        assert!(weak.upgrade().is_none());
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use resource_manager_errors::ResourceManagerResult;
+
+/*
+HOT RELOADING.
+
+When an artist re-exports a texture or a model, the resource manager can reload it while the game is running.
+
+ResourceManager::hot_reload() polls the modification time of the files of the loaded resources, and of their metadata
+files, which change their post processing. A modified resource is decoded again, and replaces the previous version behind its handles: the handles given to the game stay valid.
+A model is reloaded with its dependencies. If the new version can't be loaded (the file is being written,
+or is malformed), the previous version is kept, and the error is reported.
+
+Each reload produces a ReloadEvent, returned by hot_reload() and given to the reload listeners, with the resources
+depending on the reloaded resource: a material or a renderer may have to update the data it built from it.
+
+Polling the files has a cost: hot_reload() should be called in development builds, every second or so.
+*/
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Image,
+    Model,
+    Sound,
+    Buffer,
+    //A resource loaded by a registered resource loader, with the name of its type.
+    Custom(&'static str),
+}
+
+#[derive(Debug)]
+pub struct ReloadEvent {
+    pub path: PathBuf,
+    pub kind: ResourceKind,
+    //The resources depending on the reloaded resource.
+    pub dependents: Vec<PathBuf>,
+    //The error if the new version couldn't be loaded. The previous version is kept.
+    pub result: ResourceManagerResult<()>,
+}
+
+impl ReloadEvent {
+    pub fn is_reloaded(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+//A function called with the event of every hot reload.
+pub type ReloadListener = Box<dyn FnMut(&ReloadEvent)>;
+
+//The modification times of the watched files.
+#[derive(Debug, Default)]
+pub struct FileWatcher {
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.modified.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty()
+    }
+
+    pub fn is_watched<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.modified.contains_key(path.as_ref())
+    }
+
+    //Watch the given paths, and forget the others. Return the watched paths modified since the previous poll.
+    //The paths watched for the first time aren't reported, nor the files which can't be read (being written, deleted...).
+    pub fn poll<'a, I>(&mut self, paths: I) -> Vec<PathBuf> where
+        I: IntoIterator<Item = &'a Path>,
+    {
+        let mut modified = HashMap::new();
+        let mut changed = Vec::new();
+        for path in paths {
+            let current = modification_time(path);
+            let previous = match self.modified.get(path) {
+                Some(&previous) => previous,
+                None => current,
+            };
+            if current.is_some() && current != previous {
+                trace!("The file {} has been modified.", path.display());
+                changed.push(path.to_path_buf());
+            }
+            //A file which can't be read keeps its previous modification time.
+            modified.insert(path.to_path_buf(), current.or(previous));
+        }
+        self.modified = modified;
+        changed
+    }
+
+    pub fn clear(&mut self) {
+        self.modified.clear();
+    }
+}
+
+#[cfg(test)]
+mod hot_reload_test {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::time::Duration;
+
+    #[test]
+    fn file_watcher() {
+        let directory = env::temp_dir().join("maskerad_file_watcher_test");
+        fs::create_dir_all(directory.as_path()).unwrap();
+        let path = directory.join("texture.tga");
+        fs::write(path.as_path(), b"first version").unwrap();
+
+        let mut watcher = FileWatcher::new();
+        assert!(watcher.poll(vec![path.as_path()]).is_empty());
+        assert!(watcher.is_watched(path.as_path()));
+        assert!(watcher.poll(vec![path.as_path()]).is_empty());
+
+        let modified = modification_time(path.as_path()).unwrap() + Duration::from_secs(10);
+        File::options().write(true).open(path.as_path()).unwrap().set_modified(modified).unwrap();
+        assert_eq!(watcher.poll(vec![path.as_path()]), vec![path.clone()]);
+        assert!(watcher.poll(vec![path.as_path()]).is_empty());
+
+        //A deleted file isn't reported, and the paths not given anymore are forgotten.
+        fs::remove_file(path.as_path()).unwrap();
+        assert!(watcher.poll(vec![path.as_path()]).is_empty());
+        assert!(watcher.poll(Vec::new()).is_empty());
+        assert!(watcher.is_empty());
+    }
+}
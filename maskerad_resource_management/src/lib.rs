@@ -9,10 +9,21 @@ extern crate maskerad_core;
 extern crate lewton;
 extern crate imagefmt;
 extern crate gltf;
+extern crate toml;
+extern crate cgmath;
 #[macro_use]
 extern crate log;
 
 pub mod resources;
 pub mod resource_manager;
 pub mod resource_manager_errors;
-pub mod registries;
\ No newline at end of file
+pub mod registries;
+pub mod handle;
+pub mod async_loading;
+pub mod dependencies;
+pub mod hot_reload;
+pub mod post_processing;
+pub mod resource_loader;
+pub mod cooking;
+pub mod budgets;
+pub mod resource_groups;
\ No newline at end of file
@@ -0,0 +1,163 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use toml;
+use toml::value::{Table, Value};
+
+use resources::resource_errors::ResourceResult;
+
+/*
+ASSET METADATA.
+
+The post processing of an asset is configured by a sidecar metadata file, next to the asset: the metadata of
+textures/wood.tga are in textures/wood.tga.meta. An asset without metadata file uses the default parameters.
+
+The metadata file is a TOML file, with the names of the post processors to skip, and a table of parameters
+per post processor:
+
+skip = ["premultiply_alpha"]
+
+[generate_mipmaps]
+max_levels = 4
+*/
+
+//The extension added to the path of an asset to get the path of its metadata file.
+pub const METADATA_EXTENSION: &str = "meta";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AssetMetadata {
+    skip: Vec<String>,
+    parameters: BTreeMap<String, Table>,
+}
+
+impl AssetMetadata {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_toml(toml: &str) -> ResourceResult<Self> {
+        let mut table: Table = toml::from_str(toml)?;
+        let mut metadata = AssetMetadata::new();
+
+        if let Some(Value::Array(skip)) = table.remove("skip") {
+            metadata.skip = skip.iter().filter_map(|name| name.as_str()).map(String::from).collect();
+        }
+        for (processor, parameters) in table {
+            match parameters {
+                Value::Table(parameters) => {
+                    metadata.parameters.insert(processor, parameters);
+                },
+                _ => warn!("The metadata entry {} is not a table of post processor parameters, it is ignored.", processor),
+            }
+        }
+        Ok(metadata)
+    }
+
+    //The path of the metadata file of the asset.
+    pub fn sidecar_path<P: AsRef<Path>>(asset_path: P) -> PathBuf {
+        let mut path = asset_path.as_ref().as_os_str().to_owned();
+        path.push(".");
+        path.push(METADATA_EXTENSION);
+        PathBuf::from(path)
+    }
+
+    //Read the metadata file of the asset. The default metadata if the asset has no metadata file.
+    pub fn from_sidecar<P: AsRef<Path>>(asset_path: P) -> ResourceResult<Self> {
+        let path = AssetMetadata::sidecar_path(asset_path);
+        match fs::read_to_string(path.as_path()) {
+            Ok(toml) => {
+                debug!("Reading the asset metadata file {}.", path.display());
+                AssetMetadata::from_toml(toml.as_str())
+            },
+            Err(ref io_error) if io_error.kind() == ErrorKind::NotFound => Ok(AssetMetadata::new()),
+            Err(io_error) => {
+                warn!("The asset metadata file {} can't be read, the default metadata are used: {}", path.display(), io_error);
+                Ok(AssetMetadata::new())
+            },
+        }
+    }
+
+    pub fn skips<S: AsRef<str>>(&self, processor: S) -> bool {
+        self.skip.iter().any(|skipped| skipped == processor.as_ref())
+    }
+
+    pub fn skip_processor<S: Into<String>>(&mut self, processor: S) -> &mut Self {
+        self.skip.push(processor.into());
+        self
+    }
+
+    pub fn parameter(&self, processor: &str, key: &str) -> Option<&Value> {
+        self.parameters.get(processor).and_then(|parameters| parameters.get(key))
+    }
+
+    pub fn set_parameter<S, K, V>(&mut self, processor: S, key: K, value: V) -> &mut Self where
+        S: Into<String>,
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.parameters.entry(processor.into()).or_default().insert(key.into(), value.into());
+        self
+    }
+
+    pub fn integer(&self, processor: &str, key: &str) -> Option<i64> {
+        self.parameter(processor, key).and_then(|value| value.as_integer())
+    }
+
+    //An integer parameter is accepted as a float.
+    pub fn float(&self, processor: &str, key: &str) -> Option<f64> {
+        self.parameter(processor, key).and_then(|value| {
+            value.as_float().or_else(|| value.as_integer().map(|integer| integer as f64))
+        })
+    }
+
+    pub fn boolean(&self, processor: &str, key: &str) -> Option<bool> {
+        self.parameter(processor, key).and_then(|value| value.as_bool())
+    }
+
+    pub fn string(&self, processor: &str, key: &str) -> Option<&str> {
+        self.parameter(processor, key).and_then(|value| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod asset_metadata_test {
+    use super::*;
+
+    #[test]
+    fn parse_metadata() {
+        let metadata = AssetMetadata::from_toml(r#"
+            skip = ["premultiply_alpha"]
+
+            [generate_mipmaps]
+            max_levels = 4
+
+            [normalize_sound]
+            peak = 1
+        "#).unwrap();
+        assert!(metadata.skips("premultiply_alpha"));
+        assert!(!metadata.skips("generate_mipmaps"));
+        assert_eq!(metadata.integer("generate_mipmaps", "max_levels"), Some(4));
+        assert_eq!(metadata.float("normalize_sound", "peak"), Some(1.0));
+        assert_eq!(metadata.boolean("generate_mipmaps", "max_levels"), None);
+        assert!(AssetMetadata::from_toml("skip = [").is_err());
+
+        let mut built = AssetMetadata::new();
+        built.skip_processor("premultiply_alpha").set_parameter("generate_mipmaps", "max_levels", 4);
+        built.set_parameter("normalize_sound", "peak", 1);
+        assert_eq!(built, metadata);
+    }
+
+    #[test]
+    fn sidecar_files() {
+        assert_eq!(AssetMetadata::sidecar_path("textures/wood.tga"), Path::new("textures/wood.tga.meta"));
+        assert_eq!(AssetMetadata::from_sidecar("textures/no_metadata.tga").unwrap(), AssetMetadata::new());
+    }
+}
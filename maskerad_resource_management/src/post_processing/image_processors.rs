@@ -0,0 +1,171 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use imagefmt::Image;
+
+use post_processing::asset_metadata::AssetMetadata;
+use post_processing::post_processor::PostProcessor;
+use resources::image_resource::{ImageResource, ColorFormat, channel_count};
+use resources::resource_errors::{ResourceError, ResourceResult};
+
+//The index of the alpha channel in a pixel. None for the formats without alpha.
+fn alpha_index(format: ColorFormat) -> Option<usize> {
+    match format {
+        ColorFormat::YA => Some(1),
+        ColorFormat::RGBA | ColorFormat::BGRA => Some(3),
+        ColorFormat::AY | ColorFormat::ARGB | ColorFormat::ABGR => Some(0),
+        ColorFormat::Y | ColorFormat::RGB | ColorFormat::BGR | ColorFormat::Auto => None,
+    }
+}
+
+//Half the size of the image, averaging the pixels by blocks of 2x2.
+fn downsample(image: &Image<u8>, channels: usize) -> Image<u8> {
+    let width = (image.w / 2).max(1);
+    let height = (image.h / 2).max(1);
+    let mut buf = Vec::with_capacity(width * height * channels);
+    for y in 0..height {
+        for x in 0..width {
+            let xs = [(x * 2).min(image.w - 1), (x * 2 + 1).min(image.w - 1)];
+            let ys = [(y * 2).min(image.h - 1), (y * 2 + 1).min(image.h - 1)];
+            for channel in 0..channels {
+                let mut sum = 0u32;
+                for &sy in ys.iter() {
+                    for &sx in xs.iter() {
+                        sum += u32::from(image.buf[(sy * image.w + sx) * channels + channel]);
+                    }
+                }
+                buf.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+
+    Image {
+        w: width,
+        h: height,
+        fmt: image.fmt,
+        buf,
+    }
+}
+
+//Generate the mip levels of the image, down to 1x1, with a box filter.
+//Parameters: max_levels, the maximum number of levels, the base level included.
+pub struct GenerateMipmaps;
+
+impl PostProcessor<ImageResource> for GenerateMipmaps {
+    fn name(&self) -> &str {
+        "generate_mipmaps"
+    }
+
+    fn process(&self, resource: &mut ImageResource, metadata: &AssetMetadata) -> ResourceResult<()> {
+        let channels = channel_count(resource.color_format()).ok_or_else(|| {
+            ResourceError::PostProcessingError(String::from("The color format of the image is unknown."))
+        })?;
+        let max_levels = metadata.integer(self.name(), "max_levels").map(|levels| levels.max(1) as usize).unwrap_or(usize::MAX);
+
+        let mut mipmaps = Vec::new();
+        let mut previous = resource.image().clone();
+        while (previous.w > 1 || previous.h > 1) && mipmaps.len() + 1 < max_levels {
+            let level = downsample(&previous, channels);
+            mipmaps.push(level.clone());
+            previous = level;
+        }
+        *resource.mipmaps_mut() = mipmaps;
+        Ok(())
+    }
+}
+
+//Multiply the color channels by the alpha channel, for the blending of premultiplied colors.
+//The images without alpha channel, or already premultiplied, are unchanged.
+pub struct PremultiplyAlpha;
+
+impl PostProcessor<ImageResource> for PremultiplyAlpha {
+    fn name(&self) -> &str {
+        "premultiply_alpha"
+    }
+
+    fn process(&self, resource: &mut ImageResource, _metadata: &AssetMetadata) -> ResourceResult<()> {
+        let format = resource.color_format();
+        let (channels, alpha) = match (channel_count(format), alpha_index(format)) {
+            (Some(channels), Some(alpha)) => (channels, alpha),
+            _ => return Ok(()),
+        };
+        if resource.is_premultiplied() {
+            return Ok(());
+        }
+
+        let premultiply = |pixels: &mut [u8]| {
+            for pixel in pixels.chunks_mut(channels) {
+                let alpha_value = u32::from(pixel[alpha]);
+                for (index, value) in pixel.iter_mut().enumerate() {
+                    if index != alpha {
+                        *value = ((u32::from(*value) * alpha_value + 127) / 255) as u8;
+                    }
+                }
+            }
+        };
+        premultiply(resource.pixels_mut());
+        for mipmap in resource.mipmaps_mut().iter_mut() {
+            premultiply(mipmap.buf.as_mut_slice());
+        }
+        resource.set_premultiplied(true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod image_processors_test {
+    use super::*;
+    use imagefmt::ColFmt;
+
+    fn image(w: usize, h: usize, fmt: ColFmt, buf: Vec<u8>) -> ImageResource {
+        ImageResource::from(Image {
+            w,
+            h,
+            fmt,
+            buf,
+        })
+    }
+
+    #[test]
+    fn generate_mipmaps() {
+        let mut resource = image(4, 2, ColFmt::Y, vec![0, 100, 200, 40, 0, 100, 200, 40]);
+        GenerateMipmaps.process(&mut resource, &AssetMetadata::new()).unwrap();
+        assert_eq!(resource.mip_level_count(), 3);
+        let level = resource.mip_level(1).unwrap();
+        assert_eq!((level.w, level.h), (2, 1));
+        assert_eq!(level.buf, vec![50, 120]);
+        assert_eq!(resource.mip_level(2).unwrap().buf, vec![85]);
+
+        let mut metadata = AssetMetadata::new();
+        metadata.set_parameter("generate_mipmaps", "max_levels", 2);
+        GenerateMipmaps.process(&mut resource, &metadata).unwrap();
+        assert_eq!(resource.mip_level_count(), 2);
+
+        let mut unknown = image(1, 1, ColFmt::Auto, vec![0]);
+        assert!(GenerateMipmaps.process(&mut unknown, &AssetMetadata::new()).is_err());
+    }
+
+    #[test]
+    fn premultiply_alpha() {
+        let mut resource = image(2, 1, ColFmt::RGBA, vec![255, 128, 0, 128, 10, 20, 30, 255]);
+        PremultiplyAlpha.process(&mut resource, &AssetMetadata::new()).unwrap();
+        assert_eq!(resource.image().buf, vec![128, 64, 0, 128, 10, 20, 30, 255]);
+        assert!(resource.is_premultiplied());
+
+        //Not applied twice.
+        PremultiplyAlpha.process(&mut resource, &AssetMetadata::new()).unwrap();
+        assert_eq!(resource.image().buf[0], 128);
+
+        let mut argb = image(1, 1, ColFmt::ARGB, vec![0, 255, 255, 255]);
+        PremultiplyAlpha.process(&mut argb, &AssetMetadata::new()).unwrap();
+        assert_eq!(argb.image().buf, vec![0, 0, 0, 0]);
+
+        let mut rgb = image(1, 1, ColFmt::RGB, vec![1, 2, 3]);
+        PremultiplyAlpha.process(&mut rgb, &AssetMetadata::new()).unwrap();
+        assert!(!rgb.is_premultiplied());
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::HashMap;
+use cgmath::{InnerSpace, Point3, Vector2, Vector3};
+use gltf::{Accessor, Semantic};
+use gltf::accessor::DataType;
+use gltf::json::Value;
+use gltf::mesh::Mode;
+use maskerad_core::math::bounding_volumes::Aabb;
+
+use post_processing::asset_metadata::AssetMetadata;
+use post_processing::post_processor::PostProcessor;
+use resources::model_resource::ModelResource;
+use resources::resource_errors::ResourceResult;
+
+//A point from the min or max value of a POSITION accessor.
+fn point(value: Option<Value>) -> Option<Point3<f32>> {
+    let value = value?;
+    let components = value.as_array()?;
+    match (components.first()?.as_f64(), components.get(1)?.as_f64(), components.get(2)?.as_f64()) {
+        (Some(x), Some(y), Some(z)) => Some(Point3::new(x as f32, y as f32, z as f32)),
+        _ => None,
+    }
+}
+
+//Compute the bounding box of the meshes of the model, in the space of the meshes, from the min and max values
+//of their POSITION accessors. The buffers of the model aren't needed.
+pub struct ComputeBounds;
+
+impl PostProcessor<ModelResource> for ComputeBounds {
+    fn name(&self) -> &str {
+        "compute_bounds"
+    }
+
+    fn process(&self, resource: &mut ModelResource, _metadata: &AssetMetadata) -> ResourceResult<()> {
+        let mut bounds: Option<Aabb> = None;
+        for mesh in resource.gltf().meshes() {
+            for primitive in mesh.primitives() {
+                let positions = match primitive.get(&Semantic::Positions) {
+                    Some(positions) => positions,
+                    None => continue,
+                };
+                if let (Some(min), Some(max)) = (point(positions.min()), point(positions.max())) {
+                    let primitive_bounds = Aabb::new(min, max);
+                    bounds = Some(match bounds {
+                        Some(bounds) => bounds.union(&primitive_bounds),
+                        None => primitive_bounds,
+                    });
+                }
+            }
+        }
+        resource.set_bounds(bounds);
+        Ok(())
+    }
+}
+
+//Read a component of an accessor, a normalized integer is mapped to [0, 1].
+fn read_component(data: &[u8], data_type: DataType, normalized: bool) -> Option<f64> {
+    let value = match data_type {
+        DataType::I8 => f64::from(*data.first()? as i8),
+        DataType::U8 => f64::from(*data.first()?),
+        DataType::I16 => f64::from(i16::from_le_bytes([*data.first()?, *data.get(1)?])),
+        DataType::U16 => f64::from(u16::from_le_bytes([*data.first()?, *data.get(1)?])),
+        DataType::U32 => f64::from(u32::from_le_bytes([*data.first()?, *data.get(1)?, *data.get(2)?, *data.get(3)?])),
+        DataType::F32 => return Some(f64::from(f32::from_le_bytes([*data.first()?, *data.get(1)?, *data.get(2)?, *data.get(3)?]))),
+    };
+    if normalized {
+        let max = match data_type {
+            DataType::I8 => 127.0,
+            DataType::U8 => 255.0,
+            DataType::I16 => 32767.0,
+            DataType::U16 => 65535.0,
+            _ => 4294967295.0,
+        };
+        Some((value / max).max(-1.0))
+    } else {
+        Some(value)
+    }
+}
+
+//The components of the elements of an accessor, from the buffers of the model. None for the accessors of external
+//buffers which haven't been given to the model, the sparse accessors, or the accessors out of their buffer.
+fn read_accessor(model: &ModelResource, buffers: &mut HashMap<usize, Option<Vec<u8>>>, accessor: &Accessor) -> Option<Vec<f64>> {
+    if accessor.sparse().is_some() {
+        return None;
+    }
+    let view = accessor.view();
+    let buffer_index = view.buffer().index();
+    let data = buffers.entry(buffer_index).or_insert_with(|| model.buffer_data(buffer_index)).as_ref()?;
+
+    let component_count = accessor.dimensions().multiplicity();
+    let component_size = accessor.data_type().size();
+    let stride = view.stride().unwrap_or_else(|| accessor.size());
+    let start = view.offset().checked_add(accessor.offset())?;
+    let mut components = Vec::with_capacity(accessor.count().saturating_mul(component_count).min(data.len()));
+    for element in 0..accessor.count() {
+        for component in 0..component_count {
+            let offset = element.checked_mul(stride)?.checked_add(start + component * component_size)?;
+            let bytes = data.get(offset..offset.checked_add(component_size)?)?;
+            components.push(read_component(bytes, accessor.data_type(), accessor.normalized())?);
+        }
+    }
+    Some(components)
+}
+
+//The tangents of a vertex list, by angle-weighted per-vertex accumulation: the tangent and the bitangent of each
+//triangle are given by its uv derivatives, and accumulated on its vertices weighted by the angle of the triangle
+//at the vertex. This isn't MikkTSpace: the tangents may differ from the ones of the tools baking the normal maps.
+//The tangent is orthogonalized against the normal, and w is the handedness of the bitangent.
+pub fn generate_tangents(positions: &[Vector3<f32>], normals: &[Vector3<f32>], uvs: &[Vector2<f32>], triangles: &[[usize; 3]]) -> Vec<[f32; 4]> {
+    let mut tangents = vec![Vector3::new(0.0f32, 0.0, 0.0); positions.len()];
+    let mut bitangents = vec![Vector3::new(0.0f32, 0.0, 0.0); positions.len()];
+
+    for triangle in triangles {
+        if triangle.iter().any(|&vertex| vertex >= positions.len() || vertex >= normals.len() || vertex >= uvs.len()) {
+            continue;
+        }
+        let [a, b, c] = *triangle;
+        let (edge1, edge2) = (positions[b] - positions[a], positions[c] - positions[a]);
+        let (delta1, delta2) = (uvs[b] - uvs[a], uvs[c] - uvs[a]);
+        let determinant = delta1.x * delta2.y - delta2.x * delta1.y;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge1 * delta2.y - edge2 * delta1.y) / determinant;
+        let bitangent = (edge2 * delta1.x - edge1 * delta2.x) / determinant;
+
+        for corner in 0..3 {
+            let vertex = triangle[corner];
+            let to_next = positions[triangle[(corner + 1) % 3]] - positions[vertex];
+            let to_previous = positions[triangle[(corner + 2) % 3]] - positions[vertex];
+            if to_next.magnitude2() <= 0.0 || to_previous.magnitude2() <= 0.0 {
+                continue;
+            }
+            let angle = to_next.normalize().dot(to_previous.normalize()).clamp(-1.0, 1.0).acos();
+            tangents[vertex] += tangent * angle;
+            bitangents[vertex] += bitangent * angle;
+        }
+    }
+
+    positions.iter().enumerate().map(|(vertex, _)| {
+        let normal = normals.get(vertex).cloned().unwrap_or_else(|| Vector3::new(0.0, 0.0, 1.0));
+        let mut tangent = tangents[vertex] - normal * normal.dot(tangents[vertex]);
+        if tangent.magnitude2() <= f32::EPSILON {
+            //No uv derivatives: any direction orthogonal to the normal.
+            let axis = if normal.x.abs() < 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+            tangent = axis - normal * normal.dot(axis);
+        }
+        let tangent = tangent.normalize();
+        let handedness = if normal.cross(tangent).dot(bitangents[vertex]) < 0.0 { -1.0 } else { 1.0 };
+        [tangent.x, tangent.y, tangent.z, handedness]
+    }).collect()
+}
+
+//Generate the tangents of the triangle primitives with normals and uvs, but without tangents, for normal mapping.
+//The embedded buffers (GLB binary chunk, data uris) are read from the model. The external buffers are given to the
+//model by the resource manager, when the model is loaded with its dependencies: otherwise their primitives are skipped.
+//The uv set is given by the "uv_set" parameter, 0 by default.
+pub struct GenerateTangents;
+
+impl PostProcessor<ModelResource> for GenerateTangents {
+    fn name(&self) -> &str {
+        "generate_tangents"
+    }
+
+    fn process(&self, resource: &mut ModelResource, metadata: &AssetMetadata) -> ResourceResult<()> {
+        let uv_set = metadata.integer(self.name(), "uv_set").unwrap_or(0).max(0) as u32;
+        let mut buffers = HashMap::new();
+        let mut generated = Vec::new();
+
+        for mesh in resource.gltf().meshes() {
+            for (primitive_index, primitive) in mesh.primitives().enumerate() {
+                if primitive.mode() != Mode::Triangles || primitive.get(&Semantic::Tangents).is_some() {
+                    continue;
+                }
+                let accessors = (primitive.get(&Semantic::Positions), primitive.get(&Semantic::Normals), primitive.get(&Semantic::TexCoords(uv_set)));
+                let (positions, normals, uvs) = match accessors {
+                    (Some(positions), Some(normals), Some(uvs)) => (positions, normals, uvs),
+                    _ => continue,
+                };
+                let data = (
+                    read_accessor(resource, &mut buffers, &positions),
+                    read_accessor(resource, &mut buffers, &normals),
+                    read_accessor(resource, &mut buffers, &uvs),
+                );
+                let (positions, normals, uvs) = match data {
+                    (Some(positions), Some(normals), Some(uvs)) => (positions, normals, uvs),
+                    _ => {
+                        debug!("The buffers of the primitive {} of the mesh {} can't be read, its tangents aren't generated.", primitive_index, mesh.index());
+                        continue;
+                    },
+                };
+                let positions: Vec<Vector3<f32>> = positions.chunks(3).map(|p| Vector3::new(p[0] as f32, p[1] as f32, p[2] as f32)).collect();
+                let normals: Vec<Vector3<f32>> = normals.chunks(3).map(|n| Vector3::new(n[0] as f32, n[1] as f32, n[2] as f32)).collect();
+                let uvs: Vec<Vector2<f32>> = uvs.chunks(2).map(|uv| Vector2::new(uv[0] as f32, uv[1] as f32)).collect();
+
+                let indices: Vec<usize> = match primitive.indices() {
+                    Some(indices) => match read_accessor(resource, &mut buffers, &indices) {
+                        Some(indices) => indices.into_iter().map(|index| index as usize).collect(),
+                        None => continue,
+                    },
+                    None => (0..positions.len()).collect(),
+                };
+                let triangles: Vec<[usize; 3]> = indices.chunks(3).filter(|triangle| triangle.len() == 3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+                generated.push((mesh.index(), primitive_index, generate_tangents(&positions, &normals, &uvs, &triangles)));
+            }
+        }
+
+        for (mesh, primitive, tangents) in generated {
+            resource.set_tangents(mesh, primitive, Some(tangents));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod model_processors_test {
+    use super::*;
+    use resources::model_resource::glb_data;
+
+    #[test]
+    fn compute_bounds() {
+        let mut cube = ModelResource::unit_cube().unwrap();
+        assert!(cube.bounds().is_none());
+        ComputeBounds.process(&mut cube, &AssetMetadata::new()).unwrap();
+        let bounds = cube.bounds().unwrap();
+        assert_eq!(bounds.min(), Point3::new(-0.5, -0.5, -0.5));
+        assert_eq!(bounds.max(), Point3::new(0.5, 0.5, 0.5));
+    }
+
+    //A quad in the xy plane facing +z, with u along +x and v along +y: 6 u16 indices, 4 positions, normals and uvs.
+    const QUAD_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "meshes": [ { "primitives": [ { "attributes": { "POSITION": 1, "NORMAL": 2, "TEXCOORD_0": 3 }, "indices": 0 } ] } ],
+        "buffers": [ {
+            "byteLength": 140,
+            "uri": "data:application/octet-stream;base64,AAABAAIAAAACAAMAAAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAACAPwAAgD8AAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/AAAAAAAAgD8AAIA/AAAAAAAAgD8="
+        } ],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 12 },
+            { "buffer": 0, "byteOffset": 12, "byteLength": 128 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5123, "count": 6, "type": "SCALAR" },
+            { "bufferView": 1, "byteOffset": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "byteOffset": 48, "componentType": 5126, "count": 4, "type": "VEC3" },
+            { "bufferView": 1, "byteOffset": 96, "componentType": 5126, "count": 4, "type": "VEC2" }
+        ]
+    }"#;
+
+    #[test]
+    fn generate_quad_tangents() {
+        let mut quad = ModelResource::from_reader(QUAD_GLTF.as_bytes()).unwrap();
+        GenerateTangents.process(&mut quad, &AssetMetadata::new()).unwrap();
+        let tangents = quad.tangents(0, 0).unwrap();
+        assert_eq!(tangents.len(), 4);
+        for tangent in tangents {
+            assert!((tangent[0] - 1.0).abs() < 1e-5 && tangent[1].abs() < 1e-5 && tangent[2].abs() < 1e-5, "{:?}", tangent);
+            assert_eq!(tangent[3], 1.0);
+        }
+
+        //Mirrored uvs flip the handedness.
+        let positions = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)];
+        let normals = [Vector3::new(0.0, 0.0, 1.0); 3];
+        let uvs = [Vector2::new(0.0, 1.0), Vector2::new(1.0, 1.0), Vector2::new(0.0, 0.0)];
+        let mirrored = generate_tangents(&positions, &normals, &uvs, &[[0, 1, 2]]);
+        assert_eq!(mirrored[0][3], -1.0);
+
+        //The quad data in the binary chunk of a GLB file, and in an external buffer given by the resource manager.
+        let bin = quad.buffer_data(0).unwrap();
+        let glb_json = QUAD_GLTF.replace(r#""byteLength": 140,"#, r#""byteLength": 140"#);
+        let glb_json: String = glb_json.lines().filter(|line| !line.contains("\"uri\"")).collect::<Vec<_>>().join("\n");
+        let mut glb = ModelResource::from_reader(glb_data(&glb_json, &bin).as_slice()).unwrap();
+        GenerateTangents.process(&mut glb, &AssetMetadata::new()).unwrap();
+        assert_eq!(glb.tangents(0, 0), quad.tangents(0, 0));
+
+        let external_json = glb_json.replace(r#""byteLength": 140"#, r#""byteLength": 140, "uri": "quad.bin""#);
+        let mut external = ModelResource::from_reader(external_json.as_bytes()).unwrap();
+        GenerateTangents.process(&mut external, &AssetMetadata::new()).unwrap();
+        assert!(external.tangents(0, 0).is_none());
+        let mut buffers = HashMap::new();
+        buffers.insert(0, bin);
+        external.set_external_buffers(buffers);
+        GenerateTangents.process(&mut external, &AssetMetadata::new()).unwrap();
+        assert_eq!(external.tangents(0, 0), quad.tangents(0, 0));
+
+        //The unit cube has no normals nor uvs.
+        let mut cube = ModelResource::unit_cube().unwrap();
+        GenerateTangents.process(&mut cube, &AssetMetadata::new()).unwrap();
+        assert!(cube.tangents(0, 0).is_none());
+    }
+}
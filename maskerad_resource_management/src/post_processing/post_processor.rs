@@ -0,0 +1,161 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use post_processing::asset_metadata::AssetMetadata;
+use resources::resource_errors::{ResourceError, ResourceResult};
+
+/*
+POST PROCESSING.
+
+A resource can be fine-tuned after it has been decoded: the mipmaps of a texture are generated, the bounds
+of a model are computed, a sound is resampled...
+
+The resource manager has a chain of post processors per resource type. The post processors of a chain run
+in the order they have been added, after the decoding of every resource of the type (hot reloads included).
+A post processor which fails makes the loading fail.
+
+The post processors are configured per asset by the metadata file of the asset (see the asset_metadata module),
+in which a post processor is identified by its name.
+
+The resources loaded asynchronously are post processed by the loading jobs, on the worker threads: the post processors
+are Send + Sync, and a chain is cloned into the jobs by sharing its post processors.
+*/
+
+pub trait PostProcessor<T>: Send + Sync {
+    //The name of the post processor in the metadata files.
+    fn name(&self) -> &str;
+
+    fn process(&self, resource: &mut T, metadata: &AssetMetadata) -> ResourceResult<()>;
+}
+
+pub struct PostProcessorChain<T> {
+    processors: Vec<Arc<dyn PostProcessor<T>>>,
+}
+
+impl<T> Clone for PostProcessorChain<T> {
+    fn clone(&self) -> Self {
+        PostProcessorChain {
+            processors: self.processors.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for PostProcessorChain<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PostProcessorChain {:?}", self.names())
+    }
+}
+
+impl<T> Default for PostProcessorChain<T> {
+    fn default() -> Self {
+        PostProcessorChain {
+            processors: Vec::new(),
+        }
+    }
+}
+
+impl<T> PostProcessorChain<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add<P>(&mut self, processor: P) -> &mut Self where
+        P: PostProcessor<T> + 'static,
+    {
+        debug!("Adding the post processor {} to the chain.", processor.name());
+        self.processors.push(Arc::new(processor));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.processors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.processors.iter().map(|processor| processor.name()).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.processors.clear();
+    }
+
+    //Run the post processors not skipped by the metadata of the asset.
+    pub fn run(&self, path: &Path, resource: &mut T, metadata: &AssetMetadata) -> ResourceResult<()> {
+        for processor in self.processors.iter() {
+            if metadata.skips(processor.name()) {
+                trace!("The post processor {} is skipped for the asset {}.", processor.name(), path.display());
+                continue;
+            }
+            trace!("Post processing the asset {} with {}.", path.display(), processor.name());
+            processor.process(resource, metadata).map_err(|resource_error| {
+                ResourceError::PostProcessingError(format!("The post processor {} failed on {}: {}", processor.name(), path.display(), resource_error))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod post_processor_test {
+    use super::*;
+
+    struct Add(i32);
+
+    impl PostProcessor<i32> for Add {
+        fn name(&self) -> &str {
+            "add"
+        }
+
+        fn process(&self, resource: &mut i32, metadata: &AssetMetadata) -> ResourceResult<()> {
+            let amount = metadata.integer(self.name(), "amount").map(|amount| amount as i32).unwrap_or(self.0);
+            *resource = resource.checked_add(amount).ok_or_else(|| ResourceError::PostProcessingError(String::from("overflow")))?;
+            Ok(())
+        }
+    }
+
+    struct Double;
+
+    impl PostProcessor<i32> for Double {
+        fn name(&self) -> &str {
+            "double"
+        }
+
+        fn process(&self, resource: &mut i32, _metadata: &AssetMetadata) -> ResourceResult<()> {
+            *resource *= 2;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn chain_order_and_metadata() {
+        let mut chain = PostProcessorChain::new();
+        chain.add(Add(1)).add(Double);
+        assert_eq!(chain.names(), vec!["add", "double"]);
+
+        let mut resource = 3;
+        chain.run(Path::new("three"), &mut resource, &AssetMetadata::new()).unwrap();
+        assert_eq!(resource, 8);
+
+        let mut metadata = AssetMetadata::new();
+        metadata.skip_processor("double").set_parameter("add", "amount", 10);
+        let mut resource = 3;
+        chain.run(Path::new("three"), &mut resource, &metadata).unwrap();
+        assert_eq!(resource, 13);
+
+        let mut resource = i32::MAX;
+        let error = chain.run(Path::new("max"), &mut resource, &AssetMetadata::new()).unwrap_err();
+        assert!(error.to_string().contains("add failed on max"));
+    }
+}
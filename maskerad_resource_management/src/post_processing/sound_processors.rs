@@ -0,0 +1,148 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io::{Read, Seek};
+
+use post_processing::asset_metadata::AssetMetadata;
+use post_processing::post_processor::PostProcessor;
+use resources::sound_resource::{DecodedSound, SoundResource};
+use resources::resource_errors::{ResourceError, ResourceResult};
+
+/*
+The sound post processors decode the sounds in memory: they're meant for the short sounds (effects, voices),
+not for the music, which should be streamed.
+*/
+
+//Scale the samples so the loudest sample reaches the peak level.
+pub fn normalize(sound: &mut DecodedSound, peak: f32) {
+    let loudest = sound.channels.iter()
+        .flat_map(|channel| channel.iter())
+        .map(|sample| i32::from(*sample).abs())
+        .max()
+        .unwrap_or(0);
+    if loudest == 0 {
+        return;
+    }
+
+    let gain = peak.clamp(0.0, 1.0) * f32::from(i16::MAX) / loudest as f32;
+    for sample in sound.channels.iter_mut().flat_map(|channel| channel.iter_mut()) {
+        *sample = (f32::from(*sample) * gain).round().clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16;
+    }
+}
+
+//Convert the sound to another sample rate, with a linear interpolation.
+pub fn resample(sound: &mut DecodedSound, sample_rate: u32) {
+    if sample_rate == 0 || sample_rate == sound.sample_rate || sound.sample_rate == 0 {
+        return;
+    }
+
+    let ratio = f64::from(sound.sample_rate) / f64::from(sample_rate);
+    for channel in sound.channels.iter_mut() {
+        if channel.is_empty() {
+            continue;
+        }
+        let length = (channel.len() as f64 / ratio).round().max(1.0) as usize;
+        let resampled = (0..length).map(|index| {
+            let position = index as f64 * ratio;
+            let before = (position.floor() as usize).min(channel.len() - 1);
+            let after = (before + 1).min(channel.len() - 1);
+            let fraction = position - before as f64;
+            (f64::from(channel[before]) * (1.0 - fraction) + f64::from(channel[after]) * fraction).round() as i16
+        }).collect();
+        *channel = resampled;
+    }
+    sound.sample_rate = sample_rate;
+}
+
+fn decoded<R: Read + Seek>(resource: &mut SoundResource<R>) -> ResourceResult<Option<&mut DecodedSound>> {
+    resource.decode()?;
+    Ok(resource.decoded_mut())
+}
+
+//The names of the sound post processors in the metadata files.
+pub const NORMALIZE_SOUND: &str = "normalize_sound";
+pub const RESAMPLE_SOUND: &str = "resample_sound";
+
+//Parameters: peak, the level of the loudest sample between 0.0 and 1.0 (1.0 by default).
+pub struct NormalizeSound;
+
+impl<R: Read + Seek> PostProcessor<SoundResource<R>> for NormalizeSound {
+    fn name(&self) -> &str {
+        NORMALIZE_SOUND
+    }
+
+    fn process(&self, resource: &mut SoundResource<R>, metadata: &AssetMetadata) -> ResourceResult<()> {
+        let peak = metadata.float(NORMALIZE_SOUND, "peak").unwrap_or(1.0) as f32;
+        if let Some(sound) = decoded(resource)? {
+            normalize(sound, peak);
+        }
+        Ok(())
+    }
+}
+
+//Parameters: sample_rate, the sample rate of the resampled sound (the sample rate given to new() by default).
+pub struct ResampleSound {
+    sample_rate: u32,
+}
+
+impl ResampleSound {
+    pub fn new(sample_rate: u32) -> Self {
+        ResampleSound {
+            sample_rate,
+        }
+    }
+}
+
+impl<R: Read + Seek> PostProcessor<SoundResource<R>> for ResampleSound {
+    fn name(&self) -> &str {
+        RESAMPLE_SOUND
+    }
+
+    fn process(&self, resource: &mut SoundResource<R>, metadata: &AssetMetadata) -> ResourceResult<()> {
+        let sample_rate = match metadata.integer(RESAMPLE_SOUND, "sample_rate") {
+            Some(sample_rate) if sample_rate > 0 && sample_rate <= i64::from(u32::MAX) => sample_rate as u32,
+            Some(sample_rate) => return Err(ResourceError::PostProcessingError(format!("{} is not a valid sample rate.", sample_rate))),
+            None => self.sample_rate,
+        };
+        if let Some(sound) = decoded(resource)? {
+            resample(sound, sample_rate);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod sound_processors_test {
+    use super::*;
+
+    fn sound() -> DecodedSound {
+        DecodedSound {
+            sample_rate: 4,
+            channels: vec![vec![0, 100, -200, 100], vec![0, 0, 0, 0]],
+        }
+    }
+
+    #[test]
+    fn normalize_sound() {
+        let mut sound = sound();
+        normalize(&mut sound, 0.5);
+        assert_eq!(sound.channels[0], vec![0, 8192, -16384, 8192]);
+        assert_eq!(sound.channels[1], vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn resample_sound() {
+        let mut sound = sound();
+        resample(&mut sound, 8);
+        assert_eq!(sound.sample_rate, 8);
+        assert_eq!(sound.len(), 8);
+        assert_eq!(&sound.channels[0][..4], &[0, 50, 100, -50]);
+
+        resample(&mut sound, 4);
+        assert_eq!(sound.channels[0], vec![0, 100, -200, 100]);
+    }
+}
@@ -7,4 +7,6 @@
 
 pub mod model_registry;
 pub mod sound_registry;
-pub mod image_registry;
\ No newline at end of file
+pub mod image_registry;
+pub mod buffer_registry;
+pub mod registry;
\ No newline at end of file
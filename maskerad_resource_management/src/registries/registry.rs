@@ -0,0 +1,503 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use handle::{Handle, HandleId, ReleaseQueue, WeakHandle};
+
+/*
+REGISTRY.
+
+The resources of a type, owned by the registry, in slots indexed by the handles.
+
+- Getting a resource from a handle is O(1): the index of the handle is the index of the slot, and the
+  generation of the handle must be the generation of the slot.
+- The resources are also indexed by path: loading a resource already loaded returns a new handle to it.
+- The free slots are reused. The generation of a slot is incremented when its resource is freed.
+
+The resources without strong handles are freed when the registry is modified, or by collect().
+
+A resource loaded asynchronously is reserved first: its handle exists, but the resource isn't available
+until its loading is completed. If its loading fails, its path is removed from the registry, so it can be loaded again.
+
+A registry retaining the unused resources keeps a strong handle to each of its resources: the resources without
+other strong handles stay loaded, and are freed by evict(). The uses of the resources (get(), get_mut()) are ordered
+by a clock shared by the registries of the thread, so the least recently used resource of several registries can be found.
+*/
+
+thread_local! {
+    static USE_CLOCK: Cell<u64> = const { Cell::new(0) };
+}
+
+fn next_use() -> u64 {
+    USE_CLOCK.with(|clock| {
+        clock.set(clock.get() + 1);
+        clock.get()
+    })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed,
+}
+
+#[derive(Debug)]
+enum ResourceState<T> {
+    Loading,
+    Loaded(T),
+    //The id of the placeholder used instead of the resource, if any.
+    Failed(Option<HandleId>),
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    state: ResourceState<T>,
+    path: PathBuf,
+    handle: WeakHandle<T>,
+    //The strong handle of a registry retaining its unused resources.
+    retained: Option<Handle<T>>,
+    last_use: Cell<u64>,
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    generation: u32,
+    entry: Option<Entry<T>>,
+}
+
+pub struct Registry<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<u32>,
+    paths: HashMap<PathBuf, HandleId>,
+    release_queue: ReleaseQueue,
+    retain_unused: bool,
+}
+
+impl<T> fmt::Debug for Registry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Registry [resources: {}, slots: {}, released: {}]", self.len(), self.slots.len(), self.release_queue.borrow().len())
+    }
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        debug!("Creating a default Registry.");
+        Registry {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            paths: HashMap::new(),
+            release_queue: Rc::new(RefCell::new(Vec::new())),
+            retain_unused: false,
+        }
+    }
+}
+
+impl<T> Registry<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    //The number of resources in the registry, including the released resources not freed yet.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    fn entry(&self, id: HandleId) -> Option<&Entry<T>> {
+        self.slots.get(id.index())
+            .filter(|slot| slot.generation == id.generation())
+            .and_then(|slot| slot.entry.as_ref())
+    }
+
+    fn entry_mut(&mut self, id: HandleId) -> Option<&mut Entry<T>> {
+        self.slots.get_mut(id.index())
+            .filter(|slot| slot.generation == id.generation())
+            .and_then(|slot| slot.entry.as_mut())
+    }
+
+    //The id of the entry holding the content of the resource: the resource, or the placeholder of a failed resource.
+    fn content_id(&self, id: HandleId) -> HandleId {
+        match self.entry(id) {
+            Some(&Entry { state: ResourceState::Failed(Some(placeholder)), .. }) => placeholder,
+            _ => id,
+        }
+    }
+
+    //None if the resource has been removed from the registry, or isn't loaded yet.
+    //The placeholder of a resource which failed to load is returned instead of the resource.
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        match self.entry(self.content_id(handle.id())) {
+            Some(&Entry { state: ResourceState::Loaded(ref resource), ref last_use, .. }) => {
+                last_use.set(next_use());
+                Some(resource)
+            },
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        let id = self.content_id(handle.id());
+        match self.entry_mut(id) {
+            Some(&mut Entry { state: ResourceState::Loaded(ref mut resource), ref last_use, .. }) => {
+                last_use.set(next_use());
+                Some(resource)
+            },
+            _ => None,
+        }
+    }
+
+    //Keep the resources without strong handles loaded, until they're evicted. False to free them again.
+    pub fn set_retain_unused(&mut self, retain_unused: bool) -> &mut Self {
+        self.retain_unused = retain_unused;
+        for entry in self.slots.iter_mut().filter_map(|slot| slot.entry.as_mut()) {
+            entry.retained = if retain_unused {
+                entry.handle.upgrade()
+            } else {
+                None
+            };
+        }
+        self
+    }
+
+    pub fn retains_unused(&self) -> bool {
+        self.retain_unused
+    }
+
+    //The loaded resources, with their ids.
+    pub fn resources(&self) -> Vec<(HandleId, &T)> {
+        self.paths.values().filter_map(|id| match self.entry(*id) {
+            Some(&Entry { state: ResourceState::Loaded(ref resource), .. }) => Some((*id, resource)),
+            _ => None,
+        }).collect()
+    }
+
+    //The loaded resource has no strong handle but the handle retained by the registry.
+    pub fn is_unused(&self, id: HandleId) -> bool {
+        match self.entry(id) {
+            Some(entry) => matches!(entry.state, ResourceState::Loaded(_)) && entry.handle.strong_count() <= entry.retained.is_some() as usize,
+            None => false,
+        }
+    }
+
+    //The unused resources, with the time of their last use, from the least recently used.
+    pub fn unused(&self) -> Vec<(HandleId, u64)> {
+        let mut unused: Vec<(HandleId, u64)> = self.paths.values()
+            .filter(|id| self.is_unused(**id))
+            .filter_map(|id| self.entry(*id).map(|entry| (*id, entry.last_use.get())))
+            .collect();
+        unused.sort_by_key(|&(_, last_use)| last_use);
+        unused
+    }
+
+    //Free an unused resource. Return None if the resource is used, or isn't loaded.
+    pub fn evict(&mut self, id: HandleId) -> Option<T> {
+        if !self.is_unused(id) {
+            return None;
+        }
+        debug!("Evicting the resource {} from the Registry.", id);
+        self.free(id)
+    }
+
+    //None if the resource has been removed from the registry.
+    pub fn state(&self, handle: &Handle<T>) -> Option<LoadState> {
+        self.entry(handle.id()).map(|entry| {
+            match entry.state {
+                ResourceState::Loading => LoadState::Loading,
+                ResourceState::Loaded(_) => LoadState::Loaded,
+                ResourceState::Failed(_) => LoadState::Failed,
+            }
+        })
+    }
+
+    //The path of the resource.
+    pub fn path(&self, handle: &Handle<T>) -> Option<&Path> {
+        self.path_of_id(handle.id())
+    }
+
+    pub fn path_of_id(&self, id: HandleId) -> Option<&Path> {
+        self.entry(id).map(|entry| entry.path.as_path())
+    }
+
+    pub fn contains_path<I: AsRef<Path>>(&self, path: I) -> bool {
+        self.handle(path).is_some()
+    }
+
+    //A strong handle to the resource loaded from the path. None if the resource isn't loaded, or has been released.
+    pub fn handle<I: AsRef<Path>>(&self, path: I) -> Option<Handle<T>> {
+        trace!("Trying to get a handle to the resource with path {}.", path.as_ref().display());
+        self.paths.get(path.as_ref())
+            .and_then(|id| self.entry(*id))
+            .and_then(|entry| entry.handle.upgrade())
+    }
+
+    //Insert a resource and return a strong handle to it.
+    //If a resource with the same path still has strong handles, its content is replaced and the handles now refer to the new content.
+    pub fn insert<I>(&mut self, path: I, resource: T) -> Handle<T> where
+        I: Into<PathBuf>,
+    {
+        let path = path.into();
+        debug!("Inserting the resource with path {} in the Registry.", path.display());
+        self.insert_state(path, ResourceState::Loaded(resource))
+    }
+
+    //Reserve a slot for a resource loaded asynchronously, and return a strong handle to it.
+    //If the path is already in the registry, return a handle to its resource instead.
+    pub fn reserve<I>(&mut self, path: I) -> Handle<T> where
+        I: Into<PathBuf>,
+    {
+        let path = path.into();
+        if let Some(handle) = self.handle(path.as_path()) {
+            return handle;
+        }
+        debug!("Reserving a slot for the resource with path {} in the Registry.", path.display());
+        self.insert_state(path, ResourceState::Loading)
+    }
+
+    //Complete the loading of a reserved resource. Return false if the resource isn't loading anymore
+    //(released, removed, or replaced by insert()): the resource is dropped.
+    pub fn complete(&mut self, id: HandleId, resource: T) -> bool {
+        match self.entry_mut(id) {
+            Some(entry) => {
+                if let ResourceState::Loading = entry.state {
+                    entry.state = ResourceState::Loaded(resource);
+                    true
+                } else {
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+
+    //Mark a reserved resource as failed, and remove its path from the registry.
+    //The handles of the resource give access to the placeholder, if there's one.
+    pub fn fail(&mut self, id: HandleId, placeholder: Option<&Handle<T>>) -> bool {
+        let path = match self.entry_mut(id) {
+            Some(entry) => {
+                if let ResourceState::Loading = entry.state {
+                    entry.state = ResourceState::Failed(placeholder.map(Handle::id));
+                    entry.retained = None;
+                    entry.path.clone()
+                } else {
+                    return false;
+                }
+            },
+            None => return false,
+        };
+        if self.paths.get(path.as_path()) == Some(&id) {
+            self.paths.remove(path.as_path());
+        }
+        true
+    }
+
+    fn insert_state(&mut self, path: PathBuf, state: ResourceState<T>) -> Handle<T> {
+        self.collect();
+
+        if let Some(handle) = self.handle(path.as_path()) {
+            if let Some(entry) = self.entry_mut(handle.id()) {
+                entry.state = state;
+                entry.last_use.set(next_use());
+            }
+            return handle;
+        }
+
+        let index = match self.free_slots.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    entry: None,
+                });
+                (self.slots.len() - 1) as u32
+            },
+        };
+
+        let id = HandleId::new(index, self.slots[index as usize].generation);
+        let handle = Handle::new(id, &self.release_queue);
+        self.slots[index as usize].entry = Some(Entry {
+            state,
+            path: path.clone(),
+            handle: handle.downgrade(),
+            retained: if self.retain_unused { Some(handle.clone()) } else { None },
+            last_use: Cell::new(next_use()),
+        });
+        self.paths.insert(path, id);
+        handle
+    }
+
+    //Remove the resource, even if it has strong handles. These handles won't find any resource.
+    pub fn remove(&mut self, handle: &Handle<T>) -> Option<T> {
+        debug!("Removing the resource {} from the Registry.", handle.id());
+        self.free(handle.id())
+    }
+
+    fn free(&mut self, id: HandleId) -> Option<T> {
+        let slot = match self.slots.get_mut(id.index()) {
+            Some(slot) if slot.generation == id.generation() => slot,
+            _ => return None,
+        };
+        let entry = slot.entry.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(id.index() as u32);
+
+        if self.paths.get(entry.path.as_path()) == Some(&id) {
+            self.paths.remove(entry.path.as_path());
+        }
+        match entry.state {
+            ResourceState::Loaded(resource) => Some(resource),
+            _ => None,
+        }
+    }
+
+    //Free the resources without strong handles. Return the number of resources freed.
+    pub fn collect(&mut self) -> usize {
+        let released: Vec<HandleId> = self.release_queue.borrow_mut().drain(..).collect();
+        let mut freed = 0;
+        for id in released {
+            //The resource may have been removed, or may have been given a new strong handle by insert().
+            let unused = self.entry(id).map(|entry| !entry.handle.is_alive()).unwrap_or(false);
+            if unused {
+                self.free(id);
+                freed += 1;
+            }
+        }
+        if freed > 0 {
+            debug!("{} released resources have been freed from the Registry.", freed);
+        }
+        freed
+    }
+
+    //The paths of the resources.
+    pub fn paths(&self) -> Vec<&Path> {
+        self.paths.keys().map(|path| path.as_path()).collect()
+    }
+
+    pub fn clear(&mut self) {
+        debug!("Clearing the Registry.");
+        for slot in self.slots.iter_mut() {
+            if slot.entry.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+            }
+        }
+        self.free_slots = (0..self.slots.len() as u32).rev().collect();
+        self.paths.clear();
+        self.release_queue.borrow_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod registry_test {
+    use super::*;
+
+    #[test]
+    fn handles_free_resources() {
+        let mut registry = Registry::new();
+        let first = registry.insert("first", 1);
+        let second = registry.insert("second", 2);
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get(&first), Some(&1));
+        assert_eq!(registry.handle("second"), Some(second.clone()));
+        assert_eq!(registry.path(&second), Some(Path::new("second")));
+
+        //Dropping the last strong handle frees the resource.
+        let weak_first = first.downgrade();
+        drop(first);
+        assert!(!registry.contains_path("first"));
+        assert_eq!(registry.collect(), 1);
+        assert_eq!(registry.len(), 1);
+        assert!(weak_first.upgrade().is_none());
+
+        //The slot is reused with a new generation: the old id doesn't find the new resource.
+        let third = registry.insert("third", 3);
+        assert_eq!(third.id().index(), weak_first.id().index());
+        assert_ne!(third.id().generation(), weak_first.id().generation());
+
+        //A resource with strong handles is replaced in place.
+        let second_again = registry.insert("second", 20);
+        assert_eq!(second_again, second);
+        assert_eq!(registry.get(&second), Some(&20));
+
+        assert_eq!(registry.remove(&third), Some(3));
+        assert_eq!(registry.get(&third), None);
+
+        registry.clear();
+        assert!(registry.is_empty());
+        assert_eq!(registry.get(&second), None);
+    }
+
+    #[test]
+    fn reserved_resources() {
+        let mut registry = Registry::new();
+        let loading = registry.reserve("loading");
+        assert_eq!(registry.state(&loading), Some(LoadState::Loading));
+        assert_eq!(registry.get(&loading), None);
+        assert_eq!(registry.reserve("loading"), loading);
+
+        assert!(registry.complete(loading.id(), 1));
+        assert_eq!(registry.state(&loading), Some(LoadState::Loaded));
+        assert_eq!(registry.get(&loading), Some(&1));
+        assert!(!registry.complete(loading.id(), 2));
+
+        let failing = registry.reserve("failing");
+        assert!(registry.fail(failing.id(), None));
+        assert_eq!(registry.state(&failing), Some(LoadState::Failed));
+        assert_eq!(registry.get(&failing), None);
+        assert!(!registry.contains_path("failing"));
+
+        //A failed resource gives access to its placeholder.
+        let placeholder = registry.insert("placeholder", 0);
+        let replaced = registry.reserve("replaced");
+        assert!(registry.fail(replaced.id(), Some(&placeholder)));
+        assert_eq!(registry.state(&replaced), Some(LoadState::Failed));
+        assert_eq!(registry.get(&replaced), Some(&0));
+
+        //A released resource is freed, its completion is dropped.
+        let released = registry.reserve("released");
+        let id = released.id();
+        drop(released);
+        assert_eq!(registry.collect(), 1);
+        assert!(!registry.complete(id, 3));
+    }
+
+    #[test]
+    fn retained_resources() {
+        let mut registry = Registry::new();
+        registry.set_retain_unused(true);
+        let first = registry.insert("first", 1).id();
+        let second = registry.insert("second", 2);
+        let third = registry.insert("third", 3).id();
+        assert_eq!(registry.collect(), 0);
+        assert_eq!(registry.resources().len(), 3);
+
+        //The used resources can't be evicted, the unused ones are evicted from the least recently used.
+        assert!(!registry.is_unused(second.id()));
+        assert_eq!(registry.evict(second.id()), None);
+        let third_handle = registry.handle("third").unwrap();
+        registry.get(&third_handle);
+        drop(third_handle);
+        let unused: Vec<HandleId> = registry.unused().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(unused, vec![first, third]);
+        assert_eq!(registry.evict(first), Some(1));
+        assert!(!registry.contains_path("first"));
+
+        //The resources aren't retained anymore.
+        registry.set_retain_unused(false);
+        assert_eq!(registry.collect(), 1);
+        assert_eq!(registry.len(), 1);
+        drop(second);
+        assert_eq!(registry.collect(), 1);
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml;
+use toml::value::{Table, Value};
+
+use resources::resource_errors::ResourceResult;
+
+/*
+RESOURCE GROUPS.
+
+The resources of a game are loaded and unloaded by groups: the resources used everywhere ("global"), the resources
+of a level ("level_3"), of a menu ("ui_menu")... The groups are declared by a TOML manifest:
+
+[groups]
+global = ["textures/font.tga", "sounds/click.ogg"]
+level_3 = ["models/castle.gltf", "textures/font.tga"]
+
+The kind of a resource is found from its extension: images, glTF models, OGG sounds and buffers.
+The models are loaded with their dependencies.
+
+A loaded group keeps strong handles to its resources. A resource of several groups is loaded once, and stays loaded
+while one of its groups is loaded. ResourceManager::transition_groups() loads the groups of the next level before
+unloading the groups of the previous one: only the resources which aren't shared are loaded and freed.
+*/
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceGroupManifest {
+    groups: BTreeMap<String, Vec<PathBuf>>,
+}
+
+impl ResourceGroupManifest {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_toml(toml: &str) -> ResourceResult<Self> {
+        let mut table: Table = toml::from_str(toml)?;
+        let mut manifest = ResourceGroupManifest::new();
+        let groups = match table.remove("groups") {
+            Some(Value::Table(groups)) => groups,
+            _ => {
+                warn!("The resource group manifest has no table of groups.");
+                return Ok(manifest);
+            },
+        };
+
+        for (name, resources) in groups {
+            match resources {
+                Value::Array(resources) => {
+                    manifest.set_group(name, resources.iter().filter_map(|resource| resource.as_str()).map(PathBuf::from).collect());
+                },
+                _ => warn!("The resource group {} is not an array of paths, it is ignored.", name),
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> ResourceResult<Self> {
+        let toml = fs::read_to_string(path.as_ref())?;
+        ResourceGroupManifest::from_toml(toml.as_str())
+    }
+
+    //Declare a group, replacing the group with the same name.
+    pub fn set_group<S: Into<String>>(&mut self, name: S, resources: Vec<PathBuf>) -> &mut Self {
+        self.groups.insert(name.into(), resources);
+        self
+    }
+
+    pub fn group(&self, name: &str) -> Option<&[PathBuf]> {
+        self.groups.get(name).map(|resources| resources.as_slice())
+    }
+
+    //The names of the groups, sorted.
+    pub fn groups(&self) -> Vec<&str> {
+        self.groups.keys().map(|name| name.as_str()).collect()
+    }
+
+    //The groups with the resource.
+    pub fn groups_of<P: AsRef<Path>>(&self, resource: P) -> Vec<&str> {
+        self.groups.iter()
+            .filter(|(_, resources)| resources.iter().any(|path| path.as_path() == resource.as_ref()))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+//The resources of the loaded groups.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupLoadReport {
+    //The resources loaded by the groups.
+    pub loaded: Vec<PathBuf>,
+    //The resources already loaded, by another group or by the game.
+    pub shared: Vec<PathBuf>,
+}
+
+#[cfg(test)]
+mod resource_groups_test {
+    use super::*;
+
+    #[test]
+    fn resource_group_manifest() {
+        let manifest = ResourceGroupManifest::from_toml(r#"
+            [groups]
+            global = ["textures/font.tga", "sounds/click.ogg"]
+            level_3 = ["models/castle.gltf", "textures/font.tga"]
+            broken = 3
+        "#).unwrap();
+        assert_eq!(manifest.groups(), vec!["global", "level_3"]);
+        assert_eq!(manifest.group("level_3").unwrap(), &[PathBuf::from("models/castle.gltf"), PathBuf::from("textures/font.tga")]);
+        assert_eq!(manifest.groups_of("textures/font.tga"), vec!["global", "level_3"]);
+        assert!(manifest.group("broken").is_none());
+
+        assert!(ResourceGroupManifest::from_toml("").unwrap().groups().is_empty());
+        assert!(ResourceGroupManifest::from_toml("[groups").is_err());
+    }
+}
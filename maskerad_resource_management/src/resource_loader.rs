@@ -0,0 +1,281 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::any::{self, Any};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use budgets::ResourceCategory;
+use handle::HandleId;
+use registries::registry::Registry;
+use resources::resource_errors::ResourceResult;
+
+/*
+RESOURCE LOADERS.
+
+The images, models and sounds are loaded by the resource manager itself. The other asset types of a game
+(dialogue trees, move lists, level files...) are loaded by the resource loaders registered by the game:
+
+struct DialogueLoader;
+
+impl<R: Read> ResourceLoader<R> for DialogueLoader {
+    type Resource = DialogueTree;
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue"]
+    }
+
+    fn load(&self, reader: &mut R, path: &Path) -> ResourceResult<DialogueTree> {
+        ...
+    }
+}
+
+resource_manager.register_loader(DialogueLoader)?;
+let dialogue: Handle<DialogueTree> = resource_manager.load("dialogues/intro.dialogue", reader)?;
+
+A registry is created for the resource type when its loader is registered. The resources of a registered type
+have the handles, the caching, the placeholders, the errors and the hot reloading of the built-in types.
+A loader giving a category to its resources puts them in the budget of the category, with the memory size it computes.
+
+A loader which fails returns a ResourceError, ResourceError::CustomError wraps the errors of the game.
+*/
+
+pub trait ResourceLoader<R> {
+    type Resource;
+
+    //The extensions of the files loaded by the loader, without the dot.
+    fn extensions(&self) -> &[&str];
+
+    fn load(&self, reader: &mut R, path: &Path) -> ResourceResult<Self::Resource>;
+
+    //The budget category of the resources. None if they aren't budgeted.
+    fn category(&self) -> Option<ResourceCategory> {
+        None
+    }
+
+    //The bytes used by a resource in memory, counted in the budget of its category.
+    fn memory_size(&self, _resource: &Self::Resource) -> usize {
+        0
+    }
+}
+
+//The extension of a path, in lower case.
+pub fn extension_of<P: AsRef<Path>>(path: P) -> Option<String> {
+    path.as_ref().extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_lowercase())
+}
+
+//A loader, with its resource type erased.
+pub(crate) trait AnyLoader<R> {
+    fn load_any(&self, reader: &mut R, path: &Path) -> ResourceResult<Box<dyn Any>>;
+    fn category_any(&self) -> Option<ResourceCategory>;
+    //0 if the resource isn't of the loader's type.
+    fn memory_size_any(&self, resource: &dyn Any) -> usize;
+}
+
+impl<R, L> AnyLoader<R> for L where
+    L: ResourceLoader<R>,
+    L::Resource: 'static,
+{
+    fn load_any(&self, reader: &mut R, path: &Path) -> ResourceResult<Box<dyn Any>> {
+        let resource = self.load(reader, path)?;
+        Ok(Box::new(resource))
+    }
+
+    fn category_any(&self) -> Option<ResourceCategory> {
+        self.category()
+    }
+
+    fn memory_size_any(&self, resource: &dyn Any) -> usize {
+        resource.downcast_ref::<L::Resource>().map_or(0, |resource| self.memory_size(resource))
+    }
+}
+
+//A registry, with its resource type erased.
+pub(crate) trait AnyRegistry {
+    fn paths(&self) -> Vec<PathBuf>;
+    //Replace the resource behind the handles of the path. False if the resource isn't of the registry's type.
+    fn replace(&mut self, path: &Path, resource: Box<dyn Any>) -> bool;
+    //Remove the resource with the path, even if it has strong handles.
+    fn remove_path(&mut self, path: &Path) -> bool;
+    //Insert the resource, and return a strong handle to it (a Handle<T>). None if the resource isn't of the registry's type.
+    fn insert_any(&mut self, path: &Path, resource: Box<dyn Any>) -> Option<Box<dyn Any>>;
+    //A strong handle (a Handle<T>) to the resource with the path.
+    fn handle_any(&self, path: &Path) -> Option<Box<dyn Any>>;
+    fn collect(&mut self) -> usize;
+    fn clear(&mut self);
+    fn set_retain_unused(&mut self, retain_unused: bool);
+    //The loaded resources, with their ids and whether they're unused.
+    fn resources_any(&self) -> Vec<(HandleId, &dyn Any, bool)>;
+    //The least recently used resource without strong handles, with the time of its last use.
+    fn least_recently_used(&self) -> Option<(HandleId, u64)>;
+    //Free an unused resource, with its path.
+    fn evict_any(&mut self, id: HandleId) -> Option<(PathBuf, Box<dyn Any>)>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyRegistry for Registry<T> {
+    fn paths(&self) -> Vec<PathBuf> {
+        Registry::paths(self).into_iter().map(Path::to_path_buf).collect()
+    }
+
+    fn replace(&mut self, path: &Path, resource: Box<dyn Any>) -> bool {
+        match resource.downcast::<T>() {
+            Ok(resource) => {
+                self.insert(path, *resource);
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    fn remove_path(&mut self, path: &Path) -> bool {
+        match self.handle(path) {
+            Some(handle) => self.remove(&handle).is_some(),
+            None => false,
+        }
+    }
+
+    fn insert_any(&mut self, path: &Path, resource: Box<dyn Any>) -> Option<Box<dyn Any>> {
+        let resource = resource.downcast::<T>().ok()?;
+        Some(Box::new(self.insert(path, *resource)))
+    }
+
+    fn handle_any(&self, path: &Path) -> Option<Box<dyn Any>> {
+        self.handle(path).map(|handle| Box::new(handle) as Box<dyn Any>)
+    }
+
+    fn collect(&mut self) -> usize {
+        Registry::collect(self)
+    }
+
+    fn clear(&mut self) {
+        Registry::clear(self)
+    }
+
+    fn set_retain_unused(&mut self, retain_unused: bool) {
+        Registry::set_retain_unused(self, retain_unused);
+    }
+
+    fn resources_any(&self) -> Vec<(HandleId, &dyn Any, bool)> {
+        self.resources().into_iter().map(|(id, resource)| (id, resource as &dyn Any, self.is_unused(id))).collect()
+    }
+
+    fn least_recently_used(&self) -> Option<(HandleId, u64)> {
+        self.unused().first().cloned()
+    }
+
+    fn evict_any(&mut self, id: HandleId) -> Option<(PathBuf, Box<dyn Any>)> {
+        let path = self.path_of_id(id).map(Path::to_path_buf).unwrap_or_default();
+        self.evict(id).map(|resource| (path, Box::new(resource) as Box<dyn Any>))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+//The loader, the registry and the placeholder of a registered resource type.
+pub(crate) struct CustomType<R> {
+    pub name: &'static str,
+    pub extensions: Vec<String>,
+    pub loader: Rc<dyn AnyLoader<R>>,
+    pub registry: Box<dyn AnyRegistry>,
+    //The strong handle to the placeholder (a Handle<T>), which keeps it loaded.
+    pub placeholder: Option<Box<dyn Any>>,
+}
+
+impl<R> CustomType<R> {
+    pub fn new<L>(loader: L) -> Self where
+        L: ResourceLoader<R> + 'static,
+        L::Resource: 'static,
+    {
+        CustomType {
+            name: any::type_name::<L::Resource>(),
+            extensions: loader.extensions().iter().map(|extension| extension.to_lowercase()).collect(),
+            loader: Rc::new(loader),
+            registry: Box::new(Registry::<L::Resource>::new()),
+            placeholder: None,
+        }
+    }
+
+    pub fn registry<T: 'static>(&self) -> Option<&Registry<T>> {
+        self.registry.as_any().downcast_ref::<Registry<T>>()
+    }
+
+    pub fn registry_mut<T: 'static>(&mut self) -> Option<&mut Registry<T>> {
+        self.registry.as_any_mut().downcast_mut::<Registry<T>>()
+    }
+}
+
+#[cfg(test)]
+mod resource_loader_test {
+    use super::*;
+    use std::io::Read;
+    use resources::resource_errors::ResourceError;
+    use handle::Handle;
+
+    struct LineLoader;
+
+    impl<R: Read> ResourceLoader<R> for LineLoader {
+        type Resource = Vec<String>;
+
+        fn extensions(&self) -> &[&str] {
+            &["TXT", "lines"]
+        }
+
+        fn load(&self, reader: &mut R, _path: &Path) -> ResourceResult<Vec<String>> {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            Ok(text.lines().map(String::from).collect())
+        }
+    }
+
+    #[test]
+    fn erased_loader_and_registry() {
+        let mut custom_type: CustomType<&[u8]> = CustomType::new(LineLoader);
+        assert_eq!(custom_type.extensions, vec![String::from("txt"), String::from("lines")]);
+        assert_eq!(custom_type.name, any::type_name::<Vec<String>>());
+        assert_eq!(extension_of("dialogues/Intro.TXT"), Some(String::from("txt")));
+
+        let resource = custom_type.loader.load_any(&mut &b"first\nsecond"[..], Path::new("a.txt")).unwrap();
+        let handle = custom_type.registry_mut::<Vec<String>>().unwrap().insert("a.txt", Vec::new());
+        assert!(custom_type.registry.replace(Path::new("a.txt"), resource));
+        assert!(custom_type.registry.handle_any(Path::new("a.txt")).unwrap().downcast_ref::<Handle<Vec<String>>>().is_some());
+        let other = custom_type.registry.insert_any(Path::new("b.txt"), Box::new(vec![String::new()])).unwrap();
+        assert!(custom_type.registry.handle_any(Path::new("b.txt")).is_some());
+        drop(other);
+        assert_eq!(custom_type.registry.collect(), 1);
+        assert!(custom_type.registry.insert_any(Path::new("c.txt"), Box::new(3u32)).is_none());
+        assert_eq!(custom_type.registry::<Vec<String>>().unwrap().get(&handle).unwrap().len(), 2);
+        assert!(!custom_type.registry.replace(Path::new("a.txt"), Box::new(3u32)));
+        assert!(custom_type.registry::<u32>().is_none());
+        assert!(custom_type.registry.remove_path(Path::new("a.txt")));
+        assert!(custom_type.registry.paths().is_empty());
+
+        //The unused resources retained for a budget can be evicted.
+        assert_eq!(custom_type.loader.category_any(), None);
+        assert_eq!(custom_type.loader.memory_size_any(&vec![String::new()]), 0);
+        custom_type.registry.set_retain_unused(true);
+        drop(custom_type.registry.insert_any(Path::new("b.txt"), Box::new(vec![String::new()])).unwrap());
+        assert_eq!(custom_type.registry.collect(), 0);
+        let (id, _) = custom_type.registry.least_recently_used().unwrap();
+        assert!(custom_type.registry.resources_any().iter().any(|&(resource, _, unused)| resource == id && unused));
+        assert_eq!(custom_type.registry.evict_any(id).unwrap().0, PathBuf::from("b.txt"));
+        assert!(custom_type.registry.least_recently_used().is_none());
+
+        let invalid = custom_type.loader.load_any(&mut &[0xffu8, 0xfe][..], Path::new("b.txt"));
+        match invalid {
+            Err(ResourceError::BufferError(_, _)) => {},
+            _ => panic!("Invalid UTF-8 should be an error."),
+        }
+    }
+}
@@ -8,169 +8,2273 @@
 use registries::image_registry::ImageRegistry;
 use registries::model_registry::ModelRegistry;
 use registries::sound_registry::SoundRegistry;
+use registries::buffer_registry::BufferRegistry;
 
 use resources::sound_resource::SoundResource;
 use resources::model_resource::ModelResource;
 use resources::image_resource::{ImageResource, ColorFormat};
+use resources::buffer_resource::BufferResource;
+use resources::texture_resource::TextureResource;
 
 use resource_manager_errors::{ResourceManagerError, ResourceManagerResult};
-use maskerad_core::allocators::stacks::MemStack;
+use resources::resource_errors::{ResourceError, ResourceResult};
+use handle::Handle;
+use registries::registry::{Registry, LoadState};
+use async_loading::{BatchId, LoadProgress, PendingLoads, Completed};
+use maskerad_core::jobs::job_system::Spawner;
+use dependencies::{Dependency, DependencyGraph, DependencyKind};
+use hot_reload::{FileWatcher, ReloadEvent, ReloadListener, ResourceKind};
+use post_processing::post_processor::PostProcessorChain;
+use post_processing::asset_metadata::AssetMetadata;
+use maskerad_core::filesystem::filesystem_error::FileSystemError;
+use resource_loader::{ResourceLoader, CustomType, extension_of};
+use cooking::cook_manifest::{AssetKind, CookManifest};
+use cooking::cooked_formats;
+use budgets::{MemorySize, ResidencyStats, ResourceCategory};
+use maskerad_core::allocators::stats::MemoryReport;
+use handle::HandleId;
+use resource_groups::{ResourceGroupManifest, GroupLoadReport};
 
+use std::any::{self, Any, TypeId};
+use std::collections::HashMap;
 use std::path::{PathBuf, Path};
-use std::io::{Read, Seek};
-use std::cell::{RefCell, Cell, Ref, RefMut};
-
-pub struct ResourceManager<'a, R: 'a + Read + Seek> {
-    double_ended_allocator: (MemStack, MemStack),
-    image_resources: RefCell<ImageRegistry<'a>>,
-    model_resources: RefCell<ModelRegistry<'a>>,
-    sound_resources: RefCell<SoundRegistry<'a, R>>,
-    marker_global_resource: Cell<usize>,
-    marker_global_resource_copy: Cell<usize>,
+use std::io::{self, Read, Seek};
+use std::mem;
+use std::cell::{Cell, RefCell, Ref, RefMut};
+
+/*
+RESOURCE MANAGER.
+
+Load the resources of the game, and keep them in a registry per resource type.
+
+Loading a resource returns a strong handle to it. Loading a resource already loaded doesn't read the reader,
+and returns a new handle to the loaded resource. A resource is freed when its last strong handle is dropped,
+the next time its registry is modified or when collect_unused() is called.
+
+A resource which can't be loaded (a corrupt file, an unsupported format...) leaves the registries untouched.
+The loading functions return a LoadingError with the path of the resource, or, if a placeholder has been
+configured for the resource type, a handle to the placeholder: a checkerboard texture, a unit cube or a silence.
+The game keeps running, and the error is logged.
+
+The load_*_async functions return a handle in the Loading state, and decode the resource on the worker
+threads of the job system given to set_spawner() (on the calling thread without job system).
+The resources are post processed by the loading jobs too, with the post processors and the metadata of the assets
+at the time of the request. They are published by update(), on the main thread. See the async_loading module.
+A resource which can't be loaded asynchronously is in the Failed state, and its handle gives access to the placeholder
+of its type, if there's one.
+
+load_model_with_dependencies() loads the external buffers and images of a model before the model, with a function
+opening the dependencies. The model keeps its dependencies loaded, and a dependency still referenced by a loaded
+model can't be unloaded. See the dependencies module. A dependency which can't be loaded isn't replaced by
+a placeholder: the loading of the model fails.
+
+hot_reload() reloads the resources modified on disk behind their handles, and notifies the reload listeners.
+See the hot_reload module.
+
+The decoded images, models and sounds go through the post processor chain of their type, configured per asset
+by the metadata given to set_asset_metadata() or by the metadata file next to the asset. See the post_processing module.
+
+The other resource types are loaded by the resource loaders given to register_loader(), with load(), get() and
+set_placeholder(). See the resource_loader module.
+
+The memory of the textures, meshes and audio can be budgeted with set_budget(): the unused resources of a budgeted
+category stay resident, and are evicted from the least recently used when the category exceeds its budget.
+See the budgets module.
+
+The load_cooked_* functions load the files cooked by the asset cooker, found in the manifest given to
+set_cook_manifest(). The cooked resources are registered with the path of their source asset. See the cooking module.
+The images compressed by the asset cooker are loaded by load_cooked_texture(), with the registered TextureLoader.
+*/
+
+//The paths of the placeholders in the registries.
+pub const IMAGE_PLACEHOLDER_PATH: &str = "maskerad_placeholders/image";
+pub const MODEL_PLACEHOLDER_PATH: &str = "maskerad_placeholders/model";
+pub const SOUND_PLACEHOLDER_PATH: &str = "maskerad_placeholders/sound";
+//The placeholder of a custom resource type has the name of the type after this directory.
+pub const PLACEHOLDER_DIRECTORY: &str = "maskerad_placeholders";
+
+//The strong handles to the placeholders, which keep them loaded.
+struct Placeholders<R: Read + Seek> {
+    image: Option<Handle<ImageResource>>,
+    model: Option<Handle<ModelResource>>,
+    sound: Option<Handle<SoundResource<R>>>,
 }
 
-impl<'a, R: 'a + Read + Seek> ResourceManager<'a, R> {
-    pub fn with_capacity(capacity: usize, capacity_copy: usize) -> Self {
-        ResourceManager {
-            double_ended_allocator: (MemStack::with_capacity(capacity/2, capacity_copy/2), MemStack::with_capacity(capacity/2, capacity_copy/2)),
-            image_resources: RefCell::new(ImageRegistry::new()),
-            model_resources: RefCell::new(ModelRegistry::new()),
-            sound_resources: RefCell::new(SoundRegistry::new()),
-            marker_global_resource: Cell::new(0),
-            marker_global_resource_copy: Cell::new(0),
+//The strong handles to the dependencies of a resource, which keep them loaded.
+#[derive(Default)]
+struct DependencyHandles {
+    buffers: Vec<Handle<BufferResource>>,
+    images: Vec<Handle<ImageResource>>,
+    //The images cooked as compressed textures.
+    textures: Vec<Handle<TextureResource>>,
+}
+
+//The strong handles to the resources of a loaded group, which keep them loaded.
+struct GroupHandles<R: Read + Seek> {
+    images: Vec<Handle<ImageResource>>,
+    models: Vec<Handle<ModelResource>>,
+    sounds: Vec<Handle<SoundResource<R>>>,
+    buffers: Vec<Handle<BufferResource>>,
+    //The handles to the resources of the registered types (Handle<T>).
+    custom: Vec<Box<dyn Any>>,
+}
+
+impl<R: Read + Seek> GroupHandles<R> {
+    fn new() -> Self {
+        GroupHandles {
+            images: Vec::new(),
+            models: Vec::new(),
+            sounds: Vec::new(),
+            buffers: Vec::new(),
+            custom: Vec::new(),
         }
     }
+}
 
-    pub fn set_marker_global_resources(&self, marker: usize) {
-        debug!("Setting the marker to the end of the global resources.");
-        self.marker_global_resource.set(marker);
+//The residency counters of a resource category.
+#[derive(Default)]
+struct ResidencyCounters {
+    high_water_mark: usize,
+    evictions: usize,
+}
+
+//Add the resources of the registry to the residency stats.
+fn add_residency<T: MemorySize>(registry: &Registry<T>, stats: &mut ResidencyStats) {
+    for (id, resource) in registry.resources() {
+        stats.add_resource(resource.memory_size(), registry.is_unused(id));
     }
+}
+
+//The least recently used resource of the registry, without handles outside the registry.
+fn least_recently_used<T>(registry: &RefCell<Registry<T>>) -> Option<(HandleId, u64)> {
+    registry.borrow().unused().first().cloned()
+}
+
+//Evict a resource. Return the bytes freed.
+fn evict<T: MemorySize>(registry: &RefCell<Registry<T>>, id: HandleId) -> Option<usize> {
+    let path = registry.borrow().path_of_id(id).map(Path::to_path_buf).unwrap_or_default();
+    debug!("Evicting the unused resource {} from the resource manager.", path.display());
+    registry.borrow_mut().evict(id).map(|resource| resource.memory_size())
+}
+
+//Open a resource of a group.
+fn open_group_resource<R, F>(open: &mut F, path: &Path, group: &str) -> ResourceManagerResult<R> where
+    F: FnMut(&Path) -> io::Result<R>,
+{
+    open(path).map_err(|io_error| {
+        error!("The resource {} of the group {} can't be opened: {}", path.display(), group, io_error);
+        ResourceManagerError::FilesystemError(format!("The resource {} of the group {} can't be opened.", path.display(), group), FileSystemError::from(io_error))
+    })
+}
 
-    pub fn marker_global_resources(&self) -> usize {
-        debug!("Getting the marker to the end of the global resources.");
-        self.marker_global_resource.get()
+//Return the placeholder if there's one, the loading error otherwise.
+fn placeholder_or_error<T>(placeholder: Option<Handle<T>>, path: &Path, error: ResourceError) -> ResourceManagerResult<Handle<T>> {
+    match placeholder {
+        Some(placeholder) => {
+            warn!("Could not load the resource {}, using a placeholder: {}", path.display(), error);
+            Ok(placeholder)
+        },
+        None => {
+            error!("Could not load the resource {}: {}", path.display(), error);
+            Err(ResourceManagerError::LoadingError(String::from("Could not load the resource."), path.to_path_buf(), error))
+        },
     }
+}
+
+pub struct ResourceManager<R: Read + Seek> {
+    image_resources: RefCell<ImageRegistry>,
+    model_resources: RefCell<ModelRegistry>,
+    sound_resources: RefCell<SoundRegistry<R>>,
+    buffer_resources: RefCell<BufferRegistry>,
+    dependency_graph: RefCell<DependencyGraph>,
+    dependency_handles: RefCell<HashMap<PathBuf, DependencyHandles>>,
+    file_watcher: RefCell<FileWatcher>,
+    reload_listeners: RefCell<Vec<ReloadListener>>,
+    image_post_processors: RefCell<PostProcessorChain<ImageResource>>,
+    model_post_processors: RefCell<PostProcessorChain<ModelResource>>,
+    sound_post_processors: RefCell<PostProcessorChain<SoundResource<R>>>,
+    asset_metadata: RefCell<HashMap<PathBuf, AssetMetadata>>,
+    custom_types: RefCell<HashMap<TypeId, CustomType<R>>>,
+    cook_manifest: RefCell<Option<CookManifest>>,
+    budgets: RefCell<HashMap<ResourceCategory, usize>>,
+    residency_counters: RefCell<HashMap<ResourceCategory, ResidencyCounters>>,
+    group_manifest: RefCell<ResourceGroupManifest>,
+    loaded_groups: RefCell<HashMap<String, GroupHandles<R>>>,
+    placeholders: RefCell<Placeholders<R>>,
+    spawner: RefCell<Option<Spawner>>,
+    pending_images: RefCell<PendingLoads<ImageResource>>,
+    pending_models: RefCell<PendingLoads<ModelResource>>,
+    pending_sounds: RefCell<PendingLoads<SoundResource<R>>>,
+    batches: RefCell<HashMap<BatchId, LoadProgress>>,
+    next_batch: Cell<u32>,
+}
 
-    pub fn set_marker_global_resources_copy(&self, marker: usize) {
-        debug!("Setting the marker to the end of the global resources (copy).");
-        self.marker_global_resource_copy .set(marker);
+impl<R: Read + Seek> Default for ResourceManager<R> {
+    fn default() -> Self {
+        debug!("Creating a default ResourceManager.");
+        ResourceManager {
+            image_resources: RefCell::new(ImageRegistry::new()),
+            model_resources: RefCell::new(ModelRegistry::new()),
+            sound_resources: RefCell::new(SoundRegistry::new()),
+            buffer_resources: RefCell::new(BufferRegistry::new()),
+            dependency_graph: RefCell::new(DependencyGraph::new()),
+            dependency_handles: RefCell::new(HashMap::new()),
+            file_watcher: RefCell::new(FileWatcher::new()),
+            reload_listeners: RefCell::new(Vec::new()),
+            image_post_processors: RefCell::new(PostProcessorChain::new()),
+            model_post_processors: RefCell::new(PostProcessorChain::new()),
+            sound_post_processors: RefCell::new(PostProcessorChain::new()),
+            asset_metadata: RefCell::new(HashMap::new()),
+            custom_types: RefCell::new(HashMap::new()),
+            cook_manifest: RefCell::new(None),
+            budgets: RefCell::new(HashMap::new()),
+            residency_counters: RefCell::new(HashMap::new()),
+            group_manifest: RefCell::new(ResourceGroupManifest::new()),
+            loaded_groups: RefCell::new(HashMap::new()),
+            placeholders: RefCell::new(Placeholders {
+                image: None,
+                model: None,
+                sound: None,
+            }),
+            spawner: RefCell::new(None),
+            pending_images: RefCell::new(PendingLoads::default()),
+            pending_models: RefCell::new(PendingLoads::default()),
+            pending_sounds: RefCell::new(PendingLoads::default()),
+            batches: RefCell::new(HashMap::new()),
+            next_batch: Cell::new(0),
+        }
     }
+}
 
-    pub fn marker_global_resources_copy(&self) -> usize {
-        debug!("Getting the marker to the end of the global resources (copy).");
-        self.marker_global_resource_copy.get()
+impl<R: Read + Seek> ResourceManager<R> {
+    pub fn new() -> Self {
+        Default::default()
     }
 
-    pub fn image_resources(&'a self) -> Ref<ImageRegistry> {
+    pub fn image_resources(&self) -> Ref<'_, ImageRegistry> {
         self.image_resources.borrow()
     }
 
-    pub fn image_resources_mut(&'a self) -> RefMut<ImageRegistry> {
+    pub fn image_resources_mut(&self) -> RefMut<'_, ImageRegistry> {
         self.image_resources.borrow_mut()
     }
 
-    pub fn model_resources(&'a self) -> Ref<ModelRegistry> {
+    pub fn model_resources(&self) -> Ref<'_, ModelRegistry> {
         self.model_resources.borrow()
     }
 
-    pub fn model_resources_mut(&'a self) -> RefMut<ModelRegistry> {
+    pub fn model_resources_mut(&self) -> RefMut<'_, ModelRegistry> {
         self.model_resources.borrow_mut()
     }
 
-    pub fn sound_resources(&'a self) -> Ref<SoundRegistry<R>> {
+    pub fn sound_resources(&self) -> Ref<'_, SoundRegistry<R>> {
         self.sound_resources.borrow()
     }
 
-    pub fn sound_resources_mut(&'a self) -> RefMut<SoundRegistry<R>> {
+    pub fn sound_resources_mut(&self) -> RefMut<'_, SoundRegistry<R>> {
         self.sound_resources.borrow_mut()
     }
 
-    pub fn load_image<P>(&'a self, path: P, reader: &mut R, requested_format: ColorFormat) -> ResourceManagerResult<()> where
+    pub fn buffer_resources(&self) -> Ref<'_, BufferRegistry> {
+        self.buffer_resources.borrow()
+    }
+
+    pub fn buffer_resources_mut(&self) -> RefMut<'_, BufferRegistry> {
+        self.buffer_resources.borrow_mut()
+    }
+
+    pub fn dependency_graph(&self) -> Ref<'_, DependencyGraph> {
+        self.dependency_graph.borrow()
+    }
+
+    pub fn image_post_processors_mut(&self) -> RefMut<'_, PostProcessorChain<ImageResource>> {
+        self.image_post_processors.borrow_mut()
+    }
+
+    pub fn model_post_processors_mut(&self) -> RefMut<'_, PostProcessorChain<ModelResource>> {
+        self.model_post_processors.borrow_mut()
+    }
+
+    pub fn sound_post_processors_mut(&self) -> RefMut<'_, PostProcessorChain<SoundResource<R>>> {
+        self.sound_post_processors.borrow_mut()
+    }
+
+    //The metadata of an asset, used instead of its metadata file. None to use the metadata file again.
+    pub fn set_asset_metadata<P>(&self, path: P, metadata: Option<AssetMetadata>) where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        match metadata {
+            Some(metadata) => {
+                self.asset_metadata.borrow_mut().insert(path, metadata);
+            },
+            None => {
+                self.asset_metadata.borrow_mut().remove(path.as_path());
+            },
+        }
+    }
+
+    //The metadata given to set_asset_metadata(), or the metadata file of the asset.
+    pub fn asset_metadata<P: AsRef<Path>>(&self, path: P) -> ResourceResult<AssetMetadata> {
+        match self.asset_metadata.borrow().get(path.as_ref()) {
+            Some(metadata) => Ok(metadata.clone()),
+            None => AssetMetadata::from_sidecar(path),
+        }
+    }
+
+    //Run the post processor chain on a decoded resource.
+    fn post_process<T>(&self, processors: &RefCell<PostProcessorChain<T>>, path: &Path, mut resource: T) -> ResourceResult<T> {
+        let processors = processors.borrow();
+        if !processors.is_empty() {
+            let metadata = self.asset_metadata(path)?;
+            processors.run(path, &mut resource, &metadata)?;
+        }
+        Ok(resource)
+    }
+
+    //The post processing of a resource loaded asynchronously, run by its loading job.
+    fn post_processing_job<T: 'static>(&self, processors: &RefCell<PostProcessorChain<T>>, path: &Path) -> impl FnOnce(T) -> ResourceResult<T> + Send + 'static {
+        let processors = processors.borrow().clone();
+        let metadata = if processors.is_empty() {
+            Ok(AssetMetadata::new())
+        } else {
+            self.asset_metadata(path)
+        };
+        let path = path.to_path_buf();
+
+        move |mut resource| {
+            if !processors.is_empty() {
+                processors.run(path.as_path(), &mut resource, &metadata?)?;
+            }
+            Ok(resource)
+        }
+    }
+
+    pub fn image(&self, handle: &Handle<ImageResource>) -> Option<Ref<'_, ImageResource>> {
+        Ref::filter_map(self.image_resources.borrow(), |registry| registry.get(handle)).ok()
+    }
+
+    pub fn model(&self, handle: &Handle<ModelResource>) -> Option<Ref<'_, ModelResource>> {
+        Ref::filter_map(self.model_resources.borrow(), |registry| registry.get(handle)).ok()
+    }
+
+    pub fn sound(&self, handle: &Handle<SoundResource<R>>) -> Option<RefMut<'_, SoundResource<R>>> {
+        RefMut::filter_map(self.sound_resources.borrow_mut(), |registry| registry.get_mut(handle)).ok()
+    }
+
+    pub fn buffer(&self, handle: &Handle<BufferResource>) -> Option<Ref<'_, BufferResource>> {
+        Ref::filter_map(self.buffer_resources.borrow(), |registry| registry.get(handle)).ok()
+    }
+
+    //The external buffers of a model loaded with its dependencies.
+    pub fn model_buffers(&self, handle: &Handle<ModelResource>) -> Vec<Handle<BufferResource>> {
+        let path = match self.model_resources.borrow().path(handle) {
+            Some(path) => path.to_path_buf(),
+            None => return Vec::new(),
+        };
+        self.dependency_handles.borrow().get(&path).map(|handles| handles.buffers.clone()).unwrap_or_default()
+    }
+
+    //The external images of a model loaded with its dependencies.
+    pub fn model_images(&self, handle: &Handle<ModelResource>) -> Vec<Handle<ImageResource>> {
+        let path = match self.model_resources.borrow().path(handle) {
+            Some(path) => path.to_path_buf(),
+            None => return Vec::new(),
+        };
+        self.dependency_handles.borrow().get(&path).map(|handles| handles.images.clone()).unwrap_or_default()
+    }
+
+    //The external images of a cooked model, cooked as compressed textures.
+    pub fn model_textures(&self, handle: &Handle<ModelResource>) -> Vec<Handle<TextureResource>> {
+        let path = match self.model_resources.borrow().path(handle) {
+            Some(path) => path.to_path_buf(),
+            None => return Vec::new(),
+        };
+        self.dependency_handles.borrow().get(&path).map(|handles| handles.textures.clone()).unwrap_or_default()
+    }
+
+    //The size hint is the size of the file, counted in the progress of the batch. None if it's unknown.
+    pub fn load_image_async<P, B>(&self, path: P, mut reader: R, requested_format: ColorFormat, size_hint: Option<u64>, batch: B) -> Handle<ImageResource> where
         P: AsRef<Path> + Into<PathBuf>,
+        B: Into<Option<BatchId>>,
+        R: Send + 'static,
     {
-        debug!("Loading image data with path {} in resource manager.", path.as_ref().display());
-        //Move the image in an allocator
-        let image_ref = self.double_ended_allocator.0.allocate( || {
-            ImageResource::from_reader(reader, requested_format).expect("Could not create an image resource from reader")
-        })?;
-        //Add in registry.
-        self.image_resources.borrow_mut().insert(path.as_ref(), image_ref);
+        let post_process = self.post_processing_job(&self.image_post_processors, path.as_ref());
+        self.load_async(&self.image_resources, &self.pending_images, path.as_ref(), size_hint.unwrap_or(0), batch.into(), move || {
+            ImageResource::from_reader(&mut reader, requested_format).and_then(post_process)
+        })
+    }
+
+    pub fn load_model_async<P, B>(&self, path: P, reader: R, size_hint: Option<u64>, batch: B) -> Handle<ModelResource> where
+        P: AsRef<Path> + Into<PathBuf>,
+        B: Into<Option<BatchId>>,
+        R: Send + 'static,
+    {
+        let post_process = self.post_processing_job(&self.model_post_processors, path.as_ref());
+        self.load_async(&self.model_resources, &self.pending_models, path.as_ref(), size_hint.unwrap_or(0), batch.into(), move || {
+            ModelResource::from_reader(reader).and_then(post_process)
+        })
+    }
+
+    pub fn load_sound_async<P, B>(&self, path: P, reader: R, size_hint: Option<u64>, batch: B) -> Handle<SoundResource<R>> where
+        P: AsRef<Path> + Into<PathBuf>,
+        B: Into<Option<BatchId>>,
+        R: Send + 'static,
+    {
+        let post_process = self.post_processing_job(&self.sound_post_processors, path.as_ref());
+        self.load_async(&self.sound_resources, &self.pending_sounds, path.as_ref(), size_hint.unwrap_or(0), batch.into(), move || {
+            SoundResource::from_reader(reader).and_then(post_process)
+        })
+    }
+
+    //The resource returned when an image can't be loaded. None to return an error instead.
+    pub fn set_image_placeholder(&self, placeholder: Option<ImageResource>) {
+        debug!("Setting the image placeholder.");
+        let mut registry = self.image_resources.borrow_mut();
+        let mut placeholders = self.placeholders.borrow_mut();
+        if let Some(previous) = placeholders.image.take() {
+            registry.remove(&previous);
+        }
+        placeholders.image = placeholder.map(|placeholder| registry.insert(IMAGE_PLACEHOLDER_PATH, placeholder));
+    }
+
+    pub fn set_model_placeholder(&self, placeholder: Option<ModelResource>) {
+        debug!("Setting the model placeholder.");
+        let mut registry = self.model_resources.borrow_mut();
+        let mut placeholders = self.placeholders.borrow_mut();
+        if let Some(previous) = placeholders.model.take() {
+            registry.remove(&previous);
+        }
+        placeholders.model = placeholder.map(|placeholder| registry.insert(MODEL_PLACEHOLDER_PATH, placeholder));
+    }
+
+    pub fn set_sound_placeholder(&self, placeholder: Option<SoundResource<R>>) {
+        debug!("Setting the sound placeholder.");
+        let mut registry = self.sound_resources.borrow_mut();
+        let mut placeholders = self.placeholders.borrow_mut();
+        if let Some(previous) = placeholders.sound.take() {
+            registry.remove(&previous);
+        }
+        placeholders.sound = placeholder.map(|placeholder| registry.insert(SOUND_PLACEHOLDER_PATH, placeholder));
+    }
+
+    //Use a 64x64 checkerboard, a unit cube and a silence as placeholders.
+    pub fn use_default_placeholders(&self) -> ResourceManagerResult<()> {
+        self.set_image_placeholder(Some(ImageResource::checkerboard(64, 8)));
+        self.set_model_placeholder(Some(ModelResource::unit_cube()?));
+        self.set_sound_placeholder(Some(SoundResource::silence()));
         Ok(())
     }
 
-    pub fn load_model<P>(&'a self, path: P, reader: R) -> ResourceManagerResult<()> where
+    pub fn image_placeholder(&self) -> Option<Handle<ImageResource>> {
+        self.placeholders.borrow().image.clone()
+    }
+
+    pub fn model_placeholder(&self) -> Option<Handle<ModelResource>> {
+        self.placeholders.borrow().model.clone()
+    }
+
+    pub fn sound_placeholder(&self) -> Option<Handle<SoundResource<R>>> {
+        self.placeholders.borrow().sound.clone()
+    }
+
+    pub fn load_image<P>(&self, path: P, reader: &mut R, requested_format: ColorFormat) -> ResourceManagerResult<Handle<ImageResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+    {
+        self.load_image_or(path, reader, requested_format, self.image_placeholder())
+    }
+
+    //Load an image, or return the given placeholder if it can't be loaded.
+    fn load_image_or<P>(&self, path: P, reader: &mut R, requested_format: ColorFormat, placeholder: Option<Handle<ImageResource>>) -> ResourceManagerResult<Handle<ImageResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+    {
+        if let Some(handle) = self.image_resources.borrow().handle(path.as_ref()) {
+            debug!("The image with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading image data with path {} in resource manager.", path.as_ref().display());
+        match ImageResource::from_reader(reader, requested_format).and_then(|image| self.post_process(&self.image_post_processors, path.as_ref(), image)) {
+            Ok(image) => Ok(self.image_resources.borrow_mut().insert(path, image)),
+            Err(resource_error) => placeholder_or_error(placeholder, path.as_ref(), resource_error),
+        }
+    }
+
+    pub fn load_model<P>(&self, path: P, reader: R) -> ResourceManagerResult<Handle<ModelResource>> where
         P: AsRef<Path> + Into<PathBuf>,
     {
+        if let Some(handle) = self.model_resources.borrow().handle(path.as_ref()) {
+            debug!("The model with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
         debug!("Loading model data with path {} in resource manager.", path.as_ref().display());
-        //create gltf.
-        let model_ref = self.double_ended_allocator.0.allocate(|| {
-            ModelResource::from_reader( reader).expect("Could not create a model resource from reader")
+        match ModelResource::from_reader(reader).and_then(|model| self.post_process(&self.model_post_processors, path.as_ref(), model)) {
+            Ok(model) => Ok(self.model_resources.borrow_mut().insert(path, model)),
+            Err(resource_error) => placeholder_or_error(self.model_placeholder(), path.as_ref(), resource_error),
+        }
+    }
+
+    pub fn load_sound<P>(&self, path: P, reader: R) -> ResourceManagerResult<Handle<SoundResource<R>>> where
+        P: AsRef<Path> + Into<PathBuf>,
+    {
+        if let Some(handle) = self.sound_resources.borrow().handle(path.as_ref()) {
+            debug!("The sound with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading sound data with path {} in resource manager.", path.as_ref().display());
+        match SoundResource::from_reader(reader).and_then(|sound| self.post_process(&self.sound_post_processors, path.as_ref(), sound)) {
+            Ok(sound) => Ok(self.sound_resources.borrow_mut().insert(path, sound)),
+            Err(resource_error) => placeholder_or_error(self.sound_placeholder(), path.as_ref(), resource_error),
+        }
+    }
+
+    pub fn load_buffer<P>(&self, path: P, reader: R) -> ResourceManagerResult<Handle<BufferResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+    {
+        if let Some(handle) = self.buffer_resources.borrow().handle(path.as_ref()) {
+            debug!("The buffer with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading buffer data with path {} in resource manager.", path.as_ref().display());
+        match BufferResource::from_reader(reader) {
+            Ok(buffer) => Ok(self.buffer_resources.borrow_mut().insert(path, buffer)),
+            Err(resource_error) => placeholder_or_error(None, path.as_ref(), resource_error),
+        }
+    }
+
+    //Register the loader of a resource type, and create the registry of the type.
+    //A resource type, or an extension, can't have two loaders.
+    pub fn register_loader<L>(&self, loader: L) -> ResourceManagerResult<()> where
+        L: ResourceLoader<R> + 'static,
+        L::Resource: 'static,
+    {
+        let mut custom_type = CustomType::new(loader);
+        //The resources of a budgeted category stay resident like the built-in ones.
+        if let Some(category) = custom_type.loader.category_any() {
+            custom_type.registry.set_retain_unused(self.budget(category).is_some());
+        }
+        let mut custom_types = self.custom_types.borrow_mut();
+        if custom_types.contains_key(&TypeId::of::<L::Resource>()) {
+            error!("A loader is already registered for the resource type {}.", custom_type.name);
+            return Err(ResourceManagerError::LoaderError(format!("A loader is already registered for the resource type {}.", custom_type.name)));
+        }
+        for extension in custom_type.extensions.iter() {
+            if let Some(other) = custom_types.values().find(|other| other.extensions.contains(extension)) {
+                error!("The extension {} is already loaded as {}.", extension, other.name);
+                return Err(ResourceManagerError::LoaderError(format!("The extension {} is already loaded as {}.", extension, other.name)));
+            }
+        }
+
+        debug!("Registering the loader of the resource type {}.", custom_type.name);
+        custom_types.insert(TypeId::of::<L::Resource>(), custom_type);
+        Ok(())
+    }
+
+    pub fn has_loader<T: 'static>(&self) -> bool {
+        self.custom_types.borrow().contains_key(&TypeId::of::<T>())
+    }
+
+    //The name of the registered resource type loaded from the files with the extension of the path.
+    pub fn resource_type_of<P: AsRef<Path>>(&self, path: P) -> Option<&'static str> {
+        let extension = extension_of(path)?;
+        self.custom_types.borrow().values().find(|custom_type| custom_type.extensions.contains(&extension)).map(|custom_type| custom_type.name)
+    }
+
+    //Load a resource of a type registered with register_loader().
+    pub fn load<T, P>(&self, path: P, reader: R) -> ResourceManagerResult<Handle<T>> where
+        T: 'static,
+        P: AsRef<Path> + Into<PathBuf>,
+    {
+        self.load_or(path, reader, self.placeholder::<T>())
+    }
+
+    //Load a resource of a registered type, or return the given placeholder if it can't be loaded.
+    fn load_or<T, P>(&self, path: P, mut reader: R, placeholder: Option<Handle<T>>) -> ResourceManagerResult<Handle<T>> where
+        T: 'static,
+        P: AsRef<Path> + Into<PathBuf>,
+    {
+        let loader = {
+            let custom_types = self.custom_types.borrow();
+            let custom_type = match custom_types.get(&TypeId::of::<T>()) {
+                Some(custom_type) => custom_type,
+                None => {
+                    error!("No loader is registered for the resource type {}.", any::type_name::<T>());
+                    return Err(ResourceManagerError::LoaderError(format!("No loader is registered for the resource type {}.", any::type_name::<T>())));
+                },
+            };
+            if let Some(extension) = extension_of(path.as_ref()) {
+                if let Some(other) = custom_types.values().find(|other| other.extensions.contains(&extension)) {
+                    if other.name != custom_type.name {
+                        error!("The resource {} is loaded as {}, not as {}.", path.as_ref().display(), other.name, custom_type.name);
+                        return Err(ResourceManagerError::LoaderError(format!("The files with the extension {} are loaded as {}, not as {}.", extension, other.name, custom_type.name)));
+                    }
+                }
+            }
+            if let Some(handle) = custom_type.registry::<T>().and_then(|registry| registry.handle(path.as_ref())) {
+                debug!("The {} with path {} is already loaded.", custom_type.name, path.as_ref().display());
+                return Ok(handle);
+            }
+            //The loader is called without borrowing the registries: it may use the resource manager.
+            custom_type.loader.clone()
+        };
+
+        debug!("Loading {} data with path {} in resource manager.", any::type_name::<T>(), path.as_ref().display());
+        match loader.load_any(&mut reader, path.as_ref()) {
+            Ok(resource) => {
+                let resource = resource.downcast::<T>().map_err(|_| {
+                    ResourceManagerError::LoaderError(format!("The loader of {} returned another resource type.", any::type_name::<T>()))
+                })?;
+                let mut custom_types = self.custom_types.borrow_mut();
+                custom_types.get_mut(&TypeId::of::<T>())
+                    .and_then(|custom_type| custom_type.registry_mut::<T>())
+                    .map(|registry| registry.insert(path, *resource))
+                    .ok_or_else(|| ResourceManagerError::LoaderError(format!("No loader is registered for the resource type {}.", any::type_name::<T>())))
+            },
+            Err(resource_error) => placeholder_or_error(placeholder, path.as_ref(), resource_error),
+        }
+    }
+
+    pub fn get<T: 'static>(&self, handle: &Handle<T>) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.custom_types.borrow(), |custom_types| {
+            custom_types.get(&TypeId::of::<T>()).and_then(|custom_type| custom_type.registry::<T>()).and_then(|registry| registry.get(handle))
+        }).ok()
+    }
+
+    pub fn get_mut<T: 'static>(&self, handle: &Handle<T>) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.custom_types.borrow_mut(), |custom_types| {
+            custom_types.get_mut(&TypeId::of::<T>()).and_then(|custom_type| custom_type.registry_mut::<T>()).and_then(|registry| registry.get_mut(handle))
+        }).ok()
+    }
+
+    //The registry of a registered resource type.
+    pub fn registry<T: 'static>(&self) -> Option<Ref<'_, Registry<T>>> {
+        Ref::filter_map(self.custom_types.borrow(), |custom_types| {
+            custom_types.get(&TypeId::of::<T>()).and_then(|custom_type| custom_type.registry::<T>())
+        }).ok()
+    }
+
+    pub fn registry_mut<T: 'static>(&self) -> Option<RefMut<'_, Registry<T>>> {
+        RefMut::filter_map(self.custom_types.borrow_mut(), |custom_types| {
+            custom_types.get_mut(&TypeId::of::<T>()).and_then(|custom_type| custom_type.registry_mut::<T>())
+        }).ok()
+    }
+
+    //The resource returned when a resource of a registered type can't be loaded. None to return an error instead.
+    pub fn set_placeholder<T: 'static>(&self, placeholder: Option<T>) -> ResourceManagerResult<()> {
+        debug!("Setting the {} placeholder.", any::type_name::<T>());
+        let mut custom_types = self.custom_types.borrow_mut();
+        let custom_type = custom_types.get_mut(&TypeId::of::<T>()).ok_or_else(|| {
+            ResourceManagerError::LoaderError(format!("No loader is registered for the resource type {}.", any::type_name::<T>()))
         })?;
-        //Add in registry.
-        self.model_resources.borrow_mut().insert(path.as_ref(), model_ref);
+        let path = Path::new(PLACEHOLDER_DIRECTORY).join(custom_type.name);
+        let previous = custom_type.placeholder.take().and_then(|previous| previous.downcast::<Handle<T>>().ok());
+        let placeholder = match custom_type.registry_mut::<T>() {
+            Some(registry) => {
+                if let Some(previous) = previous {
+                    registry.remove(&previous);
+                }
+                placeholder.map(|placeholder| registry.insert(path, placeholder))
+            },
+            None => None,
+        };
+        custom_type.placeholder = placeholder.map(|handle| Box::new(handle) as Box<dyn Any>);
         Ok(())
     }
 
-    pub fn load_sound<P>(&'a self, path: P, reader: R) -> ResourceManagerResult<()> where
+    pub fn placeholder<T: 'static>(&self) -> Option<Handle<T>> {
+        self.custom_types.borrow().get(&TypeId::of::<T>())
+            .and_then(|custom_type| custom_type.placeholder.as_ref())
+            .and_then(|placeholder| placeholder.downcast_ref::<Handle<T>>())
+            .cloned()
+    }
+
+    //Load a resource of a registered type, found from the extension of its path. Return a strong handle (a Handle<T>).
+    fn load_any<F>(&self, path: &Path, open: F) -> ResourceManagerResult<Box<dyn Any>> where
+        F: FnOnce(&Path) -> ResourceManagerResult<R>,
+    {
+        let extension = extension_of(path).unwrap_or_default();
+        let (type_id, loader) = {
+            let custom_types = self.custom_types.borrow();
+            let (type_id, custom_type) = match custom_types.iter().find(|&(_, custom_type)| custom_type.extensions.contains(&extension)) {
+                Some(custom_type) => custom_type,
+                None => {
+                    error!("No loader is registered for the resource {}.", path.display());
+                    return Err(ResourceManagerError::LoaderError(format!("No loader is registered for the extension {}.", extension)));
+                },
+            };
+            if let Some(handle) = custom_type.registry.handle_any(path) {
+                debug!("The {} with path {} is already loaded.", custom_type.name, path.display());
+                return Ok(handle);
+            }
+            (*type_id, custom_type.loader.clone())
+        };
+
+        debug!("Loading data with path {} in resource manager.", path.display());
+        let mut reader = open(path)?;
+        let resource = loader.load_any(&mut reader, path);
+        let mut custom_types = self.custom_types.borrow_mut();
+        let custom_type = custom_types.get_mut(&type_id).ok_or_else(|| {
+            ResourceManagerError::LoaderError(format!("No loader is registered for the extension {}.", extension))
+        })?;
+        match resource {
+            Ok(resource) => {
+                custom_type.registry.insert_any(path, resource).ok_or_else(|| {
+                    ResourceManagerError::LoaderError(format!("The loader of {} returned another resource type.", custom_type.name))
+                })
+            },
+            Err(resource_error) => {
+                //The placeholder is in the registry, after the placeholder directory.
+                let placeholder = custom_type.placeholder.as_ref()
+                    .and_then(|_| custom_type.registry.handle_any(Path::new(PLACEHOLDER_DIRECTORY).join(custom_type.name).as_path()));
+                match placeholder {
+                    Some(placeholder) => {
+                        warn!("Could not load the resource {}, using a placeholder: {}", path.display(), resource_error);
+                        Ok(placeholder)
+                    },
+                    None => {
+                        error!("Could not load the resource {}: {}", path.display(), resource_error);
+                        Err(ResourceManagerError::LoadingError(String::from("Could not load the resource."), path.to_path_buf(), resource_error))
+                    },
+                }
+            },
+        }
+    }
+
+    //The manifest of the resource groups loaded by load_group().
+    pub fn set_group_manifest(&self, manifest: ResourceGroupManifest) {
+        *self.group_manifest.borrow_mut() = manifest;
+    }
+
+    pub fn group_manifest(&self) -> Ref<'_, ResourceGroupManifest> {
+        self.group_manifest.borrow()
+    }
+
+    pub fn is_group_loaded(&self, name: &str) -> bool {
+        self.loaded_groups.borrow().contains_key(name)
+    }
+
+    //The names of the loaded groups, sorted.
+    pub fn loaded_groups(&self) -> Vec<String> {
+        let mut groups: Vec<String> = self.loaded_groups.borrow().keys().cloned().collect();
+        groups.sort();
+        groups
+    }
+
+    //Load the resources of a group of the group manifest. The resources are opened with the open function, from
+    //their path. The resources already loaded are shared. If a resource can't be loaded, the group isn't loaded.
+    pub fn load_group<F>(&self, name: &str, mut open: F) -> ResourceManagerResult<GroupLoadReport> where
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        let paths = match self.group_manifest.borrow().group(name) {
+            Some(paths) => paths.to_vec(),
+            None => {
+                error!("The group {} is not in the group manifest.", name);
+                return Err(ResourceManagerError::GroupError(String::from("The group is not in the group manifest."), String::from(name)));
+            },
+        };
+        if self.is_group_loaded(name) {
+            debug!("The group {} is already loaded.", name);
+            return Ok(GroupLoadReport {
+                loaded: Vec::new(),
+                shared: paths,
+            });
+        }
+
+        debug!("Loading the group {} in resource manager.", name);
+        let mut handles = GroupHandles::new();
+        let mut report = GroupLoadReport::default();
+        for path in paths {
+            let kind = extension_of(path.as_path()).and_then(|extension| AssetKind::from_extension(extension.as_str()));
+            let shared = match kind {
+                Some(AssetKind::Image) => {
+                    let loaded = self.image_resources.borrow().handle(path.as_path());
+                    let shared = loaded.is_some();
+                    let handle = match loaded {
+                        Some(handle) => handle,
+                        None => self.load_image(path.as_path(), &mut open_group_resource(&mut open, path.as_path(), name)?, ColorFormat::Auto)?,
+                    };
+                    handles.images.push(handle);
+                    shared
+                },
+                Some(AssetKind::Model) => {
+                    let loaded = self.model_resources.borrow().handle(path.as_path());
+                    let shared = loaded.is_some();
+                    let handle = match loaded {
+                        Some(handle) => handle,
+                        None => self.load_model_with_dependencies(path.as_path(), open_group_resource(&mut open, path.as_path(), name)?, &mut open)?,
+                    };
+                    handles.models.push(handle);
+                    shared
+                },
+                Some(AssetKind::Sound) => {
+                    let loaded = self.sound_resources.borrow().handle(path.as_path());
+                    let shared = loaded.is_some();
+                    let handle = match loaded {
+                        Some(handle) => handle,
+                        None => self.load_sound(path.as_path(), open_group_resource(&mut open, path.as_path(), name)?)?,
+                    };
+                    handles.sounds.push(handle);
+                    shared
+                },
+                Some(AssetKind::Buffer) => {
+                    let loaded = self.buffer_resources.borrow().handle(path.as_path());
+                    let shared = loaded.is_some();
+                    let handle = match loaded {
+                        Some(handle) => handle,
+                        None => self.load_buffer(path.as_path(), open_group_resource(&mut open, path.as_path(), name)?)?,
+                    };
+                    handles.buffers.push(handle);
+                    shared
+                },
+                //The source assets have no texture kind: only their cooked file has.
+                Some(AssetKind::Texture) | None => {
+                    let mut opened = false;
+                    let handle = self.load_any(path.as_path(), |path| {
+                        opened = true;
+                        open_group_resource(&mut open, path, name)
+                    })?;
+                    handles.custom.push(handle);
+                    !opened
+                },
+            };
+            if shared {
+                report.shared.push(path);
+            } else {
+                report.loaded.push(path);
+            }
+        }
+
+        self.loaded_groups.borrow_mut().insert(String::from(name), handles);
+        Ok(report)
+    }
+
+    //Unload a group. Its resources are freed, unless they're used by another loaded group or by the game.
+    //Return false if the group isn't loaded.
+    pub fn unload_group(&self, name: &str) -> bool {
+        match self.loaded_groups.borrow_mut().remove(name) {
+            Some(_) => debug!("Unloading the group {} from the resource manager.", name),
+            None => return false,
+        }
+        self.collect_unused();
+        true
+    }
+
+    //Load the groups of the next level, then unload the groups of the previous level which aren't in the next level.
+    //Only the resources which aren't shared by the two levels are loaded and freed.
+    //If a group can't be loaded, no group is unloaded.
+    pub fn transition_groups<F>(&self, unload: &[&str], load: &[&str], mut open: F) -> ResourceManagerResult<GroupLoadReport> where
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        debug!("Transitioning from the groups {} to the groups {}.", unload.join(", "), load.join(", "));
+        let mut report = GroupLoadReport::default();
+        for name in load {
+            let group_report = self.load_group(name, &mut open)?;
+            report.loaded.extend(group_report.loaded);
+            report.shared.extend(group_report.shared);
+        }
+        for name in unload.iter().filter(|name| !load.contains(name)) {
+            self.unload_group(name);
+        }
+        Ok(report)
+    }
+
+    //The manifest of the cooked assets loaded by the load_cooked_* functions.
+    pub fn set_cook_manifest(&self, manifest: Option<CookManifest>) {
+        *self.cook_manifest.borrow_mut() = manifest;
+    }
+
+    pub fn cook_manifest(&self) -> Option<Ref<'_, CookManifest>> {
+        Ref::filter_map(self.cook_manifest.borrow(), |manifest| manifest.as_ref()).ok()
+    }
+
+    //Open the cooked file of a source asset, found in the cook manifest.
+    fn open_cooked<F>(&self, path: &Path, kind: AssetKind, open: &mut F) -> ResourceManagerResult<R> where
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        let output = match self.cook_manifest().as_ref().and_then(|manifest| manifest.entry(path)) {
+            Some(entry) if entry.kind == kind => entry.output.clone(),
+            Some(entry) => {
+                error!("The resource {} has been cooked as a {}, not as a {}.", path.display(), entry.kind.name(), kind.name());
+                return Err(ResourceManagerError::NotCooked(format!("The resource has been cooked as a {}, not as a {}.", entry.kind.name(), kind.name()), path.to_path_buf()));
+            },
+            None => {
+                error!("The resource {} is not in the cook manifest.", path.display());
+                return Err(ResourceManagerError::NotCooked(String::from("The resource is not in the cook manifest."), path.to_path_buf()));
+            },
+        };
+        open(output.as_path()).map_err(|io_error| {
+            ResourceManagerError::FilesystemError(format!("The cooked file {} can't be opened.", output.display()), FileSystemError::from(io_error))
+        })
+    }
+
+    //Load the cooked image of the source asset at path. The cooked file is opened with the open function.
+    pub fn load_cooked_image<P, F>(&self, path: P, open: F) -> ResourceManagerResult<Handle<ImageResource>> where
         P: AsRef<Path> + Into<PathBuf>,
+        F: FnMut(&Path) -> io::Result<R>,
     {
-        debug!("Loading sound data with path {} in resource manager.", path.as_ref().display());
-        //create ogg.
-        let sound_ref = self.double_ended_allocator.0.allocate(|| {
-            SoundResource::from_reader(reader).expect("Could not create a sound resource from reader")
+        self.load_cooked_image_or(path, open, self.image_placeholder())
+    }
+
+    //Load a cooked image, or return the given placeholder if it can't be read.
+    fn load_cooked_image_or<P, F>(&self, path: P, mut open: F, placeholder: Option<Handle<ImageResource>>) -> ResourceManagerResult<Handle<ImageResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        if let Some(handle) = self.image_resources.borrow().handle(path.as_ref()) {
+            debug!("The image with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading cooked image data with path {} in resource manager.", path.as_ref().display());
+        let mut reader = self.open_cooked(path.as_ref(), AssetKind::Image, &mut open)?;
+        match cooked_formats::read_image(&mut reader) {
+            Ok(image) => Ok(self.image_resources.borrow_mut().insert(path, image)),
+            Err(resource_error) => placeholder_or_error(placeholder, path.as_ref(), resource_error),
+        }
+    }
+
+    pub fn load_cooked_sound<P, F>(&self, path: P, mut open: F) -> ResourceManagerResult<Handle<SoundResource<R>>> where
+        P: AsRef<Path> + Into<PathBuf>,
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        if let Some(handle) = self.sound_resources.borrow().handle(path.as_ref()) {
+            debug!("The sound with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading cooked sound data with path {} in resource manager.", path.as_ref().display());
+        let mut reader = self.open_cooked(path.as_ref(), AssetKind::Sound, &mut open)?;
+        match cooked_formats::read_sound(&mut reader) {
+            Ok(sound) => Ok(self.sound_resources.borrow_mut().insert(path, SoundResource::from_decoded(sound))),
+            Err(resource_error) => placeholder_or_error(self.sound_placeholder(), path.as_ref(), resource_error),
+        }
+    }
+
+    //Load the image at path cooked as a compressed texture, with the TextureLoader given to register_loader().
+    pub fn load_cooked_texture<P, F>(&self, path: P, open: F) -> ResourceManagerResult<Handle<TextureResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        self.load_cooked_texture_or(path, open, self.placeholder::<TextureResource>())
+    }
+
+    //Load a cooked texture, or return the given placeholder if it can't be read.
+    fn load_cooked_texture_or<P, F>(&self, path: P, mut open: F, placeholder: Option<Handle<TextureResource>>) -> ResourceManagerResult<Handle<TextureResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        let loaded = self.registry::<TextureResource>().and_then(|registry| registry.handle(path.as_ref()));
+        if let Some(handle) = loaded {
+            debug!("The texture with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading cooked texture data with path {} in resource manager.", path.as_ref().display());
+        let reader = self.open_cooked(path.as_ref(), AssetKind::Texture, &mut open)?;
+        self.load_or(path, reader, placeholder)
+    }
+
+    //Load the cooked model of the source asset at path, with its cooked dependencies. The dependencies missing
+    //from the cook manifest are opened from their source path.
+    pub fn load_cooked_model<P, F>(&self, path: P, mut open: F) -> ResourceManagerResult<Handle<ModelResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        if let Some(handle) = self.model_resources.borrow().handle(path.as_ref()) {
+            debug!("The model with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading cooked model data with path {} in resource manager.", path.as_ref().display());
+        let reader = self.open_cooked(path.as_ref(), AssetKind::Model, &mut open)?;
+        //Like the other cooked resources, the model is not post processed at runtime.
+        let model = match ModelResource::from_reader(reader) {
+            Ok(model) => model,
+            Err(resource_error) => return placeholder_or_error(self.model_placeholder(), path.as_ref(), resource_error),
+        };
+        let handles = self.load_model_dependencies(path.as_ref(), &model, &mut open, true)?;
+        Ok(self.insert_model_with_dependencies(path.as_ref(), model, handles))
+    }
+
+    //Load a model and its external buffers and images. The dependencies are opened with the open function,
+    //from their path, and are loaded before the model. The dependencies already loaded are shared.
+    pub fn load_model_with_dependencies<P, F>(&self, path: P, reader: R, mut open: F) -> ResourceManagerResult<Handle<ModelResource>> where
+        P: AsRef<Path> + Into<PathBuf>,
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        if let Some(handle) = self.model_resources.borrow().handle(path.as_ref()) {
+            debug!("The model with path {} is already loaded.", path.as_ref().display());
+            return Ok(handle);
+        }
+
+        debug!("Loading model data with path {} and its dependencies in resource manager.", path.as_ref().display());
+        let model = match ModelResource::from_reader(reader) {
+            Ok(model) => model,
+            Err(resource_error) => return placeholder_or_error(self.model_placeholder(), path.as_ref(), resource_error),
+        };
+        //The model is post processed after the loading of its dependencies, to read its external buffers.
+        let handles = self.load_model_dependencies(path.as_ref(), &model, &mut open, false)?;
+        match self.post_process_model(path.as_ref(), model) {
+            Ok(model) => Ok(self.insert_model_with_dependencies(path.as_ref(), model, handles)),
+            Err(resource_error) => placeholder_or_error(self.model_placeholder(), path.as_ref(), resource_error),
+        }
+    }
+
+    //Run the post processors of the models, with the data of the external buffers loaded as dependencies.
+    fn post_process_model(&self, path: &Path, mut model: ModelResource) -> ResourceResult<ModelResource> {
+        if self.model_post_processors.borrow().is_empty() {
+            return Ok(model);
+        }
+        let external_buffers = {
+            let buffer_resources = self.buffer_resources.borrow();
+            model.gltf().buffers().filter_map(|buffer| {
+                let dependency = Dependency::from_uri(path, buffer.uri(), DependencyKind::Buffer)?;
+                let handle = buffer_resources.handle(dependency.path.as_path())?;
+                buffer_resources.get(&handle).map(|data| (buffer.index(), data.data().to_vec()))
+            }).collect()
+        };
+        model.set_external_buffers(external_buffers);
+        let mut model = self.post_process(&self.model_post_processors, path, model)?;
+        model.set_external_buffers(HashMap::new());
+        Ok(model)
+    }
+
+    //Load the dependencies of the model. The dependencies already loaded are shared. With cooked, the dependencies
+    //in the cook manifest are loaded from their cooked file. The dependencies aren't replaced by their placeholders.
+    fn load_model_dependencies<F>(&self, path: &Path, model: &ModelResource, open: &mut F, cooked: bool) -> ResourceManagerResult<DependencyHandles> where
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        let dependencies = model.dependencies(path);
+
+        for dependency in dependencies.iter() {
+            let cycle = if dependency.path.as_path() == path {
+                Some(vec![path.to_path_buf(), dependency.path.clone()])
+            } else {
+                self.dependency_graph.borrow().dependency_chain(dependency.path.as_path(), path).map(|chain| {
+                    let mut cycle = vec![path.to_path_buf()];
+                    cycle.extend(chain);
+                    cycle
+                })
+            };
+            if let Some(cycle) = cycle {
+                error!("The model {} depends on itself.", path.display());
+                return Err(ResourceManagerError::DependencyCycle(String::from("The model depends on itself."), cycle));
+            }
+        }
+
+        let open_dependency = |open: &mut F, dependency_path: &Path| {
+            open(dependency_path).map_err(|io_error| {
+                error!("The dependency {} of the model {} can't be opened: {}", dependency_path.display(), path.display(), io_error);
+                ResourceManagerError::MissingDependency(format!("A dependency of the model {} can't be opened.", path.display()), dependency_path.to_path_buf(), io_error)
+            })
+        };
+
+        let mut handles = DependencyHandles::default();
+        for dependency in dependencies.iter() {
+            let dependency_path = dependency.path.as_path();
+            let cooked_kind = if cooked {
+                self.cook_manifest().and_then(|manifest| manifest.entry(dependency_path).map(|entry| entry.kind))
+            } else {
+                None
+            };
+            let cooked_dependency = cooked_kind.is_some();
+            match dependency.kind {
+                DependencyKind::Buffer => {
+                    let loaded = self.buffer_resources.borrow().handle(dependency_path);
+                    let handle = match loaded {
+                        Some(handle) => handle,
+                        None if cooked_dependency => self.load_buffer(dependency_path, self.open_cooked(dependency_path, AssetKind::Buffer, open)?)?,
+                        None => self.load_buffer(dependency_path, open_dependency(open, dependency_path)?)?,
+                    };
+                    handles.buffers.push(handle);
+                },
+                //The images cooked as compressed textures are loaded with the TextureLoader.
+                DependencyKind::Image if cooked_kind == Some(AssetKind::Texture) => {
+                    handles.textures.push(self.load_cooked_texture_or(dependency_path, &mut *open, None)?);
+                },
+                DependencyKind::Image => {
+                    let loaded = self.image_resources.borrow().handle(dependency_path);
+                    let handle = match loaded {
+                        Some(handle) => handle,
+                        None if cooked_dependency => self.load_cooked_image_or(dependency_path, &mut *open, None)?,
+                        None => self.load_image_or(dependency_path, &mut open_dependency(open, dependency_path)?, ColorFormat::Auto, None)?,
+                    };
+                    handles.images.push(handle);
+                },
+            }
+        }
+        Ok(handles)
+    }
+
+    //Insert the model with the handles to its dependencies. If the model is already loaded, it is replaced
+    //behind its handles, and its dependencies are updated.
+    fn insert_model_with_dependencies(&self, path: &Path, model: ModelResource, handles: DependencyHandles) -> Handle<ModelResource> {
+        let dependencies = model.dependencies(path);
+        let handle = self.model_resources.borrow_mut().insert(path, model);
+        self.dependency_graph.borrow_mut().add(path, dependencies.into_iter().map(|dependency| dependency.path).collect());
+        self.dependency_handles.borrow_mut().insert(path.to_path_buf(), handles);
+        handle
+    }
+
+    //Called with the event of every hot reload.
+    pub fn add_reload_listener<F>(&self, listener: F) where
+        F: FnMut(&ReloadEvent) + 'static,
+    {
+        self.reload_listeners.borrow_mut().push(Box::new(listener));
+    }
+
+    //Reload the resources whose file, or metadata file, has been modified since the previous call. The modified files
+    //are opened with the open function. The first call only records the modification times of the files.
+    //The listeners can add listeners, or load resources, but not call hot_reload().
+    pub fn hot_reload<F>(&self, mut open: F) -> Vec<ReloadEvent> where
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        self.collect_unused();
+        let mut resources: HashMap<PathBuf, ResourceKind> = HashMap::new();
+        resources.extend(self.buffer_resources.borrow().paths().into_iter().map(|path| (path.to_path_buf(), ResourceKind::Buffer)));
+        resources.extend(self.image_resources.borrow().paths().into_iter().map(|path| (path.to_path_buf(), ResourceKind::Image)));
+        resources.extend(self.sound_resources.borrow().paths().into_iter().map(|path| (path.to_path_buf(), ResourceKind::Sound)));
+        resources.extend(self.model_resources.borrow().paths().into_iter().map(|path| (path.to_path_buf(), ResourceKind::Model)));
+        for custom_type in self.custom_types.borrow().values() {
+            resources.extend(custom_type.registry.paths().into_iter().map(|path| (path, ResourceKind::Custom(custom_type.name))));
+        }
+
+        //The metadata files are watched with the resources: they change the post processing.
+        let mut watched: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for path in resources.keys() {
+            watched.insert(AssetMetadata::sidecar_path(path), path.clone());
+            watched.insert(path.clone(), path.clone());
+        }
+        let mut modified: Vec<PathBuf> = Vec::new();
+        for path in self.file_watcher.borrow_mut().poll(watched.keys().map(|path| path.as_path())) {
+            let resource = &watched[&path];
+            if !modified.contains(resource) {
+                modified.push(resource.clone());
+            }
+        }
+        //The models are reloaded after their dependencies.
+        modified.sort_by_key(|path| resources[path] == ResourceKind::Model);
+
+        let mut events = Vec::with_capacity(modified.len());
+        for path in modified {
+            let kind = resources[&path];
+            debug!("Hot reloading the resource {}.", path.display());
+            let result = self.reload(path.as_path(), kind, &mut open);
+            if let Err(ref reload_error) = result {
+                error!("Could not reload the resource {}, the previous version is kept: {}", path.display(), reload_error);
+            }
+
+            let dependents = self.dependency_graph.borrow().dependents(path.as_path()).into_iter().map(|dependent| dependent.to_path_buf()).collect();
+            let event = ReloadEvent {
+                path,
+                kind,
+                dependents,
+                result,
+            };
+            //The listeners are called without borrowing the list: they may add listeners.
+            let mut listeners = mem::take(&mut *self.reload_listeners.borrow_mut());
+            for listener in listeners.iter_mut() {
+                listener(&event);
+            }
+            let mut added = mem::take(&mut *self.reload_listeners.borrow_mut());
+            listeners.append(&mut added);
+            *self.reload_listeners.borrow_mut() = listeners;
+            events.push(event);
+        }
+        events
+    }
+
+    //Decode the new version of the resource, and replace the previous version behind its handles.
+    fn reload<F>(&self, path: &Path, kind: ResourceKind, open: &mut F) -> ResourceManagerResult<()> where
+        F: FnMut(&Path) -> io::Result<R>,
+    {
+        let mut reader = open(path).map_err(|io_error| {
+            ResourceManagerError::FilesystemError(format!("The resource {} can't be opened.", path.display()), FileSystemError::from(io_error))
         })?;
-        //Add in registry
-        self.sound_resources.borrow_mut().insert(path.as_ref(), sound_ref);
+        let loading_error = |resource_error| {
+            ResourceManagerError::LoadingError(String::from("Could not reload the resource."), path.to_path_buf(), resource_error)
+        };
+
+        match kind {
+            ResourceKind::Image => {
+                let handle = self.image_resources.borrow().handle(path);
+                //The image keeps the color format it has been loaded with.
+                let color_format = handle.as_ref()
+                    .and_then(|handle| self.image(handle).map(|image| image.color_format()))
+                    .unwrap_or(ColorFormat::Auto);
+                let image = ImageResource::from_reader(&mut reader, color_format)
+                    .and_then(|image| self.post_process(&self.image_post_processors, path, image))
+                    .map_err(loading_error)?;
+                self.image_resources.borrow_mut().insert(path, image);
+            },
+            ResourceKind::Model => {
+                let model = ModelResource::from_reader(reader).map_err(loading_error)?;
+                if self.dependency_graph.borrow().contains(path) {
+                    let handles = self.load_model_dependencies(path, &model, open, false)?;
+                    let model = self.post_process_model(path, model).map_err(loading_error)?;
+                    self.insert_model_with_dependencies(path, model, handles);
+                } else {
+                    let model = self.post_process(&self.model_post_processors, path, model).map_err(loading_error)?;
+                    self.model_resources.borrow_mut().insert(path, model);
+                }
+            },
+            ResourceKind::Sound => {
+                let sound = SoundResource::from_reader(reader)
+                    .and_then(|sound| self.post_process(&self.sound_post_processors, path, sound))
+                    .map_err(loading_error)?;
+                self.sound_resources.borrow_mut().insert(path, sound);
+            },
+            ResourceKind::Buffer => {
+                let buffer = BufferResource::from_reader(reader).map_err(loading_error)?;
+                self.buffer_resources.borrow_mut().insert(path, buffer);
+            },
+            ResourceKind::Custom(name) => {
+                let loader = self.custom_types.borrow().values()
+                    .find(|custom_type| custom_type.name == name)
+                    .map(|custom_type| custom_type.loader.clone())
+                    .ok_or_else(|| ResourceManagerError::LoaderError(format!("No loader is registered for the resource type {}.", name)))?;
+                let resource = loader.load_any(&mut reader, path).map_err(loading_error)?;
+                if let Some(custom_type) = self.custom_types.borrow_mut().values_mut().find(|custom_type| custom_type.name == name) {
+                    custom_type.registry.replace(path, resource);
+                }
+            },
+        }
         Ok(())
     }
 
+    //Unload the resource with the given path, even if it has strong handles. Return false if no resource has this path.
+    //A resource still referenced by another loaded resource can't be unloaded.
+    pub fn unload<P>(&self, path: P) -> ResourceManagerResult<bool> where
+        P: AsRef<Path>,
+    {
+        self.collect_unused();
+        let path = path.as_ref();
+        if self.dependency_graph.borrow().is_referenced(path) {
+            let dependents: Vec<String> = self.dependency_graph.borrow().dependents(path).iter().map(|dependent| dependent.display().to_string()).collect();
+            error!("The resource {} is still referenced by {}.", path.display(), dependents.join(", "));
+            return Err(ResourceManagerError::ReferencedResource(format!("The resource is still referenced by {}.", dependents.join(", ")), path.to_path_buf()));
+        }
+
+        debug!("Unloading the resource with path {} from the resource manager.", path.display());
+        let image = self.image_resources.borrow().handle(path);
+        let model = self.model_resources.borrow().handle(path);
+        let sound = self.sound_resources.borrow().handle(path);
+        let buffer = self.buffer_resources.borrow().handle(path);
+        let mut unloaded = false;
+        if let Some(image) = image {
+            unloaded |= self.image_resources.borrow_mut().remove(&image).is_some();
+        }
+        if let Some(model) = model {
+            unloaded |= self.model_resources.borrow_mut().remove(&model).is_some();
+        }
+        if let Some(sound) = sound {
+            unloaded |= self.sound_resources.borrow_mut().remove(&sound).is_some();
+        }
+        if let Some(buffer) = buffer {
+            unloaded |= self.buffer_resources.borrow_mut().remove(&buffer).is_some();
+        }
+        for custom_type in self.custom_types.borrow_mut().values_mut() {
+            unloaded |= custom_type.registry.remove_path(path);
+        }
+
+        //The dependencies of the resource are released.
+        self.dependency_graph.borrow_mut().remove(path);
+        self.dependency_handles.borrow_mut().remove(path);
+        self.collect_unused();
+        Ok(unloaded)
+    }
+
+    //Forget the dependencies of the models which have been released.
+    fn release_dependencies(&self) {
+        let released: Vec<PathBuf> = {
+            let model_resources = self.model_resources.borrow();
+            self.dependency_graph.borrow().resources().into_iter()
+                .filter(|resource| !model_resources.contains_path(resource))
+                .map(|resource| resource.to_path_buf())
+                .collect()
+        };
+        for resource in released.iter() {
+            trace!("Releasing the dependencies of the resource {}.", resource.display());
+            self.dependency_graph.borrow_mut().remove(resource);
+            self.dependency_handles.borrow_mut().remove(resource);
+        }
+    }
+
+    //Reserve the resource, and decode it with a job if it isn't loaded or loading yet.
+    fn load_async<T, F>(&self, registry: &RefCell<Registry<T>>, pending: &RefCell<PendingLoads<T>>, path: &Path, bytes: u64, batch: Option<BatchId>, decode: F) -> Handle<T> where
+        T: Send + 'static,
+        F: FnOnce() -> ResourceResult<T> + Send + 'static,
+    {
+        let handle = registry.borrow_mut().reserve(path);
+        let id = handle.id();
+        let state = registry.borrow().state(&handle);
+        let mut pending_loads = pending.borrow_mut();
+
+        if state != Some(LoadState::Loading) || pending_loads.is_loading(id) {
+            //Already loaded, or being loaded by another request.
+            debug!("The resource with path {} is already loaded or loading.", path.display());
+            let mut batches = self.batches.borrow_mut();
+            if state == Some(LoadState::Loading) {
+                pending_loads.wait(id, batch, 0, &mut batches);
+            } else if let Some(progress) = batch.and_then(|batch| batches.get_mut(&batch)) {
+                progress.add_item(0);
+                progress.finish_item(0, true);
+            }
+            return handle;
+        }
+
+        debug!("Loading the resource with path {} asynchronously.", path.display());
+        pending_loads.wait(id, batch, bytes, &mut self.batches.borrow_mut());
+        let sender = pending_loads.sender();
+        drop(pending_loads);
+
+        let job = move || {
+            //The resource manager may have been dropped.
+            let _ = sender.send(Completed {
+                id,
+                result: decode(),
+            });
+        };
+        match *self.spawner.borrow() {
+            Some(ref spawner) => {
+                spawner.spawn(job);
+            },
+            None => job(),
+        }
+        handle
+    }
+
+    //The job system decoding the resources loaded asynchronously. None to decode them on the calling thread.
+    pub fn set_spawner(&self, spawner: Option<Spawner>) {
+        *self.spawner.borrow_mut() = spawner;
+    }
+
+    //Start a new batch of asynchronous loads.
+    pub fn begin_batch(&self) -> BatchId {
+        let batch = BatchId::new(self.next_batch.get());
+        self.next_batch.set(self.next_batch.get() + 1);
+        self.batches.borrow_mut().insert(batch, LoadProgress::default());
+        batch
+    }
+
+    //None if the batch has been ended.
+    pub fn progress(&self, batch: BatchId) -> Option<LoadProgress> {
+        self.batches.borrow().get(&batch).cloned()
+    }
+
+    //Stop tracking the progress of the batch, and return its last progress.
+    pub fn end_batch(&self, batch: BatchId) -> Option<LoadProgress> {
+        self.batches.borrow_mut().remove(&batch)
+    }
+
+    //The number of asynchronous loads not published yet.
+    pub fn pending_loads(&self) -> usize {
+        self.pending_images.borrow().len() + self.pending_models.borrow().len() + self.pending_sounds.borrow().len()
+    }
+
+    //Publish the resources decoded since the last update, and enforce the budgets. Must be called by the main thread,
+    //once per frame. Return the number of loads completed.
+    pub fn update(&self) -> usize {
+        let completed = self.publish_pending_loads();
+        self.enforce_budgets();
+        completed
+    }
+
+    fn publish_pending_loads(&self) -> usize {
+        let mut batches = self.batches.borrow_mut();
+        self.pending_images.borrow_mut().publish(&mut self.image_resources.borrow_mut(), self.image_placeholder(), &mut batches)
+            + self.pending_models.borrow_mut().publish(&mut self.model_resources.borrow_mut(), self.model_placeholder(), &mut batches)
+            + self.pending_sounds.borrow_mut().publish(&mut self.sound_resources.borrow_mut(), self.sound_placeholder(), &mut batches)
+    }
+
+    //The memory budget of a resource category, in bytes. None to free the unused resources of the category
+    //as soon as possible again. Return the number of resources evicted to respect the budget.
+    pub fn set_budget(&self, category: ResourceCategory, budget: Option<usize>) -> usize {
+        debug!("Setting the budget of the {} to {:?} bytes.", category.name(), budget);
+        match budget {
+            Some(budget) => {
+                self.budgets.borrow_mut().insert(category, budget);
+            },
+            None => {
+                self.budgets.borrow_mut().remove(&category);
+            },
+        }
+
+        let retain_unused = budget.is_some();
+        match category {
+            ResourceCategory::Textures => {
+                self.image_resources.borrow_mut().set_retain_unused(retain_unused);
+            },
+            ResourceCategory::Meshes => {
+                self.model_resources.borrow_mut().set_retain_unused(retain_unused);
+                self.buffer_resources.borrow_mut().set_retain_unused(retain_unused);
+            },
+            ResourceCategory::Audio => {
+                self.sound_resources.borrow_mut().set_retain_unused(retain_unused);
+            },
+        }
+        for custom_type in self.custom_types.borrow_mut().values_mut().filter(|custom_type| custom_type.loader.category_any() == Some(category)) {
+            custom_type.registry.set_retain_unused(retain_unused);
+        }
+        self.enforce_budgets()
+    }
+
+    pub fn budget(&self, category: ResourceCategory) -> Option<usize> {
+        self.budgets.borrow().get(&category).cloned()
+    }
+
+    pub fn residency_stats(&self, category: ResourceCategory) -> ResidencyStats {
+        let mut stats = ResidencyStats::default();
+        match category {
+            ResourceCategory::Textures => add_residency(&self.image_resources.borrow(), &mut stats),
+            ResourceCategory::Meshes => {
+                add_residency(&self.model_resources.borrow(), &mut stats);
+                add_residency(&self.buffer_resources.borrow(), &mut stats);
+            },
+            ResourceCategory::Audio => add_residency(&self.sound_resources.borrow(), &mut stats),
+        }
+        for custom_type in self.custom_types.borrow().values().filter(|custom_type| custom_type.loader.category_any() == Some(category)) {
+            for (_, resource, unused) in custom_type.registry.resources_any() {
+                stats.add_resource(custom_type.loader.memory_size_any(resource), unused);
+            }
+        }
+        stats.budget = self.budget(category);
+
+        let mut counters = self.residency_counters.borrow_mut();
+        let counters = counters.entry(category).or_default();
+        counters.high_water_mark = counters.high_water_mark.max(stats.resident_bytes);
+        stats.high_water_mark = counters.high_water_mark;
+        stats.evictions = counters.evictions;
+        stats
+    }
+
+    //The residency stats of every category, named resources/<category>.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::new();
+        for category in ResourceCategory::all().iter() {
+            report.add(format!("resources/{}", category.name()), self.residency_stats(*category).memory_stats());
+        }
+        report
+    }
+
+    //Evict the least recently used resource of the category. Return the bytes freed, None if every resource is used.
+    fn evict_least_recently_used(&self, category: ResourceCategory) -> Option<usize> {
+        //The resources of the registered loaders in the category, if one of them is the least recently used.
+        if let Some((type_id, id, custom_use)) = self.least_recently_used_custom(category) {
+            let built_in_use = match category {
+                ResourceCategory::Textures => least_recently_used(&self.image_resources).map(|(_, last_use)| last_use),
+                ResourceCategory::Meshes => {
+                    least_recently_used(&self.model_resources).into_iter().chain(least_recently_used(&self.buffer_resources)).map(|(_, last_use)| last_use).min()
+                },
+                ResourceCategory::Audio => least_recently_used(&self.sound_resources).map(|(_, last_use)| last_use),
+            };
+            let custom_first = match built_in_use {
+                Some(built_in_use) => custom_use < built_in_use,
+                None => true,
+            };
+            if custom_first {
+                return self.evict_custom(type_id, id);
+            }
+        }
+
+        match category {
+            ResourceCategory::Textures => {
+                least_recently_used(&self.image_resources).and_then(|(id, _)| evict(&self.image_resources, id))
+            },
+            ResourceCategory::Meshes => {
+                match (least_recently_used(&self.model_resources), least_recently_used(&self.buffer_resources)) {
+                    (Some((_, model_use)), Some((buffer, buffer_use))) if buffer_use < model_use => evict(&self.buffer_resources, buffer),
+                    (Some((model, _)), _) => evict(&self.model_resources, model),
+                    (None, Some((buffer, _))) => evict(&self.buffer_resources, buffer),
+                    (None, None) => None,
+                }
+            },
+            ResourceCategory::Audio => {
+                least_recently_used(&self.sound_resources).and_then(|(id, _)| evict(&self.sound_resources, id))
+            },
+        }
+    }
+
+    //The least recently used resource of the registered loaders in the category, without strong handles.
+    fn least_recently_used_custom(&self, category: ResourceCategory) -> Option<(TypeId, HandleId, u64)> {
+        self.custom_types.borrow().iter()
+            .filter(|&(_, custom_type)| custom_type.loader.category_any() == Some(category))
+            .filter_map(|(type_id, custom_type)| custom_type.registry.least_recently_used().map(|(id, last_use)| (*type_id, id, last_use)))
+            .min_by_key(|&(_, _, last_use)| last_use)
+    }
+
+    //Evict a resource of a registered loader. Return the bytes freed.
+    fn evict_custom(&self, type_id: TypeId, id: HandleId) -> Option<usize> {
+        let mut custom_types = self.custom_types.borrow_mut();
+        let custom_type = custom_types.get_mut(&type_id)?;
+        let (path, resource) = custom_type.registry.evict_any(id)?;
+        debug!("Evicting the unused resource {} from the resource manager.", path.display());
+        Some(custom_type.loader.memory_size_any(resource.as_ref()))
+    }
+
+    //Evict the unused resources of the categories over their budget. Return the number of resources evicted.
+    pub fn enforce_budgets(&self) -> usize {
+        let mut evicted = 0;
+        for category in ResourceCategory::all().iter().cloned() {
+            let budget = match self.budget(category) {
+                Some(budget) => budget,
+                None => continue,
+            };
+            let mut resident_bytes = self.residency_stats(category).resident_bytes;
+            while resident_bytes > budget {
+                match self.evict_least_recently_used(category) {
+                    Some(freed) => {
+                        resident_bytes = resident_bytes.saturating_sub(freed);
+                        evicted += 1;
+                        if let Some(counters) = self.residency_counters.borrow_mut().get_mut(&category) {
+                            counters.evictions += 1;
+                        }
+                        //The dependencies of an evicted model are released, and can be evicted in turn.
+                        self.collect_unused();
+                    },
+                    None => {
+                        warn!("The {} use {} bytes, over their budget of {} bytes, but every resident resource is used.", category.name(), resident_bytes, budget);
+                        break;
+                    },
+                }
+            }
+        }
+        evicted
+    }
+
+    //Free the resources without strong handles. Return the number of resources freed.
+    //The unused resources kept resident by a budget aren't freed, see enforce_budgets().
+    //The dependencies of the freed resources are freed too, if they're not used anymore.
+    pub fn collect_unused(&self) -> usize {
+        debug!("Freeing the unused resources of the resource manager.");
+        let freed = self.model_resources.borrow_mut().collect() + self.sound_resources.borrow_mut().collect();
+        self.release_dependencies();
+        let freed_custom: usize = self.custom_types.borrow_mut().values_mut().map(|custom_type| custom_type.registry.collect()).sum();
+        freed + freed_custom + self.image_resources.borrow_mut().collect() + self.buffer_resources.borrow_mut().collect()
+    }
+
+    //Unload every resource, placeholders included. The remaining handles won't find any resource.
     pub fn clear(&self) {
         debug!("unloading resources from the resource manager.");
-        //The game has been closed if the global resources must be unloaded. Clear everything.
         self.model_resources.borrow_mut().clear();
         self.sound_resources.borrow_mut().clear();
         self.image_resources.borrow_mut().clear();
-        self.double_ended_allocator.0.reset();
-        self.double_ended_allocator.0.reset_copy();
-        self.double_ended_allocator.1.reset();
-        self.double_ended_allocator.1.reset_copy();
-    }
-
-    //TODO: needed ?
-    pub fn unload_temporary_data(&self) {
-        debug!("Unloading temporary data.");
-        self.double_ended_allocator.1.reset();
-        self.double_ended_allocator.1.reset_copy();
+        self.buffer_resources.borrow_mut().clear();
+        self.dependency_graph.borrow_mut().clear();
+        self.dependency_handles.borrow_mut().clear();
+        self.file_watcher.borrow_mut().clear();
+        self.loaded_groups.borrow_mut().clear();
+        //The loaders and the manifests stay registered.
+        for custom_type in self.custom_types.borrow_mut().values_mut() {
+            custom_type.registry.clear();
+            custom_type.placeholder = None;
+        }
+        let mut placeholders = self.placeholders.borrow_mut();
+        placeholders.image = None;
+        placeholders.model = None;
+        placeholders.sound = None;
     }
 }
 
 #[cfg(test)]
 mod resource_manager_test {
     use super::*;
-    use std::io::BufReader;
+    use std::io::{BufReader, Cursor};
     use std::fs::File;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use std::env;
+    use std::fs;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use maskerad_core::jobs::job_system::JobSystem;
+    use post_processing::post_processor::PostProcessor;
+    use post_processing::image_processors::{GenerateMipmaps, PremultiplyAlpha};
+    use post_processing::model_processors::{ComputeBounds, GenerateTangents};
+    use post_processing::sound_processors::{NormalizeSound, ResampleSound};
+    use cgmath::{InnerSpace, Vector3};
+    use cooking::asset_cooker::AssetCooker;
+    use resources::texture_resource::{TextureFormat, TextureLoader};
+    use resources::texture_containers::TextureContainer;
+    use cooking::texture_compression;
     #[test]
     fn resource_manager_creation() {
-        let resource_manager: ResourceManager<BufReader<File>> = ResourceManager::with_capacity(100, 100);
+        let resource_manager: ResourceManager<BufReader<File>> = ResourceManager::new();
+        assert!(resource_manager.model_resources().is_empty());
+        assert!(resource_manager.sound_resources().is_empty());
+        assert!(resource_manager.image_resources().is_empty());
+    }
+
+    #[test]
+    fn resource_manager_malformed_resources() {
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        let garbage = || Cursor::new(vec![0u8, 1, 2, 3, 4, 5, 6, 7]);
+
+        let error = resource_manager.load_image("broken.tga", &mut garbage(), ColorFormat::Auto).unwrap_err();
+        assert_eq!(error.path(), Some(Path::new("broken.tga")));
+        assert!(resource_manager.load_model("broken.gltf", garbage()).is_err());
+        assert!(resource_manager.load_sound("broken.ogg", garbage()).is_err());
+        assert!(resource_manager.image_resources().is_empty());
         assert!(resource_manager.model_resources().is_empty());
         assert!(resource_manager.sound_resources().is_empty());
+
+        resource_manager.use_default_placeholders().unwrap();
+        let image = resource_manager.load_image("broken.tga", &mut garbage(), ColorFormat::Auto).unwrap();
+        assert_eq!(Some(image), resource_manager.image_placeholder());
+        assert!(!resource_manager.image_resources().contains_path("broken.tga"));
+        let model = resource_manager.load_model("broken.gltf", garbage()).unwrap();
+        assert!(resource_manager.model(&model).is_some());
+        let sound = resource_manager.load_sound("broken.ogg", garbage()).unwrap();
+        assert!(resource_manager.sound(&sound).unwrap().is_silence());
+        assert_eq!(resource_manager.sound(&sound).unwrap().decompress_packet().unwrap(), None);
+
+        resource_manager.set_image_placeholder(None);
+        assert!(resource_manager.load_image("broken.tga", &mut garbage(), ColorFormat::Auto).is_err());
         assert!(resource_manager.image_resources().is_empty());
     }
 
     #[test]
     fn resource_manager_load_unload_asynchronously_resource() {
+        let job_system = JobSystem::new(2).unwrap();
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        resource_manager.set_spawner(Some(job_system.spawner()));
+        let image_data = include_bytes!("../../test_resources/images/Untitled.tga").to_vec();
 
+        let batch = resource_manager.begin_batch();
+        let image = resource_manager.load_image_async("untitled.tga", Cursor::new(image_data.clone()), ColorFormat::Auto, Some(image_data.len() as u64), batch);
+        let broken = resource_manager.load_image_async("broken.tga", Cursor::new(vec![0u8; 16]), ColorFormat::Auto, Some(16), batch);
+        //A second request of a resource being loaded returns the same handle.
+        assert_eq!(resource_manager.load_image_async("untitled.tga", Cursor::new(image_data.clone()), ColorFormat::Auto, None, batch), image);
+        assert_eq!(resource_manager.progress(batch).unwrap().bytes_total, image_data.len() as u64 + 16);
+
+        let start = Instant::now();
+        while !resource_manager.progress(batch).unwrap().is_complete() {
+            assert!(start.elapsed() < Duration::from_secs(10), "the asynchronous loads never completed");
+            resource_manager.update();
+            thread::yield_now();
+        }
+
+        let progress = resource_manager.end_batch(batch).unwrap();
+        assert_eq!(progress.items_total, 3);
+        assert_eq!(progress.items_done, 2);
+        assert_eq!(progress.items_failed, 1);
+        assert_eq!(progress.fraction(), 1.0);
+        assert_eq!(resource_manager.pending_loads(), 0);
+
+        assert_eq!(resource_manager.image_resources().state(&image), Some(LoadState::Loaded));
+        assert!(resource_manager.image(&image).is_some());
+        assert_eq!(resource_manager.image_resources().state(&broken), Some(LoadState::Failed));
+        assert!(resource_manager.image(&broken).is_none());
+
+        //Without job system, the resources are decoded on the calling thread, and published by update().
+        resource_manager.set_spawner(None);
+        let model = resource_manager.load_model_async("cube.gltf", Cursor::new(Vec::new()), None, None);
+        assert_eq!(resource_manager.model_resources().state(&model), Some(LoadState::Loading));
+        assert_eq!(resource_manager.update(), 1);
+        assert_eq!(resource_manager.model_resources().state(&model), Some(LoadState::Failed));
+
+        //A failed resource gives access to the placeholder.
+        resource_manager.set_image_placeholder(Some(ImageResource::checkerboard(4, 2)));
+        let broken = resource_manager.load_image_async("broken_again.tga", Cursor::new(vec![0u8; 16]), ColorFormat::Auto, None, None);
+        resource_manager.update();
+        assert_eq!(resource_manager.image_resources().state(&broken), Some(LoadState::Failed));
+        assert_eq!(resource_manager.image(&broken).unwrap().width(), 4);
+
+        //A resource replaced during its loading counts as loaded.
+        let batch = resource_manager.begin_batch();
+        let replaced = resource_manager.load_image_async("replaced.tga", Cursor::new(image_data), ColorFormat::Auto, None, batch);
+        resource_manager.image_resources_mut().insert("replaced.tga", ImageResource::checkerboard(2, 1));
+        resource_manager.update();
+        let progress = resource_manager.end_batch(batch).unwrap();
+        assert_eq!((progress.items_done, progress.items_failed), (1, 0));
+        assert_eq!(resource_manager.image(&replaced).unwrap().width(), 2);
+    }
+
+    struct FailingProcessor;
+
+    impl PostProcessor<ImageResource> for FailingProcessor {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn process(&self, _resource: &mut ImageResource, _metadata: &AssetMetadata) -> ResourceResult<()> {
+            Err(ResourceError::PostProcessingError(String::from("always fails")))
+        }
     }
 
     #[test]
     fn resource_manager_post_process_resource() {
-        //fine_tuning of the resource after it has been loaded
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        let image_data = include_bytes!("../../test_resources/images/Untitled.tga").to_vec();
+        resource_manager.image_post_processors_mut().add(PremultiplyAlpha).add(GenerateMipmaps);
+        resource_manager.model_post_processors_mut().add(ComputeBounds);
+        resource_manager.sound_post_processors_mut().add(ResampleSound::new(22050)).add(NormalizeSound);
+
+        let image = resource_manager.load_image("full.tga", &mut Cursor::new(image_data.clone()), ColorFormat::RGBA).unwrap();
+        let levels = {
+            let image = resource_manager.image(&image).unwrap();
+            assert!(image.is_premultiplied());
+            assert!(image.mip_level_count() > 1);
+            let smallest = image.mip_level(image.mip_level_count() - 1).unwrap();
+            assert_eq!((smallest.w, smallest.h), (1, 1));
+            image.mip_level_count()
+        };
+
+        //The processors are configured per asset.
+        let mut metadata = AssetMetadata::new();
+        metadata.skip_processor("premultiply_alpha").set_parameter("generate_mipmaps", "max_levels", 2);
+        resource_manager.set_asset_metadata("configured.tga", Some(metadata));
+        let configured = resource_manager.load_image("configured.tga", &mut Cursor::new(image_data.clone()), ColorFormat::RGBA).unwrap();
+        assert!(!resource_manager.image(&configured).unwrap().is_premultiplied());
+        assert_eq!(resource_manager.image(&configured).unwrap().mip_level_count(), 2.min(levels));
+
+        let model = resource_manager.load_model("cube.gltf", model_data("cube.bin", "wood.tga")).unwrap();
+        let bounds = resource_manager.model(&model).unwrap().bounds().unwrap();
+        assert_eq!(bounds.size(), Vector3::new(1.0, 1.0, 1.0));
+
+        let sound = resource_manager.load_sound("untitled.ogg", Cursor::new(include_bytes!("../../test_resources/ogg/untitled.ogg").to_vec())).unwrap();
+        {
+            let sound = resource_manager.sound(&sound).unwrap();
+            let decoded = sound.decoded().unwrap();
+            assert_eq!(decoded.sample_rate, 22050);
+            let loudest = decoded.channels.iter().flat_map(|channel| channel.iter()).map(|sample| i32::from(*sample).abs()).max().unwrap();
+            assert!(loudest == 0 || loudest >= i32::from(i16::MAX) - 1);
+        }
+
+        //A failing post processor makes the loading fail, even asynchronously.
+        resource_manager.image_post_processors_mut().add(FailingProcessor);
+        let error = resource_manager.load_image("failing.tga", &mut Cursor::new(image_data.clone()), ColorFormat::Auto).unwrap_err();
+        assert_eq!(error.path(), Some(Path::new("failing.tga")));
+        let failing = resource_manager.load_image_async("failing_async.tga", Cursor::new(image_data), ColorFormat::Auto, None, None);
+        resource_manager.update();
+        assert_eq!(resource_manager.image_resources().state(&failing), Some(LoadState::Failed));
+    }
+
+    //Record the threads post processing the images.
+    struct RecordThread(Arc<Mutex<Vec<thread::ThreadId>>>);
+
+    impl PostProcessor<ImageResource> for RecordThread {
+        fn name(&self) -> &str {
+            "record_thread"
+        }
+
+        fn process(&self, _resource: &mut ImageResource, _metadata: &AssetMetadata) -> ResourceResult<()> {
+            self.0.lock().unwrap().push(thread::current().id());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn resource_manager_post_process_asynchronously_resource() {
+        let job_system = JobSystem::new(1).unwrap();
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        resource_manager.set_spawner(Some(job_system.spawner()));
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        resource_manager.image_post_processors_mut().add(RecordThread(threads.clone()));
+
+        let image_data = include_bytes!("../../test_resources/images/Untitled.tga").to_vec();
+        let image = resource_manager.load_image_async("untitled.tga", Cursor::new(image_data), ColorFormat::Auto, None, None);
+        let start = Instant::now();
+        while resource_manager.image_resources().state(&image) == Some(LoadState::Loading) {
+            assert!(start.elapsed() < Duration::from_secs(10), "the asynchronous load never completed");
+            resource_manager.update();
+            thread::yield_now();
+        }
+
+        //The image has been post processed by the loading job, on the worker thread.
+        assert_eq!(resource_manager.image_resources().state(&image), Some(LoadState::Loaded));
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_ne!(threads[0], thread::current().id());
+    }
+
+    //The lines of a dialogue, a game defined resource type.
+    struct Dialogue(Vec<String>);
+
+    struct DialogueLoader;
+
+    impl<R: Read> ResourceLoader<R> for DialogueLoader {
+        type Resource = Dialogue;
+
+        fn extensions(&self) -> &[&str] {
+            &["dialogue"]
+        }
+
+        fn load(&self, reader: &mut R, _path: &Path) -> ResourceResult<Dialogue> {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            Ok(Dialogue(text.lines().map(String::from).collect()))
+        }
+    }
+
+    struct TextLoader(&'static [&'static str]);
+
+    impl<R: Read> ResourceLoader<R> for TextLoader {
+        type Resource = String;
+
+        fn extensions(&self) -> &[&str] {
+            self.0
+        }
+
+        fn load(&self, reader: &mut R, _path: &Path) -> ResourceResult<String> {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+
+    #[test]
+    fn resource_manager_custom_resource_loader() {
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        let dialogue_data = || Cursor::new(b"Hello.\nGoodbye.".to_vec());
+        assert!(resource_manager.load::<Dialogue, _>("intro.dialogue", dialogue_data()).is_err());
+
+        resource_manager.register_loader(DialogueLoader).unwrap();
+        assert!(resource_manager.has_loader::<Dialogue>());
+        assert!(resource_manager.register_loader(DialogueLoader).is_err());
+        //The extension is already registered.
+        assert!(resource_manager.register_loader(TextLoader(&["txt", "DIALOGUE"])).is_err());
+        assert!(!resource_manager.has_loader::<String>());
+        resource_manager.register_loader(TextLoader(&["txt"])).unwrap();
+        assert!(resource_manager.resource_type_of("dialogues/Intro.DIALOGUE").unwrap().ends_with("Dialogue"));
+
+        let dialogue: Handle<Dialogue> = resource_manager.load("intro.dialogue", dialogue_data()).unwrap();
+        assert_eq!(resource_manager.get(&dialogue).unwrap().0, vec![String::from("Hello."), String::from("Goodbye.")]);
+        assert_eq!(resource_manager.load::<Dialogue, _>("intro.dialogue", Cursor::new(Vec::new())).unwrap(), dialogue);
+        resource_manager.get_mut(&dialogue).unwrap().0.push(String::from("..."));
+        assert_eq!(resource_manager.registry::<Dialogue>().unwrap().get(&dialogue).unwrap().0.len(), 3);
+
+        //The files of another type, and the invalid files, aren't loaded.
+        assert!(resource_manager.load::<Dialogue, _>("intro.txt", dialogue_data()).is_err());
+        let error = resource_manager.load::<Dialogue, _>("broken.dialogue", Cursor::new(vec![0xff, 0xfe])).unwrap_err();
+        assert_eq!(error.path(), Some(Path::new("broken.dialogue")));
+        resource_manager.set_placeholder(Some(Dialogue(Vec::new()))).unwrap();
+        let broken = resource_manager.load::<Dialogue, _>("broken.dialogue", Cursor::new(vec![0xff, 0xfe])).unwrap();
+        assert_eq!(Some(broken), resource_manager.placeholder::<Dialogue>());
+        assert!(resource_manager.set_placeholder(Some(0u32)).is_err());
+
+        //The resources of a registered type are freed like the others.
+        drop(dialogue);
+        assert_eq!(resource_manager.collect_unused(), 1);
+        assert_eq!(resource_manager.registry::<Dialogue>().unwrap().len(), 1);
+        resource_manager.clear();
+        assert!(resource_manager.registry::<Dialogue>().unwrap().is_empty());
+        assert!(resource_manager.placeholder::<Dialogue>().is_none());
+    }
+
+    //A cube with an external buffer and an external texture.
+    fn model_data(buffer_uri: &str, image_uri: &str) -> Cursor<Vec<u8>> {
+        let gltf = format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "meshes": [ {{ "primitives": [ {{ "attributes": {{ "POSITION": 1 }}, "indices": 0 }} ] }} ],
+            "buffers": [ {{ "byteLength": 168, "uri": "{}" }} ],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 72 }},
+                {{ "buffer": 0, "byteOffset": 72, "byteLength": 96 }}
+            ],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5123, "count": 36, "type": "SCALAR" }},
+                {{ "bufferView": 1, "componentType": 5126, "count": 8, "type": "VEC3", "min": [-0.5, -0.5, -0.5], "max": [0.5, 0.5, 0.5] }}
+            ],
+            "images": [ {{ "uri": "{}" }} ]
+        }}"#, buffer_uri, image_uri);
+        Cursor::new(gltf.into_bytes())
     }
 
     #[test]
     fn resource_manager_composite_resource_and_referential_integrity() {
-        //Composite resource -> Model has mesh, anims, skeletons...
-        //Referential integrity -> Model has a mesh, which has a skeletons and anims. Skeleton must be loaded before anims...
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        let mut files = HashMap::new();
+        files.insert(PathBuf::from("models/cube.bin"), vec![0u8; 168]);
+        files.insert(PathBuf::from("models/other.bin"), vec![0u8; 168]);
+        files.insert(PathBuf::from("textures/wood.tga"), include_bytes!("../../test_resources/images/Untitled.tga").to_vec());
+        let opened = RefCell::new(Vec::new());
+        let mut open = |path: &Path| {
+            opened.borrow_mut().push(path.to_path_buf());
+            files.get(path).cloned().map(Cursor::new).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        };
+
+        //The dependencies are loaded with the model.
+        let cube = resource_manager.load_model_with_dependencies("models/cube.gltf", model_data("cube.bin", "../textures/wood.tga"), &mut open).unwrap();
+        assert_eq!(resource_manager.model_buffers(&cube).len(), 1);
+        assert_eq!(resource_manager.buffer(&resource_manager.model_buffers(&cube)[0]).unwrap().len(), 168);
+        let texture = resource_manager.model_images(&cube).pop().unwrap();
+        assert_eq!(resource_manager.image_resources().path(&texture), Some(Path::new("textures/wood.tga")));
+        assert_eq!(resource_manager.dependency_graph().dependencies("models/cube.gltf").len(), 2);
+
+        //The dependencies already loaded are shared.
+        let other = resource_manager.load_model_with_dependencies("models/other.gltf", model_data("other.bin", "../textures/wood.tga"), &mut open).unwrap();
+        assert_eq!(resource_manager.model_images(&other), vec![texture.clone()]);
+        assert_eq!(resource_manager.image_resources().len(), 1);
+        assert_eq!(opened.borrow().len(), 3);
+
+        //A referenced resource can't be unloaded.
+        drop(texture);
+        match resource_manager.unload("textures/wood.tga") {
+            Err(ResourceManagerError::ReferencedResource(_, path)) => assert_eq!(path, Path::new("textures/wood.tga")),
+            other => panic!("The texture shouldn't be unloaded: {:?}", other),
+        }
+        assert!(resource_manager.unload("models/other.gltf").unwrap());
+        assert!(resource_manager.model(&other).is_none());
+        assert_eq!(resource_manager.buffer_resources().len(), 1);
+        assert_eq!(resource_manager.image_resources().len(), 1);
+
+        //Missing dependencies and cycles are errors, and nothing is loaded.
+        let missing = resource_manager.load_model_with_dependencies("models/missing.gltf", model_data("missing.bin", "../textures/wood.tga"), &mut open).unwrap_err();
+        assert_eq!(missing.path(), Some(Path::new("models/missing.bin")));
+        assert!(!resource_manager.model_resources().contains_path("models/missing.gltf"));
+        match resource_manager.load_model_with_dependencies("models/self.gltf", model_data("self.gltf", "../textures/wood.tga"), &mut open) {
+            Err(ResourceManagerError::DependencyCycle(_, cycle)) => assert_eq!(cycle, vec![PathBuf::from("models/self.gltf"), PathBuf::from("models/self.gltf")]),
+            other => panic!("The cycle should be detected: {:?}", other),
+        }
+
+        //A corrupt dependency isn't replaced by a placeholder.
+        resource_manager.use_default_placeholders().unwrap();
+        files.insert(PathBuf::from("textures/corrupt.tga"), vec![0u8; 16]);
+        let corrupt = resource_manager.load_model_with_dependencies("models/corrupt.gltf", model_data("cube.bin", "../textures/corrupt.tga"), |path: &Path| {
+            files.get(path).cloned().map(Cursor::new).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        }).unwrap_err();
+        assert_eq!(corrupt.path(), Some(Path::new("textures/corrupt.tga")));
+        assert!(!resource_manager.model_resources().contains_path("models/corrupt.gltf"));
+        resource_manager.set_image_placeholder(None);
+        resource_manager.set_model_placeholder(None);
+        resource_manager.set_sound_placeholder(None);
+
+        //The dependencies are freed with the last model using them.
+        drop(cube);
+        resource_manager.collect_unused();
+        assert!(resource_manager.model_resources().is_empty());
+        assert!(resource_manager.buffer_resources().is_empty());
+        assert!(resource_manager.image_resources().is_empty());
+        assert!(resource_manager.dependency_graph().is_empty());
+    }
+
+    #[test]
+    fn resource_manager_generate_tangents_from_external_buffer() {
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        resource_manager.model_post_processors_mut().add(GenerateTangents);
+        //The fixture has no uvs: read the positions as uvs.
+        let gltf = include_str!("../../test_resources/gltf/untitled.gltf")
+            .replacen("\n    ], \n    \"asset\"", ", {\"bufferView\" : 1, \"componentType\" : 5126, \"count\" : 24, \"type\" : \"VEC2\"}\n    ], \n    \"asset\"", 1)
+            .replace("\"POSITION\" : 1", "\"POSITION\" : 1, \"TEXCOORD_0\" : 3");
+        let mut open = |path: &Path| {
+            assert_eq!(path, Path::new("gltf/untitled.bin"));
+            Ok(Cursor::new(include_bytes!("../../test_resources/gltf/untitled.bin").to_vec()))
+        };
+
+        let model = resource_manager.load_model_with_dependencies("gltf/untitled.gltf", Cursor::new(gltf.into_bytes()), &mut open).unwrap();
+        let model = resource_manager.model(&model).unwrap();
+        let tangents = model.tangents(0, 0).unwrap();
+        assert_eq!(tangents.len(), 24);
+        //The external buffer isn't kept in the model: it's a resource of its own.
+        assert!(model.buffer_data(0).is_none());
+        let normals = include_bytes!("../../test_resources/gltf/untitled.bin")[324..].chunks(12).map(|normal| {
+            let component = |index: usize| f32::from_bits(u32::from_le_bytes([normal[index], normal[index + 1], normal[index + 2], normal[index + 3]]));
+            Vector3::new(component(0), component(4), component(8))
+        });
+        for (tangent, normal) in tangents.iter().zip(normals) {
+            let tangent = Vector3::new(tangent[0], tangent[1], tangent[2]);
+            assert!((tangent.magnitude() - 1.0).abs() < 1e-4, "{:?}", tangent);
+            assert!(tangent.dot(normal).abs() < 1e-4, "{:?} {:?}", tangent, normal);
+        }
+    }
+
+    #[test]
+    fn resource_manager_hot_reload() {
+        let directory = env::temp_dir().join("maskerad_hot_reload_test");
+        fs::create_dir_all(directory.as_path()).unwrap();
+        let model_path = directory.join("cube.gltf");
+        let buffer_path = directory.join("cube.bin");
+        let image_path = directory.join("wood.tga");
+        let image_data = include_bytes!("../../test_resources/images/Untitled.tga").to_vec();
+        fs::write(model_path.as_path(), model_data("cube.bin", "wood.tga").into_inner()).unwrap();
+        fs::write(buffer_path.as_path(), vec![0u8; 168]).unwrap();
+        fs::write(image_path.as_path(), image_data.as_slice()).unwrap();
+        //Change the content and the modification time of a file.
+        let modify = |path: &Path, data: &[u8]| {
+            let modified = fs::metadata(path).unwrap().modified().unwrap() + Duration::from_secs(10);
+            fs::write(path, data).unwrap();
+            File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+        };
+
+        let resource_manager: Rc<ResourceManager<Cursor<Vec<u8>>>> = Rc::new(ResourceManager::new());
+        resource_manager.image_post_processors_mut().add(GenerateMipmaps);
+        let open = |path: &Path| fs::read(path).map(Cursor::new);
+        let model = resource_manager.load_model_with_dependencies(model_path.as_path(), Cursor::new(fs::read(model_path.as_path()).unwrap()), open).unwrap();
+        let buffer = resource_manager.model_buffers(&model).pop().unwrap();
+        let image = resource_manager.model_images(&model).pop().unwrap();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let listener_events = events.clone();
+        resource_manager.add_reload_listener(move |event| listener_events.borrow_mut().push((event.path.clone(), event.is_reloaded())));
+        //A listener can add listeners.
+        let weak_manager = Rc::downgrade(&resource_manager);
+        let added = Rc::new(Cell::new(0));
+        let listener_added = added.clone();
+        resource_manager.add_reload_listener(move |_| {
+            let listener_added = listener_added.clone();
+            if let Some(resource_manager) = weak_manager.upgrade() {
+                resource_manager.add_reload_listener(move |_| listener_added.set(listener_added.get() + 1));
+            }
+        });
+        assert!(resource_manager.hot_reload(open).is_empty());
+
+        //The new version replaces the previous one behind the handles, and the dependents are notified.
+        modify(buffer_path.as_path(), vec![1u8; 168].as_slice());
+        let reloaded = resource_manager.hot_reload(open);
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].kind, ResourceKind::Buffer);
+        assert_eq!(reloaded[0].dependents, vec![model_path.clone()]);
+        assert_eq!(resource_manager.buffer(&buffer).unwrap().data()[0], 1);
+        assert_eq!(events.borrow().as_slice(), &[(buffer_path.clone(), true)]);
+
+        //A new version which can't be loaded keeps the previous version.
+        modify(image_path.as_path(), b"not an image");
+        let failed = resource_manager.hot_reload(open);
+        assert_eq!(failed[0].kind, ResourceKind::Image);
+        assert_eq!(failed[0].result.as_ref().unwrap_err().path(), Some(image_path.as_path()));
+        assert!(resource_manager.image(&image).is_some());
+
+        //A model is reloaded with its dependencies.
+        modify(image_path.as_path(), image_data.as_slice());
+        modify(model_path.as_path(), model_data("cube.bin", "other_wood.tga").into_inner().as_slice());
+        fs::write(directory.join("other_wood.tga"), image_data.as_slice()).unwrap();
+        let reloaded = resource_manager.hot_reload(open);
+        assert_eq!(reloaded.iter().map(|event| event.kind).collect::<Vec<_>>(), vec![ResourceKind::Image, ResourceKind::Model]);
+        assert!(reloaded.iter().all(|event| event.is_reloaded()));
+        assert!(resource_manager.model(&model).is_some());
+        assert_eq!(resource_manager.image_resources().path(&resource_manager.model_images(&model)[0]), Some(directory.join("other_wood.tga").as_path()));
+        assert_eq!(events.borrow().len(), 4);
+        //A listener added by every event, and called by the next ones: 1 + 2 + 3.
+        assert_eq!(added.get(), 6);
+
+        //A modified metadata file reloads its resource.
+        let sidecar_path = AssetMetadata::sidecar_path(image_path.as_path());
+        fs::write(sidecar_path.as_path(), "[generate_mipmaps]\nmax_levels = 1").unwrap();
+        let reloaded = resource_manager.hot_reload(open);
+        assert_eq!(reloaded.iter().map(|event| event.path.clone()).collect::<Vec<_>>(), vec![image_path.clone()]);
+        assert_eq!(resource_manager.image(&image).unwrap().mip_level_count(), 1);
+        modify(sidecar_path.as_path(), b"[generate_mipmaps]\nmax_levels = 2");
+        assert_eq!(resource_manager.hot_reload(open).len(), 1);
+        assert_eq!(resource_manager.image(&image).unwrap().mip_level_count(), 2);
+
+        fs::remove_dir_all(directory.as_path()).unwrap();
+    }
+
+    #[test]
+    fn resource_manager_load_cooked_resource() {
+        let directory = env::temp_dir().join("maskerad_cooked_resources_test");
+        let _ = fs::remove_dir_all(directory.as_path());
+        let source_directory = directory.join("source");
+        let output_directory = directory.join("cooked");
+        fs::create_dir_all(source_directory.join("models")).unwrap();
+        fs::create_dir_all(source_directory.join("textures")).unwrap();
+        fs::write(source_directory.join("models/cube.gltf"), model_data("cube.bin", "../textures/wood.tga").into_inner()).unwrap();
+        fs::write(source_directory.join("models/cube.bin"), vec![0u8; 168]).unwrap();
+        fs::write(source_directory.join("textures/wood.tga"), &include_bytes!("../../test_resources/images/Untitled.tga")[..]).unwrap();
+        fs::write(source_directory.join("music.ogg"), &include_bytes!("../../test_resources/ogg/untitled.ogg")[..]).unwrap();
+        fs::write(source_directory.join("textures/sky.tga"), &include_bytes!("../../test_resources/images/Untitled.tga")[..]).unwrap();
+        fs::write(source_directory.join("textures/sky.tga.meta"), "[texture_compression]\nformat = \"bc1\"").unwrap();
+        fs::write(source_directory.join("models/sky_box.gltf"), model_data("cube.bin", "../textures/sky.tga").into_inner()).unwrap();
+
+        let mut cooker = AssetCooker::new(source_directory.as_path(), output_directory.as_path());
+        cooker.image_post_processors_mut().add(GenerateMipmaps);
+        assert_eq!(cooker.cook().unwrap().cooked.len(), 6);
+
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        resource_manager.set_cook_manifest(Some(CookManifest::from_path(cooker.manifest_path()).unwrap()));
+        let open = |path: &Path| fs::read(output_directory.join(path)).map(Cursor::new);
+        resource_manager.model_post_processors_mut().add(ComputeBounds);
+
+        //The resources are registered with the path of their source, the dependencies are cooked too.
+        //The cooked resources are not post processed by the resource manager.
+        let model = resource_manager.load_cooked_model("models/cube.gltf", open).unwrap();
+        assert!(resource_manager.model(&model).unwrap().bounds().is_none());
+        assert_eq!(resource_manager.model_buffers(&model).len(), 1);
+        let image = resource_manager.model_images(&model).pop().unwrap();
+        assert_eq!(resource_manager.image_resources().path(&image), Some(Path::new("textures/wood.tga")));
+        assert!(resource_manager.image(&image).unwrap().mip_level_count() > 1);
+        assert_eq!(resource_manager.load_cooked_image("textures/wood.tga", open).unwrap(), image);
+
+        let sound = resource_manager.load_cooked_sound("music.ogg", open).unwrap();
+        assert!(resource_manager.sound(&sound).unwrap().decoded().is_some());
+
+        //The compressed images are textures, loaded by the TextureLoader.
+        assert!(resource_manager.load_cooked_texture("textures/sky.tga", open).is_err());
+        assert!(resource_manager.load_cooked_model("models/sky_box.gltf", open).is_err());
+        resource_manager.register_loader(TextureLoader).unwrap();
+        let texture = resource_manager.load_cooked_texture("textures/sky.tga", open).unwrap();
+        assert_eq!(resource_manager.get(&texture).unwrap().format(), TextureFormat::Bc1RgbUnorm);
+        assert_eq!(resource_manager.load_cooked_texture("textures/sky.tga", open).unwrap(), texture);
+        assert!(resource_manager.load_cooked_image("textures/sky.tga", open).is_err());
+
+        //The dependencies cooked as compressed textures are loaded as textures.
+        let sky_box = resource_manager.load_cooked_model("models/sky_box.gltf", open).unwrap();
+        assert!(resource_manager.model_images(&sky_box).is_empty());
+        assert_eq!(resource_manager.model_textures(&sky_box), vec![texture]);
+
+        match resource_manager.load_cooked_sound("textures/wood.tga", open) {
+            Err(ResourceManagerError::NotCooked(_, ref path)) => assert_eq!(path, Path::new("textures/wood.tga")),
+            _ => panic!("An image isn't a cooked sound."),
+        }
+        assert!(resource_manager.load_cooked_image("missing.tga", open).is_err());
+        resource_manager.set_cook_manifest(None);
+        assert!(resource_manager.load_cooked_sound("other.ogg", open).is_err());
+
+        fs::remove_dir_all(directory.as_path()).unwrap();
+    }
+
+    #[test]
+    fn resource_manager_resource_budgets() {
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        let image_data = || Cursor::new(include_bytes!("../../test_resources/images/Untitled.tga").to_vec());
+        let size = {
+            let image = resource_manager.load_image("size.tga", &mut image_data(), ColorFormat::RGBA).unwrap();
+            let size = resource_manager.image(&image).unwrap().memory_size();
+            size
+        };
+        assert_eq!(resource_manager.collect_unused(), 1);
+
+        //The unused resources stay resident, and are evicted from the least recently used over the budget.
+        assert_eq!(resource_manager.set_budget(ResourceCategory::Textures, Some(size * 2)), 0);
+        drop(resource_manager.load_image("a.tga", &mut image_data(), ColorFormat::RGBA).unwrap());
+        let b = resource_manager.load_image("b.tga", &mut image_data(), ColorFormat::RGBA).unwrap();
+        drop(resource_manager.load_image("c.tga", &mut image_data(), ColorFormat::RGBA).unwrap());
+        assert_eq!(resource_manager.collect_unused(), 0);
+        let a = resource_manager.load_image("a.tga", &mut Cursor::new(Vec::new()), ColorFormat::RGBA).unwrap();
+        assert!(resource_manager.image(&a).is_some());
+        drop(a);
+        assert_eq!(resource_manager.residency_stats(ResourceCategory::Textures).resident_bytes, size * 3);
+
+        resource_manager.update();
+        assert!(resource_manager.image_resources().contains_path("a.tga"));
+        assert!(!resource_manager.image_resources().contains_path("c.tga"));
+        let stats = resource_manager.residency_stats(ResourceCategory::Textures);
+        assert_eq!((stats.resident_count, stats.unused_count, stats.evictions), (2, 1, 1));
+        assert_eq!(stats.high_water_mark, size * 3);
+        assert!(!stats.is_over_budget());
+
+        //The used resources are never evicted.
+        assert_eq!(resource_manager.set_budget(ResourceCategory::Textures, Some(0)), 1);
+        assert!(resource_manager.image(&b).is_some());
+        assert!(resource_manager.residency_stats(ResourceCategory::Textures).is_over_budget());
+        assert!(resource_manager.memory_report().get("resources/textures").unwrap().is_over_budget());
+
+        //Without budget, the unused resources are freed again.
+        resource_manager.set_budget(ResourceCategory::Textures, None);
+        drop(b);
+        assert_eq!(resource_manager.collect_unused(), 1);
+
+        //An evicted model releases its dependencies, which are evicted in turn.
+        resource_manager.set_budget(ResourceCategory::Meshes, Some(0));
+        let open = |path: &Path| if path.extension().unwrap() == "bin" { Ok(Cursor::new(vec![0u8; 168])) } else { Ok(image_data()) };
+        let model = resource_manager.load_model_with_dependencies("cube.gltf", model_data("cube.bin", "wood.tga"), open).unwrap();
+        assert_eq!(resource_manager.enforce_budgets(), 0);
+        drop(model);
+        assert_eq!(resource_manager.enforce_budgets(), 2);
+        assert!(resource_manager.model_resources().is_empty());
+        assert!(resource_manager.buffer_resources().is_empty());
+        assert_eq!(resource_manager.residency_stats(ResourceCategory::Meshes).evictions, 2);
+    }
+
+    #[test]
+    fn resource_manager_texture_budget() {
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        resource_manager.set_budget(ResourceCategory::Textures, Some(0));
+        resource_manager.register_loader(TextureLoader).unwrap();
+        let texture = texture_compression::compress(&ImageResource::checkerboard(8, 4), TextureFormat::Bc1RgbaUnorm).unwrap();
+        let size = texture.memory_size();
+        let mut data = Vec::new();
+        TextureContainer::Dds.write(&texture, &mut data).unwrap();
+
+        //The textures of the TextureLoader are counted in the textures budget, and stay resident.
+        assert_eq!(resource_manager.set_budget(ResourceCategory::Textures, Some(size)), 0);
+        drop(resource_manager.load::<TextureResource, _>("a.dds", Cursor::new(data.clone())).unwrap());
+        assert_eq!(resource_manager.collect_unused(), 0);
+        let stats = resource_manager.residency_stats(ResourceCategory::Textures);
+        assert_eq!((stats.resident_count, stats.resident_bytes, stats.unused_bytes), (1, size, size));
+
+        //The least recently used texture is evicted over the budget, before the images used later.
+        let image = resource_manager.load_image("wood.tga", &mut Cursor::new(include_bytes!("../../test_resources/images/Untitled.tga").to_vec()), ColorFormat::RGBA).unwrap();
+        drop(image);
+        let b = resource_manager.load::<TextureResource, _>("b.dds", Cursor::new(data)).unwrap();
+        resource_manager.update();
+        assert!(!resource_manager.registry::<TextureResource>().unwrap().contains_path("a.dds"));
+        assert!(resource_manager.image_resources().is_empty());
+        assert!(resource_manager.get(&b).is_some());
+        let stats = resource_manager.residency_stats(ResourceCategory::Textures);
+        assert_eq!((stats.resident_count, stats.resident_bytes, stats.evictions), (1, size, 2));
+
+        resource_manager.set_budget(ResourceCategory::Textures, None);
+        drop(b);
+        assert_eq!(resource_manager.collect_unused(), 1);
+    }
+
+    #[test]
+    fn resource_manager_resource_groups() {
+        let resource_manager: ResourceManager<Cursor<Vec<u8>>> = ResourceManager::new();
+        resource_manager.register_loader(DialogueLoader).unwrap();
+        resource_manager.set_group_manifest(ResourceGroupManifest::from_toml(r#"
+            [groups]
+            global = ["textures/font.tga", "dialogues/intro.dialogue"]
+            level_2 = ["models/cube.gltf", "textures/font.tga"]
+            level_3 = ["models/cube.gltf", "textures/sky.tga"]
+            broken = ["textures/missing.tga"]
+        "#).unwrap());
+        let mut files = HashMap::new();
+        files.insert(PathBuf::from("models/cube.gltf"), model_data("cube.bin", "wood.tga").into_inner());
+        files.insert(PathBuf::from("models/cube.bin"), vec![0u8; 168]);
+        files.insert(PathBuf::from("models/wood.tga"), include_bytes!("../../test_resources/images/Untitled.tga").to_vec());
+        files.insert(PathBuf::from("textures/font.tga"), include_bytes!("../../test_resources/images/Untitled.tga").to_vec());
+        files.insert(PathBuf::from("textures/sky.tga"), include_bytes!("../../test_resources/images/Untitled.tga").to_vec());
+        files.insert(PathBuf::from("dialogues/intro.dialogue"), b"Hello.".to_vec());
+        let opened = RefCell::new(Vec::new());
+        let open = |path: &Path| {
+            opened.borrow_mut().push(path.to_path_buf());
+            files.get(path).cloned().map(Cursor::new).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+        };
+
+        let report = resource_manager.load_group("global", &open).unwrap();
+        assert_eq!(report.loaded, vec![PathBuf::from("textures/font.tga"), PathBuf::from("dialogues/intro.dialogue")]);
+        let dialogue: Handle<Dialogue> = resource_manager.registry::<Dialogue>().unwrap().handle("dialogues/intro.dialogue").unwrap();
+        assert_eq!(resource_manager.get(&dialogue).unwrap().0, vec![String::from("Hello.")]);
+        drop(dialogue);
+
+        //The resources of the other loaded groups are shared.
+        let report = resource_manager.load_group("level_2", &open).unwrap();
+        assert_eq!(report.loaded, vec![PathBuf::from("models/cube.gltf")]);
+        assert_eq!(report.shared, vec![PathBuf::from("textures/font.tga")]);
+        assert_eq!(resource_manager.loaded_groups(), vec![String::from("global"), String::from("level_2")]);
+        assert_eq!(resource_manager.collect_unused(), 0);
+
+        //The transition only loads and frees the delta.
+        opened.borrow_mut().clear();
+        let report = resource_manager.transition_groups(&["level_2"], &["level_3"], &open).unwrap();
+        assert_eq!(report.loaded, vec![PathBuf::from("textures/sky.tga")]);
+        assert_eq!(report.shared, vec![PathBuf::from("models/cube.gltf")]);
+        assert_eq!(*opened.borrow(), vec![PathBuf::from("textures/sky.tga")]);
+        assert!(!resource_manager.is_group_loaded("level_2"));
+        assert!(resource_manager.model_resources().contains_path("models/cube.gltf"));
+
+        //The shared resources stay loaded while one of their groups is loaded.
+        assert!(resource_manager.unload_group("global"));
+        assert!(!resource_manager.unload_group("global"));
+        assert!(!resource_manager.image_resources().contains_path("textures/font.tga"));
+        assert!(resource_manager.registry::<Dialogue>().unwrap().is_empty());
+        assert!(resource_manager.unload_group("level_3"));
+        assert!(resource_manager.model_resources().is_empty());
+        assert!(resource_manager.image_resources().is_empty());
+        assert!(resource_manager.buffer_resources().is_empty());
+
+        //A group which can't be loaded isn't loaded.
+        match resource_manager.load_group("level_4", &open) {
+            Err(ResourceManagerError::GroupError(_, ref group)) => assert_eq!(group, "level_4"),
+            _ => panic!("An unknown group should be an error."),
+        }
+        assert!(resource_manager.load_group("broken", &open).is_err());
+        assert!(resource_manager.loaded_groups().is_empty());
     }
 
     #[test]
@@ -7,9 +7,12 @@
 
 use std::error::Error;
 use std::fmt;
+use std::io::Error as IOError;
+use std::path::{Path, PathBuf};
 use resources::resource_errors::ResourceError;
 use maskerad_core::filesystem::filesystem_error::FileSystemError;
 use maskerad_core::allocators::errors::AllocationError;
+use maskerad_core::maskerad_error::{MaskeradError, ErrorKind};
 
 
 #[derive(Debug)]
@@ -17,10 +20,38 @@ pub enum ResourceManagerError {
     FilesystemError(String, FileSystemError),
     ResourceError(String, ResourceError),
     AllocationError(String, AllocationError),
+    //A resource file which can't be loaded, with its path.
+    LoadingError(String, PathBuf, ResourceError),
+    //A dependency which can't be opened, with its path.
+    MissingDependency(String, PathBuf, IOError),
+    //The chain of dependencies leading back to the first resource.
+    DependencyCycle(String, Vec<PathBuf>),
+    //A resource which can't be unloaded, with its path.
+    ReferencedResource(String, PathBuf),
+    //A resource type without resource loader, or a resource loader which can't be registered.
+    LoaderError(String),
+    //A resource without cooked file in the cook manifest, with its path.
+    NotCooked(String, PathBuf),
+    //A resource group which can't be loaded, with its name.
+    GroupError(String, String),
 }
 
-unsafe impl Send for ResourceManagerError {}
-unsafe impl Sync for ResourceManagerError {}
+impl ResourceManagerError {
+    //The path of the resource which couldn't be loaded.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            &ResourceManagerError::LoadingError(_, ref path, _) |
+            &ResourceManagerError::MissingDependency(_, ref path, _) |
+            &ResourceManagerError::ReferencedResource(_, ref path) |
+            &ResourceManagerError::NotCooked(_, ref path) => {
+                Some(path.as_path())
+            },
+            _ => {
+                None
+            },
+        }
+    }
+}
 
 impl fmt::Display for ResourceManagerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -34,26 +65,34 @@ impl fmt::Display for ResourceManagerError {
             &ResourceManagerError::AllocationError(ref description, _) => {
                 write!(f, "Allocation error: {}", description)
             },
-        }
-    }
-}
-
-impl Error for ResourceManagerError {
-    fn description(&self) -> &str {
-        match self {
-            &ResourceManagerError::FilesystemError(_, _) => {
-                "ResourceNotFound"
+            &ResourceManagerError::LoadingError(ref description, ref path, _) => {
+                write!(f, "Loading error: {} ({})", description, path.display())
+            },
+            &ResourceManagerError::MissingDependency(ref description, ref path, _) => {
+                write!(f, "Missing dependency: {} ({})", description, path.display())
             },
-            &ResourceManagerError::ResourceError(_, _) => {
-                "ResourceError"
+            &ResourceManagerError::DependencyCycle(ref description, ref chain) => {
+                let chain: Vec<String> = chain.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "Dependency cycle: {} ({})", description, chain.join(" -> "))
             },
-            &ResourceManagerError::AllocationError(_, _) => {
-                "AllocationError"
+            &ResourceManagerError::ReferencedResource(ref description, ref path) => {
+                write!(f, "Referenced resource: {} ({})", description, path.display())
+            },
+            &ResourceManagerError::LoaderError(ref description) => {
+                write!(f, "Loader error: {}", description)
+            },
+            &ResourceManagerError::NotCooked(ref description, ref path) => {
+                write!(f, "Not cooked: {} ({})", description, path.display())
+            },
+            &ResourceManagerError::GroupError(ref description, ref group) => {
+                write!(f, "Group error: {} ({})", description, group)
             },
         }
     }
+}
 
-    fn cause(&self) -> Option<&Error> {
+impl Error for ResourceManagerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             &ResourceManagerError::FilesystemError(_, ref file_system_error) => {
                 Some(file_system_error)
@@ -64,6 +103,19 @@ impl Error for ResourceManagerError {
             &ResourceManagerError::AllocationError(_, ref alloc_error) => {
                 Some(alloc_error)
             },
+            &ResourceManagerError::LoadingError(_, _, ref resource_error) => {
+                Some(resource_error)
+            },
+            &ResourceManagerError::MissingDependency(_, _, ref io_error) => {
+                Some(io_error)
+            },
+            &ResourceManagerError::DependencyCycle(_, _) |
+            &ResourceManagerError::ReferencedResource(_, _) |
+            &ResourceManagerError::LoaderError(_) |
+            &ResourceManagerError::NotCooked(_, _) |
+            &ResourceManagerError::GroupError(_, _) => {
+                None
+            },
         }
     }
 }
@@ -86,4 +138,10 @@ impl From<AllocationError> for ResourceManagerError {
     fn from(error: AllocationError) -> Self {
         ResourceManagerError::AllocationError(format!("Error while allocating something in an allocator."), error)
     }
+}
+
+impl From<ResourceManagerError> for MaskeradError {
+    fn from(error: ResourceManagerError) -> Self {
+        MaskeradError::new(ErrorKind::Resource, error)
+    }
 }
\ No newline at end of file
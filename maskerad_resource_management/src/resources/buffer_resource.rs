@@ -0,0 +1,40 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io::Read;
+
+use resources::resource_errors::ResourceResult;
+
+//The raw data of a binary file, like the external buffers of the glTF models (vertices, indices, animations...).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferResource(Vec<u8>);
+
+impl From<Vec<u8>> for BufferResource {
+    fn from(data: Vec<u8>) -> Self {
+        BufferResource(data)
+    }
+}
+
+impl BufferResource {
+    pub fn from_reader<R: Read>(mut reader: R) -> ResourceResult<BufferResource> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(BufferResource(data))
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
@@ -5,11 +5,14 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use imagefmt::{Image, read_from, read, read_info, read_info_from, ColFmt, Info};
-use std::io::{Read, Seek};
+use imagefmt::{self, Image, read_from, read, read_info, read_info_from, ColFmt, Info};
+use imagefmt::Error as ImageError;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
 use std::path::Path;
 
 use resources::resource_errors::{ResourceError, ResourceResult};
+use resource_loader::extension_of;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ColorFormat {
@@ -42,29 +45,381 @@ impl Into<ColFmt> for ColorFormat {
     }
 }
 
+impl From<ColFmt> for ColorFormat {
+    fn from(format: ColFmt) -> Self {
+        match format {
+            ColFmt::Auto => ColorFormat::Auto,
+            ColFmt::Y => ColorFormat::Y,
+            ColFmt::YA => ColorFormat::YA,
+            ColFmt::AY => ColorFormat::AY,
+            ColFmt::RGB => ColorFormat::RGB,
+            ColFmt::RGBA => ColorFormat::RGBA,
+            ColFmt::BGR => ColorFormat::BGR,
+            ColFmt::BGRA => ColorFormat::BGRA,
+            ColFmt::ARGB => ColorFormat::ARGB,
+            ColFmt::ABGR => ColorFormat::ABGR,
+        }
+    }
+}
+
+//The number of channels of a color format. None for ColorFormat::Auto, which isn't the format of a decoded image.
+pub fn channel_count(format: ColorFormat) -> Option<usize> {
+    match format {
+        ColorFormat::Y => Some(1),
+        ColorFormat::YA | ColorFormat::AY => Some(2),
+        ColorFormat::RGB | ColorFormat::BGR => Some(3),
+        ColorFormat::RGBA | ColorFormat::BGRA | ColorFormat::ARGB | ColorFormat::ABGR => Some(4),
+        ColorFormat::Auto => None,
+    }
+}
+
+//The filter used to resize an image.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ResizeFilter {
+    //The closest pixel, for pixel art.
+    Nearest,
+    //The 4 closest pixels, weighted by their distance.
+    Bilinear,
+    //The average of the pixels covered by the resized pixel, for the downscaling.
+    Box,
+}
+
+//The file formats an image can be saved to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ImageFileFormat {
+    Tga,
+    Png,
+    //The BMP files can't store the grayscale images.
+    Bmp,
+}
+
+impl ImageFileFormat {
+    //The file format of a path, from its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match extension_of(path).as_deref() {
+            Some("tga") => Some(ImageFileFormat::Tga),
+            Some("png") => Some(ImageFileFormat::Png),
+            Some("bmp") => Some(ImageFileFormat::Bmp),
+            _ => None,
+        }
+    }
+}
+
+fn invalid_argument(description: &str, argument: &'static str) -> ResourceError {
+    ResourceError::ImageError(String::from(description), ImageError::InvalidArg(argument))
+}
+
+//The pixel of the image at x, y, the coordinates clamped to the image.
+fn clamped_pixel(image: &Image<u8>, channels: usize, x: isize, y: isize) -> &[u8] {
+    let x = x.max(0).min(image.w as isize - 1) as usize;
+    let y = y.max(0).min(image.h as isize - 1) as usize;
+    let start = (y * image.w + x) * channels;
+    &image.buf[start..start + channels]
+}
+
+fn resize_image(image: &Image<u8>, channels: usize, width: usize, height: usize, filter: ResizeFilter) -> Image<u8> {
+    let x_ratio = image.w as f32 / width as f32;
+    let y_ratio = image.h as f32 / height as f32;
+    let mut buf = Vec::with_capacity(width * height * channels);
+    for y in 0..height {
+        for x in 0..width {
+            match filter {
+                ResizeFilter::Nearest => {
+                    let sx = ((x as f32 + 0.5) * x_ratio) as isize;
+                    let sy = ((y as f32 + 0.5) * y_ratio) as isize;
+                    buf.extend_from_slice(clamped_pixel(image, channels, sx, sy));
+                },
+                ResizeFilter::Bilinear => {
+                    let sx = (x as f32 + 0.5) * x_ratio - 0.5;
+                    let sy = (y as f32 + 0.5) * y_ratio - 0.5;
+                    let (x0, y0) = (sx.floor(), sy.floor());
+                    let (fx, fy) = (sx - x0, sy - y0);
+                    let (x0, y0) = (x0 as isize, y0 as isize);
+                    let top_left = clamped_pixel(image, channels, x0, y0);
+                    let top_right = clamped_pixel(image, channels, x0 + 1, y0);
+                    let bottom_left = clamped_pixel(image, channels, x0, y0 + 1);
+                    let bottom_right = clamped_pixel(image, channels, x0 + 1, y0 + 1);
+                    for channel in 0..channels {
+                        let top = f32::from(top_left[channel]) * (1.0 - fx) + f32::from(top_right[channel]) * fx;
+                        let bottom = f32::from(bottom_left[channel]) * (1.0 - fx) + f32::from(bottom_right[channel]) * fx;
+                        buf.push((top * (1.0 - fy) + bottom * fy).round() as u8);
+                    }
+                },
+                ResizeFilter::Box => {
+                    //The covered pixels, at least one.
+                    let x_start = x * image.w / width;
+                    let x_end = ((x + 1) * image.w / width).max(x_start + 1);
+                    let y_start = y * image.h / height;
+                    let y_end = ((y + 1) * image.h / height).max(y_start + 1);
+                    let count = ((x_end - x_start) * (y_end - y_start)) as u32;
+                    for channel in 0..channels {
+                        let mut sum = 0u32;
+                        for sy in y_start..y_end {
+                            for sx in x_start..x_end {
+                                sum += u32::from(image.buf[(sy * image.w + sx) * channels + channel]);
+                            }
+                        }
+                        buf.push(((sum + count / 2) / count) as u8);
+                    }
+                },
+            }
+        }
+    }
+
+    Image {
+        w: width,
+        h: height,
+        fmt: image.fmt,
+        buf,
+    }
+}
+
+//The buffer of the image has the w * h pixels of its size.
+fn has_valid_size(image: &Image<u8>, channels: usize) -> bool {
+    channels != 0 && image.w.checked_mul(image.h).and_then(|pixels| pixels.checked_mul(channels)) == Some(image.buf.len())
+}
+
+fn flip_image(image: &mut Image<u8>, channels: usize, horizontally: bool) {
+    if !has_valid_size(image, channels) {
+        warn!("The image of {}x{} pixels has a buffer of {} bytes, it is not flipped.", image.w, image.h, image.buf.len());
+        return;
+    }
+    let row_length = image.w * channels;
+    if horizontally {
+        for row in image.buf.chunks_mut(row_length) {
+            for x in 0..image.w / 2 {
+                let mirror = image.w - 1 - x;
+                for channel in 0..channels {
+                    row.swap(x * channels + channel, mirror * channels + channel);
+                }
+            }
+        }
+    } else {
+        for y in 0..image.h / 2 {
+            let (top, bottom) = image.buf.split_at_mut((image.h - 1 - y) * row_length);
+            top[y * row_length..(y + 1) * row_length].swap_with_slice(&mut bottom[..row_length]);
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct ImageResource(Image<u8>);
+pub struct ImageResource {
+    image: Image<u8>,
+    //The mip levels after the base level, from the largest to the smallest.
+    mipmaps: Vec<Image<u8>>,
+    premultiplied: bool,
+}
 
 impl From<Image<u8>> for ImageResource {
     fn from(image: Image<u8>) -> Self {
-        ImageResource(image)
+        ImageResource {
+            image,
+            mipmaps: Vec::new(),
+            premultiplied: false,
+        }
     }
 }
 
 impl ImageResource {
+    //A magenta and black checkerboard, in RGBA, used in place of the images which can't be loaded.
+    pub fn checkerboard(size: usize, cell_size: usize) -> ImageResource {
+        let cell_size = cell_size.max(1);
+        let mut buf = Vec::with_capacity(size * size * 4);
+        for y in 0..size {
+            for x in 0..size {
+                if (x / cell_size + y / cell_size) & 1 == 0 {
+                    buf.extend_from_slice(&[255, 0, 255, 255]);
+                } else {
+                    buf.extend_from_slice(&[0, 0, 0, 255]);
+                }
+            }
+        }
+
+        ImageResource::from(Image {
+            w: size,
+            h: size,
+            fmt: ColFmt::RGBA,
+            buf,
+        })
+    }
+
     pub fn from_path<P, C>(path: P, requested_format: C) -> ResourceResult<ImageResource> where
         P: AsRef<Path>,
         C: Into<ColFmt>
     {
         let img = read(path.as_ref(), requested_format.into())?;
-        Ok(ImageResource(img))
+        Ok(ImageResource::from(img))
     }
 
     pub fn from_reader<R: Read + Seek, C>(reader: &mut R, requested_format: C) -> ResourceResult<ImageResource> where
         C: Into<ColFmt>
     {
         let img = read_from(reader, requested_format.into())?;
-        Ok(ImageResource(img))
+        Ok(ImageResource::from(img))
+    }
+
+    //The color format of the decoded pixels.
+    pub fn color_format(&self) -> ColorFormat {
+        ColorFormat::from(self.image.fmt)
+    }
+
+    pub fn width(&self) -> usize {
+        self.image.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.image.h
+    }
+
+    //The number of channels of a pixel, 0 if the color format is unknown.
+    pub fn channel_count(&self) -> usize {
+        channel_count(self.color_format()).unwrap_or(0)
+    }
+
+    //The pixels of the base level, row by row from the top, in the color format of the image.
+    pub fn pixels(&self) -> &[u8] {
+        self.image.buf.as_slice()
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        self.image.buf.as_mut_slice()
+    }
+
+    //The channels of the pixel at x, y in the base level.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<&[u8]> {
+        let channels = self.channel_count();
+        if x >= self.image.w || y >= self.image.h || channels == 0 {
+            return None;
+        }
+        let start = (y * self.image.w + x) * channels;
+        self.image.buf.get(start..start + channels)
+    }
+
+    //The image, with its mip levels, converted to another color format.
+    pub fn convert(&self, format: ColorFormat) -> ResourceResult<ImageResource> {
+        let mut converted = ImageResource::from(self.image.convert(format.into())?);
+        for mipmap in self.mipmaps.iter() {
+            converted.mipmaps.push(mipmap.convert(format.into())?);
+        }
+        converted.premultiplied = self.premultiplied;
+        Ok(converted)
+    }
+
+    //A region of the base level, without mip levels.
+    pub fn sub_image(&self, x: usize, y: usize, width: usize, height: usize) -> ResourceResult<ImageResource> {
+        let channels = self.channel_count();
+        if !has_valid_size(&self.image, channels) {
+            return Err(invalid_argument("The buffer of the image doesn't match its size.", "invalid image"));
+        }
+        let outside = match (x.checked_add(width), y.checked_add(height)) {
+            (Some(right), Some(bottom)) => right > self.image.w || bottom > self.image.h,
+            _ => true,
+        };
+        if width == 0 || height == 0 || outside {
+            return Err(invalid_argument("The region is outside of the image.", "invalid region"));
+        }
+
+        let row_length = self.image.w * channels;
+        let mut buf = Vec::with_capacity(width * height * channels);
+        for row in self.image.buf.chunks(row_length).skip(y).take(height) {
+            buf.extend_from_slice(&row[x * channels..(x + width) * channels]);
+        }
+        let mut sub_image = ImageResource::from(Image {
+            w: width,
+            h: height,
+            fmt: self.image.fmt,
+            buf,
+        });
+        sub_image.premultiplied = self.premultiplied;
+        Ok(sub_image)
+    }
+
+    //Mirror the image, and its mip levels, left to right.
+    pub fn flip_horizontally(&mut self) -> &mut Self {
+        let channels = self.channel_count();
+        flip_image(&mut self.image, channels, true);
+        for mipmap in self.mipmaps.iter_mut() {
+            flip_image(mipmap, channels, true);
+        }
+        self
+    }
+
+    //Mirror the image, and its mip levels, top to bottom. The graphics APIs with the first row at the bottom
+    //want the images flipped vertically.
+    pub fn flip_vertically(&mut self) -> &mut Self {
+        let channels = self.channel_count();
+        flip_image(&mut self.image, channels, false);
+        for mipmap in self.mipmaps.iter_mut() {
+            flip_image(mipmap, channels, false);
+        }
+        self
+    }
+
+    //The base level resized. The mip levels aren't resized, generate them again from the resized image.
+    pub fn resize(&self, width: usize, height: usize, filter: ResizeFilter) -> ResourceResult<ImageResource> {
+        let channels = self.channel_count();
+        if width == 0 || height == 0 || !has_valid_size(&self.image, channels) {
+            return Err(invalid_argument("The image can't be resized to an empty image, or from an invalid image.", "invalid dimensions"));
+        }
+
+        let mut resized = ImageResource::from(resize_image(&self.image, channels, width, height, filter));
+        resized.premultiplied = self.premultiplied;
+        Ok(resized)
+    }
+
+    //Write the base level in the file format. The mip levels aren't written.
+    pub fn write_to<W: Write>(&self, writer: &mut W, file_format: ImageFileFormat) -> ResourceResult<()> {
+        let image = &self.image;
+        let color_type = image.fmt.color_type();
+        match file_format {
+            ImageFileFormat::Tga => imagefmt::tga::write(writer, image.w, image.h, image.fmt, image.buf.as_slice(), color_type, None)?,
+            ImageFileFormat::Png => imagefmt::png::write(writer, image.w, image.h, image.fmt, image.buf.as_slice(), color_type, None)?,
+            ImageFileFormat::Bmp => imagefmt::bmp::write(writer, image.w, image.h, image.fmt, image.buf.as_slice(), color_type, None)?,
+        }
+        Ok(())
+    }
+
+    //Save the base level in a file. The file format is found from the extension of the path: tga, png or bmp.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ResourceResult<()> {
+        let file_format = ImageFileFormat::from_path(path.as_ref()).ok_or_else(|| {
+            ResourceError::ImageError(format!("The image {} can't be saved in this file format.", path.as_ref().display()), ImageError::Unsupported("image type not supported for writing"))
+        })?;
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+        self.write_to(&mut writer, file_format)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    //The base level of the image.
+    pub fn image(&self) -> &Image<u8> {
+        &self.image
+    }
+
+    //The number of mip levels, the base level included.
+    pub fn mip_level_count(&self) -> usize {
+        self.mipmaps.len() + 1
+    }
+
+    //The level 0 is the base level.
+    pub fn mip_level(&self, level: usize) -> Option<&Image<u8>> {
+        match level {
+            0 => Some(&self.image),
+            _ => self.mipmaps.get(level - 1),
+        }
+    }
+
+    pub fn mipmaps_mut(&mut self) -> &mut Vec<Image<u8>> {
+        &mut self.mipmaps
+    }
+
+    //The color channels have been multiplied by the alpha channel.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    pub fn set_premultiplied(&mut self, premultiplied: bool) -> &mut Self {
+        self.premultiplied = premultiplied;
+        self
     }
 
     pub fn infos_from_path<P>(path: P) -> ResourceResult<Info> where
@@ -82,3 +437,84 @@ impl ImageResource {
     }
 }
 
+
+#[cfg(test)]
+mod image_resource_test {
+    use super::*;
+    use std::io::Cursor;
+
+    //A 3x2 RGB image, the pixel x, y has the value 10 * y + x.
+    fn image() -> ImageResource {
+        ImageResource::from(Image {
+            w: 3,
+            h: 2,
+            fmt: ColFmt::RGB,
+            buf: (0..6).flat_map(|pixel| {
+                let value = (pixel / 3) * 10 + pixel % 3;
+                vec![value; 3]
+            }).collect(),
+        })
+    }
+
+    #[test]
+    fn image_accessors_and_transformations() {
+        let mut image = image();
+        assert_eq!((image.width(), image.height(), image.channel_count()), (3, 2, 3));
+        assert_eq!(image.pixel(2, 1), Some(&[12u8, 12, 12][..]));
+        assert!(image.pixel(3, 0).is_none());
+        assert_eq!(image.color_format(), ColorFormat::RGB);
+
+        let converted = image.convert(ColorFormat::RGBA).unwrap();
+        assert_eq!(converted.pixel(1, 0), Some(&[1u8, 1, 1, 255][..]));
+
+        let sub_image = image.sub_image(1, 1, 2, 1).unwrap();
+        assert_eq!(sub_image.pixels(), &[11, 11, 11, 12, 12, 12]);
+        assert!(image.sub_image(2, 0, 2, 1).is_err());
+        assert!(image.sub_image(usize::MAX, 0, 2, 1).is_err());
+        assert!(image.sub_image(0, 1, 1, usize::MAX).is_err());
+
+        image.flip_horizontally();
+        assert_eq!(image.pixel(0, 0), Some(&[2u8, 2, 2][..]));
+        image.flip_vertically();
+        assert_eq!(image.pixel(0, 0), Some(&[12u8, 12, 12][..]));
+        assert_eq!(image.pixel(2, 1), Some(&[0u8, 0, 0][..]));
+
+        //A buffer which doesn't match the size of the image is refused, not indexed out of bounds.
+        let mut invalid = ImageResource::from(Image {
+            w: 3,
+            h: 2,
+            fmt: ColFmt::RGB,
+            buf: vec![0; 4],
+        });
+        assert!(invalid.sub_image(0, 0, 1, 1).is_err());
+        invalid.flip_horizontally().flip_vertically();
+        assert_eq!(invalid.pixels(), &[0; 4]);
+    }
+
+    #[test]
+    fn image_resize() {
+        let image = ImageResource::from(Image {
+            w: 2,
+            h: 2,
+            fmt: ColFmt::Y,
+            buf: vec![0, 100, 100, 200],
+        });
+        assert_eq!(image.resize(4, 4, ResizeFilter::Nearest).unwrap().pixels()[..4], [0, 0, 100, 100]);
+        assert_eq!(image.resize(4, 1, ResizeFilter::Bilinear).unwrap().pixels(), &[50, 75, 125, 150]);
+        assert_eq!(image.resize(1, 1, ResizeFilter::Box).unwrap().pixels(), &[100]);
+        assert!(image.resize(0, 1, ResizeFilter::Box).is_err());
+    }
+
+    #[test]
+    fn image_round_trip() {
+        let image = image().convert(ColorFormat::RGBA).unwrap();
+        for file_format in [ImageFileFormat::Tga, ImageFileFormat::Png, ImageFileFormat::Bmp].iter() {
+            let mut file = Vec::new();
+            image.write_to(&mut file, *file_format).unwrap();
+            let read = ImageResource::from_reader(&mut Cursor::new(file), ColorFormat::RGBA).unwrap();
+            assert_eq!(read.pixels(), image.pixels());
+        }
+        assert_eq!(ImageFileFormat::from_path("textures/Wood.PNG"), Some(ImageFileFormat::Png));
+        assert!(image.save("textures/wood.jpg").is_err());
+    }
+}
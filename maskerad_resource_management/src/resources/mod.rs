@@ -8,4 +8,7 @@
 pub mod model_resource;
 pub mod image_resource;
 pub mod sound_resource;
+pub mod buffer_resource;
+pub mod texture_resource;
+pub mod texture_containers;
 pub mod resource_errors;
\ No newline at end of file
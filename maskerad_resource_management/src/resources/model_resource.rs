@@ -6,16 +6,78 @@
 // copied, modified, or distributed except according to those terms.
 
 use gltf::{Gltf, Glb};
+use gltf::image::Data;
+use std::collections::HashMap;
 use std::io::Read;
+use std::mem;
+use std::path::Path;
 
 use resources::resource_errors::ResourceResult;
+use dependencies::{Dependency, DependencyKind};
+use maskerad_core::math::bounding_volumes::Aabb;
+
+//A cube of size 1 centered on the origin: 36 u16 indices followed by 8 positions, in an embedded buffer.
+const UNIT_CUBE_GLTF: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [ { "nodes": [0] } ],
+    "nodes": [ { "mesh": 0 } ],
+    "meshes": [ { "primitives": [ { "attributes": { "POSITION": 1 }, "indices": 0 } ] } ],
+    "buffers": [ {
+        "byteLength": 168,
+        "uri": "data:application/octet-stream;base64,AAABAAMAAAADAAIABAAGAAcABAAHAAUAAAAEAAUAAAAFAAEAAgADAAcAAgAHAAYAAAACAAYAAAAGAAQAAQAFAAcAAQAHAAMAAAAAvwAAAL8AAAC/AAAAvwAAAL8AAAA/AAAAvwAAAD8AAAC/AAAAvwAAAD8AAAA/AAAAPwAAAL8AAAC/AAAAPwAAAL8AAAA/AAAAPwAAAD8AAAC/AAAAPwAAAD8AAAA/"
+    } ],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 72, "target": 34963 },
+        { "buffer": 0, "byteOffset": 72, "byteLength": 96, "target": 34962 }
+    ],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5123, "count": 36, "type": "SCALAR" },
+        { "bufferView": 1, "componentType": 5126, "count": 8, "type": "VEC3", "min": [-0.5, -0.5, -0.5], "max": [0.5, 0.5, 0.5] }
+    ]
+}"#;
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+//Decode the base64 data of a data uri. None if the data isn't valid base64.
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(data.len() / 4 * 3);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+    for byte in data.trim_end_matches('=').bytes() {
+        let value = BASE64_ALPHABET.iter().position(|&character| character == byte)? as u32;
+        bits = (bits << 6) | value;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            decoded.push((bits >> bit_count) as u8);
+        }
+    }
+    Some(decoded)
+}
 
 #[derive(Debug)]
-pub struct ModelResource(Gltf);
+pub struct ModelResource {
+    gltf: Gltf,
+    //The binary chunk of a GLB file.
+    blob: Option<Vec<u8>>,
+    //The bounding box of the meshes, computed by a post processor.
+    bounds: Option<Aabb>,
+    //The tangents of the primitives, by mesh and primitive index, computed by a post processor.
+    tangents: HashMap<(usize, usize), Vec<[f32; 4]>>,
+    //The data of the external buffers, by buffer index, given to the post processors by the resource manager.
+    external_buffers: HashMap<usize, Vec<u8>>,
+}
 
 impl From<Gltf> for ModelResource {
-    fn from(model: Gltf) -> Self {
-        ModelResource(model)
+    fn from(gltf: Gltf) -> Self {
+        ModelResource {
+            gltf,
+            blob: None,
+            bounds: None,
+            tangents: HashMap::new(),
+            external_buffers: HashMap::new(),
+        }
     }
 }
 
@@ -24,11 +86,126 @@ impl ModelResource {
         G: AsRef<Glb<'a>>
     {
         let gltf = Gltf::from_glb(glb.as_ref())?.validate_completely()?;
-        Ok(ModelResource(gltf))
+        let mut model = ModelResource::from(gltf);
+        model.blob = glb.as_ref().bin.map(|bin| bin.to_vec());
+        Ok(model)
+    }
+
+    //A unit cube, used in place of the models which can't be loaded.
+    pub fn unit_cube() -> ResourceResult<ModelResource> {
+        let gltf = Gltf::from_str(UNIT_CUBE_GLTF)?.validate_completely()?;
+        Ok(ModelResource::from(gltf))
+    }
+
+    //A glTF file, or a GLB file with its binary chunk.
+    pub fn from_reader<R: Read>(mut reader: R) -> ResourceResult<ModelResource> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.starts_with(b"glTF") {
+            return ModelResource::from_glb(Box::new(Glb::from_slice(data.as_slice())?));
+        }
+        let gltf = Gltf::from_slice(data.as_slice())?.validate_completely()?;
+        Ok(ModelResource::from(gltf))
+    }
+
+    pub fn gltf(&self) -> &Gltf {
+        &self.gltf
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    pub fn set_bounds(&mut self, bounds: Option<Aabb>) -> &mut Self {
+        self.bounds = bounds;
+        self
     }
 
-    pub fn from_reader<R: Read>(reader: R) -> ResourceResult<ModelResource> {
-        let gltf = Gltf::from_reader(reader)?.validate_completely()?;
-        Ok(ModelResource(gltf))
+    //The tangents of a primitive, xyz and the handedness of the bitangent in w.
+    pub fn tangents(&self, mesh: usize, primitive: usize) -> Option<&[[f32; 4]]> {
+        self.tangents.get(&(mesh, primitive)).map(|tangents| tangents.as_slice())
     }
-}
\ No newline at end of file
+
+    pub fn set_tangents(&mut self, mesh: usize, primitive: usize, tangents: Option<Vec<[f32; 4]>>) -> &mut Self {
+        match tangents {
+            Some(tangents) => {
+                self.tangents.insert((mesh, primitive), tangents);
+            },
+            None => {
+                self.tangents.remove(&(mesh, primitive));
+            },
+        }
+        self
+    }
+
+    //The content of a buffer: the binary chunk of a GLB file, or a base64 data uri. None for the external buffers,
+    //loaded as dependencies, unless they're given to the post processors with set_external_buffers().
+    pub fn buffer_data(&self, index: usize) -> Option<Vec<u8>> {
+        let buffer = self.gltf.buffers().nth(index)?;
+        let uri = buffer.uri();
+        if uri == "#bin" {
+            return self.blob.clone();
+        }
+        if !uri.starts_with("data:") {
+            return self.external_buffers.get(&index).cloned();
+        }
+        let data = uri.split(";base64,").nth(1)?;
+        decode_base64(data)
+    }
+
+    //The data of the external buffers, by buffer index, read by the post processors. The resource manager
+    //removes them after the post processing: the buffers are resources of their own.
+    pub fn set_external_buffers(&mut self, buffers: HashMap<usize, Vec<u8>>) -> &mut Self {
+        self.external_buffers = buffers;
+        self
+    }
+
+    //The bytes of the data held by the model: the binary chunk of a GLB file, the base64 data uris of its
+    //embedded buffers and the tangents. The external buffers are separate resources.
+    pub fn data_size(&self) -> usize {
+        let blob = self.blob.as_ref().map_or(0, Vec::len);
+        let data_uris: usize = self.gltf.buffers().filter(|buffer| buffer.uri().starts_with("data:")).map(|buffer| buffer.uri().len()).sum();
+        let tangents: usize = self.tangents.values().map(|tangents| tangents.len() * mem::size_of::<[f32; 4]>()).sum();
+        blob + data_uris + tangents
+    }
+
+    //The external buffers and images of the model loaded from model_path.
+    pub fn dependencies<P: AsRef<Path>>(&self, model_path: P) -> Vec<Dependency> {
+        let model_path = model_path.as_ref();
+        let buffers = self.gltf.buffers().filter_map(|buffer| {
+            Dependency::from_uri(model_path, buffer.uri(), DependencyKind::Buffer)
+        });
+        let images = self.gltf.images().filter_map(|image| {
+            match image.data() {
+                Data::Uri { uri, .. } => Dependency::from_uri(model_path, uri, DependencyKind::Image),
+                Data::View { .. } => None,
+            }
+        });
+
+        let mut dependencies: Vec<Dependency> = Vec::new();
+        for dependency in buffers.chain(images) {
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+        dependencies
+    }
+}
+
+//A GLB file with a JSON chunk and a binary chunk, for the tests.
+#[cfg(test)]
+pub(crate) fn glb_data(json: &str, bin: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    let padding = (4 - json.len() % 4) % 4;
+    json.extend(vec![b' '; padding]);
+    let mut data = b"glTF".to_vec();
+    for value in [2, (12 + 8 + json.len() + 8 + bin.len()) as u32, json.len() as u32, 0x4E4F_534A].iter() {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend(json);
+    for value in [bin.len() as u32, 0x004E_4942].iter() {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(bin);
+    data
+}
@@ -7,21 +7,31 @@
 
 use std::fmt;
 use std::error::Error;
+use std::io::Error as IOError;
 
 use imagefmt::Error as ImageError;
 use lewton::VorbisError as SoundError;
 use gltf::Error as ModelError;
+use toml::de::Error as MetadataError;
+use maskerad_core::maskerad_error::{MaskeradError, ErrorKind};
 
 #[derive(Debug)]
 pub enum ResourceError {
     ImageError(String, ImageError),
     SoundError(String, SoundError),
     ModelError(String, ModelError),
+    BufferError(String, IOError),
+    MetadataError(String, MetadataError),
+    //A post processor which couldn't process a resource.
+    PostProcessingError(String),
+    //An error of a resource loader registered by the game.
+    CustomError(String, Box<dyn Error + Send + Sync>),
+    //A cooked file which is truncated, corrupt or cooked by another importer version.
+    CookedDataError(String, IOError),
+    //A texture file which is malformed, or in a format which isn't supported.
+    TextureError(String),
 }
 
-unsafe impl Send for ResourceError {}
-unsafe impl Sync for ResourceError {}
-
 impl fmt::Display for ResourceError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -34,26 +44,30 @@ impl fmt::Display for ResourceError {
             &ResourceError::ModelError(ref desc, _) => {
                 write!(f, "Model resource error: {}", desc)
             },
-        }
-    }
-}
-
-impl Error for ResourceError {
-    fn description(&self) -> &str {
-        match self {
-            &ResourceError::ImageError(_, _) => {
-                "ImageError"
+            &ResourceError::BufferError(ref desc, _) => {
+                write!(f, "Buffer resource error: {}", desc)
             },
-            &ResourceError::SoundError(_, _) => {
-                "SoundError"
+            &ResourceError::MetadataError(ref desc, _) => {
+                write!(f, "Metadata error: {}", desc)
             },
-            &ResourceError::ModelError(_, _) => {
-                "ModelError"
+            &ResourceError::PostProcessingError(ref desc) => {
+                write!(f, "Post processing error: {}", desc)
+            },
+            &ResourceError::CustomError(ref desc, _) => {
+                write!(f, "Custom resource error: {}", desc)
+            },
+            &ResourceError::CookedDataError(ref desc, _) => {
+                write!(f, "Cooked data error: {}", desc)
+            },
+            &ResourceError::TextureError(ref desc) => {
+                write!(f, "Texture resource error: {}", desc)
             },
         }
     }
+}
 
-    fn cause(&self) -> Option<&Error> {
+impl Error for ResourceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             &ResourceError::ImageError(_, ref image_error) => {
                 Some(image_error)
@@ -64,6 +78,22 @@ impl Error for ResourceError {
             &ResourceError::ModelError(_, ref model_error) => {
                 Some(model_error)
             },
+            &ResourceError::BufferError(_, ref io_error) => {
+                Some(io_error)
+            },
+            &ResourceError::MetadataError(_, ref metadata_error) => {
+                Some(metadata_error)
+            },
+            &ResourceError::PostProcessingError(_) |
+            &ResourceError::TextureError(_) => {
+                None
+            },
+            &ResourceError::CustomError(_, ref custom_error) => {
+                Some(custom_error.as_ref())
+            },
+            &ResourceError::CookedDataError(_, ref io_error) => {
+                Some(io_error)
+            },
         }
     }
 }
@@ -86,4 +116,22 @@ impl From<ModelError> for ResourceError {
     fn from(error: ModelError) -> Self {
         ResourceError::ModelError(String::from("Erro while loading a model file."), error)
     }
+}
+
+impl From<IOError> for ResourceError {
+    fn from(error: IOError) -> Self {
+        ResourceError::BufferError(String::from("Error while reading a buffer file."), error)
+    }
+}
+
+impl From<MetadataError> for ResourceError {
+    fn from(error: MetadataError) -> Self {
+        ResourceError::MetadataError(String::from("Error while parsing an asset metadata file."), error)
+    }
+}
+
+impl From<ResourceError> for MaskeradError {
+    fn from(error: ResourceError) -> Self {
+        MaskeradError::new(ErrorKind::Resource, error)
+    }
 }
\ No newline at end of file
@@ -13,11 +13,40 @@ use std::io::{Read, Seek};
 use resources::resource_errors::{ResourceError, ResourceResult};
 
 
-pub struct SoundResource<T: Read + Seek>(OggStreamReader<T>);
+//The number of samples per channel in the packets of a decoded sound.
+const DECODED_PACKET_SIZE: usize = 4096;
+
+//The samples of a sound decoded in memory, one vector of samples per channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedSound {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<i16>>,
+}
+
+impl DecodedSound {
+    //The number of samples per channel.
+    pub fn len(&self) -> usize {
+        self.channels.iter().map(|channel| channel.len()).max().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+enum SoundData<T: Read + Seek> {
+    Stream(Box<OggStreamReader<T>>),
+    //A sound decoded in memory, and the position of the next packet.
+    Decoded(DecodedSound, usize),
+    //A sound without any packet, used in place of the sounds which can't be loaded.
+    Silence,
+}
+
+pub struct SoundResource<T: Read + Seek>(SoundData<T>);
 
 impl<T: Read + Seek> From<OggStreamReader<T>> for SoundResource<T> {
     fn from(ogg_stream: OggStreamReader<T>) -> Self {
-        SoundResource(ogg_stream)
+        SoundResource(SoundData::Stream(Box::new(ogg_stream)))
     }
 }
 
@@ -25,12 +54,101 @@ impl<T: Read + Seek> SoundResource<T> {
     //TODO: Doesn't work well for async I/O. See this: https://docs.rs/lewton/0.8.0/lewton/inside_ogg/async/index.html
     pub fn from_reader(reader: T) -> ResourceResult<SoundResource<T>> {
         let sound_stream = OggStreamReader::new(reader)?;
-        Ok(SoundResource(sound_stream))
+        Ok(SoundResource(SoundData::Stream(Box::new(sound_stream))))
+    }
+
+    //A sound already decoded, from a cooked sound file.
+    pub fn from_decoded(sound: DecodedSound) -> SoundResource<T> {
+        SoundResource(SoundData::Decoded(sound, 0))
+    }
+
+    pub fn silence() -> SoundResource<T> {
+        SoundResource(SoundData::Silence)
+    }
+
+    pub fn is_silence(&self) -> bool {
+        match self.0 {
+            SoundData::Silence => true,
+            SoundData::Stream(_) | SoundData::Decoded(_, _) => false,
+        }
+    }
+
+    //None for a silence.
+    pub fn sample_rate(&self) -> Option<u32> {
+        match self.0 {
+            SoundData::Stream(ref stream) => Some(stream.ident_hdr.audio_sample_rate),
+            SoundData::Decoded(ref sound, _) => Some(sound.sample_rate),
+            SoundData::Silence => None,
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        match self.0 {
+            SoundData::Stream(ref stream) => stream.ident_hdr.audio_channels as usize,
+            SoundData::Decoded(ref sound, _) => sound.channels.len(),
+            SoundData::Silence => 0,
+        }
+    }
+
+    //Decode the remaining packets of the stream in memory. The short sounds, or the sounds which must be
+    //post processed, are decoded once instead of being decoded while they're played.
+    pub fn decode(&mut self) -> ResourceResult<()> {
+        let sound = match self.0 {
+            SoundData::Stream(ref mut stream) => {
+                let channel_count = stream.ident_hdr.audio_channels as usize;
+                let mut sound = DecodedSound {
+                    sample_rate: stream.ident_hdr.audio_sample_rate,
+                    channels: vec![Vec::new(); channel_count],
+                };
+                while let Some(packet) = stream.read_dec_packet()? {
+                    for (channel, samples) in sound.channels.iter_mut().zip(packet) {
+                        channel.extend(samples);
+                    }
+                }
+                sound
+            },
+            SoundData::Decoded(_, _) | SoundData::Silence => return Ok(()),
+        };
+        self.0 = SoundData::Decoded(sound, 0);
+        Ok(())
+    }
+
+    //None if the sound hasn't been decoded in memory.
+    pub fn decoded(&self) -> Option<&DecodedSound> {
+        match self.0 {
+            SoundData::Decoded(ref sound, _) => Some(sound),
+            _ => None,
+        }
+    }
+
+    pub fn decoded_mut(&mut self) -> Option<&mut DecodedSound> {
+        match self.0 {
+            SoundData::Decoded(ref mut sound, _) => Some(sound),
+            _ => None,
+        }
     }
 
+    //None at the end of the stream. A silence has no packet.
     pub fn decompress_packet(&mut self) -> ResourceResult<Option<Vec<Vec<i16>>>> {
-        self.0.read_dec_packet().map_err(|vorbis_error| {
-            ResourceError::from(vorbis_error)
-        })
+        match self.0 {
+            SoundData::Stream(ref mut stream) => {
+                stream.read_dec_packet().map_err(|vorbis_error| {
+                    ResourceError::from(vorbis_error)
+                })
+            },
+            SoundData::Decoded(ref sound, ref mut position) => {
+                if *position >= sound.len() {
+                    return Ok(None);
+                }
+                let start = *position;
+                *position += DECODED_PACKET_SIZE;
+                Ok(Some(sound.channels.iter().map(|channel| {
+                    channel[start.min(channel.len())..(*position).min(channel.len())].to_vec()
+                }).collect()))
+            },
+            SoundData::Silence => {
+                Ok(None)
+            },
+        }
     }
 }
\ No newline at end of file
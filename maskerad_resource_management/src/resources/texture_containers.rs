@@ -0,0 +1,531 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::convert::TryFrom;
+use std::io::Write;
+
+use resources::resource_errors::{ResourceError, ResourceResult};
+use resources::texture_resource::{TextureFormat, TextureLayout, TextureResource};
+
+/*
+TEXTURE CONTAINERS.
+
+KTX2 (Khronos): the Vulkan format of the texture, its dimensions, and an index of its mip levels. The data of a
+level holds its layers, faces and depth slices, like the TextureResource. The supercompressed files (Basis
+Universal, Zstandard) aren't supported.
+
+DDS (DirectX): a FourCC (DXT1, DXT3, DXT5, ATI1, ATI2...) or the DXGI format of a DX10 header, and the surfaces
+stored layer by layer, face by face, each with its mip chain. The surfaces are reordered by mip level.
+The DDS files are written with a DX10 header, which has the arrays and the sRGB formats.
+
+The KTX2 files are written with the data format descriptor of their format, for the 8 bits RGBA, BC1 and BC3
+formats: the formats of the textures compressed by the asset cooker.
+*/
+
+pub const KTX2_IDENTIFIER: &[u8] = b"\xABKTX 20\xBB\r\n\x1A\n";
+pub const DDS_MAGIC: &[u8] = b"DDS ";
+
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+const KHR_DF_MODEL_RGBSDA: u32 = 1;
+const KHR_DF_MODEL_BC1A: u32 = 128;
+const KHR_DF_MODEL_BC3: u32 = 130;
+const KHR_DF_PRIMARIES_BT709: u32 = 1;
+const KHR_DF_TRANSFER_LINEAR: u32 = 1;
+const KHR_DF_TRANSFER_SRGB: u32 = 2;
+const KHR_DF_CHANNEL_ALPHA: u32 = 15;
+const KHR_DF_SAMPLE_DATATYPE_LINEAR: u32 = 0x10;
+
+const DDS_HEADER_SIZE: u32 = 124;
+const DDS_PIXEL_FORMAT_SIZE: u32 = 32;
+const DDS_DATA_OFFSET: usize = 128;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDSD_DEPTH: u32 = 0x80_0000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x40_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+const DDS_DIMENSION_TEXTURE2D: u32 = 3;
+const DDS_DIMENSION_TEXTURE3D: u32 = 4;
+
+//The formats of the FourCC of the DDS files without DX10 header.
+const FOUR_CC_FORMATS: [(&[u8; 4], TextureFormat); 10] = [
+    (b"DXT1", TextureFormat::Bc1RgbaUnorm),
+    (b"DXT2", TextureFormat::Bc2Unorm),
+    (b"DXT3", TextureFormat::Bc2Unorm),
+    (b"DXT4", TextureFormat::Bc3Unorm),
+    (b"DXT5", TextureFormat::Bc3Unorm),
+    (b"ATI1", TextureFormat::Bc4Unorm),
+    (b"BC4U", TextureFormat::Bc4Unorm),
+    (b"BC4S", TextureFormat::Bc4Snorm),
+    (b"ATI2", TextureFormat::Bc5Unorm),
+    (b"BC5U", TextureFormat::Bc5Unorm),
+];
+
+fn texture_error(description: &str) -> ResourceError {
+    ResourceError::TextureError(String::from(description))
+}
+
+fn truncated() -> ResourceError {
+    texture_error("The texture file is truncated.")
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> ResourceResult<u32> {
+    let field = offset.checked_add(4).and_then(|end| bytes.get(offset..end)).ok_or_else(truncated)?;
+    Ok(u32::from(field[0]) | u32::from(field[1]) << 8 | u32::from(field[2]) << 16 | u32::from(field[3]) << 24)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> ResourceResult<u64> {
+    Ok(u64::from(read_u32(bytes, offset)?) | u64::from(read_u32(bytes, offset + 4)?) << 32)
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> ResourceResult<()> {
+    writer.write_all(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8])?;
+    Ok(())
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> ResourceResult<()> {
+    write_u32(writer, value as u32)?;
+    write_u32(writer, (value >> 32) as u32)
+}
+
+//The container a texture is written in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TextureContainer {
+    Ktx2,
+    Dds,
+}
+
+impl TextureContainer {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ktx2" => Some(TextureContainer::Ktx2),
+            "dds" => Some(TextureContainer::Dds),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            TextureContainer::Ktx2 => "ktx2",
+            TextureContainer::Dds => "dds",
+        }
+    }
+
+    pub fn write<W: Write>(&self, texture: &TextureResource, writer: &mut W) -> ResourceResult<()> {
+        match *self {
+            TextureContainer::Ktx2 => write_ktx2(texture, writer),
+            TextureContainer::Dds => write_dds(texture, writer),
+        }
+    }
+}
+
+pub fn read_ktx2(bytes: &[u8]) -> ResourceResult<TextureResource> {
+    if !bytes.starts_with(KTX2_IDENTIFIER) {
+        return Err(texture_error("The texture isn't a KTX2 file."));
+    }
+    let vk_format = read_u32(bytes, 12)?;
+    let format = TextureFormat::from_vk_format(vk_format).ok_or_else(|| {
+        texture_error("The format of the KTX2 texture isn't supported.")
+    })?;
+    if read_u32(bytes, 44)? != 0 {
+        return Err(texture_error("The supercompressed KTX2 textures aren't supported."));
+    }
+
+    //The depth, the layers and the levels are 0 when the texture isn't 3D, isn't an array or has no mip chain.
+    let layout = TextureLayout {
+        format,
+        width: read_u32(bytes, 20)?,
+        height: read_u32(bytes, 24)?.max(1),
+        depth: read_u32(bytes, 28)?.max(1),
+        layer_count: read_u32(bytes, 32)?.max(1),
+        face_count: read_u32(bytes, 36)?,
+    };
+    let level_count = read_u32(bytes, 40)?.max(1);
+    if level_count > layout.max_mip_level_count() {
+        return Err(texture_error("The KTX2 texture has more mip levels than its dimensions allow."));
+    }
+
+    let mut levels = Vec::with_capacity(level_count as usize);
+    for level in 0..level_count {
+        let entry = KTX2_HEADER_SIZE + level as usize * KTX2_LEVEL_INDEX_ENTRY_SIZE;
+        let offset = usize::try_from(read_u64(bytes, entry)?).map_err(|_| truncated())?;
+        let length = usize::try_from(read_u64(bytes, entry + 8)?).map_err(|_| truncated())?;
+        //The level is checked against the layout and the file before being copied.
+        if length != layout.level_size(level)? {
+            return Err(texture_error("The size of a mip level doesn't match the layout of the KTX2 texture."));
+        }
+        let end = offset.checked_add(length).ok_or_else(truncated)?;
+        levels.push(bytes.get(offset..end).ok_or_else(truncated)?.to_vec());
+    }
+    TextureResource::new(layout, levels)
+}
+
+//The data format descriptor of a format written in the KTX2 files: its total size, and a basic descriptor block.
+fn ktx2_data_format_descriptor(format: TextureFormat) -> Option<Vec<u8>> {
+    //The samples: the bit offset, the bit length, the channel and the upper value.
+    let (color_model, samples): (u32, Vec<(u32, u32, u32, u32)>) = match format {
+        TextureFormat::R8G8B8A8Unorm | TextureFormat::R8G8B8A8Srgb => {
+            (KHR_DF_MODEL_RGBSDA, vec![(0, 8, 0, 255), (8, 8, 1, 255), (16, 8, 2, 255), (24, 8, KHR_DF_CHANNEL_ALPHA, 255)])
+        },
+        TextureFormat::B8G8R8A8Unorm | TextureFormat::B8G8R8A8Srgb => {
+            (KHR_DF_MODEL_RGBSDA, vec![(0, 8, 2, 255), (8, 8, 1, 255), (16, 8, 0, 255), (24, 8, KHR_DF_CHANNEL_ALPHA, 255)])
+        },
+        //The BC1 channel 1 is the color with the punch through alpha.
+        TextureFormat::Bc1RgbUnorm | TextureFormat::Bc1RgbSrgb => (KHR_DF_MODEL_BC1A, vec![(0, 64, 0, u32::MAX)]),
+        TextureFormat::Bc1RgbaUnorm | TextureFormat::Bc1RgbaSrgb => (KHR_DF_MODEL_BC1A, vec![(0, 64, 1, u32::MAX)]),
+        TextureFormat::Bc3Unorm | TextureFormat::Bc3Srgb => {
+            (KHR_DF_MODEL_BC3, vec![(0, 64, KHR_DF_CHANNEL_ALPHA, u32::MAX), (64, 64, 0, u32::MAX)])
+        },
+        _ => return None,
+    };
+    let transfer = if format.is_srgb() { KHR_DF_TRANSFER_SRGB } else { KHR_DF_TRANSFER_LINEAR };
+    let (block_width, block_height) = format.block_size();
+    let block_size = 24 + 16 * samples.len() as u32;
+
+    let mut descriptor = Vec::with_capacity(4 + block_size as usize);
+    let words = [
+        4 + block_size,
+        0,
+        2 | block_size << 16,
+        color_model | KHR_DF_PRIMARIES_BT709 << 8 | transfer << 16,
+        (block_width - 1) | (block_height - 1) << 8,
+        format.block_bytes() as u32,
+        0,
+    ];
+    for word in words.iter() {
+        descriptor.extend_from_slice(&word.to_le_bytes());
+    }
+    for &(offset, length, channel, upper) in samples.iter() {
+        //The alpha of the sRGB formats is linear.
+        let qualifiers = if format.is_srgb() && channel == KHR_DF_CHANNEL_ALPHA { KHR_DF_SAMPLE_DATATYPE_LINEAR } else { 0 };
+        for word in [offset | (length - 1) << 16 | (channel | qualifiers) << 24, 0, 0, upper].iter() {
+            descriptor.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+    Some(descriptor)
+}
+
+//Write the texture in a KTX2 file, without supercompression. Only the 8 bits RGBA, BC1 and BC3 formats can be written.
+pub fn write_ktx2<W: Write>(texture: &TextureResource, writer: &mut W) -> ResourceResult<()> {
+    let layout = texture.layout();
+    let descriptor = ktx2_data_format_descriptor(layout.format).ok_or_else(|| {
+        texture_error("The format of the texture can't be written in a KTX2 file.")
+    })?;
+    let level_count = texture.mip_level_count();
+    let descriptor_offset = KTX2_HEADER_SIZE + level_count * KTX2_LEVEL_INDEX_ENTRY_SIZE;
+
+    //The levels are stored from the smallest, each aligned on the bytes of a block (a multiple of 4).
+    let levels: Vec<&[u8]> = (0..level_count).filter_map(|level| texture.level(level)).collect();
+    let mut offsets = vec![0; level_count];
+    let mut end = descriptor_offset + descriptor.len();
+    for level in (0..level_count).rev() {
+        offsets[level] = end.next_multiple_of(layout.format.block_bytes());
+        end = offsets[level] + levels[level].len();
+    }
+
+    //The depth and the layers are 0 when the texture isn't 3D or isn't an array.
+    let depth = if layout.depth > 1 { layout.depth } else { 0 };
+    let layer_count = if layout.layer_count > 1 { layout.layer_count } else { 0 };
+    writer.write_all(KTX2_IDENTIFIER)?;
+    let fields = [
+        layout.format.vk_format(), 1, layout.width, layout.height, depth, layer_count, layout.face_count, level_count as u32, 0,
+        descriptor_offset as u32, descriptor.len() as u32, 0, 0,
+    ];
+    for field in fields.iter() {
+        write_u32(writer, *field)?;
+    }
+    //No supercompression global data.
+    write_u64(writer, 0)?;
+    write_u64(writer, 0)?;
+    for (offset, data) in offsets.iter().zip(levels.iter()) {
+        write_u64(writer, *offset as u64)?;
+        write_u64(writer, data.len() as u64)?;
+        write_u64(writer, data.len() as u64)?;
+    }
+    writer.write_all(descriptor.as_slice())?;
+
+    let mut position = descriptor_offset + descriptor.len();
+    for level in (0..level_count).rev() {
+        writer.write_all(&vec![0; offsets[level] - position])?;
+        writer.write_all(levels[level])?;
+        position = offsets[level] + levels[level].len();
+    }
+    Ok(())
+}
+
+pub fn read_dds(bytes: &[u8]) -> ResourceResult<TextureResource> {
+    if !bytes.starts_with(DDS_MAGIC) {
+        return Err(texture_error("The texture isn't a DDS file."));
+    }
+    if read_u32(bytes, 4)? != DDS_HEADER_SIZE || read_u32(bytes, 76)? != DDS_PIXEL_FORMAT_SIZE {
+        return Err(texture_error("The header of the DDS texture is invalid."));
+    }
+    let flags = read_u32(bytes, 8)?;
+    let pixel_format_flags = read_u32(bytes, 80)?;
+    let four_cc = bytes.get(84..88).ok_or_else(truncated)?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    let mut layout = TextureLayout::new(TextureFormat::R8G8B8A8Unorm, read_u32(bytes, 16)?, read_u32(bytes, 12)?);
+    if flags & DDSD_DEPTH != 0 || caps2 & DDSCAPS2_VOLUME != 0 {
+        layout.depth = read_u32(bytes, 24)?.max(1);
+    }
+    let level_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        read_u32(bytes, 28)?.max(1)
+    } else {
+        1
+    };
+
+    let mut data_offset = DDS_DATA_OFFSET;
+    if pixel_format_flags & DDPF_FOURCC != 0 && four_cc == b"DX10" {
+        let dxgi_format = read_u32(bytes, DDS_DATA_OFFSET)?;
+        layout.format = TextureFormat::from_dxgi_format(dxgi_format).ok_or_else(|| {
+            texture_error("The DXGI format of the DDS texture isn't supported.")
+        })?;
+        if read_u32(bytes, DDS_DATA_OFFSET + 8)? & DDS_RESOURCE_MISC_TEXTURECUBE != 0 {
+            layout.face_count = 6;
+        }
+        layout.layer_count = read_u32(bytes, DDS_DATA_OFFSET + 12)?.max(1);
+        data_offset += DDS_DX10_HEADER_SIZE;
+    } else {
+        layout.format = if pixel_format_flags & DDPF_FOURCC != 0 {
+            FOUR_CC_FORMATS.iter().find(|&&(code, _)| &code[..] == four_cc).map(|&(_, format)| format).ok_or_else(|| {
+                texture_error("The FourCC of the DDS texture isn't supported.")
+            })?
+        } else if pixel_format_flags & DDPF_RGB != 0 && read_u32(bytes, 88)? == 32 {
+            match (read_u32(bytes, 92)?, read_u32(bytes, 100)?) {
+                (0xff, 0xff_0000) => TextureFormat::R8G8B8A8Unorm,
+                (0xff_0000, 0xff) => TextureFormat::B8G8R8A8Unorm,
+                _ => return Err(texture_error("The channel masks of the DDS texture aren't supported.")),
+            }
+        } else {
+            return Err(texture_error("The pixel format of the DDS texture isn't supported."));
+        };
+        if caps2 & DDSCAPS2_CUBEMAP != 0 {
+            if caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != DDSCAPS2_CUBEMAP_ALL_FACES {
+                return Err(texture_error("The DDS cubemaps without all their faces aren't supported."));
+            }
+            layout.face_count = 6;
+        }
+    }
+    if level_count > layout.max_mip_level_count() {
+        return Err(texture_error("The DDS texture has more mip levels than its dimensions allow."));
+    }
+
+    //The size of the data is checked against the file before allocating the levels.
+    let surface_sizes = (0..level_count).map(|level| layout.surface_size(level)).collect::<ResourceResult<Vec<usize>>>()?;
+    let level_sizes = (0..level_count).map(|level| layout.level_size(level)).collect::<ResourceResult<Vec<usize>>>()?;
+    let end = level_sizes.iter().try_fold(data_offset, |end, &size| end.checked_add(size)).ok_or_else(truncated)?;
+    if end > bytes.len() {
+        return Err(truncated());
+    }
+
+    //The surfaces are stored with their mip chain, the levels are built surface by surface.
+    let mut levels: Vec<Vec<u8>> = level_sizes.iter().map(|&size| Vec::with_capacity(size)).collect();
+    let mut offset = data_offset;
+    for _ in 0..layout.surface_count()? {
+        for (data, &size) in levels.iter_mut().zip(surface_sizes.iter()) {
+            data.extend_from_slice(&bytes[offset..offset + size]);
+            offset += size;
+        }
+    }
+    TextureResource::new(layout, levels)
+}
+
+//Write the texture in a DDS file, with a DX10 header. The formats without DXGI format can't be written.
+pub fn write_dds<W: Write>(texture: &TextureResource, writer: &mut W) -> ResourceResult<()> {
+    let layout = texture.layout();
+    let dxgi_format = layout.format.dxgi_format().ok_or_else(|| {
+        texture_error("The format of the texture can't be stored in a DDS file.")
+    })?;
+    let level_count = texture.mip_level_count() as u32;
+    let volume = layout.depth > 1;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_MIPMAPCOUNT | DDSD_LINEARSIZE;
+    let mut caps = DDSCAPS_TEXTURE;
+    let mut caps2 = 0;
+    if volume {
+        flags |= DDSD_DEPTH;
+        caps2 |= DDSCAPS2_VOLUME;
+    }
+    if level_count > 1 {
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+    if texture.is_cubemap() {
+        caps |= DDSCAPS_COMPLEX;
+        caps2 |= DDSCAPS2_CUBEMAP | DDSCAPS2_CUBEMAP_ALL_FACES;
+    }
+
+    writer.write_all(DDS_MAGIC)?;
+    for field in [DDS_HEADER_SIZE, flags, layout.height, layout.width, layout.surface_size(0)? as u32, layout.depth, level_count].iter() {
+        write_u32(writer, *field)?;
+    }
+    writer.write_all(&[0; 44])?;
+    write_u32(writer, DDS_PIXEL_FORMAT_SIZE)?;
+    write_u32(writer, DDPF_FOURCC)?;
+    writer.write_all(b"DX10")?;
+    writer.write_all(&[0; 20])?;
+    for field in [caps, caps2, 0, 0, 0].iter() {
+        write_u32(writer, *field)?;
+    }
+
+    let dimension = if volume { DDS_DIMENSION_TEXTURE3D } else { DDS_DIMENSION_TEXTURE2D };
+    let misc = if texture.is_cubemap() { DDS_RESOURCE_MISC_TEXTURECUBE } else { 0 };
+    for field in [dxgi_format, dimension, misc, layout.layer_count, 0].iter() {
+        write_u32(writer, *field)?;
+    }
+
+    for layer in 0..layout.layer_count {
+        for face in 0..layout.face_count {
+            for level in 0..texture.mip_level_count() {
+                writer.write_all(texture.surface(level, layer, face).ok_or_else(truncated)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod texture_containers_test {
+    use super::*;
+
+    fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+        write_u32(bytes, value).unwrap();
+    }
+
+    //A BC3 2D array of 2 layers, 8x4, with 2 mip levels.
+    fn ktx2_file() -> Vec<u8> {
+        let level_sizes = [2 * 2 * 16, 2 * 16];
+        let mut bytes = KTX2_IDENTIFIER.to_vec();
+        for field in [137, 1, 8, 4, 0, 2, 1, 2, 0].iter() {
+            push_u32(&mut bytes, *field);
+        }
+        bytes.extend_from_slice(&[0; 32]);
+        //The smallest level is stored first.
+        let data_offset = KTX2_HEADER_SIZE + 2 * KTX2_LEVEL_INDEX_ENTRY_SIZE;
+        let offsets = [data_offset + level_sizes[1], data_offset];
+        for level in 0..2 {
+            for field in [offsets[level], level_sizes[level], level_sizes[level]].iter() {
+                push_u32(&mut bytes, *field as u32);
+                push_u32(&mut bytes, 0);
+            }
+        }
+        bytes.extend(vec![1; level_sizes[1]]);
+        bytes.extend(vec![0; level_sizes[0]]);
+        bytes
+    }
+
+    #[test]
+    fn ktx2_texture() {
+        let texture = read_ktx2(ktx2_file().as_slice()).unwrap();
+        assert_eq!(texture.format(), TextureFormat::Bc3Unorm);
+        assert_eq!((texture.width(), texture.height(), texture.mip_level_count()), (8, 4, 2));
+        assert!(texture.is_array() && !texture.is_cubemap());
+        assert_eq!(texture.surface(1, 1, 0).unwrap(), &[1; 16][..]);
+
+        let mut unsupported = ktx2_file();
+        unsupported[12] = 0;
+        assert!(read_ktx2(unsupported.as_slice()).is_err());
+        let mut supercompressed = ktx2_file();
+        supercompressed[44] = 2;
+        assert!(read_ktx2(supercompressed.as_slice()).is_err());
+        let truncated = ktx2_file();
+        assert!(read_ktx2(&truncated[..truncated.len() - 1]).is_err());
+
+        //Malformed headers: huge dimensions overflow the level sizes, huge layer counts don't match the level index.
+        let mut huge = ktx2_file();
+        huge[20..28].copy_from_slice(&[0xff; 8]);
+        assert!(read_ktx2(huge.as_slice()).is_err());
+        let mut layers = ktx2_file();
+        layers[32..36].copy_from_slice(&[0xff; 4]);
+        assert!(read_ktx2(layers.as_slice()).is_err());
+        let mut level_offset = ktx2_file();
+        level_offset[KTX2_HEADER_SIZE..KTX2_HEADER_SIZE + 8].copy_from_slice(&[0xff; 8]);
+        assert!(read_ktx2(level_offset.as_slice()).is_err());
+    }
+
+    #[test]
+    fn ktx2_written_texture() {
+        //A BC3 sRGB texture, 8x8 with 4 mip levels, written and read back.
+        let layout = TextureLayout::new(TextureFormat::Bc3Srgb, 8, 8);
+        let levels = (0..4).map(|level| vec![level as u8 + 1; layout.level_size(level).unwrap()]).collect();
+        let texture = TextureResource::new(layout, levels).unwrap();
+        let mut file = Vec::new();
+        TextureContainer::Ktx2.write(&texture, &mut file).unwrap();
+
+        let read = read_ktx2(file.as_slice()).unwrap();
+        assert_eq!(*read.layout(), layout);
+        for level in 0..4 {
+            assert_eq!(read.level(level), texture.level(level));
+        }
+        //The descriptor follows the level index, the levels are aligned on the blocks.
+        assert_eq!(read_u32(&file, 48).unwrap() as usize, KTX2_HEADER_SIZE + 4 * KTX2_LEVEL_INDEX_ENTRY_SIZE);
+        assert_eq!(read_u32(&file, 52).unwrap(), 4 + 24 + 2 * 16);
+        assert!((0..4).all(|level| read_u64(&file, KTX2_HEADER_SIZE + level * KTX2_LEVEL_INDEX_ENTRY_SIZE).unwrap().is_multiple_of(16)));
+
+        let etc2 = TextureResource::new(TextureLayout::new(TextureFormat::Etc2R8G8B8Unorm, 4, 4), vec![vec![0; 8]]).unwrap();
+        assert!(write_ktx2(&etc2, &mut Vec::new()).is_err());
+        assert_eq!(TextureContainer::from_name("dds").map(|container| container.extension()), Some("dds"));
+    }
+
+    #[test]
+    fn dds_texture() {
+        //A BC1 sRGB cubemap, 4x4 with 3 mip levels, written and read back.
+        let mut layout = TextureLayout::new(TextureFormat::Bc1RgbaSrgb, 4, 4);
+        layout.face_count = 6;
+        let levels = (0..3).map(|level| (0..layout.level_size(level).unwrap()).map(|byte| (byte / 8) as u8 + level as u8 * 10).collect()).collect();
+        let texture = TextureResource::new(layout, levels).unwrap();
+        let mut file = Vec::new();
+        write_dds(&texture, &mut file).unwrap();
+        assert_eq!(file.len(), DDS_DATA_OFFSET + DDS_DX10_HEADER_SIZE + 6 * 3 * 8);
+
+        let read = read_dds(file.as_slice()).unwrap();
+        assert_eq!(*read.layout(), layout);
+        for level in 0..3 {
+            assert_eq!(read.level(level), texture.level(level));
+        }
+
+        //A DXT5 file, without DX10 header and mip levels.
+        let mut legacy = DDS_MAGIC.to_vec();
+        for field in [DDS_HEADER_SIZE, DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT, 4, 8, 32, 0, 0].iter() {
+            push_u32(&mut legacy, *field);
+        }
+        legacy.extend_from_slice(&[0; 44]);
+        push_u32(&mut legacy, DDS_PIXEL_FORMAT_SIZE);
+        push_u32(&mut legacy, DDPF_FOURCC);
+        legacy.extend_from_slice(b"DXT5");
+        legacy.extend_from_slice(&[0; 40]);
+        legacy.extend(vec![7; 32]);
+        let read = read_dds(legacy.as_slice()).unwrap();
+        assert_eq!(read.format(), TextureFormat::Bc3Unorm);
+        assert_eq!((read.width(), read.height(), read.mip_level_count()), (8, 4, 1));
+
+        assert!(write_dds(&TextureResource::new(TextureLayout::new(TextureFormat::Etc2R8G8B8Unorm, 4, 4), vec![vec![0; 8]]).unwrap(), &mut Vec::new()).is_err());
+        assert!(read_dds(&legacy[..legacy.len() - 1]).is_err());
+
+        //Malformed headers: huge dimensions, and a cubemap array of 2^32 - 1 layers, refused before allocating.
+        let mut huge = legacy.clone();
+        huge[12..20].copy_from_slice(&[0xff; 8]);
+        assert!(read_dds(huge.as_slice()).is_err());
+        let mut layers = file.clone();
+        layers[DDS_DATA_OFFSET + 12..DDS_DATA_OFFSET + 16].copy_from_slice(&[0xff; 4]);
+        assert!(read_dds(layers.as_slice()).is_err());
+    }
+}
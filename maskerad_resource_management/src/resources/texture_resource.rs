@@ -0,0 +1,419 @@
+// Copyright 2017-2018 Maskerad Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use std::io::Read;
+use std::path::Path;
+
+use budgets::{MemorySize, ResourceCategory};
+use resources::resource_errors::{ResourceError, ResourceResult};
+use resources::texture_containers::{self, KTX2_IDENTIFIER, DDS_MAGIC};
+use resource_loader::ResourceLoader;
+
+/*
+TEXTURE RESOURCES.
+
+The textures ready for the GPU: block compressed (BC1 to BC7, ETC2, ASTC) or not, with their mip levels, their
+array layers and their cubemap faces. They're loaded from KTX2 and DDS files, and uploaded as they are.
+The ImageResource stays the format of the decoded images (TGA, PNG, BMP, JPEG), which the tools process.
+
+The data of a mip level holds its surfaces one after the other: for each array layer, for each face, the depth
+slices of the level. This is the layout of the KTX2 levels, and of a Vulkan buffer to image copy.
+
+The textures are loaded by the TextureLoader, registered by the game:
+
+resource_manager.register_loader(TextureLoader)?;
+let texture: Handle<TextureResource> = resource_manager.load("textures/sky.ktx2", reader)?;
+*/
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TextureFormat {
+    R8G8B8A8Unorm,
+    R8G8B8A8Srgb,
+    B8G8R8A8Unorm,
+    B8G8R8A8Srgb,
+    Bc1RgbUnorm,
+    Bc1RgbSrgb,
+    Bc1RgbaUnorm,
+    Bc1RgbaSrgb,
+    Bc2Unorm,
+    Bc2Srgb,
+    Bc3Unorm,
+    Bc3Srgb,
+    Bc4Unorm,
+    Bc4Snorm,
+    Bc5Unorm,
+    Bc5Snorm,
+    Bc6hUfloat,
+    Bc6hSfloat,
+    Bc7Unorm,
+    Bc7Srgb,
+    Etc2R8G8B8Unorm,
+    Etc2R8G8B8Srgb,
+    Etc2R8G8B8A1Unorm,
+    Etc2R8G8B8A1Srgb,
+    Etc2R8G8B8A8Unorm,
+    Etc2R8G8B8A8Srgb,
+    Astc {
+        block_width: u8,
+        block_height: u8,
+        srgb: bool,
+    },
+}
+
+//The block sizes of the ASTC formats, in the order of their Vulkan formats.
+const ASTC_BLOCK_SIZES: [(u8, u8); 14] = [
+    (4, 4), (5, 4), (5, 5), (6, 5), (6, 6), (8, 5), (8, 6), (8, 8), (10, 5), (10, 6), (10, 8), (10, 10), (12, 10), (12, 12),
+];
+
+//The Vulkan format of the first ASTC format, VK_FORMAT_ASTC_4x4_UNORM_BLOCK.
+const VK_FORMAT_ASTC_FIRST: u32 = 157;
+
+//The formats with a Vulkan format and a DXGI format (0 for the formats DXGI doesn't have).
+const FORMATS: [(TextureFormat, u32, u32); 26] = [
+    (TextureFormat::R8G8B8A8Unorm, 37, 28),
+    (TextureFormat::R8G8B8A8Srgb, 43, 29),
+    (TextureFormat::B8G8R8A8Unorm, 44, 87),
+    (TextureFormat::B8G8R8A8Srgb, 50, 91),
+    (TextureFormat::Bc1RgbUnorm, 131, 0),
+    (TextureFormat::Bc1RgbSrgb, 132, 0),
+    (TextureFormat::Bc1RgbaUnorm, 133, 71),
+    (TextureFormat::Bc1RgbaSrgb, 134, 72),
+    (TextureFormat::Bc2Unorm, 135, 74),
+    (TextureFormat::Bc2Srgb, 136, 75),
+    (TextureFormat::Bc3Unorm, 137, 77),
+    (TextureFormat::Bc3Srgb, 138, 78),
+    (TextureFormat::Bc4Unorm, 139, 80),
+    (TextureFormat::Bc4Snorm, 140, 81),
+    (TextureFormat::Bc5Unorm, 141, 83),
+    (TextureFormat::Bc5Snorm, 142, 84),
+    (TextureFormat::Bc6hUfloat, 143, 95),
+    (TextureFormat::Bc6hSfloat, 144, 96),
+    (TextureFormat::Bc7Unorm, 145, 98),
+    (TextureFormat::Bc7Srgb, 146, 99),
+    (TextureFormat::Etc2R8G8B8Unorm, 147, 0),
+    (TextureFormat::Etc2R8G8B8Srgb, 148, 0),
+    (TextureFormat::Etc2R8G8B8A1Unorm, 149, 0),
+    (TextureFormat::Etc2R8G8B8A1Srgb, 150, 0),
+    (TextureFormat::Etc2R8G8B8A8Unorm, 151, 0),
+    (TextureFormat::Etc2R8G8B8A8Srgb, 152, 0),
+];
+
+impl TextureFormat {
+    //The format of a VkFormat, as stored in the KTX2 files.
+    pub fn from_vk_format(vk_format: u32) -> Option<Self> {
+        if let Some(&(format, _, _)) = FORMATS.iter().find(|&&(_, vk, _)| vk == vk_format) {
+            return Some(format);
+        }
+        let astc = vk_format.checked_sub(VK_FORMAT_ASTC_FIRST)?;
+        ASTC_BLOCK_SIZES.get((astc / 2) as usize).map(|&(block_width, block_height)| TextureFormat::Astc {
+            block_width,
+            block_height,
+            srgb: astc % 2 == 1,
+        })
+    }
+
+    pub fn vk_format(&self) -> u32 {
+        match *self {
+            TextureFormat::Astc { block_width, block_height, srgb } => {
+                let index = ASTC_BLOCK_SIZES.iter().position(|&size| size == (block_width, block_height)).unwrap_or(0) as u32;
+                VK_FORMAT_ASTC_FIRST + index * 2 + srgb as u32
+            },
+            format => FORMATS.iter().find(|&&(other, _, _)| other == format).map(|&(_, vk, _)| vk).unwrap_or(0),
+        }
+    }
+
+    //The format of a DXGI_FORMAT, as stored in the DX10 header of the DDS files.
+    pub fn from_dxgi_format(dxgi_format: u32) -> Option<Self> {
+        if dxgi_format == 0 {
+            return None;
+        }
+        FORMATS.iter().find(|&&(_, _, dxgi)| dxgi == dxgi_format).map(|&(format, _, _)| format)
+    }
+
+    //None for the formats DXGI doesn't have: the ETC2 and ASTC formats, and BC1 without alpha.
+    pub fn dxgi_format(&self) -> Option<u32> {
+        FORMATS.iter().find(|&&(other, _, _)| other == *self).map(|&(_, _, dxgi)| dxgi).filter(|&dxgi| dxgi != 0)
+    }
+
+    //The size of a block of texels, 1x1 for the formats which aren't block compressed.
+    pub fn block_size(&self) -> (u32, u32) {
+        match *self {
+            TextureFormat::R8G8B8A8Unorm |
+            TextureFormat::R8G8B8A8Srgb |
+            TextureFormat::B8G8R8A8Unorm |
+            TextureFormat::B8G8R8A8Srgb => (1, 1),
+            TextureFormat::Astc { block_width, block_height, .. } => (u32::from(block_width), u32::from(block_height)),
+            _ => (4, 4),
+        }
+    }
+
+    //The bytes of a block of texels.
+    pub fn block_bytes(&self) -> usize {
+        match *self {
+            TextureFormat::R8G8B8A8Unorm |
+            TextureFormat::R8G8B8A8Srgb |
+            TextureFormat::B8G8R8A8Unorm |
+            TextureFormat::B8G8R8A8Srgb => 4,
+            TextureFormat::Bc1RgbUnorm |
+            TextureFormat::Bc1RgbSrgb |
+            TextureFormat::Bc1RgbaUnorm |
+            TextureFormat::Bc1RgbaSrgb |
+            TextureFormat::Bc4Unorm |
+            TextureFormat::Bc4Snorm |
+            TextureFormat::Etc2R8G8B8Unorm |
+            TextureFormat::Etc2R8G8B8Srgb |
+            TextureFormat::Etc2R8G8B8A1Unorm |
+            TextureFormat::Etc2R8G8B8A1Srgb => 8,
+            _ => 16,
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.block_size() != (1, 1)
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        match *self {
+            TextureFormat::R8G8B8A8Srgb |
+            TextureFormat::B8G8R8A8Srgb |
+            TextureFormat::Bc1RgbSrgb |
+            TextureFormat::Bc1RgbaSrgb |
+            TextureFormat::Bc2Srgb |
+            TextureFormat::Bc3Srgb |
+            TextureFormat::Bc7Srgb |
+            TextureFormat::Etc2R8G8B8Srgb |
+            TextureFormat::Etc2R8G8B8A1Srgb |
+            TextureFormat::Etc2R8G8B8A8Srgb => true,
+            TextureFormat::Astc { srgb, .. } => srgb,
+            _ => false,
+        }
+    }
+}
+
+//The format and the dimensions of a texture.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TextureLayout {
+    pub format: TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    //1 for the 2D textures.
+    pub depth: u32,
+    //1 for the textures which aren't arrays.
+    pub layer_count: u32,
+    //6 for the cubemaps, 1 otherwise.
+    pub face_count: u32,
+}
+
+impl TextureLayout {
+    //A 2D texture.
+    pub fn new(format: TextureFormat, width: u32, height: u32) -> Self {
+        TextureLayout {
+            format,
+            width,
+            height,
+            depth: 1,
+            layer_count: 1,
+            face_count: 1,
+        }
+    }
+
+    //The width, height and depth of a mip level.
+    pub fn level_dimensions(&self, level: u32) -> (u32, u32, u32) {
+        let shrink = |size: u32| size.checked_shr(level).unwrap_or(0).max(1);
+        (shrink(self.width), shrink(self.height), shrink(self.depth))
+    }
+
+    //The bytes of a surface (a layer of a face) of a mip level. An error if the size doesn't fit in memory.
+    pub fn surface_size(&self, level: u32) -> ResourceResult<usize> {
+        let (width, height, depth) = self.level_dimensions(level);
+        let (block_width, block_height) = self.format.block_size();
+        (width.div_ceil(block_width) as usize).checked_mul(height.div_ceil(block_height) as usize)
+            .and_then(|blocks| blocks.checked_mul(depth as usize))
+            .and_then(|blocks| blocks.checked_mul(self.format.block_bytes()))
+            .ok_or_else(too_large)
+    }
+
+    //The bytes of a mip level, with all its surfaces.
+    pub fn level_size(&self, level: u32) -> ResourceResult<usize> {
+        self.surface_size(level)?.checked_mul(self.surface_count()?).ok_or_else(too_large)
+    }
+
+    pub fn surface_count(&self) -> ResourceResult<usize> {
+        (self.layer_count as usize).checked_mul(self.face_count as usize).ok_or_else(too_large)
+    }
+
+    //The number of mip levels of a complete mip chain, down to 1x1x1.
+    pub fn max_mip_level_count(&self) -> u32 {
+        32 - self.width.max(self.height).max(self.depth).max(1).leading_zeros()
+    }
+}
+
+#[derive(Debug)]
+pub struct TextureResource {
+    layout: TextureLayout,
+    //The mip levels, from the largest to the smallest.
+    levels: Vec<Vec<u8>>,
+}
+
+fn texture_error(description: &str) -> ResourceError {
+    ResourceError::TextureError(String::from(description))
+}
+
+fn too_large() -> ResourceError {
+    texture_error("The size of the texture overflows.")
+}
+
+impl TextureResource {
+    //A texture with the data of its mip levels. The size of every level must match the layout.
+    pub fn new(layout: TextureLayout, levels: Vec<Vec<u8>>) -> ResourceResult<TextureResource> {
+        if layout.width == 0 || layout.height == 0 || layout.depth == 0 || layout.layer_count == 0 {
+            return Err(texture_error("The texture has no texel."));
+        }
+        if layout.face_count != 1 && layout.face_count != 6 {
+            return Err(texture_error("A texture has 1 face, or 6 faces for a cubemap."));
+        }
+        if layout.face_count == 6 && (layout.width != layout.height || layout.depth != 1) {
+            return Err(texture_error("The faces of a cubemap are square and 2D."));
+        }
+        if levels.is_empty() || levels.len() as u32 > layout.max_mip_level_count() {
+            return Err(texture_error("The number of mip levels doesn't match the dimensions of the texture."));
+        }
+        for (level, data) in levels.iter().enumerate() {
+            if data.len() != layout.level_size(level as u32)? {
+                return Err(texture_error("The size of a mip level doesn't match the layout of the texture."));
+            }
+        }
+
+        Ok(TextureResource {
+            layout,
+            levels,
+        })
+    }
+
+    //A texture from a KTX2 or a DDS file.
+    pub fn from_reader<R: Read>(reader: &mut R) -> ResourceResult<TextureResource> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        if bytes.starts_with(KTX2_IDENTIFIER) {
+            texture_containers::read_ktx2(bytes.as_slice())
+        } else if bytes.starts_with(DDS_MAGIC) {
+            texture_containers::read_dds(bytes.as_slice())
+        } else {
+            Err(texture_error("The texture isn't a KTX2 or a DDS file."))
+        }
+    }
+
+    pub fn layout(&self) -> &TextureLayout {
+        &self.layout
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.layout.format
+    }
+
+    pub fn width(&self) -> u32 {
+        self.layout.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.layout.height
+    }
+
+    pub fn is_cubemap(&self) -> bool {
+        self.layout.face_count == 6
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.layout.layer_count > 1
+    }
+
+    pub fn mip_level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    //The data of a mip level, with all its surfaces. The level 0 is the base level.
+    pub fn level(&self, level: usize) -> Option<&[u8]> {
+        self.levels.get(level).map(|data| data.as_slice())
+    }
+
+    //The data of a layer of a face of a mip level.
+    pub fn surface(&self, level: usize, layer: u32, face: u32) -> Option<&[u8]> {
+        if layer >= self.layout.layer_count || face >= self.layout.face_count {
+            return None;
+        }
+        //The sizes have been checked by TextureResource::new().
+        let size = self.layout.surface_size(level as u32).ok()?;
+        let start = (layer as usize * self.layout.face_count as usize + face as usize) * size;
+        self.level(level).and_then(|data| data.get(start..start + size))
+    }
+}
+
+//Load the KTX2 and DDS textures.
+pub struct TextureLoader;
+
+impl<R: Read> ResourceLoader<R> for TextureLoader {
+    type Resource = TextureResource;
+
+    fn extensions(&self) -> &[&str] {
+        &["ktx2", "dds"]
+    }
+
+    fn load(&self, reader: &mut R, _path: &Path) -> ResourceResult<TextureResource> {
+        TextureResource::from_reader(reader)
+    }
+
+    fn category(&self) -> Option<ResourceCategory> {
+        Some(ResourceCategory::Textures)
+    }
+
+    fn memory_size(&self, texture: &TextureResource) -> usize {
+        texture.memory_size()
+    }
+}
+
+#[cfg(test)]
+mod texture_resource_test {
+    use super::*;
+
+    #[test]
+    fn texture_formats_and_layouts() {
+        assert_eq!(TextureFormat::from_vk_format(137), Some(TextureFormat::Bc3Unorm));
+        assert_eq!(TextureFormat::from_vk_format(172), Some(TextureFormat::Astc { block_width: 8, block_height: 8, srgb: true }));
+        assert_eq!(TextureFormat::Astc { block_width: 8, block_height: 8, srgb: true }.vk_format(), 172);
+        assert_eq!(TextureFormat::from_vk_format(185), None);
+        assert_eq!(TextureFormat::from_dxgi_format(98), Some(TextureFormat::Bc7Unorm));
+        assert_eq!(TextureFormat::Etc2R8G8B8A8Srgb.dxgi_format(), None);
+        assert!(TextureFormat::Bc1RgbaSrgb.is_srgb() && TextureFormat::Bc1RgbaSrgb.is_compressed());
+
+        //A BC1 cubemap of 2 layers, 10x10: 3x3 blocks of 8 bytes per surface.
+        let mut layout = TextureLayout::new(TextureFormat::Bc1RgbaUnorm, 10, 10);
+        layout.face_count = 6;
+        layout.layer_count = 2;
+        assert_eq!(layout.max_mip_level_count(), 4);
+        assert_eq!(layout.surface_size(0).unwrap(), 72);
+        assert_eq!(layout.level_dimensions(3), (1, 1, 1));
+        assert_eq!(layout.level_size(3).unwrap(), 8 * 12);
+
+        let levels = (0..4).map(|level| vec![level as u8; layout.level_size(level).unwrap()]).collect();
+        let texture = TextureResource::new(layout, levels).unwrap();
+        assert!(texture.is_cubemap() && texture.is_array());
+        assert_eq!(texture.surface(1, 1, 5).unwrap(), &[1; 32][..]);
+        assert!(texture.surface(1, 2, 0).is_none());
+
+        assert!(TextureResource::new(layout, vec![vec![0; 10]]).is_err());
+        assert!(TextureResource::new(layout, vec![Vec::new(); 5]).is_err());
+        assert!(TextureResource::from_reader(&mut &b"not a texture"[..]).is_err());
+
+        //Huge dimensions: the sizes overflow instead of wrapping.
+        let mut huge = TextureLayout::new(TextureFormat::R8G8B8A8Unorm, u32::MAX, u32::MAX);
+        huge.depth = u32::MAX;
+        huge.layer_count = u32::MAX;
+        assert!(huge.surface_size(0).is_err());
+        assert!(huge.level_size(0).is_err());
+        assert!(TextureResource::new(huge, vec![Vec::new()]).is_err());
+    }
+}
@@ -2,8 +2,13 @@ extern crate maskerad_game_engine;
 
 use maskerad_game_engine::core::filesystem::filesystem::Filesystem;
 use maskerad_game_engine::core::filesystem::game_directories::RootDir;
+use maskerad_game_engine::core::filesystem::compression::{Codec, CompressedReader};
 
 use maskerad_game_engine::resource_management::resource_manager::ResourceManager;
+use std::io;
+use std::io::BufReader;
+use std::fs::File;
+
 use maskerad_game_engine::resource_management::resources::{model_resource::ModelResource, image_resource::{ImageResource, ColorFormat}, sound_resource::SoundResource};
 
 #[test]
@@ -11,17 +16,18 @@ fn resource_manager_load_unload_get_resource() {
     //Filesystem, StackAlloc, ResourceManager.
     let fs = Filesystem::new("test_resource_man", "Malkaviel")
         .expect(format!("{}::{} Could not create fs.", file!(), line!()).as_str());
-    let mut resource_man = ResourceManager::with_capacity(10000000, 10000000); //10 mb
+    let resource_man = ResourceManager::new();
 
     //Load image
     let image_path = fs.construct_path_from_root(RootDir::WorkingDirectory, "test_resources/images/Untitled.tga")
         .expect(format!("{}::{} Could not create tga path.", file!(), line!()).as_str());
     let mut image_reader = Filesystem::open(image_path.as_path())
         .expect(format!("{}::{} Could no create image reader.", file!(), line!()).as_str());
-    resource_man.load_image(image_path.as_path(), &mut image_reader, ColorFormat::Auto)
+    let image = resource_man.load_image(image_path.as_path(), &mut image_reader, ColorFormat::Auto)
         .expect(format!("{}::{} Could not load image in resource manager", file!(), line!()).as_str());
     assert!(!resource_man.image_resources().is_empty());
-    assert!(resource_man.image_resources().get(image_path.as_path()).is_some());
+    assert!(resource_man.image(&image).is_some());
+    assert_eq!(resource_man.image_resources().handle(image_path.as_path()), Some(image.clone()));
 
     //Load gltf
     let model_path = fs.construct_path_from_root(RootDir::WorkingDirectory, "test_resources/gltf/untitled.gltf")
@@ -29,24 +35,63 @@ fn resource_manager_load_unload_get_resource() {
 
     let model_reader = Filesystem::open(model_path.as_path())
         .expect(format!("{}::{} Could not create a reader to read the model's file.", file!(), line!()).as_str());
-    resource_man.load_model(model_path.as_path(), model_reader)
+    let model = resource_man.load_model(model_path.as_path(), model_reader)
         .expect(format!("{}::{} Could not put the model in the resource manager.", file!(), line!()).as_str());
     assert!(!resource_man.model_resources().is_empty());
-    assert!(resource_man.model_resources().get(model_path.as_path()).is_some());
+    assert!(resource_man.model(&model).is_some());
 
     //Load ogg
     let sound_path = fs.construct_path_from_root(RootDir::WorkingDirectory, "test_resources/ogg/untitled.ogg")
         .expect(format!("{}::{} Could not create sound path.", file!(), line!()).as_str());
     let sound_reader = Filesystem::open(sound_path.as_path())
         .expect(format!("{}::{} Could not create sound reader", file!(), line!()).as_str());
-    resource_man.load_sound(sound_path.as_path(), sound_reader)
+    let sound = resource_man.load_sound(sound_path.as_path(), sound_reader)
         .expect(format!("{}::{} Could not load sound resource in the resource manager", file!(), line!()).as_str());
     assert!(!resource_man.sound_resources().is_empty());
-    assert!(resource_man.sound_resources().get(sound_path.as_path()).is_some());
+    assert!(resource_man.sound(&sound).is_some());
+
+    //Unload the image when its last handle is dropped.
+    drop(image);
+    assert_eq!(resource_man.collect_unused(), 1);
+    assert!(resource_man.image_resources().is_empty());
 
     //unload
     resource_man.clear();
+    assert!(resource_man.model(&model).is_none());
     assert!(resource_man.sound_resources().is_empty());
     assert!(resource_man.model_resources().is_empty());
     assert!(resource_man.image_resources().is_empty());
-}
\ No newline at end of file
+}
+
+#[test]
+fn resource_manager_load_compressed_resource() {
+    let fs = Filesystem::new("test_resource_man_compressed", "Malkaviel")
+        .expect(format!("{}::{} Could not create fs.", file!(), line!()).as_str());
+    let resource_man: ResourceManager<CompressedReader<BufReader<File>>> = ResourceManager::new();
+
+    //Compress the image
+    let image_path = fs.construct_path_from_root(RootDir::WorkingDirectory, "test_resources/images/Untitled.tga")
+        .expect(format!("{}::{} Could not create tga path.", file!(), line!()).as_str());
+    let compressed_dir = fs.construct_path_from_root(RootDir::EngineLogRoot, "compressed_resources")
+        .expect(format!("{}::{} Could not create the compressed resources path.", file!(), line!()).as_str());
+    Filesystem::mkdir(compressed_dir.as_path())
+        .expect(format!("{}::{} Could not create the compressed resources directory.", file!(), line!()).as_str());
+    let compressed_image_path = compressed_dir.join("Untitled.tga.lz4");
+
+    let mut image_reader = Filesystem::open(image_path.as_path())
+        .expect(format!("{}::{} Could no create image reader.", file!(), line!()).as_str());
+    let mut compressed_writer = Filesystem::create_compressed(compressed_image_path.as_path())
+        .expect(format!("{}::{} Could not create the compressed image.", file!(), line!()).as_str());
+    io::copy(&mut image_reader, &mut compressed_writer)
+        .expect(format!("{}::{} Could not compress the image.", file!(), line!()).as_str());
+    compressed_writer.finish()
+        .expect(format!("{}::{} Could not finish the compressed image.", file!(), line!()).as_str());
+
+    //Load the compressed image
+    let mut compressed_reader = Filesystem::open_compressed(compressed_image_path.as_path())
+        .expect(format!("{}::{} Could not open the compressed image.", file!(), line!()).as_str());
+    assert_eq!(compressed_reader.codec(), Codec::Lz4);
+    let image = resource_man.load_image(Codec::strip_extension(compressed_image_path.as_path()), &mut compressed_reader, ColorFormat::Auto)
+        .expect(format!("{}::{} Could not load the compressed image in resource manager", file!(), line!()).as_str());
+    assert_eq!(resource_man.image_resources().handle(compressed_dir.join("Untitled.tga")), Some(image));
+}